@@ -0,0 +1,202 @@
+//! Benchmarks of counting, n-gram extraction and sorting.
+//!
+//! The benchmarks run on a synthetic corpus with Zipf-distributed tokens.
+//! The corpus is configured through environment variables:
+//!
+//! * `BENCH_TOKENS`: number of tokens (default: 1000000)
+//! * `BENCH_TYPES`: vocabulary size (default: 50000)
+//! * `BENCH_ZIPF_S`: Zipf exponent (default: 1.07)
+//! * `BENCH_ITERS`: iterations per benchmark (default: 5)
+//!
+//! Results are written as `name<TAB>median_ms<TAB>min_ms`. With
+//! `BENCH_BASELINE` pointing to the output of an earlier run, the ratio to
+//! the baseline median is added and the run fails if a benchmark is slower
+//! than the baseline by more than `BENCH_TOLERANCE` (default: 0.1).
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::{self, Command};
+use std::time::Instant;
+
+use corpus_count::ngrams::NGrams;
+use corpus_count::rng::Rng;
+use corpus_count::sort::{count_order, ExternalSort};
+use corpus_count::temp::TempDir;
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .map(|v| v.parse().unwrap_or_else(|_| panic!("Can't parse {}", name)))
+        .unwrap_or(default)
+}
+
+/// Generate a token for a frequency rank.
+///
+/// Frequent tokens are short, as in natural language.
+fn token(mut rank: usize) -> String {
+    let mut token = String::new();
+    loop {
+        token.push((b'a' + (rank % 26) as u8) as char);
+        rank /= 26;
+        if rank == 0 {
+            return token;
+        }
+    }
+}
+
+/// Sampler of Zipf-distributed frequency ranks.
+struct Zipf {
+    cumulative: Vec<f64>,
+}
+
+impl Zipf {
+    fn new(types: usize, s: f64) -> Self {
+        let mut sum = 0.;
+        let cumulative = (1..=types)
+            .map(|rank| {
+                sum += 1. / (rank as f64).powf(s);
+                sum
+            })
+            .collect();
+        Zipf { cumulative }
+    }
+
+    fn sample(&self, rng: &mut Rng) -> usize {
+        let total = self.cumulative[self.cumulative.len() - 1];
+        let p = rng.next_f64() * total;
+        self.cumulative
+            .partition_point(|&c| c <= p)
+            .min(self.cumulative.len() - 1)
+    }
+}
+
+/// Write a corpus of `tokens` tokens with 20 tokens per line.
+fn write_corpus(path: &Path, tokens: usize, zipf: &Zipf, rng: &mut Rng) {
+    let mut writer = BufWriter::new(File::create(path).expect("Can't create corpus."));
+    for idx in 0..tokens {
+        let sep = if idx % 20 == 19 { '\n' } else { ' ' };
+        write!(writer, "{}{}", token(zipf.sample(rng)), sep).expect("Can't write corpus.");
+    }
+    writeln!(writer).expect("Can't write corpus.");
+    writer.flush().expect("Can't write corpus.");
+}
+
+/// Run `f` `iters` times, get the median and minimum time in ms.
+fn time(iters: usize, mut f: impl FnMut()) -> (f64, f64) {
+    let mut times = (0..iters)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed().as_secs_f64() * 1000.
+        })
+        .collect::<Vec<_>>();
+    times.sort_by(|t1, t2| t1.partial_cmp(t2).unwrap());
+    (times[times.len() / 2], times[0])
+}
+
+fn read_baseline(path: &str) -> HashMap<String, f64> {
+    fs::read_to_string(path)
+        .expect("Can't read baseline.")
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?;
+            let median = fields.next()?.parse().ok()?;
+            Some((name.to_string(), median))
+        })
+        .collect()
+}
+
+fn run_binary(args: &[&str]) {
+    let status = Command::new(env!("CARGO_BIN_EXE_corpus-count"))
+        .args(args)
+        .status()
+        .expect("Can't run corpus-count.");
+    assert!(status.success(), "corpus-count failed");
+}
+
+fn main() {
+    let tokens = env_or("BENCH_TOKENS", 1_000_000);
+    let types = env_or("BENCH_TYPES", 50_000);
+    let s = env_or("BENCH_ZIPF_S", 1.07);
+    let iters = env_or("BENCH_ITERS", 5);
+    let tolerance = env_or("BENCH_TOLERANCE", 0.1);
+    assert_ne!(iters, 0, "The number of iterations cannot be zero.");
+    let baseline = env::var("BENCH_BASELINE").ok().map(|p| read_baseline(&p));
+
+    let tmp_dir = TempDir::new(&env::temp_dir(), "corpus-count-bench")
+        .expect("Can't create temporary directory.");
+    let corpus = tmp_dir.path().join("corpus.txt");
+    let mut rng = Rng::new(42);
+    let zipf = Zipf::new(types, s);
+    write_corpus(&corpus, tokens, &zipf, &mut rng);
+    let corpus = corpus.to_str().expect("Non-UTF-8 temporary directory");
+    let token_counts = tmp_dir.path().join("tokens.tsv");
+    let token_counts = token_counts.to_str().unwrap();
+    let ngram_counts = tmp_dir.path().join("ngrams.tsv");
+    let ngram_counts = ngram_counts.to_str().unwrap();
+
+    let mut vocab = HashMap::new();
+    for _ in 0..tokens {
+        *vocab.entry(token(zipf.sample(&mut rng))).or_insert(0) += 1;
+    }
+    let vocab = vocab.into_iter().collect::<Vec<_>>();
+
+    let mut results = Vec::new();
+    results.push((
+        "count_tokens",
+        time(iters, || run_binary(&["-c", corpus, "-t", token_counts])),
+    ));
+    results.push((
+        "count_ngrams",
+        time(iters, || {
+            run_binary(&["-c", corpus, "-t", token_counts, "-n", ngram_counts])
+        }),
+    ));
+    results.push((
+        "ngrams",
+        time(iters, || {
+            let n = vocab
+                .iter()
+                .map(|(token, _)| NGrams::new(token, 3, 6).count())
+                .sum::<usize>();
+            assert_ne!(n, 0);
+        }),
+    ));
+    results.push((
+        "sort",
+        time(iters, || {
+            let mut items = vocab.clone();
+            items.sort_unstable_by(|(i1, c1), (i2, c2)| count_order(i1, *c1, i2, *c2));
+        }),
+    ));
+    results.push((
+        "external_sort",
+        time(iters, || {
+            let sort = ExternalSort::new((vocab.len() / 8).max(1), tmp_dir.path());
+            sort.for_each_sorted(vocab.clone(), 1, |_, _| Ok(()))
+                .expect("Can't sort.");
+        }),
+    ));
+
+    let mut regressions = 0;
+    for (name, (median, min)) in results {
+        match baseline.as_ref().and_then(|b| b.get(name)) {
+            Some(&base) => {
+                let ratio = median / base;
+                if ratio > 1. + tolerance {
+                    regressions += 1;
+                }
+                println!("{}\t{:.2}\t{:.2}\t{:.3}", name, median, min, ratio);
+            }
+            None => println!("{}\t{:.2}\t{:.2}", name, median, min),
+        }
+    }
+    if regressions != 0 {
+        eprintln!("{} benchmarks regressed", regressions);
+        process::exit(1);
+    }
+}
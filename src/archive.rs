@@ -0,0 +1,233 @@
+//! Member-by-member reading of tar and zip archives.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+use crate::gzip::DeflateDecoder;
+
+/// Kinds of supported archives.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArchiveKind {
+    Tar,
+    Zip,
+}
+
+impl ArchiveKind {
+    /// Determine the archive kind from a file name.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let path = path.to_lowercase();
+        if path.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if path.ends_with(".tar") || path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+            Some(ArchiveKind::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+const BLOCK: usize = 512;
+
+/// Call `f` for every regular file in a tar archive.
+///
+/// The reader has to be decompressed already.
+pub fn for_each_tar_member<R, F>(mut reader: R, mut f: F) -> io::Result<()>
+where
+    R: BufRead,
+    F: FnMut(&str, &mut dyn BufRead) -> io::Result<()>,
+{
+    let mut header = [0u8; BLOCK];
+    let mut long_name = None;
+    loop {
+        if !read_block(&mut reader, &mut header)? || header.iter().all(|&b| b == 0) {
+            return Ok(());
+        }
+        let size = tar_size(&header[124..136])?;
+        let padded = size.div_ceil(BLOCK as u64) * BLOCK as u64;
+        let mut data = (&mut reader).take(padded);
+        match header[156] {
+            b'0' | 0 | b'7' => {
+                let name = long_name.take().unwrap_or_else(|| tar_name(&header));
+                let name = name.trim_start_matches("./");
+                f(name, &mut (&mut data).take(size))?;
+            }
+            // GNU long name of the following member.
+            b'L' => {
+                let mut name = Vec::new();
+                (&mut data).take(size).read_to_end(&mut name)?;
+                long_name = Some(c_str(&name));
+            }
+            // PAX extended header, only the path is used.
+            b'x' => {
+                let mut records = Vec::new();
+                (&mut data).take(size).read_to_end(&mut records)?;
+                long_name = pax_path(&records).or(long_name);
+            }
+            _ => (),
+        }
+        io::copy(&mut data, &mut io::sink())?;
+    }
+}
+
+fn read_block(reader: &mut impl Read, block: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < block.len() {
+        match reader.read(&mut block[filled..])? {
+            0 if filled == 0 => return Ok(false),
+            0 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated tar header",
+                ))
+            }
+            n => filled += n,
+        }
+    }
+    Ok(true)
+}
+
+fn tar_size(field: &[u8]) -> io::Result<u64> {
+    // GNU base-256 encoding for large files.
+    if field[0] & 0x80 != 0 {
+        return Ok(field[1..]
+            .iter()
+            .fold(u64::from(field[0] & 0x7f), |acc, &b| {
+                (acc << 8) | u64::from(b)
+            }));
+    }
+    let octal = c_str(field);
+    let octal = octal.trim();
+    if octal.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(octal, 8)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid tar member size"))
+}
+
+fn tar_name(header: &[u8]) -> String {
+    let name = c_str(&header[..100]);
+    let prefix = if &header[257..262] == b"ustar" {
+        c_str(&header[345..500])
+    } else {
+        String::new()
+    };
+    if prefix.is_empty() {
+        name
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+fn pax_path(records: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(records).lines().find_map(|record| {
+        let (_, kv) = record.split_at(record.find(' ')? + 1);
+        kv.strip_prefix("path=").map(ToOwned::to_owned)
+    })
+}
+
+fn c_str(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+
+struct ZipEntry {
+    name: String,
+    method: u16,
+    compressed_size: u64,
+    offset: u64,
+}
+
+/// Call `f` for every file in a zip archive.
+///
+/// Stored and deflated members are supported.
+pub fn for_each_zip_member<F>(file: File, mut f: F) -> io::Result<()>
+where
+    F: FnMut(&str, &mut dyn BufRead) -> io::Result<()>,
+{
+    let mut reader = BufReader::new(file);
+    for entry in zip_entries(&mut reader)? {
+        if entry.name.ends_with('/') {
+            continue;
+        }
+        reader.seek(SeekFrom::Start(entry.offset))?;
+        let mut local = [0u8; 30];
+        reader.read_exact(&mut local)?;
+        if le32(&local[0..]) != LOCAL_SIGNATURE {
+            return Err(invalid_zip("invalid local file header"));
+        }
+        let skip = u64::from(le16(&local[26..])) + u64::from(le16(&local[28..]));
+        reader.seek(SeekFrom::Current(skip as i64))?;
+        let mut data = (&mut reader).take(entry.compressed_size);
+        match entry.method {
+            0 => f(&entry.name, &mut data)?,
+            8 => f(
+                &entry.name,
+                &mut BufReader::new(DeflateDecoder::new(&mut data)),
+            )?,
+            method => {
+                return Err(invalid_zip(&format!(
+                    "unsupported compression method {} for {}",
+                    method, entry.name
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+fn zip_entries(reader: &mut BufReader<File>) -> io::Result<Vec<ZipEntry>> {
+    // The end of central directory record is followed by a comment of at
+    // most 64KiB.
+    let len = reader.seek(SeekFrom::End(0))?;
+    let tail_len = len.min(22 + 0xffff);
+    reader.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = Vec::with_capacity(tail_len as usize);
+    reader.read_to_end(&mut tail)?;
+    let eocd = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&idx| le32(&tail[idx..]) == EOCD_SIGNATURE)
+        .ok_or_else(|| invalid_zip("missing end of central directory"))?;
+    let eocd = &tail[eocd..];
+    let n_entries = le16(&eocd[10..]);
+    let offset = le32(&eocd[16..]);
+    if n_entries == 0xffff || offset == 0xffff_ffff {
+        return Err(invalid_zip("zip64 archives are not supported"));
+    }
+
+    reader.seek(SeekFrom::Start(u64::from(offset)))?;
+    let mut entries = Vec::with_capacity(n_entries as usize);
+    for _ in 0..n_entries {
+        let mut header = [0u8; 46];
+        reader.read_exact(&mut header)?;
+        if le32(&header) != CENTRAL_SIGNATURE {
+            return Err(invalid_zip("invalid central directory entry"));
+        }
+        let mut name = vec![0u8; le16(&header[28..]) as usize];
+        reader.read_exact(&mut name)?;
+        let skip = i64::from(le16(&header[30..])) + i64::from(le16(&header[32..]));
+        reader.seek(SeekFrom::Current(skip))?;
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(&name).into_owned(),
+            method: le16(&header[10..]),
+            compressed_size: u64::from(le32(&header[20..])),
+            offset: u64::from(le32(&header[42..])),
+        });
+    }
+    Ok(entries)
+}
+
+fn le16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn le32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn invalid_zip(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
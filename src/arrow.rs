@@ -0,0 +1,344 @@
+//! Minimal Arrow IPC stream writer for count tables.
+//!
+//! Tables are written in the IPC streaming format: a schema message,
+//! record batch messages and the end-of-stream marker. The flatbuffer
+//! metadata is laid out front to back, every object is written after the
+//! object that refers to it and the offsets are patched once the position
+//! of the object is known.
+
+use std::io::{self, Write};
+
+/// Number of rows per record batch.
+const BATCH_ROWS: usize = 64 * 1024;
+
+/// Metadata version V5.
+const METADATA_VERSION: i16 = 4;
+
+/// `MessageHeader` union members.
+const HEADER_SCHEMA: u8 = 1;
+const HEADER_RECORD_BATCH: u8 = 3;
+
+/// `Type` union members.
+const TYPE_INT: u8 = 2;
+const TYPE_FLOATING_POINT: u8 = 3;
+const TYPE_UTF8: u8 = 5;
+
+/// `Precision` of doubles.
+const PRECISION_DOUBLE: i16 = 2;
+
+/// Field of a flatbuffer table.
+#[derive(Clone, Copy)]
+enum Slot {
+    Absent,
+    U8(u8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    /// Offset to an object that is written later.
+    Offset,
+}
+
+impl Slot {
+    fn size(self) -> usize {
+        match self {
+            Slot::Absent => 0,
+            Slot::U8(_) => 1,
+            Slot::I16(_) => 2,
+            Slot::I32(_) | Slot::Offset => 4,
+            Slot::I64(_) => 8,
+        }
+    }
+}
+
+/// Front-to-back flatbuffer builder.
+struct FlatBuilder {
+    buf: Vec<u8>,
+}
+
+impl FlatBuilder {
+    /// Start a buffer, the root offset is at position 0.
+    fn new() -> Self {
+        FlatBuilder { buf: vec![0; 4] }
+    }
+
+    fn pad_to(&mut self, align: usize, extra: usize) {
+        while !(self.buf.len() + extra).is_multiple_of(align) {
+            self.buf.push(0);
+        }
+    }
+
+    /// Point the offset at `at` to `target`.
+    fn patch(&mut self, at: usize, target: usize) {
+        let offset = (target - at) as u32;
+        self.buf[at..at + 4].copy_from_slice(&offset.to_le_bytes());
+    }
+
+    /// Write a table, returns the table position and the positions of its
+    /// offset slots by field id.
+    fn table(&mut self, slots: &[Slot]) -> (usize, Vec<usize>) {
+        // Lay out the fields by decreasing size after the vtable offset.
+        let mut order = (0..slots.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&id| std::cmp::Reverse(slots[id].size()));
+        let mut field_pos = vec![0; slots.len()];
+        let mut size = 4;
+        for &id in &order {
+            let field_size = slots[id].size();
+            if field_size == 0 {
+                continue;
+            }
+            size += (field_size - size % field_size) % field_size;
+            field_pos[id] = size;
+            size += field_size;
+        }
+
+        self.pad_to(2, 0);
+        let vtable = self.buf.len();
+        self.buf
+            .extend_from_slice(&((4 + 2 * slots.len()) as u16).to_le_bytes());
+        self.buf.extend_from_slice(&(size as u16).to_le_bytes());
+        for &pos in &field_pos {
+            self.buf.extend_from_slice(&(pos as u16).to_le_bytes());
+        }
+
+        self.pad_to(8, 0);
+        let table = self.buf.len();
+        self.buf.resize(table + size, 0);
+        self.buf[table..table + 4].copy_from_slice(&((table - vtable) as i32).to_le_bytes());
+        let mut offsets = vec![0; slots.len()];
+        for (id, slot) in slots.iter().enumerate() {
+            let pos = table + field_pos[id];
+            match *slot {
+                Slot::Absent => (),
+                Slot::U8(v) => self.buf[pos] = v,
+                Slot::I16(v) => self.buf[pos..pos + 2].copy_from_slice(&v.to_le_bytes()),
+                Slot::I32(v) => self.buf[pos..pos + 4].copy_from_slice(&v.to_le_bytes()),
+                Slot::I64(v) => self.buf[pos..pos + 8].copy_from_slice(&v.to_le_bytes()),
+                Slot::Offset => offsets[id] = pos,
+            }
+        }
+        (table, offsets)
+    }
+
+    /// Write a vector of `len` offsets, returns the vector position and
+    /// the positions of the offsets.
+    fn offset_vector(&mut self, len: usize) -> (usize, Vec<usize>) {
+        self.pad_to(4, 0);
+        let vector = self.buf.len();
+        self.buf.extend_from_slice(&(len as u32).to_le_bytes());
+        let offsets = (0..len).map(|idx| vector + 4 + 4 * idx).collect();
+        self.buf.resize(vector + 4 + 4 * len, 0);
+        (vector, offsets)
+    }
+
+    /// Write a vector of structs of two longs.
+    fn struct_vector(&mut self, structs: &[(i64, i64)]) -> usize {
+        self.pad_to(8, 4);
+        let vector = self.buf.len();
+        self.buf
+            .extend_from_slice(&(structs.len() as u32).to_le_bytes());
+        for &(a, b) in structs {
+            self.buf.extend_from_slice(&a.to_le_bytes());
+            self.buf.extend_from_slice(&b.to_le_bytes());
+        }
+        vector
+    }
+
+    fn string(&mut self, s: &str) -> usize {
+        self.pad_to(4, 0);
+        let string = self.buf.len();
+        self.buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        self.buf.extend_from_slice(s.as_bytes());
+        self.buf.push(0);
+        string
+    }
+
+    /// Get the buffer padded to a multiple of 8 bytes.
+    fn finish(mut self) -> Vec<u8> {
+        self.pad_to(8, 0);
+        self.buf
+    }
+}
+
+/// Build a message with the given header table.
+///
+/// `header` writes the header table and returns its position.
+fn message(
+    header_type: u8,
+    body_len: usize,
+    header: impl FnOnce(&mut FlatBuilder) -> usize,
+) -> Vec<u8> {
+    let mut builder = FlatBuilder::new();
+    let (message, offsets) = builder.table(&[
+        Slot::I16(METADATA_VERSION),
+        Slot::U8(header_type),
+        Slot::Offset,
+        Slot::I64(body_len as i64),
+    ]);
+    builder.patch(0, message);
+    let header = header(&mut builder);
+    builder.patch(offsets[2], header);
+    builder.finish()
+}
+
+/// Arrow type of a column.
+#[derive(Clone, Copy)]
+enum ColumnType {
+    Utf8,
+    UInt64,
+    Float64,
+}
+
+fn schema_message(columns: &[(&str, ColumnType)]) -> Vec<u8> {
+    message(HEADER_SCHEMA, 0, |builder| {
+        let (schema, offsets) = builder.table(&[Slot::Absent, Slot::Offset]);
+        let (fields, field_offsets) = builder.offset_vector(columns.len());
+        builder.patch(offsets[1], fields);
+        for (&(name, column_type), &at) in columns.iter().zip(&field_offsets) {
+            let type_id = match column_type {
+                ColumnType::Utf8 => TYPE_UTF8,
+                ColumnType::UInt64 => TYPE_INT,
+                ColumnType::Float64 => TYPE_FLOATING_POINT,
+            };
+            let (field, offsets) = builder.table(&[
+                Slot::Offset,
+                Slot::U8(0),
+                Slot::U8(type_id),
+                Slot::Offset,
+                Slot::Absent,
+                Slot::Offset,
+            ]);
+            builder.patch(at, field);
+            let name = builder.string(name);
+            builder.patch(offsets[0], name);
+            let (type_table, _) = match column_type {
+                ColumnType::Utf8 => builder.table(&[]),
+                ColumnType::UInt64 => builder.table(&[Slot::I32(64), Slot::U8(0)]),
+                ColumnType::Float64 => builder.table(&[Slot::I16(PRECISION_DOUBLE)]),
+            };
+            builder.patch(offsets[3], type_table);
+            let (children, _) = builder.offset_vector(0);
+            builder.patch(offsets[5], children);
+        }
+        schema
+    })
+}
+
+fn record_batch_message(
+    rows: usize,
+    nodes: &[(i64, i64)],
+    buffers: &[(i64, i64)],
+    body_len: usize,
+) -> Vec<u8> {
+    message(HEADER_RECORD_BATCH, body_len, |builder| {
+        let (batch, offsets) = builder.table(&[Slot::I64(rows as i64), Slot::Offset, Slot::Offset]);
+        let nodes = builder.struct_vector(nodes);
+        builder.patch(offsets[1], nodes);
+        let buffers = builder.struct_vector(buffers);
+        builder.patch(offsets[2], buffers);
+        batch
+    })
+}
+
+/// Writer of `key, count` tables in the Arrow IPC streaming format.
+///
+/// Tables can have additional `f64` columns, e.g. for normalized
+/// frequencies.
+pub struct ArrowCountWriter<W> {
+    writer: W,
+    n_values: usize,
+    offsets: Vec<i32>,
+    keys: Vec<u8>,
+    counts: Vec<u64>,
+    values: Vec<Vec<f64>>,
+}
+
+impl<W: Write> ArrowCountWriter<W> {
+    /// Start a table with a `key` string column, a `count` column and
+    /// `f64` columns with the names in `values`.
+    pub fn new(mut writer: W, key: &str, values: &[&str]) -> io::Result<Self> {
+        let mut columns = vec![(key, ColumnType::Utf8), ("count", ColumnType::UInt64)];
+        columns.extend(values.iter().map(|&name| (name, ColumnType::Float64)));
+        write_message(&mut writer, &schema_message(&columns), &[])?;
+        Ok(ArrowCountWriter {
+            writer,
+            n_values: values.len(),
+            offsets: vec![0],
+            keys: Vec::new(),
+            counts: Vec::new(),
+            values: vec![Vec::new(); values.len()],
+        })
+    }
+
+    /// Add a row, `values` are the values of the `f64` columns.
+    pub fn write(&mut self, key: &str, count: usize, values: &[f64]) -> io::Result<()> {
+        assert_eq!(values.len(), self.n_values, "Wrong number of values.");
+        self.keys.extend_from_slice(key.as_bytes());
+        if self.keys.len() > i32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Keys of a record batch exceed 2GiB",
+            ));
+        }
+        self.offsets.push(self.keys.len() as i32);
+        self.counts.push(count as u64);
+        for (column, &value) in self.values.iter_mut().zip(values) {
+            column.push(value);
+        }
+        if self.counts.len() == BATCH_ROWS {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    fn write_batch(&mut self) -> io::Result<()> {
+        let rows = self.counts.len();
+        let mut body = Vec::new();
+        let mut buffers = Vec::new();
+        let mut add_buffer = |body: &mut Vec<u8>, data: &[u8]| {
+            buffers.push((body.len() as i64, data.len() as i64));
+            body.extend_from_slice(data);
+            body.resize(body.len().div_ceil(8) * 8, 0);
+        };
+        // Columns have no nulls, so the validity buffers are empty.
+        add_buffer(&mut body, &[]);
+        add_buffer(&mut body, &le_bytes(&self.offsets, |v| v.to_le_bytes()));
+        add_buffer(&mut body, &self.keys);
+        add_buffer(&mut body, &[]);
+        add_buffer(&mut body, &le_bytes(&self.counts, |v| v.to_le_bytes()));
+        for column in &self.values {
+            add_buffer(&mut body, &[]);
+            add_buffer(&mut body, &le_bytes(column, |v| v.to_le_bytes()));
+        }
+        let nodes = vec![(rows as i64, 0); 2 + self.n_values];
+        let metadata = record_batch_message(rows, &nodes, &buffers, body.len());
+        write_message(&mut self.writer, &metadata, &body)?;
+
+        self.offsets.truncate(1);
+        self.keys.clear();
+        self.counts.clear();
+        self.values.iter_mut().for_each(Vec::clear);
+        Ok(())
+    }
+
+    /// Write the remaining rows and the end of the stream.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.counts.is_empty() {
+            self.write_batch()?;
+        }
+        self.writer.write_all(&u32::MAX.to_le_bytes())?;
+        self.writer.write_all(&0u32.to_le_bytes())?;
+        Ok(self.writer)
+    }
+}
+
+fn le_bytes<T: Copy, const N: usize>(values: &[T], to_bytes: impl Fn(T) -> [u8; N]) -> Vec<u8> {
+    values.iter().flat_map(|&v| to_bytes(v)).collect()
+}
+
+/// Write an encapsulated message.
+fn write_message(mut writer: impl Write, metadata: &[u8], body: &[u8]) -> io::Result<()> {
+    writer.write_all(&u32::MAX.to_le_bytes())?;
+    writer.write_all(&(metadata.len() as u32).to_le_bytes())?;
+    writer.write_all(metadata)?;
+    writer.write_all(body)
+}
@@ -0,0 +1,203 @@
+//! Frequency audits of term lists.
+//!
+//! Before frequency data is released, the occurrences of sensitive terms,
+//! such as slurs or personal data, are reported with example contexts.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::regex::Regex;
+
+/// Term of an audit list.
+#[derive(Clone, Debug)]
+enum Term {
+    /// Sequence of tokens.
+    Phrase(Vec<String>),
+    /// Regular expression matching whole tokens.
+    Pattern(Regex),
+}
+
+/// Occurrences of a term.
+#[derive(Clone, Debug, Default)]
+pub struct AuditCount {
+    pub count: usize,
+    /// Contexts of the first occurrences by corpus position.
+    pub examples: Vec<AuditExample>,
+}
+
+impl AuditCount {
+    /// Add the occurrences of `other`, examples stay ordered by position.
+    pub fn merge(&mut self, other: AuditCount) {
+        self.count += other.count;
+        self.examples.extend(other.examples);
+        self.examples
+            .sort_by(|ex1, ex2| (&ex1.file, ex1.line).cmp(&(&ex2.file, ex2.line)));
+    }
+}
+
+/// Context of an occurrence.
+#[derive(Clone, Debug)]
+pub struct AuditExample {
+    pub file: String,
+    /// Line number in the file, starting at 1.
+    pub line: usize,
+    pub context: String,
+}
+
+/// List of terms to audit.
+#[derive(Clone, Debug)]
+pub struct AuditTerms {
+    names: Vec<String>,
+    terms: Vec<Term>,
+    /// Phrases by their first token.
+    phrases: HashMap<String, Vec<usize>>,
+    patterns: Vec<usize>,
+    examples: usize,
+    context: usize,
+}
+
+impl AuditTerms {
+    /// Read a term list with one term per line.
+    ///
+    /// Terms are tokens or phrases of whitespace-separated tokens. Terms
+    /// enclosed in slashes are regular expressions matching whole tokens.
+    /// Up to `examples` contexts of `context` tokens on either side are
+    /// kept per term.
+    pub fn read(reader: impl BufRead, examples: usize, context: usize) -> io::Result<Self> {
+        let mut audit = AuditTerms {
+            names: Vec::new(),
+            terms: Vec::new(),
+            phrases: HashMap::new(),
+            patterns: Vec::new(),
+            examples,
+            context,
+        };
+        for line in reader.lines() {
+            let line = line?;
+            let name = line.trim();
+            if name.is_empty() {
+                continue;
+            }
+            let idx = audit.terms.len();
+            match name
+                .strip_prefix('/')
+                .and_then(|name| name.strip_suffix('/'))
+            {
+                Some(pattern) if !pattern.is_empty() => {
+                    let regex = Regex::new(pattern)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    audit.terms.push(Term::Pattern(regex));
+                    audit.patterns.push(idx);
+                }
+                _ => {
+                    let tokens = name
+                        .split_whitespace()
+                        .map(ToOwned::to_owned)
+                        .collect::<Vec<_>>();
+                    audit
+                        .phrases
+                        .entry(tokens[0].clone())
+                        .or_default()
+                        .push(idx);
+                    audit.terms.push(Term::Phrase(tokens));
+                }
+            }
+            audit.names.push(name.to_owned());
+        }
+        Ok(audit)
+    }
+
+    /// Count the terms in the tokens of line `line` of `file`.
+    ///
+    /// The examples are the contexts of the occurrences with the lowest
+    /// `(file, line)` positions, independent of the order files are read in.
+    pub fn count<S>(
+        &self,
+        tokens: &[S],
+        file: &str,
+        line: usize,
+        counts: &mut HashMap<usize, AuditCount>,
+    ) where
+        S: AsRef<str>,
+    {
+        for (start, token) in tokens.iter().enumerate() {
+            let token = token.as_ref();
+            let phrases = self.phrases.get(token).into_iter().flatten();
+            for &idx in phrases.chain(&self.patterns) {
+                let len = match &self.terms[idx] {
+                    Term::Phrase(phrase) => {
+                        let matches = phrase.len() <= tokens.len() - start
+                            && phrase
+                                .iter()
+                                .zip(&tokens[start..])
+                                .all(|(term, token)| term == token.as_ref());
+                        if !matches {
+                            continue;
+                        }
+                        phrase.len()
+                    }
+                    Term::Pattern(regex) if regex.is_full_match(token) => 1,
+                    Term::Pattern(_) => continue,
+                };
+                let audit_count = counts.entry(idx).or_default();
+                audit_count.count += 1;
+                let examples = &mut audit_count.examples;
+                let pos =
+                    examples.partition_point(|ex| (ex.file.as_str(), ex.line) <= (file, line));
+                if pos < self.examples {
+                    examples.insert(
+                        pos,
+                        AuditExample {
+                            file: file.to_owned(),
+                            line,
+                            context: self.example(tokens, start, start + len),
+                        },
+                    );
+                    examples.truncate(self.examples);
+                }
+            }
+        }
+    }
+
+    /// Format the context of an occurrence, the term is put in brackets.
+    fn example<S>(&self, tokens: &[S], start: usize, end: usize) -> String
+    where
+        S: AsRef<str>,
+    {
+        let join = |tokens: &[S]| {
+            tokens
+                .iter()
+                .map(AsRef::as_ref)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let left = join(&tokens[start.saturating_sub(self.context)..start]);
+        let right = join(&tokens[end..(end + self.context).min(tokens.len())]);
+        format!("{} [{}] {}", left, join(&tokens[start..end]), right)
+            .trim()
+            .to_owned()
+    }
+
+    /// Write `term<TAB>count<TAB>examples...` lines in the order of the
+    /// term list, terms that do not occur are reported with count 0.
+    pub fn write_report(
+        &self,
+        mut writer: impl Write,
+        counts: &HashMap<usize, AuditCount>,
+    ) -> io::Result<()> {
+        for (idx, name) in self.names.iter().enumerate() {
+            write!(writer, "{}", name)?;
+            match counts.get(&idx) {
+                Some(audit_count) => {
+                    write!(writer, "\t{}", audit_count.count)?;
+                    for example in audit_count.examples.iter().take(self.examples) {
+                        write!(writer, "\t{}", example.context)?;
+                    }
+                }
+                None => write!(writer, "\t0")?,
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+//! Frequency bins of tokens.
+//!
+//! Bins are used by frequency-bucketed embeddings and adaptive softmax,
+//! which assign the parameters of tokens by their frequency.
+
+/// Get the log-frequency bin of a count.
+///
+/// Bin `k` holds the counts in `(max_count / base^(k+1), max_count / base^k]`,
+/// so the most frequent tokens are in bin 0 and every bin spans a factor of
+/// `base`. Counts of zero are in the bin of count 1.
+pub fn log_freq_bin(count: usize, max_count: usize, base: usize) -> usize {
+    assert!(base > 1, "The bin base should be greater than 1.");
+    let max_count = max_count as u128;
+    let mut bin = 0;
+    let mut scaled = count.max(1) as u128 * base as u128;
+    while scaled <= max_count {
+        bin += 1;
+        scaled *= base as u128;
+    }
+    bin
+}
+
+/// Get adaptive softmax cutoffs that balance the token mass of clusters.
+///
+/// `counts` are sorted by descending count. Returns the ranks at which the
+/// clusters after the first start, so that every cluster covers about
+/// `1 / clusters` of the total count and has at least one token. There are
+/// fewer cutoffs if there are fewer tokens than clusters.
+pub fn balanced_cutoffs(counts: &[usize], clusters: usize) -> Vec<usize> {
+    assert_ne!(clusters, 0, "The number of clusters cannot be zero.");
+    let clusters = clusters.min(counts.len()).max(1);
+    let total = counts.iter().map(|&count| count as u128).sum::<u128>();
+    let mut cutoffs = Vec::with_capacity(clusters - 1);
+    let mut mass = 0u128;
+    let mut rank = 0;
+    for cluster in 1..clusters {
+        let min_rank = cutoffs.last().map_or(1, |&cutoff| cutoff + 1);
+        // The remaining clusters need a token each.
+        let max_rank = counts.len() - (clusters - cluster);
+        while rank < max_rank
+            && (rank < min_rank || mass * (clusters as u128) < cluster as u128 * total)
+        {
+            mass += counts[rank] as u128;
+            rank += 1;
+        }
+        cutoffs.push(rank);
+    }
+    cutoffs
+}
@@ -0,0 +1,160 @@
+//! Bloom filters of vocabularies.
+//!
+//! A Bloom filter tests whether a token is in the vocabulary with a small
+//! rate of false positives and no false negatives, in a fraction of the
+//! memory of the vocabulary.
+//!
+//! Tokens are hashed with the 64-bit FNV-1a hash `h1` of their UTF-8
+//! bytes and `h2 = splitmix64(h1) | 1`, where `splitmix64` is the output
+//! of SplitMix64 after the state `h1`. The `i`-th of `k` bits of a token is
+//! `(h1 + i * h2) mod m` with wrapping 64-bit arithmetic.
+//!
+//! The serialized filter is the magic bytes `CCBF`, the number of hashes
+//! `k` as a little-endian 32-bit integer, the number of bits `m` and the
+//! number of inserted tokens as little-endian 64-bit integers, followed by
+//! `ceil(m / 64)` little-endian 64-bit words. Bit `j` is bit `j mod 64` of
+//! word `j / 64`.
+
+use std::io::{self, Read, Write};
+
+use crate::vocab::invalid_data;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+const MAGIC: &[u8; 4] = b"CCBF";
+
+/// A Bloom filter of tokens.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BloomFilter {
+    words: Vec<u64>,
+    n_bits: u64,
+    n_hashes: u32,
+    len: u64,
+}
+
+impl BloomFilter {
+    /// Create an empty filter of `n_bits` bits and `n_hashes` hashes.
+    pub fn new(n_bits: u64, n_hashes: u32) -> Self {
+        assert_ne!(n_bits, 0, "The number of bits cannot be zero.");
+        assert_ne!(n_hashes, 0, "The number of hashes cannot be zero.");
+        BloomFilter {
+            words: vec![0; n_bits.div_ceil(64) as usize],
+            n_bits,
+            n_hashes,
+            len: 0,
+        }
+    }
+
+    /// Create an empty filter for `n_items` tokens with the false positive
+    /// rate `fpr`.
+    ///
+    /// Uses the optimal `m = -n ln(p) / ln(2)^2` bits and `k = m / n ln(2)`
+    /// hashes.
+    pub fn with_fpr(n_items: usize, fpr: f64) -> Self {
+        assert!(
+            fpr > 0. && fpr < 1.,
+            "The false positive rate should be between 0 and 1."
+        );
+        let n_items = n_items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let n_bits = (-n_items * fpr.ln() / (ln2 * ln2)).ceil().max(1.);
+        let n_hashes = (n_bits / n_items * ln2).round().max(1.);
+        Self::new(n_bits as u64, n_hashes as u32)
+    }
+
+    /// Insert a token.
+    pub fn insert(&mut self, token: &str) {
+        for bit in bits(token, self.n_bits, self.n_hashes) {
+            self.words[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+        self.len += 1;
+    }
+
+    /// Check whether a token may have been inserted.
+    pub fn contains(&self, token: &str) -> bool {
+        bits(token, self.n_bits, self.n_hashes)
+            .all(|bit| self.words[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Get the number of inserted tokens.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Check whether no tokens were inserted.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the number of bits.
+    pub fn n_bits(&self) -> u64 {
+        self.n_bits
+    }
+
+    /// Get the number of hashes per token.
+    pub fn n_hashes(&self) -> u32 {
+        self.n_hashes
+    }
+
+    /// Read a serialized filter.
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("Not a Bloom filter".to_owned()));
+        }
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf)?;
+        let n_hashes = u32::from_le_bytes(buf);
+        let mut buf = [0; 8];
+        reader.read_exact(&mut buf)?;
+        let n_bits = u64::from_le_bytes(buf);
+        reader.read_exact(&mut buf)?;
+        let len = u64::from_le_bytes(buf);
+        if n_bits == 0 || n_hashes == 0 {
+            return Err(invalid_data("Empty Bloom filter".to_owned()));
+        }
+        let words = (0..n_bits.div_ceil(64))
+            .map(|_| {
+                reader.read_exact(&mut buf)?;
+                Ok(u64::from_le_bytes(buf))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(BloomFilter {
+            words,
+            n_bits,
+            n_hashes,
+            len,
+        })
+    }
+
+    /// Write the serialized filter.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&self.n_hashes.to_le_bytes())?;
+        writer.write_all(&self.n_bits.to_le_bytes())?;
+        writer.write_all(&self.len.to_le_bytes())?;
+        for word in &self.words {
+            writer.write_all(&word.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+}
+
+/// Get the bits of a token.
+fn bits(token: &str, n_bits: u64, n_hashes: u32) -> impl Iterator<Item = u64> {
+    let h1 = token.bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    let h2 = splitmix64(h1) | 1;
+    (0..u64::from(n_hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % n_bits)
+}
+
+/// Get the output of SplitMix64 after the state `z`.
+fn splitmix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,66 @@
+//! Boilerplate word n-grams.
+//!
+//! Navigation text, cookie notices and license headers repeat the same
+//! long word n-grams across many documents. N-grams are identified by
+//! 64-bit hashes of their tokens, so that the n-grams of a large corpus
+//! can be counted without storing their text.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead};
+
+/// Get the hash of a word n-gram.
+pub fn ngram_hash<S>(tokens: &[S]) -> u64
+where
+    S: AsRef<str>,
+{
+    let mut hasher = DefaultHasher::new();
+    for token in tokens {
+        token.as_ref().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Set of boilerplate n-grams.
+#[derive(Clone, Debug, Default)]
+pub struct Boilerplate {
+    lengths: BTreeSet<usize>,
+    hashes: HashSet<u64>,
+}
+
+impl Boilerplate {
+    /// Read boilerplate n-grams from `ngram<TAB>documents` lines.
+    ///
+    /// The tokens of the n-grams are separated by spaces.
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        let mut boilerplate = Boilerplate::default();
+        for line in reader.lines() {
+            let line = line?;
+            let ngram = line.split('\t').next().unwrap_or_default();
+            let tokens = ngram.split_whitespace().collect::<Vec<_>>();
+            if tokens.is_empty() {
+                continue;
+            }
+            boilerplate.lengths.insert(tokens.len());
+            boilerplate.hashes.insert(ngram_hash(&tokens));
+        }
+        Ok(boilerplate)
+    }
+
+    /// Get a mask of the tokens that are covered by boilerplate n-grams.
+    pub fn mask<S>(&self, tokens: &[S]) -> Vec<bool>
+    where
+        S: AsRef<str>,
+    {
+        let mut mask = vec![false; tokens.len()];
+        for &n in &self.lengths {
+            for (start, ngram) in tokens.windows(n).enumerate() {
+                if self.hashes.contains(&ngram_hash(ngram)) {
+                    mask[start..start + n].iter_mut().for_each(|m| *m = true);
+                }
+            }
+        }
+        mask
+    }
+}
@@ -0,0 +1,172 @@
+//! Character n-gram language models.
+//!
+//! The model estimates the probability of the characters of a token from
+//! character n-gram counts with Witten-Bell interpolation. Tokens that are
+//! improbable under the model of a corpus, such as OCR errors and markup
+//! remnants, can be filtered by their per-character log-probability.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ngrams::NGrams;
+
+/// Character n-gram language model.
+#[derive(Clone, Debug)]
+pub struct CharLm {
+    ngrams: HashMap<String, usize>,
+    /// Total counts and the number of distinct continuations of histories.
+    histories: HashMap<String, (usize, usize)>,
+    min_n: usize,
+    max_n: usize,
+    bracket: bool,
+    /// Number of distinct characters, plus one for unknown characters.
+    n_chars: usize,
+}
+
+impl CharLm {
+    /// Estimate a model from n-gram counts of lengths `min_n` to `max_n`.
+    ///
+    /// Counts of other lengths are ignored. N-grams should be extracted
+    /// from tokens bracketed with `<` and `>` iff `bracket` is set.
+    /// Characters are drawn uniformly when the history of a character is
+    /// too short for the shortest n-grams.
+    pub fn from_ngram_counts<S>(
+        counts: impl IntoIterator<Item = (S, usize)>,
+        min_n: usize,
+        max_n: usize,
+        bracket: bool,
+    ) -> Self
+    where
+        S: AsRef<str>,
+    {
+        assert_ne!(min_n, 0, "The minimum n-gram length cannot be zero.");
+        assert!(
+            min_n <= max_n,
+            "The maximum length should be equal to or greater than the minimum length."
+        );
+        let mut ngrams = HashMap::new();
+        let mut histories = HashMap::new();
+        let mut chars = HashSet::new();
+        for (ngram, count) in counts {
+            let ngram = ngram.as_ref();
+            let n = ngram.chars().count();
+            if n < min_n || n > max_n || count == 0 {
+                continue;
+            }
+            chars.extend(ngram.chars());
+            let last = ngram.char_indices().last().map(|(idx, _)| idx).unwrap();
+            let history = histories.entry(ngram[..last].to_owned()).or_insert((0, 0));
+            history.0 += count;
+            let ngram_count = ngrams.entry(ngram.to_owned()).or_insert(0);
+            if *ngram_count == 0 {
+                history.1 += 1;
+            }
+            *ngram_count += count;
+        }
+        CharLm {
+            ngrams,
+            histories,
+            min_n,
+            max_n,
+            bracket,
+            n_chars: chars.len() + 1,
+        }
+    }
+
+    /// Estimate a model of order `order` from token counts.
+    pub fn from_token_counts<'a>(
+        counts: impl IntoIterator<Item = (&'a str, usize)>,
+        order: usize,
+        bracket: bool,
+    ) -> Self {
+        let mut ngrams = HashMap::new();
+        for (token, count) in counts {
+            let token = bracketed(token, bracket);
+            for ngram in NGrams::new(&token, 1, order) {
+                *ngrams.entry(ngram.to_owned()).or_insert(0) += count;
+            }
+        }
+        Self::from_ngram_counts(ngrams, 1, order, bracket)
+    }
+
+    /// Get the natural log-probability of a token.
+    ///
+    /// If the model is bracketed, this includes the probability of the end
+    /// of the token.
+    pub fn log_prob(&self, token: &str) -> f64 {
+        self.log_probs(token, 0).0
+    }
+
+    /// Get the natural log-probability of a token divided by the number
+    /// of predicted characters.
+    ///
+    /// Unlike `log_prob`, this does not penalize long tokens.
+    pub fn log_prob_per_char(&self, token: &str) -> f64 {
+        let (log_prob, n) = self.log_probs(token, 0);
+        log_prob / n.max(1) as f64
+    }
+
+    /// Get the log-probability per character of a token of the counts of
+    /// the model, with one occurrence of the token left out.
+    ///
+    /// N-grams that only occur in hapaxes are not probable then, so rare
+    /// noise does not make itself probable. Every n-gram is assumed to
+    /// occur once in the token.
+    pub fn held_out_log_prob_per_char(&self, token: &str) -> f64 {
+        let (log_prob, n) = self.log_probs(token, 1);
+        log_prob / n.max(1) as f64
+    }
+
+    fn log_probs(&self, token: &str, held_out: usize) -> (f64, usize) {
+        let token = bracketed(token, self.bracket);
+        let bounds = token
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .chain(Some(token.len()))
+            .collect::<Vec<_>>();
+        let n = bounds.len() - 1;
+        // The opening bracket is given.
+        let first = usize::from(self.bracket);
+        let log_prob = (first..n)
+            .map(|idx| {
+                let start = (idx + 1).saturating_sub(self.max_n);
+                self.prob(&token, &bounds[start..=idx + 1], held_out).ln()
+            })
+            .sum();
+        (log_prob, n.saturating_sub(first))
+    }
+
+    /// Get the probability of the last character of the n-gram with the
+    /// character bounds `bounds`, `held_out` is subtracted from the counts.
+    fn prob(&self, token: &str, bounds: &[usize], held_out: usize) -> f64 {
+        let n = bounds.len() - 1;
+        if n < self.min_n {
+            return 1. / self.n_chars as f64;
+        }
+        let lower = self.prob(token, &bounds[1..], held_out);
+        let history = &token[bounds[0]..bounds[n - 1]];
+        match self.histories.get(history) {
+            Some(&(total, distinct)) if total > held_out => {
+                let count = self
+                    .ngrams
+                    .get(&token[bounds[0]..bounds[n]])
+                    .copied()
+                    .unwrap_or(0);
+                let (count, distinct) = match count.saturating_sub(held_out) {
+                    0 if count > 0 => (0, distinct - 1),
+                    count => (count, distinct),
+                };
+                let total = total - held_out;
+                (count as f64 + distinct as f64 * lower) / (total + distinct) as f64
+            }
+            _ => lower,
+        }
+    }
+}
+
+fn bracketed(token: &str, bracket: bool) -> String {
+    if bracket {
+        format!("<{}>", token)
+    } else {
+        token.to_owned()
+    }
+}
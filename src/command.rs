@@ -0,0 +1,152 @@
+//! External tokenizer commands.
+//!
+//! Lines are streamed through a shell command like `mecab -Owakati` that
+//! prints one line of whitespace-separated tokens for every line it reads.
+//! One process is started per input file. Its input is written while its
+//! output is read on a separate thread, so commands that buffer their
+//! output cannot deadlock the counter.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use crate::input::Item;
+
+/// Shell command tokenizing lines.
+#[derive(Clone, Debug)]
+pub struct TokenizerCommand {
+    command: String,
+}
+
+fn command_error(msg: String) -> io::Error {
+    io::Error::other(msg)
+}
+
+impl TokenizerCommand {
+    pub fn new(command: impl Into<String>) -> Self {
+        TokenizerCommand {
+            command: command.into(),
+        }
+    }
+
+    /// Tokenize the lines of `items`.
+    ///
+    /// `f` is called with the tokenized lines and the record ends in input
+    /// order.
+    pub fn tokenize<I, F>(&self, items: I, mut f: F) -> io::Result<()>
+    where
+        I: Iterator<Item = io::Result<Item>>,
+        F: FnMut(Item),
+    {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| command_error(format!("Can't start {}: {}", self.command, err)))?;
+        let stdout = child.stdout.take().expect("Missing tokenizer output");
+        let (sender, receiver) = mpsc::channel();
+        let output = thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Items waiting for their tokenized line, `true` for lines and
+        // `false` for record ends.
+        let mut pending = VecDeque::new();
+        let mut stdin = BufWriter::new(child.stdin.take().expect("Missing tokenizer input"));
+        let mut result = (|| {
+            for item in items {
+                match item? {
+                    Item::Line(line) => {
+                        writeln!(stdin, "{}", line)?;
+                        pending.push_back(true);
+                    }
+                    Item::RecordEnd => pending.push_back(false),
+                }
+                self.drain(&mut pending, &receiver, false, &mut f)?;
+            }
+            stdin.flush()
+        })();
+        // Closing the input lets the command finish.
+        drop(stdin);
+        if result.is_ok() {
+            result = self.drain(&mut pending, &receiver, true, &mut f);
+        }
+        if result.is_err() {
+            match child.try_wait() {
+                Ok(Some(status)) if !status.success() => {
+                    result = Err(command_error(format!(
+                        "{} failed: {}",
+                        self.command, status
+                    )))
+                }
+                _ => {
+                    let _ = child.kill();
+                }
+            }
+        } else if receiver.recv().is_ok() {
+            result = Err(command_error(format!(
+                "{} printed more lines than it read",
+                self.command
+            )));
+        }
+        drop(receiver);
+        let status = child.wait()?;
+        output.join().expect("Tokenizer output thread panicked");
+        result?;
+        if !status.success() {
+            return Err(command_error(format!(
+                "{} failed: {}",
+                self.command, status
+            )));
+        }
+        Ok(())
+    }
+
+    /// Pass the pending items to `f` as long as their lines are tokenized,
+    /// waits for the lines if `block` is set.
+    fn drain(
+        &self,
+        pending: &mut VecDeque<bool>,
+        receiver: &Receiver<io::Result<String>>,
+        block: bool,
+        f: &mut impl FnMut(Item),
+    ) -> io::Result<()> {
+        while let Some(&is_line) = pending.front() {
+            if !is_line {
+                pending.pop_front();
+                f(Item::RecordEnd);
+                continue;
+            }
+            let line = if block {
+                receiver.recv().ok()
+            } else {
+                match receiver.try_recv() {
+                    Ok(line) => Some(line),
+                    Err(TryRecvError::Empty) => return Ok(()),
+                    Err(TryRecvError::Disconnected) => None,
+                }
+            };
+            match line {
+                Some(line) => {
+                    pending.pop_front();
+                    f(Item::Line(line?));
+                }
+                None => {
+                    return Err(command_error(format!(
+                        "{} printed fewer lines than it read",
+                        self.command
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+}
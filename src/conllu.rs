@@ -0,0 +1,90 @@
+//! CoNLL-U reader.
+
+use std::io::{self, BufRead};
+
+/// A token of a CoNLL-U sentence.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Token {
+    /// Position of the token in the sentence, starting at 1.
+    pub id: usize,
+    pub form: String,
+    /// Position of the head, `0` for the root and `None` if unannotated.
+    pub head: Option<usize>,
+    pub deprel: String,
+}
+
+/// Iterator over the sentences of a CoNLL-U file.
+///
+/// Comments, multiword token ranges and empty nodes are skipped.
+pub struct Sentences<R> {
+    reader: R,
+    line: String,
+}
+
+impl<R: BufRead> Sentences<R> {
+    pub fn new(reader: R) -> Self {
+        Sentences {
+            reader,
+            line: String::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Sentences<R> {
+    type Item = io::Result<Vec<Token>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut sentence = Vec::new();
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) if sentence.is_empty() => return None,
+                Ok(0) => return Some(Ok(sentence)),
+                Ok(_) => (),
+                Err(err) => return Some(Err(err)),
+            }
+            let line = self.line.trim_end_matches(&['\n', '\r'][..]);
+            if line.is_empty() {
+                if sentence.is_empty() {
+                    continue;
+                }
+                return Some(Ok(sentence));
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            match parse_token(line) {
+                Ok(Some(token)) => sentence.push(token),
+                Ok(None) => (),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Parse a token line, `None` for multiword tokens and empty nodes.
+fn parse_token(line: &str) -> io::Result<Option<Token>> {
+    let fields = line.split('\t').collect::<Vec<_>>();
+    if fields.len() < 8 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("CoNLL-U token line with less than 8 fields: {}", line),
+        ));
+    }
+    let id = match fields[0].parse() {
+        Ok(id) => id,
+        Err(_) if fields[0].contains(&['-', '.'][..]) => return Ok(None),
+        Err(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid CoNLL-U token id: {}", fields[0]),
+            ))
+        }
+    };
+    Ok(Some(Token {
+        id,
+        form: fields[1].to_owned(),
+        head: fields[6].parse().ok(),
+        deprel: fields[7].to_owned(),
+    }))
+}
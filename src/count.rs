@@ -0,0 +1,933 @@
+//! Token counting.
+
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::audit::{AuditCount, AuditTerms};
+use crate::boilerplate::Boilerplate;
+use crate::command::TokenizerCommand;
+use crate::emoji::{self, EmojiPolicy};
+use crate::filter::TokenFilter;
+use crate::input::{self, Corpus, DocumentBoundary, InputFormat, Item, LineRange};
+use crate::minhash::{self, Sketch};
+use crate::ngrams::{self, NGrams};
+use crate::normalize::Normalizer;
+use crate::options::Options;
+use crate::pii::{PiiKind, PiiPatterns};
+use crate::rewrite::Rewriter;
+use crate::sort::Collation;
+use crate::tokenize::{Tokenizer, Whitespace};
+
+/// Options for counting a corpus.
+#[derive(Clone, Debug)]
+pub struct CountOptions {
+    /// Number of threads used for reading multiple files.
+    pub threads: usize,
+    /// Only count the first occurrence of every line.
+    pub dedup_lines: bool,
+    /// Shingle size for MinHash sketches of documents, no sketches if
+    /// `None`.
+    pub sketch_shingle_size: Option<usize>,
+    /// Count the number of documents every token occurs in.
+    pub doc_freqs: bool,
+    /// Keep the token counts of every document.
+    pub document_terms: bool,
+    /// Count the number of documents every n-gram of the tokens occurs in.
+    pub ngram_doc_freqs: Option<NGramLengths>,
+    /// Store the token counts, only totals are counted otherwise.
+    pub vocab: bool,
+    /// Filter for the tokens to count.
+    pub filter: TokenFilter,
+    /// Handling of emoji in tokens.
+    pub emoji: EmojiPolicy,
+    /// Tokenizer splitting lines into tokens.
+    pub tokenizer: Arc<dyn Tokenizer>,
+    /// Command tokenizing the lines of files before the tokens are split
+    /// at whitespace.
+    pub tokenizer_cmd: Option<TokenizerCommand>,
+    /// Normalizer applied to the tokens before rewriting.
+    pub normalizer: Option<Arc<dyn Normalizer>>,
+    /// Rewrite rules applied to the tokens before filtering.
+    pub rewriter: Option<Rewriter>,
+    /// Boilerplate n-grams, the tokens they cover are not counted.
+    pub boilerplate: Option<Arc<Boilerplate>>,
+    /// Patterns of personal data that is counted and optionally masked
+    /// before tokenization.
+    pub pii: Option<Arc<PiiPatterns>>,
+    /// Terms whose occurrences are audited.
+    pub audit: Option<Arc<AuditTerms>>,
+    /// Count how often tokens occur at the start, in the middle and at the
+    /// end of lines.
+    pub positions: bool,
+    /// Keep the counts of tokens per document for dispersion measures.
+    pub dispersion: bool,
+    /// Record the vocabulary size after every this many tokens. The
+    /// corpus is read by a single thread to get a meaningful order.
+    pub growth_step: Option<usize>,
+    /// Lines are `source<TAB>target` pairs, the target side is counted
+    /// separately.
+    pub bitext: bool,
+    /// Lines are `label<TAB>text` pairs, the tokens are also counted per
+    /// label.
+    pub labeled: bool,
+    /// Count at most this many distinct tokens of every document in
+    /// `Counts::contributions`, each once.
+    pub max_contributions: Option<usize>,
+}
+
+impl Default for CountOptions {
+    fn default() -> Self {
+        CountOptions {
+            threads: 1,
+            dedup_lines: false,
+            sketch_shingle_size: None,
+            doc_freqs: false,
+            document_terms: false,
+            ngram_doc_freqs: None,
+            vocab: true,
+            filter: TokenFilter::default(),
+            emoji: EmojiPolicy::default(),
+            tokenizer: Arc::new(Whitespace),
+            tokenizer_cmd: None,
+            normalizer: None,
+            rewriter: None,
+            boilerplate: None,
+            pii: None,
+            audit: None,
+            positions: false,
+            dispersion: false,
+            growth_step: None,
+            bitext: false,
+            labeled: false,
+            max_contributions: None,
+        }
+    }
+}
+
+/// Lengths of the n-grams of tokens.
+#[derive(Clone, Copy, Debug)]
+pub struct NGramLengths {
+    pub min_n: usize,
+    pub max_n: usize,
+    /// Tokens are bracketed before their n-grams are extracted.
+    pub bracket: bool,
+}
+
+/// Token counts of a document.
+pub struct DocumentTerms {
+    /// Name of the file or archive member.
+    pub file: String,
+    /// Index of the document in the file.
+    pub index: usize,
+    pub terms: HashMap<String, usize>,
+}
+
+/// Counts of the lines with a label.
+#[derive(Debug, Default)]
+pub struct LabelCounts {
+    pub lines: usize,
+    pub tokens: HashMap<String, usize>,
+    /// Number of lines every token occurs in.
+    pub line_freqs: HashMap<String, usize>,
+}
+
+/// Counts of (a part of) a corpus.
+#[derive(Default)]
+pub struct Counts {
+    pub tokens: HashMap<String, usize>,
+    /// Number of lines read.
+    pub lines: usize,
+    /// Number of tokens counted.
+    pub num_tokens: usize,
+    /// Number of bytes in counted lines, including line terminators.
+    pub bytes: usize,
+    /// Number of lines skipped as duplicates.
+    pub duplicate_lines: usize,
+    /// MinHash sketches of the documents.
+    pub documents: Vec<minhash::Document>,
+    /// Number of non-empty documents.
+    pub num_documents: usize,
+    /// Number of documents every token occurs in, only counted if
+    /// `CountOptions::doc_freqs` is set.
+    pub doc_freqs: HashMap<String, usize>,
+    /// Token counts of the documents, only kept if
+    /// `CountOptions::document_terms` is set.
+    pub document_terms: Vec<DocumentTerms>,
+    /// Number of documents every n-gram occurs in, only counted if
+    /// `CountOptions::ngram_doc_freqs` is set.
+    pub ngram_doc_freqs: HashMap<String, usize>,
+    /// Counts of emoji sequences, only counted with
+    /// `EmojiPolicy::Separate`.
+    pub emoji: HashMap<String, usize>,
+    /// Initial, medial and final counts of tokens, only counted if
+    /// `CountOptions::positions` is set.
+    pub positions: HashMap<String, [usize; 3]>,
+    /// Counts of tokens and the lengths of the documents they occur in,
+    /// only counted if `CountOptions::dispersion` is set.
+    pub dispersion: HashMap<String, Vec<(usize, usize)>>,
+    /// Number of tokens and types after every growth step.
+    pub growth: Vec<(usize, usize)>,
+    /// Token counts of the target side of bitext.
+    pub target_tokens: HashMap<String, usize>,
+    /// Matches of the personal data patterns.
+    pub pii: HashMap<PiiKind, usize>,
+    /// Counts per label of labeled lines.
+    pub labels: HashMap<String, LabelCounts>,
+    /// Occurrences of the audited terms by their index.
+    pub audit: HashMap<usize, AuditCount>,
+    /// Number of documents that contribute every token, only counted if
+    /// `CountOptions::max_contributions` is set.
+    pub contributions: HashMap<String, usize>,
+}
+
+impl Counts {
+    pub fn count_token(&mut self, token: &str) {
+        count(&mut self.tokens, token);
+    }
+
+    /// Add the counts of `other`.
+    pub fn merge(&mut self, mut other: Counts) {
+        if other.tokens.len() > self.tokens.len() {
+            std::mem::swap(&mut self.tokens, &mut other.tokens);
+        }
+        for (token, count) in other.tokens {
+            *self.tokens.entry(token).or_insert(0) += count;
+        }
+        self.lines += other.lines;
+        self.num_tokens += other.num_tokens;
+        self.bytes += other.bytes;
+        self.duplicate_lines += other.duplicate_lines;
+        self.documents.extend(other.documents);
+        self.document_terms.extend(other.document_terms);
+        self.num_documents += other.num_documents;
+        if other.doc_freqs.len() > self.doc_freqs.len() {
+            std::mem::swap(&mut self.doc_freqs, &mut other.doc_freqs);
+        }
+        for (token, count) in other.doc_freqs {
+            *self.doc_freqs.entry(token).or_insert(0) += count;
+        }
+        if other.ngram_doc_freqs.len() > self.ngram_doc_freqs.len() {
+            std::mem::swap(&mut self.ngram_doc_freqs, &mut other.ngram_doc_freqs);
+        }
+        for (ngram, count) in other.ngram_doc_freqs {
+            *self.ngram_doc_freqs.entry(ngram).or_insert(0) += count;
+        }
+        for (emoji, count) in other.emoji {
+            *self.emoji.entry(emoji).or_insert(0) += count;
+        }
+        self.growth.extend(other.growth);
+        if other.target_tokens.len() > self.target_tokens.len() {
+            std::mem::swap(&mut self.target_tokens, &mut other.target_tokens);
+        }
+        for (token, count) in other.target_tokens {
+            *self.target_tokens.entry(token).or_insert(0) += count;
+        }
+        for (label, other) in other.labels {
+            let label_counts = self.labels.entry(label).or_default();
+            label_counts.lines += other.lines;
+            for (token, count) in other.tokens {
+                *label_counts.tokens.entry(token).or_insert(0) += count;
+            }
+            for (token, count) in other.line_freqs {
+                *label_counts.line_freqs.entry(token).or_insert(0) += count;
+            }
+        }
+        if other.contributions.len() > self.contributions.len() {
+            std::mem::swap(&mut self.contributions, &mut other.contributions);
+        }
+        for (token, count) in other.contributions {
+            *self.contributions.entry(token).or_insert(0) += count;
+        }
+        for (kind, count) in other.pii {
+            *self.pii.entry(kind).or_insert(0) += count;
+        }
+        for (idx, other) in other.audit {
+            self.audit.entry(idx).or_default().merge(other);
+        }
+        for (token, docs) in other.dispersion {
+            self.dispersion.entry(token).or_default().extend(docs);
+        }
+        if other.positions.len() > self.positions.len() {
+            std::mem::swap(&mut self.positions, &mut other.positions);
+        }
+        for (token, counts) in other.positions {
+            let positions = self.positions.entry(token).or_insert([0; 3]);
+            for (count, other) in positions.iter_mut().zip(counts.iter()) {
+                *count += other;
+            }
+        }
+    }
+}
+
+fn count(counts: &mut HashMap<String, usize>, token: &str) {
+    if let Some(cnt) = counts.get_mut(token) {
+        *cnt += 1;
+    } else {
+        counts.insert(token.to_string(), 1);
+    }
+}
+
+/// Apply the emoji policy, rewrite rules and filter to a token.
+///
+/// Returns `None` if the token is removed.
+fn prepare_token<'a>(
+    opts: &'a CountOptions,
+    emoji_counts: &mut HashMap<String, usize>,
+    token: &'a str,
+) -> Option<Cow<'a, str>> {
+    let mut token = match opts.emoji {
+        EmojiPolicy::Keep => Cow::Borrowed(token),
+        EmojiPolicy::Drop => emoji::strip_emoji(token, |_| ()),
+        EmojiPolicy::Separate => emoji::strip_emoji(token, |seq| {
+            *emoji_counts.entry(seq.to_owned()).or_insert(0) += 1
+        }),
+    };
+    if let Some(normalizer) = &opts.normalizer {
+        let normalized = normalizer.normalize(&token);
+        if normalized != token {
+            token = Cow::Owned(normalized.into_owned());
+        }
+    }
+    if let Some(rewritten) = opts.rewriter.as_ref().and_then(|r| r.rewrite(&token)) {
+        token = Cow::Owned(rewritten.into_owned());
+    }
+    if token.is_empty() || !opts.filter.keep(&token) {
+        None
+    } else {
+        Some(token)
+    }
+}
+
+/// Count a growing corpus, calling `snapshot` with the counts so far
+/// every `interval`.
+///
+/// Like `tail -f`, files are polled for appended lines and directories are
+/// rescanned for new files. A last line without a line break is counted
+/// once it is complete. Files that shrink are assumed to be truncated and
+/// are read again from the start. Only returns on errors.
+pub fn follow_corpus<F>(
+    corpus: &Corpus,
+    opts: &CountOptions,
+    interval: Duration,
+    mut snapshot: F,
+) -> io::Result<()>
+where
+    F: FnMut(&Counts) -> io::Result<()>,
+{
+    let dedup = if opts.dedup_lines {
+        Some(LineSet::default())
+    } else {
+        None
+    };
+    let mut counter = Counter::new(opts, dedup.as_ref());
+    // Read offsets and incomplete last lines of the files.
+    let mut files: HashMap<PathBuf, (u64, Vec<u8>)> = HashMap::new();
+    let mut line_no = 0;
+    loop {
+        let deadline = Instant::now() + interval;
+        for path in input::corpus_files(&corpus.paths, &corpus.include)? {
+            let mut file = match File::open(&path) {
+                Ok(file) => file,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            let (offset, partial) = files.entry(path.clone()).or_default();
+            if file.metadata()?.len() < *offset {
+                *offset = 0;
+                partial.clear();
+            }
+            file.seek(SeekFrom::Start(*offset))?;
+            *offset += file.read_to_end(partial)? as u64;
+            if let Some(end) = partial.iter().rposition(|&b| b == b'\n') {
+                let rest = partial.split_off(end + 1);
+                counter.count_reader(
+                    corpus,
+                    &path.to_string_lossy(),
+                    &partial[..],
+                    &mut line_no,
+                )?;
+                *partial = rest;
+            }
+        }
+        snapshot(&counter.counts)?;
+        thread::sleep(deadline.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// Token and n-gram count tables of a corpus.
+pub struct CountTables {
+    /// Token counts in count order, without the tokens below the token
+    /// min count if they are filtered first.
+    pub tokens: Vec<(String, usize)>,
+    /// Pruned n-gram counts of the tokens in count order.
+    pub ngrams: Vec<(String, usize)>,
+    /// The n-gram min count, derived from the n-gram target size if set.
+    pub ngram_min: usize,
+    /// The other counts of the corpus, without the token counts.
+    pub counts: Counts,
+}
+
+impl CountTables {
+    /// Build the count tables of the counts of a corpus.
+    ///
+    /// The tables are built by the same steps as the tables of
+    /// `corpus-count`, see `token_table`, `table_ngram_counts` and
+    /// `ngrams::prune_ngrams`. The binary additionally prunes by document
+    /// frequencies, leads the tokens by special tokens and can sort the
+    /// n-grams externally, which gives the same order.
+    pub fn new(mut counts: Counts, opts: &Options) -> Self {
+        let tokens = token_table(std::mem::take(&mut counts.tokens), opts, &[]);
+        let mut ngram_counts = table_ngram_counts(&tokens, opts, 0, |_| false);
+        let ngram_min = ngrams::prune_ngrams(&mut ngram_counts, opts);
+        CountTables {
+            tokens,
+            ngrams: collated_into_sorted(ngram_counts, Some(ngram_min), opts.collation()),
+            ngram_min,
+            counts,
+        }
+    }
+}
+
+/// Sort token counts into a token table, the special tokens lead in their
+/// given order.
+///
+/// Tokens below the token min count are removed if they are filtered
+/// first. Special tokens are kept regardless of their counts, tokens that
+/// do not occur in the corpus have count 0.
+pub fn token_table(
+    mut counts: HashMap<String, usize>,
+    opts: &Options,
+    special_tokens: &[String],
+) -> Vec<(String, usize)> {
+    let mut sorted = special_tokens
+        .iter()
+        .map(|token| (token.clone(), counts.remove(token).unwrap_or(0)))
+        .collect::<Vec<_>>();
+    let filter = Some(opts.token_min()).filter(|_| opts.filter_first());
+    sorted.extend(collated_into_sorted(counts, filter, opts.collation()));
+    sorted
+}
+
+/// Count the n-grams of a token table with the counts of the tokens.
+///
+/// The `special` leading tokens of the table are not split into n-grams,
+/// neither are the tokens that are `skip`ped.
+pub fn table_ngram_counts(
+    tokens: &[(String, usize)],
+    opts: &Options,
+    special: usize,
+    skip: impl Fn(&str) -> bool,
+) -> HashMap<String, usize> {
+    let tokens = tokens
+        .iter()
+        .skip(special)
+        .filter(|(token, _)| !skip(token))
+        .map(|(token, count)| (token.as_str(), *count));
+    ngrams::count_ngrams(tokens, opts)
+}
+
+/// Sort counts in count order, dropping the counts below `filter`.
+pub fn counted_into_sorted(
+    iter: impl IntoIterator<Item = (String, usize)>,
+    filter: Option<usize>,
+) -> Vec<(String, usize)> {
+    collated_into_sorted(iter, filter, Collation::Codepoint)
+}
+
+/// Sort counts in the count order of a collation, dropping the counts
+/// below `filter`.
+pub fn collated_into_sorted(
+    iter: impl IntoIterator<Item = (String, usize)>,
+    filter: Option<usize>,
+    collation: Collation,
+) -> Vec<(String, usize)> {
+    let mut items: Vec<_> = if let Some(min_freq) = filter {
+        iter.into_iter()
+            .filter(|(_, cnt)| *cnt >= min_freq)
+            .collect()
+    } else {
+        iter.into_iter().collect()
+    };
+    items.sort_unstable_by(|(t1, c1), (t2, c2)| collation.count_order(t1, *c1, t2, *c2));
+    items
+}
+
+/// Count a corpus read from a reader, e.g. an in-memory corpus.
+///
+/// The reader is read like a single plain text file of the corpus with
+/// documents separated by blank lines, the defaults of `corpus-count`.
+/// The counts are turned into the count tables of the min counts and
+/// n-gram lengths of `opts`.
+pub fn count_corpus(
+    reader: impl BufRead,
+    opts: &Options,
+    count_opts: &CountOptions,
+) -> io::Result<CountTables> {
+    let corpus = Corpus {
+        paths: Vec::new(),
+        include: Vec::new(),
+        format: InputFormat::Text,
+        documents: DocumentBoundary::Blank,
+        lines: LineRange::default(),
+        bytes: None,
+    };
+    let dedup = if count_opts.dedup_lines {
+        Some(LineSet::default())
+    } else {
+        None
+    };
+    let mut counter = Counter::new(count_opts, dedup.as_ref());
+    counter.count_reader(&corpus, "-", reader, &mut 0)?;
+    Ok(CountTables::new(counter.counts, opts))
+}
+
+/// Count the files of a corpus.
+///
+/// Every thread reads whole files and keeps its own counts, which are
+/// merged once all files are read. Files are read sequentially if the
+/// corpus is read from stdin or restricted to a range.
+pub fn count_files(corpus: &Corpus, opts: &CountOptions) -> io::Result<Counts> {
+    let dedup = if opts.dedup_lines {
+        Some(LineSet::default())
+    } else {
+        None
+    };
+    let mut counter = Counter::new(opts, dedup.as_ref());
+
+    if let Some(bytes) = corpus.bytes {
+        let path = corpus.byte_range_file()?;
+        counter.count_reader(corpus, &path.to_string_lossy(), bytes.open(path)?, &mut 0)?;
+        return Ok(counter.counts);
+    }
+
+    if corpus.paths.is_empty()
+        || opts.threads <= 1
+        || !corpus.lines.is_full()
+        || opts.growth_step.is_some()
+    {
+        let mut line_no = 0;
+        input::for_each_file(&corpus.paths, &corpus.include, |name, reader| {
+            counter.count_reader(corpus, name, reader, &mut line_no)
+        })?;
+        return Ok(counter.counts);
+    }
+
+    let files = input::corpus_files(&corpus.paths, &corpus.include)?;
+    let next = AtomicUsize::new(0);
+    let merged = Mutex::new(Counts::default());
+    thread::scope(|s| {
+        let workers = (0..opts.threads.min(files.len()))
+            .map(|_| {
+                s.spawn(|| -> io::Result<()> {
+                    let mut counter = Counter::new(opts, dedup.as_ref());
+                    while let Some(file) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        input::for_each_member(file, &corpus.include, |name, reader| {
+                            counter.count_reader(corpus, name, reader, &mut 0)
+                        })?;
+                    }
+                    merged.lock().unwrap().merge(counter.counts);
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("Counting thread panicked"))
+    })?;
+    Ok(merged.into_inner().unwrap())
+}
+
+/// Counting of lines that arrive one at a time, e.g. from a socket.
+///
+/// Every line is a document.
+pub struct StreamCounter<'a> {
+    counter: Counter<'a>,
+}
+
+impl<'a> StreamCounter<'a> {
+    pub fn new(opts: &'a CountOptions) -> Self {
+        StreamCounter {
+            counter: Counter::new(opts, None),
+        }
+    }
+
+    pub fn count_line(&mut self, line: &str) {
+        self.counter.count_line(line);
+        self.counter.end_document("-");
+    }
+
+    /// Take the counts of the lines since the last call.
+    pub fn take_counts(&mut self) -> Counts {
+        std::mem::take(&mut self.counter.counts)
+    }
+}
+
+/// Counts of a single thread and the state shared between threads.
+struct Counter<'a> {
+    opts: &'a CountOptions,
+    counts: Counts,
+    dedup: Option<&'a LineSet>,
+    doc: DocumentState,
+    /// Name of the current file.
+    file: String,
+    /// Number of lines read from the current file.
+    file_lines: usize,
+    /// Last counted token of the current line.
+    last_token: String,
+    /// Label of the current line.
+    label: String,
+    /// Distinct tokens of the current labeled line.
+    line_types: HashSet<String>,
+}
+
+/// State of the document that is currently read.
+#[derive(Default)]
+struct DocumentState {
+    /// Index of the document in the current file.
+    index: usize,
+    tokens: usize,
+    sketch: Option<Sketch>,
+    /// Counts of the distinct tokens of the document.
+    types: HashMap<String, usize>,
+    /// The first distinct tokens of the document, up to
+    /// `CountOptions::max_contributions`.
+    contributed: HashSet<String>,
+}
+
+impl<'a> Counter<'a> {
+    fn new(opts: &'a CountOptions, dedup: Option<&'a LineSet>) -> Self {
+        Counter {
+            opts,
+            counts: Counts::default(),
+            dedup,
+            doc: DocumentState::default(),
+            file: String::new(),
+            file_lines: 0,
+            last_token: String::new(),
+            label: String::new(),
+            line_types: HashSet::new(),
+        }
+    }
+
+    /// Count the lines of a reader that fall into the corpus line range.
+    ///
+    /// `line_no` is the number of corpus lines preceding the reader.
+    fn count_reader(
+        &mut self,
+        corpus: &Corpus,
+        name: &str,
+        reader: impl BufRead,
+        line_no: &mut usize,
+    ) -> io::Result<()> {
+        let range = corpus.lines;
+        if range.is_past(*line_no + 1) {
+            return Ok(());
+        }
+        self.doc.index = 0;
+        self.file.clear();
+        self.file.push_str(name);
+        self.file_lines = 0;
+        let mut items = input::lines(reader, &corpus.format)?;
+        let in_range = std::iter::from_fn(|| loop {
+            match items.next()? {
+                Ok(Item::Line(line)) => {
+                    *line_no += 1;
+                    if range.is_past(*line_no) {
+                        return None;
+                    } else if range.contains(*line_no) {
+                        return Some(Ok(Item::Line(line)));
+                    }
+                }
+                item => return Some(item),
+            }
+        });
+        match &self.opts.tokenizer_cmd {
+            Some(command) => command.tokenize(in_range, |item| {
+                self.count_item(corpus.documents, name, item)
+            })?,
+            None => {
+                for item in in_range {
+                    self.count_item(corpus.documents, name, item?);
+                }
+            }
+        }
+        self.end_document(name);
+        Ok(())
+    }
+
+    fn count_item(&mut self, documents: DocumentBoundary, name: &str, item: Item) {
+        let line = match item {
+            Item::Line(line) => line,
+            Item::RecordEnd => {
+                if documents != DocumentBoundary::Line {
+                    self.end_document(name);
+                }
+                return;
+            }
+        };
+        self.count_line(&line);
+        match documents {
+            DocumentBoundary::Line => self.end_document(name),
+            DocumentBoundary::Blank if line.trim().is_empty() => self.end_document(name),
+            _ => (),
+        }
+    }
+
+    fn count_line(&mut self, line: &str) {
+        self.counts.lines += 1;
+        self.file_lines += 1;
+        if let Some(dedup) = self.dedup {
+            // Blank lines do not contribute tokens, keep them out of the set.
+            if !line.trim().is_empty() && !dedup.insert(line) {
+                self.counts.duplicate_lines += 1;
+                return;
+            }
+        }
+        self.counts.bytes += line.len() + 1;
+        let opts = self.opts;
+        let line = match &opts.pii {
+            Some(pii) => pii.apply(line, &mut self.counts.pii),
+            None => Cow::Borrowed(line),
+        };
+        let line = line.as_ref();
+        let (line, target) = if self.opts.bitext {
+            line.split_once('\t').unwrap_or((line, ""))
+        } else {
+            (line, "")
+        };
+        let line = if opts.labeled {
+            let (label, text) = line.split_once('\t').unwrap_or(("", line));
+            self.label.clear();
+            self.label.push_str(label);
+            self.line_types.clear();
+            if !label.is_empty() {
+                self.counts
+                    .labels
+                    .entry(self.label.clone())
+                    .or_default()
+                    .lines += 1;
+            }
+            text
+        } else {
+            line
+        };
+        opts.tokenizer.tokenize(target, &mut |token| {
+            if let Some(token) = prepare_token(opts, &mut self.counts.emoji, token) {
+                count(&mut self.counts.target_tokens, &token);
+            }
+        });
+        if let Some(audit) = &opts.audit {
+            let mut tokens = Vec::new();
+            opts.tokenizer
+                .tokenize(line, &mut |token| tokens.push(token.to_owned()));
+            audit.count(&tokens, &self.file, self.file_lines, &mut self.counts.audit);
+        }
+        let mut line_tokens = 0;
+        match &opts.boilerplate {
+            Some(boilerplate) => {
+                let mut tokens = Vec::new();
+                opts.tokenizer
+                    .tokenize(line, &mut |token| tokens.push(token.to_owned()));
+                for (token, covered) in tokens.iter().zip(boilerplate.mask(&tokens)) {
+                    if !covered {
+                        self.count_token(token, &mut line_tokens);
+                    }
+                }
+            }
+            None => opts
+                .tokenizer
+                .tokenize(line, &mut |token| self.count_token(token, &mut line_tokens)),
+        }
+        if self.opts.positions && line_tokens > 0 {
+            // A token that forms the whole line is initial and final.
+            if line_tokens == 1 {
+                self.count_last_position(0);
+            }
+            self.count_last_position(2);
+        }
+    }
+
+    /// Count a token of a line, `line_tokens` is the number of tokens of
+    /// the line counted before.
+    fn count_token(&mut self, token: &str, line_tokens: &mut usize) {
+        let token = match prepare_token(self.opts, &mut self.counts.emoji, token) {
+            Some(token) => token,
+            None => return,
+        };
+        let token = token.as_ref();
+        if self.opts.vocab {
+            self.counts.count_token(token);
+        }
+        if let Some(label_counts) = self.counts.labels.get_mut(&self.label) {
+            count(&mut label_counts.tokens, token);
+            if !self.line_types.contains(token) {
+                self.line_types.insert(token.to_owned());
+                count(&mut label_counts.line_freqs, token);
+            }
+        }
+        self.counts.num_tokens += 1;
+        if let Some(step) = self.opts.growth_step {
+            if self.counts.num_tokens.is_multiple_of(step) {
+                self.counts
+                    .growth
+                    .push((self.counts.num_tokens, self.counts.tokens.len()));
+            }
+        }
+        self.doc.tokens += 1;
+        if let Some(shingle_size) = self.opts.sketch_shingle_size {
+            self.doc
+                .sketch
+                .get_or_insert_with(|| Sketch::new(shingle_size))
+                .add_token(token);
+        }
+        if self.opts.doc_freqs
+            || self.opts.document_terms
+            || self.opts.ngram_doc_freqs.is_some()
+            || self.opts.dispersion
+        {
+            if let Some(cnt) = self.doc.types.get_mut(token) {
+                *cnt += 1;
+            } else {
+                self.doc.types.insert(token.to_owned(), 1);
+            }
+        }
+        if let Some(max_contributions) = self.opts.max_contributions {
+            if self.doc.contributed.len() < max_contributions
+                && !self.doc.contributed.contains(token)
+            {
+                self.doc.contributed.insert(token.to_owned());
+            }
+        }
+        if self.opts.positions {
+            // The position of the previous token is known once the
+            // line continues.
+            if *line_tokens > 0 {
+                self.count_last_position(if *line_tokens == 1 { 0 } else { 1 });
+            }
+            self.last_token.clear();
+            self.last_token.push_str(token);
+        }
+        *line_tokens += 1;
+    }
+
+    fn count_last_position(&mut self, position: usize) {
+        let token = &self.last_token;
+        if let Some(counts) = self.counts.positions.get_mut(token) {
+            counts[position] += 1;
+        } else {
+            let mut counts = [0; 3];
+            counts[position] = 1;
+            self.counts.positions.insert(token.clone(), counts);
+        }
+    }
+
+    fn end_document(&mut self, name: &str) {
+        if self.doc.tokens == 0 {
+            return;
+        }
+        let index = self.doc.index + 1;
+        let doc = std::mem::replace(
+            &mut self.doc,
+            DocumentState {
+                index,
+                ..DocumentState::default()
+            },
+        );
+        self.counts.num_documents += 1;
+        if self.opts.document_terms {
+            self.counts.document_terms.push(DocumentTerms {
+                file: name.to_owned(),
+                index: doc.index,
+                terms: doc.types.clone(),
+            });
+        }
+        if let Some(lengths) = self.opts.ngram_doc_freqs {
+            let mut ngrams = HashSet::new();
+            for token in doc.types.keys() {
+                let token = if lengths.bracket {
+                    format!("<{}>", token)
+                } else {
+                    token.clone()
+                };
+                for ngram in NGrams::new(&token, lengths.min_n, lengths.max_n) {
+                    if !ngrams.contains(ngram) {
+                        ngrams.insert(ngram.to_owned());
+                    }
+                }
+            }
+            for ngram in ngrams {
+                *self.counts.ngram_doc_freqs.entry(ngram).or_insert(0) += 1;
+            }
+        }
+        for token in doc.contributed {
+            *self.counts.contributions.entry(token).or_insert(0) += 1;
+        }
+        for (token, count) in doc.types {
+            if self.opts.dispersion {
+                self.counts
+                    .dispersion
+                    .entry(token.clone())
+                    .or_default()
+                    .push((count, doc.tokens));
+            }
+            *self.counts.doc_freqs.entry(token).or_insert(0) += 1;
+        }
+        if let Some(signature) = doc.sketch.and_then(Sketch::finish) {
+            self.counts.documents.push(minhash::Document {
+                file: name.to_owned(),
+                index: doc.index,
+                tokens: doc.tokens,
+                signature,
+            });
+        }
+    }
+}
+
+const LINE_SET_SHARDS: usize = 64;
+
+/// Thread-safe set of line hashes.
+///
+/// Lines are stored as 128-bit hashes, the set is sharded to reduce lock
+/// contention between threads.
+struct LineSet {
+    shards: Vec<Mutex<HashSet<u128>>>,
+}
+
+impl Default for LineSet {
+    fn default() -> Self {
+        LineSet {
+            shards: (0..LINE_SET_SHARDS)
+                .map(|_| Mutex::new(HashSet::new()))
+                .collect(),
+        }
+    }
+}
+
+impl LineSet {
+    /// Insert a line, returns `false` if it was present already.
+    fn insert(&self, line: &str) -> bool {
+        let hash = line_hash(line);
+        let shard = (hash as usize) % LINE_SET_SHARDS;
+        self.shards[shard].lock().unwrap().insert(hash)
+    }
+}
+
+fn line_hash(line: &str) -> u128 {
+    let mut hash = 0;
+    for seed in 0..2u8 {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        line.hash(&mut hasher);
+        hash = (hash << 64) | u128::from(hasher.finish());
+    }
+    hash
+}
@@ -0,0 +1,370 @@
+//! Directed acyclic word graphs of vocabularies with counts.
+//!
+//! A DAWG is the minimal acyclic automaton of the tokens, so tokens share
+//! the states of their common prefixes and suffixes. This makes it much
+//! smaller than a token count file for morphologically rich languages,
+//! where many tokens share inflectional suffixes. Every state stores the
+//! number of tokens accepted from it, which numbers the tokens by their
+//! byte order (Lucchesi and Kowaltowski, 1993). The counts are stored in
+//! that order in an array of fixed-width integers, and lookups run on the
+//! serialized bytes, so DAWGs can be memory-mapped.
+//!
+//! The serialized DAWG is the magic bytes `CCDW` and the version 1 as a
+//! little-endian 32-bit integer, followed by the states, the counts and a
+//! trailer of the number of tokens, the address of the start state, the
+//! address of the counts and the width of the counts in bytes as
+//! little-endian 64-bit integers. States are addressed by their byte offset
+//! and written before the states leading to them. A state is a flag byte, 1
+//! for final states, the number of accepted tokens and the number of
+//! transitions as LEB128 integers and the transitions in byte order. A
+//! transition is the input byte and the distance from the state back to its
+//! target as an LEB128 integer. The counts are little-endian integers of
+//! the smallest width that holds the highest count.
+
+use std::collections::HashMap;
+use std::io;
+
+use crate::vocab::invalid_data;
+
+const MAGIC: &[u8; 4] = b"CCDW";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 8;
+const TRAILER_LEN: usize = 32;
+
+fn write_leb128(data: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        data.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+/// Read an LEB128 integer, returning it and its length in bytes.
+fn read_leb128(bytes: &[u8]) -> (u64, usize) {
+    let mut value = 0;
+    for (idx, &byte) in bytes.iter().enumerate() {
+        value |= u64::from(byte & 0x7f) << (7 * idx);
+        if byte < 0x80 {
+            return (value, idx + 1);
+        }
+    }
+    panic!("Truncated LEB128 integer");
+}
+
+/// Read a little-endian integer of some width.
+fn read_uint(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| (value << 8) | u64::from(byte))
+}
+
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+struct State {
+    is_final: bool,
+    transitions: Vec<(u8, u64)>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct UnfinishedState {
+    state: State,
+    // Number of tokens accepted from the state.
+    len: u64,
+    // Input of the transition to the next unfinished state.
+    last: Option<u8>,
+}
+
+/// Builder of DAWGs from tokens in byte order.
+#[derive(Clone, Debug)]
+pub struct DawgBuilder {
+    data: Vec<u8>,
+    registry: HashMap<State, u64>,
+    unfinished: Vec<UnfinishedState>,
+    last_key: Option<Vec<u8>>,
+    counts: Vec<u64>,
+}
+
+impl Default for DawgBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DawgBuilder {
+    pub fn new() -> Self {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        DawgBuilder {
+            data,
+            registry: HashMap::new(),
+            unfinished: vec![UnfinishedState::default()],
+            last_key: None,
+            counts: Vec::new(),
+        }
+    }
+
+    /// Insert a token with its count.
+    ///
+    /// Returns an error if the token does not follow the previous token in
+    /// byte order.
+    pub fn insert(&mut self, key: impl AsRef<[u8]>, count: u64) -> Result<(), String> {
+        let key = key.as_ref();
+        if let Some(last_key) = &self.last_key {
+            if key <= last_key.as_slice() {
+                return Err(format!(
+                    "Key {:?} does not follow {:?}",
+                    String::from_utf8_lossy(key),
+                    String::from_utf8_lossy(last_key)
+                ));
+            }
+        }
+
+        let prefix_len = key
+            .iter()
+            .zip(&self.unfinished)
+            .take_while(|(&input, unfinished)| unfinished.last == Some(input))
+            .count();
+        self.compile_from(prefix_len);
+        for unfinished in &mut self.unfinished {
+            unfinished.len += 1;
+        }
+        if prefix_len == key.len() {
+            self.unfinished[prefix_len].state.is_final = true;
+        } else {
+            self.unfinished[prefix_len].last = Some(key[prefix_len]);
+            for &input in &key[prefix_len + 1..] {
+                self.unfinished.push(UnfinishedState {
+                    state: State::default(),
+                    len: 1,
+                    last: Some(input),
+                });
+            }
+            self.unfinished.push(UnfinishedState {
+                state: State {
+                    is_final: true,
+                    transitions: Vec::new(),
+                },
+                len: 1,
+                last: None,
+            });
+        }
+        self.last_key = Some(key.to_vec());
+        self.counts.push(count);
+        Ok(())
+    }
+
+    /// Compile the unfinished states after the first `len` + 1 states.
+    fn compile_from(&mut self, len: usize) {
+        while self.unfinished.len() > len + 1 {
+            let unfinished = self.unfinished.pop().unwrap();
+            let addr = self.compile(unfinished.state, unfinished.len);
+            let parent = self.unfinished.last_mut().unwrap();
+            let input = parent.last.take().unwrap();
+            parent.state.transitions.push((input, addr));
+        }
+    }
+
+    fn compile(&mut self, state: State, len: u64) -> u64 {
+        if let Some(&addr) = self.registry.get(&state) {
+            return addr;
+        }
+        let addr = self.data.len() as u64;
+        self.data.push(u8::from(state.is_final));
+        write_leb128(&mut self.data, len);
+        write_leb128(&mut self.data, state.transitions.len() as u64);
+        for &(input, target) in &state.transitions {
+            self.data.push(input);
+            write_leb128(&mut self.data, addr - target);
+        }
+        self.registry.insert(state, addr);
+        addr
+    }
+
+    /// Finish the DAWG and get its serialization.
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.compile_from(0);
+        let root = self.unfinished.pop().unwrap();
+        let root = self.compile(root.state, root.len);
+
+        let max_count = self.counts.iter().copied().max().unwrap_or(0);
+        let width = (64 - max_count.leading_zeros() as usize).div_ceil(8);
+        let counts_addr = self.data.len();
+        for count in &self.counts {
+            self.data.extend_from_slice(&count.to_le_bytes()[..width]);
+        }
+        for value in &[self.counts.len(), root as usize, counts_addr, width] {
+            self.data.extend_from_slice(&(*value as u64).to_le_bytes());
+        }
+        self.data
+    }
+
+    /// Finish the DAWG.
+    pub fn finish(self) -> Dawg<Vec<u8>> {
+        Dawg::new(self.into_bytes()).expect("Built an invalid DAWG")
+    }
+}
+
+/// A state of a serialized DAWG.
+struct StateRef<'a> {
+    is_final: bool,
+    len: u64,
+    n_transitions: usize,
+    transitions: &'a [u8],
+}
+
+/// A DAWG of tokens and their counts, read from its serialization.
+#[derive(Clone, Debug)]
+pub struct Dawg<D> {
+    data: D,
+    len: u64,
+    root: u64,
+    counts_addr: usize,
+    width: usize,
+}
+
+impl<D: AsRef<[u8]>> Dawg<D> {
+    /// Read a DAWG from its serialization, e.g. a memory-mapped file.
+    ///
+    /// Only the header and the trailer are checked.
+    pub fn new(data: D) -> io::Result<Self> {
+        let bytes = data.as_ref();
+        if bytes.len() < HEADER_LEN + TRAILER_LEN || &bytes[..4] != MAGIC {
+            return Err(invalid_data("Not a DAWG".to_owned()));
+        }
+        let version = read_uint(&bytes[4..8]) as u32;
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported DAWG version {}",
+                version
+            )));
+        }
+        let trailer = &bytes[bytes.len() - TRAILER_LEN..];
+        let len = read_uint(&trailer[..8]);
+        let root = read_uint(&trailer[8..16]);
+        let counts_addr = read_uint(&trailer[16..24]) as usize;
+        let width = read_uint(&trailer[24..]) as usize;
+        let counts_len = (len as usize).checked_mul(width);
+        if width > 8
+            || root < HEADER_LEN as u64
+            || root as usize >= counts_addr
+            || counts_len.and_then(|counts_len| counts_len.checked_add(counts_addr))
+                != Some(bytes.len() - TRAILER_LEN)
+        {
+            return Err(invalid_data("Invalid DAWG trailer".to_owned()));
+        }
+        Ok(Dawg {
+            data,
+            len,
+            root,
+            counts_addr,
+            width,
+        })
+    }
+
+    fn state(&self, addr: u64) -> StateRef<'_> {
+        let bytes = &self.data.as_ref()[addr as usize..];
+        let (len, len_bytes) = read_leb128(&bytes[1..]);
+        let (n_transitions, n_bytes) = read_leb128(&bytes[1 + len_bytes..]);
+        StateRef {
+            is_final: bytes[0] == 1,
+            len,
+            n_transitions: n_transitions as usize,
+            transitions: &bytes[1 + len_bytes + n_bytes..],
+        }
+    }
+
+    /// Get the transitions of a state as inputs and target addresses.
+    fn transitions(&self, addr: u64) -> impl Iterator<Item = (u8, u64)> + '_ {
+        let state = self.state(addr);
+        let mut bytes = state.transitions;
+        (0..state.n_transitions).map(move |_| {
+            let input = bytes[0];
+            let (distance, len) = read_leb128(&bytes[1..]);
+            bytes = &bytes[1 + len..];
+            (input, addr - distance)
+        })
+    }
+
+    /// Follow a key from the start state, returning the reached state and
+    /// the number of tokens before the key in byte order.
+    fn walk(&self, key: &[u8]) -> Option<(u64, u64)> {
+        key.iter()
+            .try_fold((self.root, 0), |(addr, mut idx), &input| {
+                if self.state(addr).is_final {
+                    idx += 1;
+                }
+                for (transition_input, target) in self.transitions(addr) {
+                    if transition_input == input {
+                        return Some((target, idx));
+                    }
+                    idx += self.state(target).len;
+                }
+                None
+            })
+    }
+
+    fn count(&self, idx: u64) -> u64 {
+        let start = self.counts_addr + idx as usize * self.width;
+        read_uint(&self.data.as_ref()[start..start + self.width])
+    }
+
+    /// Get the index of a token in byte order.
+    pub fn index(&self, key: impl AsRef<[u8]>) -> Option<u64> {
+        let (addr, idx) = self.walk(key.as_ref())?;
+        if self.state(addr).is_final {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+
+    /// Get the count of a token.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<u64> {
+        self.index(key).map(|idx| self.count(idx))
+    }
+
+    /// Get the tokens starting with a prefix and their counts in byte
+    /// order.
+    pub fn prefix(&self, prefix: impl AsRef<[u8]>) -> Vec<(Vec<u8>, u64)> {
+        let prefix = prefix.as_ref();
+        let mut entries = Vec::new();
+        if let Some((addr, idx)) = self.walk(prefix) {
+            self.collect(addr, idx, &mut prefix.to_vec(), &mut entries);
+        }
+        entries
+    }
+
+    fn collect(
+        &self,
+        addr: u64,
+        mut idx: u64,
+        key: &mut Vec<u8>,
+        entries: &mut Vec<(Vec<u8>, u64)>,
+    ) {
+        if self.state(addr).is_final {
+            entries.push((key.clone(), self.count(idx)));
+            idx += 1;
+        }
+        for (input, target) in self.transitions(addr) {
+            key.push(input);
+            self.collect(target, idx, key, entries);
+            key.pop();
+            idx += self.state(target).len;
+        }
+    }
+
+    /// Get all tokens and their counts in byte order.
+    pub fn entries(&self) -> Vec<(Vec<u8>, u64)> {
+        self.prefix(b"")
+    }
+
+    /// Get the number of tokens.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Check whether the DAWG has no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
@@ -0,0 +1,47 @@
+//! Dispersion of tokens over the documents of a corpus.
+
+/// Dispersion measures of a token.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dispersion {
+    /// Juilland's D, 1 for tokens with the same relative frequency in every
+    /// document.
+    pub juilland_d: f64,
+    /// Gries' DP, 0 for tokens that are distributed like the corpus.
+    pub dp: f64,
+}
+
+impl Dispersion {
+    /// Get the dispersion of a token with `count` occurrences.
+    ///
+    /// `docs` are the counts of the token and the lengths of the documents
+    /// that contain it, out of `num_documents` documents with `num_tokens`
+    /// tokens. D is undefined for fewer than two documents and is 0 then.
+    pub fn new(
+        count: usize,
+        docs: &[(usize, usize)],
+        num_documents: usize,
+        num_tokens: usize,
+    ) -> Self {
+        let n = num_documents as f64;
+        let (mut sum, mut sum_sq) = (0., 0.);
+        let (mut diff, mut doc_share) = (0., 0.);
+        for &(doc_count, doc_len) in docs {
+            let rel_freq = doc_count as f64 / doc_len as f64;
+            sum += rel_freq;
+            sum_sq += rel_freq * rel_freq;
+            let share = doc_len as f64 / num_tokens as f64;
+            diff += (doc_count as f64 / count as f64 - share).abs();
+            doc_share += share;
+        }
+        let juilland_d = if num_documents < 2 || sum == 0. {
+            0.
+        } else {
+            let mean = sum / n;
+            let sd = (sum_sq / n - mean * mean).max(0.).sqrt();
+            1. - (sd / mean) / (n - 1.).sqrt()
+        };
+        // Documents without the token differ by their share of the corpus.
+        let dp = 0.5 * (diff + (1. - doc_share).max(0.));
+        Dispersion { juilland_d, dp }
+    }
+}
@@ -0,0 +1,265 @@
+//! Vocabularies of embedding files.
+//!
+//! Only the vocabulary of the embeddings is read, to check which tokens
+//! have embeddings. finalfusion files are read chunk by chunk, skipping
+//! all chunks but the vocabulary.
+
+use std::collections::HashSet;
+use std::io::{self, BufRead, Read};
+use std::str::FromStr;
+
+use crate::ngrams::NGrams;
+
+/// Format of an embedding file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EmbeddingFormat {
+    /// finalfusion, with a simple, bucketed or explicit subword vocabulary.
+    FinalFusion,
+    /// Text with a word and its vector per line and an optional
+    /// `count dims` header, as of fastText's `.vec` files and GloVe.
+    Text,
+    /// Binary word2vec.
+    Word2Vec,
+}
+
+impl EmbeddingFormat {
+    pub fn variants() -> &'static [&'static str] {
+        &["finalfusion", "text", "word2vec"]
+    }
+}
+
+impl FromStr for EmbeddingFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "finalfusion" => Ok(EmbeddingFormat::FinalFusion),
+            "text" => Ok(EmbeddingFormat::Text),
+            "word2vec" => Ok(EmbeddingFormat::Word2Vec),
+            _ => Err(format!("Unknown embedding format: {}", s)),
+        }
+    }
+}
+
+/// Subword n-grams of the embeddings.
+#[derive(Clone, Debug)]
+pub enum Subwords {
+    None,
+    /// N-grams are hashed into buckets, every n-gram has an embedding.
+    Buckets {
+        min_n: usize,
+        max_n: usize,
+    },
+    /// Only the n-grams of the vocabulary have embeddings.
+    Explicit {
+        min_n: usize,
+        max_n: usize,
+        ngrams: HashSet<String>,
+    },
+}
+
+/// Vocabulary of an embedding file.
+#[derive(Clone, Debug)]
+pub struct EmbeddingVocab {
+    pub words: HashSet<String>,
+    pub subwords: Subwords,
+}
+
+impl EmbeddingVocab {
+    /// Check whether a token has an embedding through its n-grams.
+    ///
+    /// The n-grams are taken from the bracketed token `<token>`.
+    pub fn has_subwords(&self, token: &str) -> bool {
+        let bracketed = format!("<{}>", token);
+        match &self.subwords {
+            Subwords::None => false,
+            Subwords::Buckets { min_n, max_n } => {
+                NGrams::new(&bracketed, *min_n, *max_n).next().is_some()
+            }
+            Subwords::Explicit {
+                min_n,
+                max_n,
+                ngrams,
+            } => NGrams::new(&bracketed, *min_n, *max_n).any(|ngram| ngrams.contains(ngram)),
+        }
+    }
+}
+
+const FINALFUSION_MAGIC: &[u8; 4] = b"FiFu";
+
+/// Identifiers of the vocabulary chunks of finalfusion.
+const SIMPLE_VOCAB: u32 = 1;
+const BUCKET_SUBWORD_VOCAB: u32 = 3;
+const FASTTEXT_SUBWORD_VOCAB: u32 = 7;
+const EXPLICIT_SUBWORD_VOCAB: u32 = 8;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Read a string prefixed by its length in bytes.
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let len = read_u32(reader)? as u64;
+    // The length is untrusted, the string grows as it is read.
+    let mut buf = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut buf)?;
+    if (buf.len() as u64) < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Truncated vocabulary",
+        ));
+    }
+    String::from_utf8(buf).map_err(|_| invalid_data("Invalid UTF-8 in vocabulary"))
+}
+
+fn read_words(reader: &mut impl Read, len: u64) -> io::Result<HashSet<String>> {
+    (0..len).map(|_| read_string(reader)).collect()
+}
+
+/// Read the vocabulary of an embedding file.
+pub fn read_vocab(reader: impl BufRead, format: EmbeddingFormat) -> io::Result<EmbeddingVocab> {
+    match format {
+        EmbeddingFormat::FinalFusion => read_finalfusion_vocab(reader),
+        EmbeddingFormat::Text => read_text_vocab(reader),
+        EmbeddingFormat::Word2Vec => read_word2vec_vocab(reader),
+    }
+}
+
+fn read_finalfusion_vocab(mut reader: impl Read) -> io::Result<EmbeddingVocab> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != FINALFUSION_MAGIC {
+        return Err(invalid_data("Not a finalfusion file"));
+    }
+    let version = read_u32(&mut reader)?;
+    if version != 1 {
+        return Err(invalid_data(format!(
+            "Unsupported finalfusion version {}",
+            version
+        )));
+    }
+    let n_chunks = read_u32(&mut reader)?;
+    for _ in 0..n_chunks {
+        read_u32(&mut reader)?;
+    }
+
+    for _ in 0..n_chunks {
+        let chunk = read_u32(&mut reader)?;
+        let len = read_u64(&mut reader)?;
+        match chunk {
+            SIMPLE_VOCAB => {
+                let vocab_len = read_u64(&mut reader)?;
+                return Ok(EmbeddingVocab {
+                    words: read_words(&mut reader, vocab_len)?,
+                    subwords: Subwords::None,
+                });
+            }
+            BUCKET_SUBWORD_VOCAB | FASTTEXT_SUBWORD_VOCAB => {
+                let vocab_len = read_u64(&mut reader)?;
+                let min_n = read_u32(&mut reader)? as usize;
+                let max_n = read_u32(&mut reader)? as usize;
+                let _buckets_exp = read_u32(&mut reader)?;
+                return Ok(EmbeddingVocab {
+                    words: read_words(&mut reader, vocab_len)?,
+                    subwords: Subwords::Buckets { min_n, max_n },
+                });
+            }
+            EXPLICIT_SUBWORD_VOCAB => {
+                let vocab_len = read_u64(&mut reader)?;
+                let ngrams_len = read_u64(&mut reader)?;
+                let min_n = read_u32(&mut reader)? as usize;
+                let max_n = read_u32(&mut reader)? as usize;
+                let words = read_words(&mut reader, vocab_len)?;
+                let ngrams = (0..ngrams_len)
+                    .map(|_| {
+                        let ngram = read_string(&mut reader)?;
+                        // The index of the n-gram.
+                        read_u64(&mut reader)?;
+                        Ok(ngram)
+                    })
+                    .collect::<io::Result<_>>()?;
+                return Ok(EmbeddingVocab {
+                    words,
+                    subwords: Subwords::Explicit {
+                        min_n,
+                        max_n,
+                        ngrams,
+                    },
+                });
+            }
+            _ => {
+                let skipped = io::copy(&mut (&mut reader).take(len), &mut io::sink())?;
+                if skipped != len {
+                    return Err(invalid_data("Truncated finalfusion chunk"));
+                }
+            }
+        }
+    }
+    Err(invalid_data("No vocabulary in finalfusion file"))
+}
+
+fn read_text_vocab(reader: impl BufRead) -> io::Result<EmbeddingVocab> {
+    let mut words = HashSet::new();
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = line?;
+        let fields = line.split(' ').collect::<Vec<_>>();
+        // fastText's header of the number of words and dimensions.
+        if line_no == 0 && fields.len() == 2 && fields.iter().all(|f| f.parse::<usize>().is_ok()) {
+            continue;
+        }
+        if !fields[0].is_empty() {
+            words.insert(fields[0].to_owned());
+        }
+    }
+    Ok(EmbeddingVocab {
+        words,
+        subwords: Subwords::None,
+    })
+}
+
+fn read_word2vec_vocab(mut reader: impl BufRead) -> io::Result<EmbeddingVocab> {
+    let mut header = String::new();
+    reader.read_line(&mut header)?;
+    let mut fields = header.split_whitespace().map(|f| f.parse::<usize>());
+    let (len, dims) = match (fields.next(), fields.next()) {
+        (Some(Ok(len)), Some(Ok(dims))) => (len, dims),
+        _ => return Err(invalid_data("Invalid word2vec header")),
+    };
+    let vector_len = dims
+        .checked_mul(4)
+        .ok_or_else(|| invalid_data("Invalid word2vec header"))? as u64;
+    // The header is untrusted, the vocabulary grows as it is read.
+    let mut words = HashSet::new();
+    let mut word = Vec::new();
+    for _ in 0..len {
+        word.clear();
+        reader.read_until(b' ', &mut word)?;
+        if word.pop() != Some(b' ') {
+            return Err(invalid_data("Truncated word2vec file"));
+        }
+        let skipped = io::copy(&mut (&mut reader).take(vector_len), &mut io::sink())?;
+        if skipped != vector_len {
+            return Err(invalid_data("Truncated word2vec file"));
+        }
+        // Words are preceded by the line break after the previous vector.
+        let start = word.iter().take_while(|&&b| b == b'\n').count();
+        let word = String::from_utf8_lossy(&word[start..]).into_owned();
+        words.insert(word);
+    }
+    Ok(EmbeddingVocab {
+        words,
+        subwords: Subwords::None,
+    })
+}
@@ -0,0 +1,115 @@
+//! Emoji detection.
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/// Handling of emoji in tokens.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum EmojiPolicy {
+    /// Count emoji as part of the tokens.
+    #[default]
+    Keep,
+    /// Remove emoji from tokens.
+    Drop,
+    /// Remove emoji from tokens and count them separately.
+    Separate,
+}
+
+impl EmojiPolicy {
+    pub fn variants() -> &'static [&'static str] {
+        &["keep", "drop", "separate"]
+    }
+}
+
+impl FromStr for EmojiPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(EmojiPolicy::Keep),
+            "drop" => Ok(EmojiPolicy::Drop),
+            "separate" => Ok(EmojiPolicy::Separate),
+            _ => Err(format!("Unknown emoji policy: {}", s)),
+        }
+    }
+}
+
+/// Check whether a character is a pictographic emoji or symbol.
+pub fn is_emoji(c: char) -> bool {
+    matches!(c as u32,
+        0x203C | 0x2049 | 0x2122 | 0x2139
+        | 0x2194..=0x21AA
+        | 0x231A..=0x23FF
+        | 0x24C2
+        | 0x25AA..=0x25FE
+        | 0x2600..=0x27BF
+        | 0x2934..=0x2935
+        | 0x2B05..=0x2B55
+        | 0x3030 | 0x303D | 0x3297 | 0x3299
+        | 0x1F000..=0x1FAFF)
+}
+
+/// Check whether a character can continue an emoji sequence.
+///
+/// These are the zero width joiner, variation selectors, the combining
+/// keycap, skin tone modifiers and tag characters.
+fn is_emoji_component(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x200D | 0xFE0E | 0xFE0F | 0x20E3 | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F
+    )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+/// Get the length of the emoji sequence at the start of `s`.
+///
+/// The sequence is a single emoji with its components, emoji joined by
+/// zero width joiners or a pair of regional indicators forming a flag.
+fn sequence_len(s: &str) -> usize {
+    let mut chars = s.chars();
+    let first = chars.next().expect("Empty emoji sequence");
+    let mut len = first.len_utf8();
+    let mut flag_start = is_regional_indicator(first);
+    let mut joined = false;
+    for c in chars {
+        let continues = if joined {
+            is_emoji(c)
+        } else {
+            (flag_start && is_regional_indicator(c)) || is_emoji_component(c)
+        };
+        if !continues {
+            break;
+        }
+        flag_start = false;
+        joined = c == '\u{200D}';
+        len += c.len_utf8();
+    }
+    len
+}
+
+/// Remove the emoji sequences from a token.
+///
+/// `f` is called with every emoji sequence, the token is borrowed if it
+/// does not contain emoji. ZWJ sequences, flags and emoji with modifiers
+/// are kept together as one sequence.
+pub fn strip_emoji<'a>(token: &'a str, mut f: impl FnMut(&'a str)) -> Cow<'a, str> {
+    let start = match token.find(is_emoji) {
+        Some(start) => start,
+        None => return Cow::Borrowed(token),
+    };
+    let mut text = String::with_capacity(token.len());
+    text.push_str(&token[..start]);
+    let mut rest = &token[start..];
+    while !rest.is_empty() {
+        let seq_len = sequence_len(rest);
+        f(&rest[..seq_len]);
+        rest = &rest[seq_len..];
+        let text_len = rest.find(is_emoji).unwrap_or(rest.len());
+        text.push_str(&rest[..text_len]);
+        rest = &rest[text_len..];
+    }
+    Cow::Owned(text)
+}
@@ -0,0 +1,299 @@
+//! Dictionaries in the order of fastText.
+//!
+//! fastText splits the corpus at whitespace, adds an end-of-sentence token
+//! for every line break and prunes rare words while reading once the
+//! dictionary grows too large. Words are then sorted with `std::sort` of
+//! libstdc++, which is not stable, so the order of words with equal counts
+//! depends on the sorting algorithm. The algorithm is reproduced here, so
+//! that vocabularies align index-for-index with fastText models.
+
+use std::collections::HashMap;
+
+/// End-of-sentence token of fastText.
+pub const EOS: &str = "</s>";
+
+/// Maximum vocabulary size of fastText, words are pruned while reading if
+/// the dictionary exceeds 75% of it.
+pub const MAX_VOCAB_SIZE: usize = 30_000_000;
+
+/// Type of a dictionary entry.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum EntryType {
+    Word,
+    Label,
+}
+
+/// Entry of a dictionary.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    pub word: String,
+    pub count: usize,
+    pub entry_type: EntryType,
+}
+
+/// Dictionary built like `Dictionary::readFromFile` of fastText.
+#[derive(Clone, Debug)]
+pub struct Dictionary {
+    entries: Vec<Entry>,
+    indices: HashMap<String, usize>,
+    label_prefix: String,
+    max_vocab_size: usize,
+    min_threshold: usize,
+    tokens: usize,
+}
+
+impl Dictionary {
+    /// Create a dictionary, words with the prefix are labels.
+    pub fn new(label_prefix: &str) -> Self {
+        Self::with_max_vocab_size(label_prefix, MAX_VOCAB_SIZE)
+    }
+
+    /// Create a dictionary with another maximum vocabulary size.
+    pub fn with_max_vocab_size(label_prefix: &str, max_vocab_size: usize) -> Self {
+        Dictionary {
+            entries: Vec::new(),
+            indices: HashMap::new(),
+            label_prefix: label_prefix.to_owned(),
+            max_vocab_size,
+            min_threshold: 1,
+            tokens: 0,
+        }
+    }
+
+    /// Add a word, pruning rare words if the dictionary is too large.
+    pub fn add(&mut self, word: &str) {
+        self.tokens += 1;
+        match self.indices.get(word) {
+            Some(&idx) => self.entries[idx].count += 1,
+            None => {
+                let entry_type = if word.starts_with(&self.label_prefix) {
+                    EntryType::Label
+                } else {
+                    EntryType::Word
+                };
+                self.indices.insert(word.to_owned(), self.entries.len());
+                self.entries.push(Entry {
+                    word: word.to_owned(),
+                    count: 1,
+                    entry_type,
+                });
+            }
+        }
+        if self.entries.len() as f64 > 0.75 * self.max_vocab_size as f64 {
+            self.min_threshold += 1;
+            self.threshold(self.min_threshold, self.min_threshold);
+        }
+    }
+
+    /// Add the words of a line followed by the end-of-sentence token.
+    pub fn add_line(&mut self, line: &str) {
+        for word in line
+            .split(&[' ', '\r', '\t', '\u{b}', '\u{c}', '\0'][..])
+            .filter(|word| !word.is_empty())
+        {
+            self.add(word);
+        }
+        self.add(EOS);
+    }
+
+    /// Sort the entries and remove rare words and labels.
+    ///
+    /// Words come before labels, both sorted by descending count.
+    pub fn threshold(&mut self, min_count: usize, min_count_label: usize) {
+        let entries = &self.entries;
+        let mut order = (0..entries.len()).collect::<Vec<_>>();
+        std_sort(&mut order, |&idx1, &idx2| {
+            let (e1, e2) = (&entries[idx1], &entries[idx2]);
+            if e1.entry_type != e2.entry_type {
+                e1.entry_type < e2.entry_type
+            } else {
+                e1.count > e2.count
+            }
+        });
+        let mut entries = std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        self.entries = order
+            .into_iter()
+            .filter_map(|idx| entries[idx].take())
+            .filter(|entry| match entry.entry_type {
+                EntryType::Word => entry.count >= min_count,
+                EntryType::Label => entry.count >= min_count_label,
+            })
+            .collect();
+        self.indices = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| (entry.word.clone(), idx))
+            .collect();
+    }
+
+    /// Get the entries in dictionary order.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Get the number of tokens read.
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+}
+
+/// Sort like `std::sort` of libstdc++, an introsort.
+///
+/// `less` is the strict weak ordering of the C++ comparator.
+pub fn std_sort<T: Copy>(v: &mut [T], less: impl Fn(&T, &T) -> bool) {
+    if v.len() > 1 {
+        let depth_limit = 2 * (usize::BITS - 1 - v.len().leading_zeros()) as usize;
+        introsort_loop(v, depth_limit, &less);
+        final_insertion_sort(v, &less);
+    }
+}
+
+const THRESHOLD: usize = 16;
+
+fn introsort_loop<T: Copy>(
+    mut v: &mut [T],
+    mut depth_limit: usize,
+    less: &impl Fn(&T, &T) -> bool,
+) {
+    while v.len() > THRESHOLD {
+        if depth_limit == 0 {
+            heap_sort(v, less);
+            return;
+        }
+        depth_limit -= 1;
+        let cut = partition_pivot(v, less);
+        let (left, right) = v.split_at_mut(cut);
+        introsort_loop(right, depth_limit, less);
+        v = left;
+    }
+}
+
+fn partition_pivot<T: Copy>(v: &mut [T], less: &impl Fn(&T, &T) -> bool) -> usize {
+    let mid = v.len() / 2;
+    let last = v.len() - 1;
+    move_median_to_first(v, 1, mid, last, less);
+    let pivot = v[0];
+    let (mut first, mut last) = (1, v.len());
+    loop {
+        while less(&v[first], &pivot) {
+            first += 1;
+        }
+        last -= 1;
+        while less(&pivot, &v[last]) {
+            last -= 1;
+        }
+        if first >= last {
+            return first;
+        }
+        v.swap(first, last);
+        first += 1;
+    }
+}
+
+fn move_median_to_first<T: Copy>(
+    v: &mut [T],
+    a: usize,
+    b: usize,
+    c: usize,
+    less: &impl Fn(&T, &T) -> bool,
+) {
+    let median = if less(&v[a], &v[b]) {
+        if less(&v[b], &v[c]) {
+            b
+        } else if less(&v[a], &v[c]) {
+            c
+        } else {
+            a
+        }
+    } else if less(&v[a], &v[c]) {
+        a
+    } else if less(&v[b], &v[c]) {
+        c
+    } else {
+        b
+    };
+    v.swap(0, median);
+}
+
+fn final_insertion_sort<T: Copy>(v: &mut [T], less: &impl Fn(&T, &T) -> bool) {
+    if v.len() > THRESHOLD {
+        insertion_sort(&mut v[..THRESHOLD], less);
+        for idx in THRESHOLD..v.len() {
+            unguarded_linear_insert(v, idx, less);
+        }
+    } else {
+        insertion_sort(v, less);
+    }
+}
+
+fn insertion_sort<T: Copy>(v: &mut [T], less: &impl Fn(&T, &T) -> bool) {
+    for idx in 1..v.len() {
+        if less(&v[idx], &v[0]) {
+            v[..=idx].rotate_right(1);
+        } else {
+            unguarded_linear_insert(v, idx, less);
+        }
+    }
+}
+
+fn unguarded_linear_insert<T: Copy>(v: &mut [T], mut idx: usize, less: &impl Fn(&T, &T) -> bool) {
+    let value = v[idx];
+    while less(&value, &v[idx - 1]) {
+        v[idx] = v[idx - 1];
+        idx -= 1;
+    }
+    v[idx] = value;
+}
+
+fn heap_sort<T: Copy>(v: &mut [T], less: &impl Fn(&T, &T) -> bool) {
+    let len = v.len();
+    if len > 1 {
+        for parent in (0..=(len - 2) / 2).rev() {
+            let value = v[parent];
+            adjust_heap(v, parent, len, value, less);
+        }
+    }
+    for last in (1..len).rev() {
+        let value = v[last];
+        v[last] = v[0];
+        adjust_heap(v, 0, last, value, less);
+    }
+}
+
+fn adjust_heap<T: Copy>(
+    v: &mut [T],
+    mut hole: usize,
+    len: usize,
+    value: T,
+    less: &impl Fn(&T, &T) -> bool,
+) {
+    let top = hole;
+    let mut child = hole;
+    while child < len.saturating_sub(1) / 2 {
+        child = 2 * (child + 1);
+        if less(&v[child], &v[child - 1]) {
+            child -= 1;
+        }
+        v[hole] = v[child];
+        hole = child;
+    }
+    if len.is_multiple_of(2) && len >= 2 && child == (len - 2) / 2 {
+        child = 2 * (child + 1);
+        v[hole] = v[child - 1];
+        hole = child - 1;
+    }
+    // Push the value up from the hole.
+    while hole > top {
+        let parent = (hole - 1) / 2;
+        if !less(&v[parent], &value) {
+            break;
+        }
+        v[hole] = v[parent];
+        hole = parent;
+    }
+    v[hole] = value;
+}
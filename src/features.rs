@@ -0,0 +1,168 @@
+//! Feature ranking of tokens for text classification.
+//!
+//! Tokens are ranked by the association of their occurrence in lines with
+//! the labels of the lines, following Yang and Pedersen (1997).
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::count::LabelCounts;
+
+/// Measures of the association between tokens and labels.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FeatureMeasure {
+    /// Maximum chi-square statistic over the labels.
+    ChiSquare,
+    /// Information gain about the label.
+    InfoGain,
+}
+
+impl FeatureMeasure {
+    pub fn variants() -> &'static [&'static str] {
+        &["chi2", "ig"]
+    }
+}
+
+impl FromStr for FeatureMeasure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chi2" => Ok(FeatureMeasure::ChiSquare),
+            "ig" => Ok(FeatureMeasure::InfoGain),
+            _ => Err(format!("Unknown feature measure: {}", s)),
+        }
+    }
+}
+
+/// Get `-p ln(p)`, which is 0 for `p = 0`.
+fn entropy_term(p: f64) -> f64 {
+    if p > 0. {
+        -p * p.ln()
+    } else {
+        0.
+    }
+}
+
+/// Get the entropy of a distribution given by counts.
+fn entropy(counts: impl Iterator<Item = f64>, total: f64) -> f64 {
+    if total == 0. {
+        return 0.;
+    }
+    counts.map(|count| entropy_term(count / total)).sum()
+}
+
+/// Rank the tokens of labeled lines.
+///
+/// Returns the tokens with their scores and the label they are most
+/// associated with, sorted by descending scores. For chi-square this is the
+/// label with the highest statistic, for information gain the label that
+/// is most probable given the token.
+pub fn rank(
+    labels: &HashMap<String, LabelCounts>,
+    measure: FeatureMeasure,
+) -> Vec<(String, f64, String)> {
+    let mut labels = labels.iter().collect::<Vec<_>>();
+    labels.sort_unstable_by_key(|(label, _)| *label);
+    let lines = labels
+        .iter()
+        .map(|(_, counts)| counts.lines as f64)
+        .collect::<Vec<_>>();
+    let total = lines.iter().sum::<f64>();
+    let prior_entropy = entropy(lines.iter().copied(), total);
+
+    // Number of lines of every label with a token.
+    let mut line_freqs: HashMap<&str, Vec<f64>> = HashMap::new();
+    for (idx, (_, counts)) in labels.iter().enumerate() {
+        for (token, &freq) in &counts.line_freqs {
+            line_freqs
+                .entry(token.as_str())
+                .or_insert_with(|| vec![0.; labels.len()])[idx] = freq as f64;
+        }
+    }
+
+    let mut ranking = line_freqs
+        .into_iter()
+        .map(|(token, with_token)| {
+            let token_lines = with_token.iter().sum::<f64>();
+            let (score, best) = match measure {
+                FeatureMeasure::ChiSquare => {
+                    // Statistics of the labels and whether the token is
+                    // positively associated with them.
+                    let stats = with_token
+                        .iter()
+                        .zip(&lines)
+                        .map(|(&a, &label_lines)| {
+                            // Contingency table of label and token occurrence.
+                            let b = token_lines - a;
+                            let c = label_lines - a;
+                            let d = total - a - b - c;
+                            let denom = (a + b) * (c + d) * (a + c) * (b + d);
+                            let chi2 = if denom == 0. {
+                                0.
+                            } else {
+                                total * (a * d - b * c).powi(2) / denom
+                            };
+                            (a * d > b * c, chi2)
+                        })
+                        .collect::<Vec<_>>();
+                    let score = stats.iter().map(|&(_, chi2)| chi2).fold(0., f64::max);
+                    let best = (0..stats.len())
+                        .max_by(|&idx1, &idx2| {
+                            let (positive1, chi2_1) = stats[idx1];
+                            let (positive2, chi2_2) = stats[idx2];
+                            positive1.cmp(&positive2).then(chi2_1.total_cmp(&chi2_2))
+                        })
+                        .unwrap_or(0);
+                    (score, best)
+                }
+                FeatureMeasure::InfoGain => {
+                    let without_token = with_token
+                        .iter()
+                        .zip(&lines)
+                        .map(|(&with, &label_lines)| label_lines - with);
+                    let conditional = token_lines / total
+                        * entropy(with_token.iter().copied(), token_lines)
+                        + (total - token_lines) / total
+                            * entropy(without_token, total - token_lines);
+                    let best = (0..with_token.len())
+                        .max_by(|&idx1, &idx2| with_token[idx1].total_cmp(&with_token[idx2]))
+                        .unwrap_or(0);
+                    (prior_entropy - conditional, best)
+                }
+            };
+            (token.to_owned(), score, labels[best].0.clone())
+        })
+        .collect::<Vec<_>>();
+    ranking.sort_unstable_by(|(token1, score1, _), (token2, score2, _)| {
+        score2.total_cmp(score1).then(token1.cmp(token2))
+    });
+    ranking
+}
+
+/// Write `token<TAB>lines<TAB>score<TAB>label` lines of a ranking.
+pub fn write_ranking(
+    mut writer: impl Write,
+    labels: &HashMap<String, LabelCounts>,
+    measure: FeatureMeasure,
+) -> io::Result<()> {
+    let mut lines = HashMap::new();
+    for counts in labels.values() {
+        for (token, &freq) in &counts.line_freqs {
+            *lines.entry(token.as_str()).or_insert(0) += freq;
+        }
+    }
+    writeln!(writer, "token\tlines\tscore\tlabel")?;
+    for (token, score, label) in rank(labels, measure) {
+        writeln!(
+            writer,
+            "{}\t{}\t{:.6}\t{}",
+            token,
+            lines[token.as_str()],
+            score,
+            label
+        )?;
+    }
+    writer.flush()
+}
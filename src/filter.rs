@@ -0,0 +1,45 @@
+//! Token filters applied before counting.
+
+use crate::regex::Regex;
+use crate::script::{Script, TokenScript};
+
+/// Filter for tokens.
+///
+/// The default filter keeps all tokens.
+#[derive(Clone, Debug, Default)]
+pub struct TokenFilter {
+    /// Only keep tokens of these scripts, all scripts if empty.
+    pub scripts: Vec<Script>,
+    /// Drop tokens with characters that are not alphabetic.
+    pub drop_non_alpha: bool,
+    /// Drop numbers, i.e. tokens with numeric and without alphabetic
+    /// characters.
+    pub drop_numeric: bool,
+    /// Drop tokens that are fully matched by any of the patterns.
+    pub drop_patterns: Vec<Regex>,
+}
+
+impl TokenFilter {
+    /// Check whether the filter keeps a token.
+    pub fn keep(&self, token: &str) -> bool {
+        if !self.scripts.is_empty() {
+            match Script::of_token(token) {
+                TokenScript::Single(script) if self.scripts.contains(&script) => (),
+                _ => return false,
+            }
+        }
+        if self.drop_non_alpha && !token.chars().all(char::is_alphabetic) {
+            return false;
+        }
+        if self.drop_numeric
+            && token.chars().any(char::is_numeric)
+            && !token.chars().any(char::is_alphabetic)
+        {
+            return false;
+        }
+        !self
+            .drop_patterns
+            .iter()
+            .any(|pattern| pattern.is_full_match(token))
+    }
+}
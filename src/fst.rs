@@ -0,0 +1,341 @@
+//! Finite state transducers mapping tokens to values.
+//!
+//! A map is a minimal acyclic transducer of the keys in byte order, built
+//! incrementally from sorted keys as by Daciuk et al. (2000). Values are
+//! the sums of the outputs on the path of a key and the final output of
+//! its last state, as in the `fst` crate. Keys with shared prefixes and
+//! suffixes share states, and lookups and prefix searches run directly on
+//! the serialized bytes, so maps can be memory-mapped.
+//!
+//! The serialized map is the magic bytes `CCFS` and the version 1 as a
+//! little-endian 32-bit integer, followed by the states, the number of
+//! keys and the address of the start state as little-endian 64-bit
+//! integers. States are addressed by their byte offset and written before
+//! the states leading to them. A state is a flag byte, 1 for final states,
+//! the final output as a little-endian 64-bit integer for final states,
+//! the number of transitions as a little-endian 16-bit integer and the
+//! transitions in byte order. A transition is the input byte, the output
+//! and the address of the target state as little-endian 64-bit integers.
+//! This layout is not the one of the `fst` crate.
+
+use std::collections::HashMap;
+use std::io;
+
+use crate::vocab::invalid_data;
+
+const MAGIC: &[u8; 4] = b"CCFS";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 8;
+const TRAILER_LEN: usize = 16;
+const TRANSITION_LEN: usize = 17;
+
+/// Read a little-endian 64-bit integer from the start of the bytes.
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(buf)
+}
+
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+struct State {
+    final_output: Option<u64>,
+    transitions: Vec<(u8, u64, u64)>,
+}
+
+impl State {
+    fn add_output(&mut self, output: u64) {
+        if let Some(final_output) = &mut self.final_output {
+            *final_output += output;
+        }
+        for (_, transition_output, _) in &mut self.transitions {
+            *transition_output += output;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct UnfinishedState {
+    state: State,
+    // The transition to the next unfinished state.
+    last: Option<(u8, u64)>,
+}
+
+/// Builder of maps from keys in byte order.
+#[derive(Clone, Debug)]
+pub struct MapBuilder {
+    data: Vec<u8>,
+    registry: HashMap<State, u64>,
+    unfinished: Vec<UnfinishedState>,
+    last_key: Option<Vec<u8>>,
+    len: u64,
+}
+
+impl Default for MapBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MapBuilder {
+    pub fn new() -> Self {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        MapBuilder {
+            data,
+            registry: HashMap::new(),
+            unfinished: vec![UnfinishedState::default()],
+            last_key: None,
+            len: 0,
+        }
+    }
+
+    /// Insert a key with its value.
+    ///
+    /// Returns an error if the key does not follow the previous key in
+    /// byte order.
+    pub fn insert(&mut self, key: impl AsRef<[u8]>, value: u64) -> Result<(), String> {
+        let key = key.as_ref();
+        if let Some(last_key) = &self.last_key {
+            if key <= last_key.as_slice() {
+                return Err(format!(
+                    "Key {:?} does not follow {:?}",
+                    String::from_utf8_lossy(key),
+                    String::from_utf8_lossy(last_key)
+                ));
+            }
+        }
+
+        // Push the outputs of shared transitions down to their targets,
+        // so that shared transitions have the common output.
+        let mut value = value;
+        let mut prefix_len = 0;
+        while prefix_len < key.len() && prefix_len + 1 < self.unfinished.len() {
+            let (input, output) = match self.unfinished[prefix_len].last {
+                Some((input, output)) if input == key[prefix_len] => (input, output),
+                _ => break,
+            };
+            let common = output.min(value);
+            self.unfinished[prefix_len].last = Some((input, common));
+            self.unfinished[prefix_len + 1]
+                .state
+                .add_output(output - common);
+            if let Some((_, last_output)) = &mut self.unfinished[prefix_len + 1].last {
+                *last_output += output - common;
+            }
+            value -= common;
+            prefix_len += 1;
+        }
+        self.compile_from(prefix_len);
+
+        if prefix_len == key.len() {
+            self.unfinished[prefix_len].state.final_output = Some(value);
+        } else {
+            self.unfinished[prefix_len].last = Some((key[prefix_len], value));
+            for &input in &key[prefix_len + 1..] {
+                self.unfinished.push(UnfinishedState {
+                    state: State::default(),
+                    last: Some((input, 0)),
+                });
+            }
+            self.unfinished.push(UnfinishedState {
+                state: State {
+                    final_output: Some(0),
+                    transitions: Vec::new(),
+                },
+                last: None,
+            });
+        }
+        self.last_key = Some(key.to_vec());
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Compile the unfinished states after the first `len` + 1 states.
+    fn compile_from(&mut self, len: usize) {
+        while self.unfinished.len() > len + 1 {
+            let unfinished = self.unfinished.pop().unwrap();
+            let addr = self.compile(unfinished.state);
+            let parent = self.unfinished.last_mut().unwrap();
+            let (input, output) = parent.last.take().unwrap();
+            parent.state.transitions.push((input, output, addr));
+        }
+    }
+
+    fn compile(&mut self, state: State) -> u64 {
+        if let Some(&addr) = self.registry.get(&state) {
+            return addr;
+        }
+        let addr = self.data.len() as u64;
+        match state.final_output {
+            Some(output) => {
+                self.data.push(1);
+                self.data.extend_from_slice(&output.to_le_bytes());
+            }
+            None => self.data.push(0),
+        }
+        self.data
+            .extend_from_slice(&(state.transitions.len() as u16).to_le_bytes());
+        for &(input, output, target) in &state.transitions {
+            self.data.push(input);
+            self.data.extend_from_slice(&output.to_le_bytes());
+            self.data.extend_from_slice(&target.to_le_bytes());
+        }
+        self.registry.insert(state, addr);
+        addr
+    }
+
+    /// Finish the map and get its serialization.
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.compile_from(0);
+        let root = self.unfinished.pop().unwrap().state;
+        let root = self.compile(root);
+        self.data.extend_from_slice(&self.len.to_le_bytes());
+        self.data.extend_from_slice(&root.to_le_bytes());
+        self.data
+    }
+
+    /// Finish the map.
+    pub fn finish(self) -> Map<Vec<u8>> {
+        Map::new(self.into_bytes()).expect("Built an invalid map")
+    }
+}
+
+/// A map of keys to values, read from its serialization.
+#[derive(Clone, Debug)]
+pub struct Map<D> {
+    data: D,
+    len: u64,
+    root: u64,
+}
+
+/// A state of a serialized map.
+struct StateRef<'a> {
+    final_output: Option<u64>,
+    transitions: &'a [u8],
+}
+
+impl<'a> StateRef<'a> {
+    fn len(&self) -> usize {
+        self.transitions.len() / TRANSITION_LEN
+    }
+
+    fn transition(&self, idx: usize) -> (u8, u64, u64) {
+        let transition = &self.transitions[idx * TRANSITION_LEN..(idx + 1) * TRANSITION_LEN];
+        (
+            transition[0],
+            read_u64(&transition[1..]),
+            read_u64(&transition[9..]),
+        )
+    }
+
+    fn find(&self, input: u8) -> Option<(u64, u64)> {
+        (0..self.len())
+            .map(|idx| self.transition(idx))
+            .find(|&(transition_input, _, _)| transition_input == input)
+            .map(|(_, output, target)| (output, target))
+    }
+}
+
+impl<D: AsRef<[u8]>> Map<D> {
+    /// Read a map from its serialization, e.g. a memory-mapped file.
+    ///
+    /// Only the header and the trailer are checked.
+    pub fn new(data: D) -> io::Result<Self> {
+        let bytes = data.as_ref();
+        if bytes.len() < HEADER_LEN + TRAILER_LEN || &bytes[..4] != MAGIC {
+            return Err(invalid_data("Not a token map".to_owned()));
+        }
+        let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported token map version {}",
+                version
+            )));
+        }
+        let trailer = &bytes[bytes.len() - TRAILER_LEN..];
+        let len = read_u64(trailer);
+        let root = read_u64(&trailer[8..]);
+        if root < HEADER_LEN as u64 || root >= (bytes.len() - TRAILER_LEN) as u64 {
+            return Err(invalid_data("Invalid start state".to_owned()));
+        }
+        Ok(Map { data, len, root })
+    }
+
+    fn state(&self, addr: u64) -> StateRef<'_> {
+        let bytes = &self.data.as_ref()[addr as usize..];
+        let (final_output, rest) = if bytes[0] == 1 {
+            (Some(read_u64(&bytes[1..])), &bytes[9..])
+        } else {
+            (None, &bytes[1..])
+        };
+        let n_transitions = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+        StateRef {
+            final_output,
+            transitions: &rest[2..2 + n_transitions * TRANSITION_LEN],
+        }
+    }
+
+    /// Follow a key from the start state, returning the reached state and
+    /// the sum of the outputs.
+    fn walk(&self, key: &[u8]) -> Option<(u64, u64)> {
+        key.iter().try_fold((self.root, 0), |(addr, sum), &input| {
+            self.state(addr)
+                .find(input)
+                .map(|(output, target)| (target, sum + output))
+        })
+    }
+
+    /// Get the value of a key.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<u64> {
+        let (addr, sum) = self.walk(key.as_ref())?;
+        self.state(addr).final_output.map(|output| sum + output)
+    }
+
+    /// Check whether the map contains a key.
+    pub fn contains_key(&self, key: impl AsRef<[u8]>) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Get the keys starting with a prefix and their values in byte order.
+    pub fn prefix(&self, prefix: impl AsRef<[u8]>) -> Vec<(Vec<u8>, u64)> {
+        let prefix = prefix.as_ref();
+        let mut entries = Vec::new();
+        if let Some((addr, sum)) = self.walk(prefix) {
+            self.collect(addr, sum, &mut prefix.to_vec(), &mut entries);
+        }
+        entries
+    }
+
+    fn collect(&self, addr: u64, sum: u64, key: &mut Vec<u8>, entries: &mut Vec<(Vec<u8>, u64)>) {
+        let state = self.state(addr);
+        if let Some(output) = state.final_output {
+            entries.push((key.clone(), sum + output));
+        }
+        for idx in 0..state.len() {
+            let (input, output, target) = state.transition(idx);
+            key.push(input);
+            self.collect(target, sum + output, key, entries);
+            key.pop();
+        }
+    }
+
+    /// Get all keys and their values in byte order.
+    pub fn entries(&self) -> Vec<(Vec<u8>, u64)> {
+        self.prefix(b"")
+    }
+
+    /// Get the number of keys.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Check whether the map has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the serialization of the map.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+}
@@ -0,0 +1,111 @@
+//! Shell-style glob patterns for selecting corpus files.
+
+use std::str::FromStr;
+
+/// A glob pattern.
+///
+/// Supported are `*` (any characters except `/`), `**` (any characters),
+/// `?` (a single character) and character classes such as `[a-z]` or
+/// `[!0-9]`. Patterns without a `/` are matched against the file name,
+/// other patterns against the whole relative path.
+#[derive(Clone, Debug)]
+pub struct Glob {
+    pattern: Vec<char>,
+    path: bool,
+}
+
+impl Glob {
+    /// Check whether `path` matches the pattern.
+    pub fn matches(&self, path: &str) -> bool {
+        let path = if self.path {
+            path
+        } else {
+            path.rsplit('/').next().unwrap_or(path)
+        };
+        let path = path.chars().collect::<Vec<_>>();
+        matches(&self.pattern, &path)
+    }
+}
+
+impl FromStr for Glob {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut open = false;
+        for c in s.chars() {
+            match c {
+                '[' => open = true,
+                ']' => open = false,
+                _ => (),
+            }
+        }
+        if open {
+            return Err(format!("Unclosed character class in pattern: {}", s));
+        }
+        Ok(Glob {
+            pattern: s.chars().collect(),
+            path: s.contains('/'),
+        })
+    }
+}
+
+/// Check whether any of the patterns matches, an empty set matches all.
+pub fn any_matches(globs: &[Glob], path: &str) -> bool {
+    globs.is_empty() || globs.iter().any(|glob| glob.matches(path))
+}
+
+fn matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => {
+            if pattern.get(1) == Some(&'*') {
+                match pattern[2..].strip_prefix(&['/']) {
+                    // `**/` matches any number of leading directories.
+                    Some(rest) => (0..=text.len())
+                        .filter(|&i| i == 0 || text[i - 1] == '/')
+                        .any(|i| matches(rest, &text[i..])),
+                    None => (0..=text.len()).any(|i| matches(&pattern[2..], &text[i..])),
+                }
+            } else {
+                (0..=text.len())
+                    .take_while(|&i| i == 0 || text[i - 1] != '/')
+                    .any(|i| matches(&pattern[1..], &text[i..]))
+            }
+        }
+        Some('?') => !text.is_empty() && text[0] != '/' && matches(&pattern[1..], &text[1..]),
+        Some('[') => {
+            let c = match text.first() {
+                Some(&c) => c,
+                None => return false,
+            };
+            let end = match pattern.iter().skip(2).position(|&c| c == ']') {
+                Some(idx) => idx + 2,
+                None => return false,
+            };
+            let (negated, class) = match pattern[1] {
+                '!' | '^' => (true, &pattern[2..end]),
+                _ => (false, &pattern[1..end]),
+            };
+            class_matches(class, c) != negated && matches(&pattern[end + 1..], &text[1..])
+        }
+        Some(&p) => text.first() == Some(&p) && matches(&pattern[1..], &text[1..]),
+    }
+}
+
+fn class_matches(class: &[char], c: char) -> bool {
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            if class[i] <= c && c <= class[i + 2] {
+                return true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
@@ -0,0 +1,996 @@
+//! Minimal DEFLATE and gzip compression and decompression.
+//!
+//! Concatenated gzip members, as found in per-record compressed WARC files,
+//! are decoded as one continuous stream. The encoder uses greedy LZ77
+//! matching with hash chains and a dynamic Huffman code per block, which
+//! trades some compression ratio for simplicity.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, BufRead, Read, Write};
+
+/// Size of the DEFLATE back-reference window.
+const WINDOW: usize = 32 * 1024;
+
+/// Number of bytes decoded per refill of the output buffer.
+const CHUNK: usize = 64 * 1024;
+
+const LEN_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LEN_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Check whether the reader starts with the gzip magic bytes.
+///
+/// Nothing is consumed from the reader.
+pub fn is_gzip(reader: &mut impl BufRead) -> io::Result<bool> {
+    let buf = reader.fill_buf()?;
+    Ok(buf.len() >= 2 && buf[0] == 0x1f && buf[1] == 0x8b)
+}
+
+/// Decoder for (possibly multi-member) gzip streams.
+pub struct GzDecoder<R> {
+    bits: BitReader<R>,
+    inflater: Option<Inflater>,
+    window: Window,
+    crc: u32,
+    size: u32,
+    done: bool,
+}
+
+impl<R: BufRead> GzDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        GzDecoder {
+            bits: BitReader::new(reader),
+            inflater: None,
+            window: Window::default(),
+            crc: 0,
+            size: 0,
+            done: false,
+        }
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        let magic = (self.bits.byte()?, self.bits.byte()?, self.bits.byte()?);
+        if magic != (0x1f, 0x8b, 8) {
+            return Err(invalid("not a gzip member"));
+        }
+        let flags = self.bits.byte()?;
+        // mtime, extra flags and OS.
+        for _ in 0..6 {
+            self.bits.byte()?;
+        }
+        if flags & 0x04 != 0 {
+            let len = self.bits.bits(16)?;
+            for _ in 0..len {
+                self.bits.byte()?;
+            }
+        }
+        // File name and comment are zero-terminated.
+        for flag in &[0x08, 0x10] {
+            if flags & flag != 0 {
+                while self.bits.byte()? != 0 {}
+            }
+        }
+        if flags & 0x02 != 0 {
+            self.bits.bits(16)?;
+        }
+        self.inflater = Some(Inflater::default());
+        self.crc = 0;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn read_trailer(&mut self) -> io::Result<()> {
+        self.bits.align();
+        let crc = self.bits.bits(32)?;
+        let size = self.bits.bits(32)?;
+        if crc != self.crc || size != self.size {
+            return Err(invalid("gzip checksum mismatch"));
+        }
+        self.inflater = None;
+        self.done = self.bits.is_eof()?;
+        Ok(())
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        while !self.window.has_data() && !self.done {
+            let inflater = match self.inflater.as_mut() {
+                Some(inflater) => inflater,
+                None => {
+                    self.read_header()?;
+                    continue;
+                }
+            };
+            let start = self.window.compact();
+            let finished = inflater.inflate(&mut self.bits, &mut self.window.buf, CHUNK)?;
+            let produced = &self.window.buf[start..];
+            self.crc = crc32(self.crc, produced);
+            self.size = self.size.wrapping_add(produced.len() as u32);
+            if finished {
+                self.read_trailer()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for GzDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fill()?;
+        Ok(self.window.read(buf))
+    }
+}
+
+/// Decoder for raw DEFLATE streams.
+pub struct DeflateDecoder<R> {
+    bits: BitReader<R>,
+    inflater: Inflater,
+    window: Window,
+    done: bool,
+}
+
+impl<R: BufRead> DeflateDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        DeflateDecoder {
+            bits: BitReader::new(reader),
+            inflater: Inflater::default(),
+            window: Window::default(),
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Read for DeflateDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !self.window.has_data() && !self.done {
+            self.window.compact();
+            self.done = self
+                .inflater
+                .inflate(&mut self.bits, &mut self.window.buf, CHUNK)?;
+        }
+        Ok(self.window.read(buf))
+    }
+}
+
+/// Decoded output that also serves as back-reference history.
+#[derive(Default)]
+struct Window {
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Window {
+    fn has_data(&self) -> bool {
+        self.pos < self.buf.len()
+    }
+
+    /// Drop consumed output beyond the history window.
+    ///
+    /// Returns the offset at which newly decoded data will start.
+    fn compact(&mut self) -> usize {
+        if self.buf.len() > WINDOW {
+            self.buf.drain(..self.buf.len() - WINDOW);
+        }
+        self.pos = self.buf.len();
+        self.pos
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let n = buf.len().min(self.buf.len() - self.pos);
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        n
+    }
+}
+
+/// LSB-first bit reader.
+struct BitReader<R> {
+    inner: R,
+    buf: u64,
+    nbits: u32,
+}
+
+impl<R: BufRead> BitReader<R> {
+    fn new(inner: R) -> Self {
+        BitReader {
+            inner,
+            buf: 0,
+            nbits: 0,
+        }
+    }
+
+    fn refill(&mut self) -> io::Result<()> {
+        while self.nbits <= 56 {
+            let data = self.inner.fill_buf()?;
+            if data.is_empty() {
+                break;
+            }
+            let n = data.len().min(((64 - self.nbits) / 8) as usize);
+            for &byte in &data[..n] {
+                self.buf |= u64::from(byte) << self.nbits;
+                self.nbits += 8;
+            }
+            self.inner.consume(n);
+        }
+        Ok(())
+    }
+
+    /// Peek at up to `n` bits, returns the bits and how many are valid.
+    fn peek(&mut self, n: u32) -> io::Result<(u32, u32)> {
+        if self.nbits < n {
+            self.refill()?;
+        }
+        Ok(((self.buf & ((1 << n) - 1)) as u32, self.nbits))
+    }
+
+    fn consume(&mut self, n: u32) {
+        self.buf >>= n;
+        self.nbits -= n;
+    }
+
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        let (value, avail) = self.peek(n)?;
+        if avail < n {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated compressed stream",
+            ));
+        }
+        self.consume(n);
+        Ok(value)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        self.bits(8).map(|b| b as u8)
+    }
+
+    fn align(&mut self) {
+        self.consume(self.nbits % 8);
+    }
+
+    /// Check for the end of input, only meaningful at byte boundaries.
+    fn is_eof(&mut self) -> io::Result<bool> {
+        if self.nbits >= 8 {
+            return Ok(false);
+        }
+        Ok(self.inner.fill_buf()?.is_empty())
+    }
+}
+
+/// Canonical Huffman code with a single-level lookup table.
+struct Huffman {
+    /// Entries are `symbol << 4 | code length`, zero marks invalid codes.
+    table: Vec<u16>,
+    max_len: u32,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> io::Result<Self> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let max_len = (1..16).rev().find(|&l| counts[l] != 0).unwrap_or(1) as u32;
+
+        let mut next_code = [0u32; 16];
+        let mut code = 0u32;
+        let mut left = 1i32;
+        for len in 1..16 {
+            left = (left << 1) - i32::from(counts[len]);
+            if left < 0 {
+                return Err(invalid("over-subscribed huffman code"));
+            }
+            code = (code + u32::from(counts[len - 1])) << 1;
+            next_code[len] = code;
+        }
+
+        let mut table = vec![0u16; 1 << max_len];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len == 0 {
+                continue;
+            }
+            let len = u32::from(len);
+            let code = next_code[len as usize];
+            next_code[len as usize] += 1;
+            let reversed = code.reverse_bits() >> (32 - len);
+            let entry = ((symbol as u16) << 4) | len as u16;
+            let mut idx = reversed as usize;
+            while idx < table.len() {
+                table[idx] = entry;
+                idx += 1 << len;
+            }
+        }
+        Ok(Huffman { table, max_len })
+    }
+
+    fn decode<R: BufRead>(&self, bits: &mut BitReader<R>) -> io::Result<u16> {
+        let (peeked, avail) = bits.peek(self.max_len)?;
+        let entry = self.table[peeked as usize];
+        let len = u32::from(entry & 0xf);
+        if len == 0 || len > avail {
+            return Err(invalid("invalid huffman code"));
+        }
+        bits.consume(len);
+        Ok(entry >> 4)
+    }
+}
+
+enum State {
+    BlockHeader,
+    Stored(usize),
+    Codes(Box<(Huffman, Huffman)>),
+}
+
+/// DEFLATE block decoder.
+struct Inflater {
+    state: State,
+    last_block: bool,
+}
+
+impl Default for Inflater {
+    fn default() -> Self {
+        Inflater {
+            state: State::BlockHeader,
+            last_block: false,
+        }
+    }
+}
+
+impl Inflater {
+    /// Decode roughly `budget` bytes into `out`.
+    ///
+    /// `out` has to contain the preceding window of decoded data. Returns
+    /// `true` once the final block was decoded.
+    fn inflate<R: BufRead>(
+        &mut self,
+        bits: &mut BitReader<R>,
+        out: &mut Vec<u8>,
+        budget: usize,
+    ) -> io::Result<bool> {
+        let limit = out.len() + budget;
+        while out.len() < limit {
+            match self.state {
+                State::BlockHeader => {
+                    if self.last_block {
+                        return Ok(true);
+                    }
+                    self.last_block = bits.bits(1)? == 1;
+                    self.state = match bits.bits(2)? {
+                        0 => {
+                            bits.align();
+                            let len = bits.bits(16)?;
+                            if bits.bits(16)? != !len & 0xffff {
+                                return Err(invalid("corrupt stored block"));
+                            }
+                            State::Stored(len as usize)
+                        }
+                        1 => State::Codes(Box::new(fixed_codes()?)),
+                        2 => State::Codes(Box::new(dynamic_codes(bits)?)),
+                        _ => return Err(invalid("invalid block type")),
+                    };
+                }
+                State::Stored(ref mut remaining) => {
+                    if *remaining == 0 {
+                        self.state = State::BlockHeader;
+                        continue;
+                    }
+                    out.push(bits.byte()?);
+                    *remaining -= 1;
+                }
+                State::Codes(ref codes) => {
+                    let (lit, dist) = &**codes;
+                    let symbol = lit.decode(bits)? as usize;
+                    if symbol < 256 {
+                        out.push(symbol as u8);
+                        continue;
+                    } else if symbol == 256 {
+                        self.state = State::BlockHeader;
+                        continue;
+                    }
+                    let idx = symbol - 257;
+                    if idx >= LEN_BASE.len() {
+                        return Err(invalid("invalid length symbol"));
+                    }
+                    let len = LEN_BASE[idx] as usize + bits.bits(LEN_EXTRA[idx].into())? as usize;
+                    let idx = dist.decode(bits)? as usize;
+                    if idx >= DIST_BASE.len() {
+                        return Err(invalid("invalid distance symbol"));
+                    }
+                    let distance =
+                        DIST_BASE[idx] as usize + bits.bits(DIST_EXTRA[idx].into())? as usize;
+                    if distance > out.len() {
+                        return Err(invalid("distance beyond window"));
+                    }
+                    let start = out.len() - distance;
+                    for i in 0..len {
+                        out.push(out[start + i]);
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn fixed_codes() -> io::Result<(Huffman, Huffman)> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].iter_mut().for_each(|l| *l = 8);
+    lengths[144..256].iter_mut().for_each(|l| *l = 9);
+    lengths[256..280].iter_mut().for_each(|l| *l = 7);
+    lengths[280..].iter_mut().for_each(|l| *l = 8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes<R: BufRead>(bits: &mut BitReader<R>) -> io::Result<(Huffman, Huffman)> {
+    let n_lit = bits.bits(5)? as usize + 257;
+    let n_dist = bits.bits(5)? as usize + 1;
+    let n_code = bits.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &idx in &CODE_LENGTH_ORDER[..n_code] {
+        code_lengths[idx] = bits.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+
+    let mut lengths = Vec::with_capacity(n_lit + n_dist);
+    while lengths.len() < n_lit + n_dist {
+        let (value, repeat) = match code_lengths.decode(bits)? {
+            len @ 0..=15 => (len as u8, 1),
+            16 => match lengths.last() {
+                Some(&prev) => (prev, 3 + bits.bits(2)?),
+                None => return Err(invalid("repeat without previous length")),
+            },
+            17 => (0, 3 + bits.bits(3)?),
+            _ => (0, 11 + bits.bits(7)?),
+        };
+        lengths.extend((0..repeat).map(|_| value));
+    }
+    if lengths.len() != n_lit + n_dist || lengths[256] == 0 {
+        return Err(invalid("invalid code lengths"));
+    }
+    Ok((
+        Huffman::new(&lengths[..n_lit])?,
+        Huffman::new(&lengths[n_lit..])?,
+    ))
+}
+
+/// Number of input bytes compressed per DEFLATE block.
+const BLOCK: usize = 256 * 1024;
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+/// Maximum number of hash chain entries checked per position.
+const MAX_CHAIN: usize = 64;
+
+/// Encoder for gzip streams.
+///
+/// The stream is completed through `try_finish`, which is also attempted
+/// on drop.
+pub struct GzEncoder<W: Write> {
+    writer: W,
+    compressor: Compressor,
+    crc: u32,
+    size: u32,
+    finished: bool,
+}
+
+impl<W: Write> GzEncoder<W> {
+    pub fn new(writer: W) -> Self {
+        let mut compressor = Compressor::default();
+        // Magic, deflate, no flags, no mtime, no extra flags, unknown OS.
+        compressor
+            .bits
+            .out
+            .extend_from_slice(&[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255]);
+        GzEncoder {
+            writer,
+            compressor,
+            crc: 0,
+            size: 0,
+            finished: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Write the final block and the gzip trailer.
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        if !self.finished {
+            self.compressor.finish();
+            let out = &mut self.compressor.bits.out;
+            out.extend_from_slice(&self.crc.to_le_bytes());
+            out.extend_from_slice(&self.size.to_le_bytes());
+            self.finished = true;
+        }
+        self.write_out()?;
+        self.writer.flush()
+    }
+
+    fn write_out(&mut self) -> io::Result<()> {
+        self.writer.write_all(&self.compressor.bits.out)?;
+        self.compressor.bits.out.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for GzEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.finished {
+            return Err(io::Error::other("write to finished gzip stream"));
+        }
+        self.crc = crc32(self.crc, buf);
+        self.size = self.size.wrapping_add(buf.len() as u32);
+        self.compressor.push(buf);
+        self.write_out()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_out()?;
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Drop for GzEncoder<W> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.try_finish();
+        }
+    }
+}
+
+/// LZ77 match or literal.
+#[derive(Clone, Copy)]
+struct Symbol {
+    /// Literal byte or match length.
+    value: u16,
+    /// Match distance, `0` for literals.
+    dist: u16,
+}
+
+/// DEFLATE compressor.
+struct Compressor {
+    /// History window followed by the input that is not compressed yet.
+    data: Vec<u8>,
+    /// Start of the pending input in `data`.
+    pending: usize,
+    /// Stream offset of `data[0]`.
+    base: usize,
+    /// Most recent stream offset + 1 per hash, `0` if none.
+    head: Vec<usize>,
+    /// Previous stream offset + 1 with the same hash per window position.
+    prev: Vec<usize>,
+    symbols: Vec<Symbol>,
+    bits: BitWriter,
+}
+
+impl Default for Compressor {
+    fn default() -> Self {
+        Compressor {
+            data: Vec::new(),
+            pending: 0,
+            base: 0,
+            head: vec![0; 1 << HASH_BITS],
+            prev: vec![0; WINDOW],
+            symbols: Vec::new(),
+            bits: BitWriter::default(),
+        }
+    }
+}
+
+impl Compressor {
+    fn push(&mut self, buf: &[u8]) {
+        self.data.extend_from_slice(buf);
+        // Keep enough lookahead for matches at the end of the block.
+        while self.data.len() - self.pending >= BLOCK + MAX_MATCH {
+            self.compress_block(self.pending + BLOCK, false);
+        }
+    }
+
+    fn finish(&mut self) {
+        while self.data.len() - self.pending > BLOCK {
+            self.compress_block(self.pending + BLOCK, false);
+        }
+        self.compress_block(self.data.len(), true);
+        self.bits.align();
+    }
+
+    /// Compress the pending input up to at least `end`.
+    fn compress_block(&mut self, end: usize, last: bool) {
+        let mut pos = self.pending;
+        while pos < end {
+            let (len, dist) = self.longest_match(pos);
+            if len >= MIN_MATCH {
+                self.symbols.push(Symbol {
+                    value: len as u16,
+                    dist: dist as u16,
+                });
+                for i in pos..pos + len {
+                    self.insert(i);
+                }
+                pos += len;
+            } else {
+                self.symbols.push(Symbol {
+                    value: u16::from(self.data[pos]),
+                    dist: 0,
+                });
+                self.insert(pos);
+                pos += 1;
+            }
+        }
+        let symbols = std::mem::take(&mut self.symbols);
+        self.bits.write_block(&symbols, last);
+        self.symbols = symbols;
+        self.symbols.clear();
+
+        self.pending = pos;
+        if self.pending > 2 * WINDOW {
+            let drop = self.pending - WINDOW;
+            self.data.drain(..drop);
+            self.pending -= drop;
+            self.base += drop;
+        }
+    }
+
+    fn hash(&self, pos: usize) -> Option<usize> {
+        let bytes = self.data.get(pos..pos + MIN_MATCH)?;
+        let v = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+        Some((v.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize)
+    }
+
+    fn insert(&mut self, pos: usize) {
+        if let Some(hash) = self.hash(pos) {
+            let abs = self.base + pos;
+            self.prev[abs % WINDOW] = self.head[hash];
+            self.head[hash] = abs + 1;
+        }
+    }
+
+    fn longest_match(&self, pos: usize) -> (usize, usize) {
+        let hash = match self.hash(pos) {
+            Some(hash) => hash,
+            None => return (0, 0),
+        };
+        let abs = self.base + pos;
+        let max_len = (self.data.len() - pos).min(MAX_MATCH);
+        let (mut best_len, mut best_dist) = (0, 0);
+        let mut candidate = self.head[hash];
+        for _ in 0..MAX_CHAIN {
+            if candidate == 0 {
+                break;
+            }
+            let cand_abs = candidate - 1;
+            let dist = abs - cand_abs;
+            if dist > WINDOW || cand_abs < self.base {
+                break;
+            }
+            let cand = cand_abs - self.base;
+            let len = self.data[cand..]
+                .iter()
+                .zip(&self.data[pos..pos + max_len])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if len > best_len {
+                best_len = len;
+                best_dist = dist;
+                if len == max_len {
+                    break;
+                }
+            }
+            let next = self.prev[cand_abs % WINDOW];
+            // Entries of the ring buffer may have been overwritten.
+            if next >= candidate {
+                break;
+            }
+            candidate = next;
+        }
+        (best_len, best_dist)
+    }
+}
+
+/// LSB-first bit writer.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u64,
+    n_bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, n_bits: u32) {
+        self.acc |= u64::from(value) << self.n_bits;
+        self.n_bits += n_bits;
+        while self.n_bits >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.n_bits -= 8;
+        }
+    }
+
+    fn align(&mut self) {
+        if self.n_bits > 0 {
+            self.out.push(self.acc as u8);
+            self.acc = 0;
+            self.n_bits = 0;
+        }
+    }
+
+    /// Write a block with dynamic Huffman codes.
+    fn write_block(&mut self, symbols: &[Symbol], last: bool) {
+        let mut lit_freqs = [0u32; 286];
+        let mut dist_freqs = [0u32; 30];
+        lit_freqs[256] = 1;
+        for sym in symbols {
+            if sym.dist == 0 {
+                lit_freqs[sym.value as usize] += 1;
+            } else {
+                lit_freqs[257 + code_index(&LEN_BASE, sym.value)] += 1;
+                dist_freqs[code_index(&DIST_BASE, sym.dist)] += 1;
+            }
+        }
+        let lit_lengths = huffman_lengths(&lit_freqs, 15);
+        let dist_lengths = huffman_lengths(&dist_freqs, 15);
+        let n_lit = 257.max(last_nonzero(&lit_lengths));
+        let n_dist = 1.max(last_nonzero(&dist_lengths));
+
+        let mut lengths = lit_lengths[..n_lit].to_vec();
+        lengths.extend_from_slice(&dist_lengths[..n_dist]);
+        let rle = run_lengths(&lengths);
+        let mut cl_freqs = [0u32; 19];
+        for &(sym, _, _) in &rle {
+            cl_freqs[sym as usize] += 1;
+        }
+        let cl_lengths = huffman_lengths(&cl_freqs, 7);
+        let n_code = 4.max(
+            CODE_LENGTH_ORDER
+                .iter()
+                .rposition(|&idx| cl_lengths[idx] != 0)
+                .map_or(0, |pos| pos + 1),
+        );
+
+        self.write(last as u32, 1);
+        self.write(2, 2);
+        self.write(n_lit as u32 - 257, 5);
+        self.write(n_dist as u32 - 1, 5);
+        self.write(n_code as u32 - 4, 4);
+        for &idx in &CODE_LENGTH_ORDER[..n_code] {
+            self.write(u32::from(cl_lengths[idx]), 3);
+        }
+        let cl_codes = canonical_codes(&cl_lengths);
+        for (sym, extra, n_extra) in rle {
+            let (code, len) = cl_codes[sym as usize];
+            self.write(code, len);
+            self.write(extra, n_extra);
+        }
+
+        let lit_codes = canonical_codes(&lit_lengths);
+        let dist_codes = canonical_codes(&dist_lengths);
+        for sym in symbols {
+            if sym.dist == 0 {
+                let (code, len) = lit_codes[sym.value as usize];
+                self.write(code, len);
+            } else {
+                let idx = code_index(&LEN_BASE, sym.value);
+                let (code, len) = lit_codes[257 + idx];
+                self.write(code, len);
+                self.write(
+                    u32::from(sym.value - LEN_BASE[idx]),
+                    u32::from(LEN_EXTRA[idx]),
+                );
+                let idx = code_index(&DIST_BASE, sym.dist);
+                let (code, len) = dist_codes[idx];
+                self.write(code, len);
+                self.write(
+                    u32::from(sym.dist - DIST_BASE[idx]),
+                    u32::from(DIST_EXTRA[idx]),
+                );
+            }
+        }
+        let (code, len) = lit_codes[256];
+        self.write(code, len);
+    }
+}
+
+/// Get the index of the length or distance code for `value`.
+fn code_index(bases: &[u16], value: u16) -> usize {
+    bases.partition_point(|&base| base <= value) - 1
+}
+
+fn last_nonzero(lengths: &[u8]) -> usize {
+    lengths
+        .iter()
+        .rposition(|&l| l != 0)
+        .map_or(0, |pos| pos + 1)
+}
+
+/// Compute Huffman code lengths limited to `limit` bits.
+///
+/// At least two symbols get codes, so that the code is always complete.
+/// Codes that are too long are shortened by flattening the frequencies.
+fn huffman_lengths(freqs: &[u32], limit: u8) -> Vec<u8> {
+    let mut freqs = freqs.to_vec();
+    for idx in 0..freqs.len() {
+        if freqs.iter().filter(|&&f| f > 0).count() >= 2 {
+            break;
+        }
+        if freqs[idx] == 0 {
+            freqs[idx] = 1;
+        }
+    }
+    loop {
+        let lengths = tree_depths(&freqs);
+        if lengths.iter().all(|&l| l <= limit) {
+            return lengths;
+        }
+        for freq in freqs.iter_mut().filter(|f| **f > 0) {
+            *freq = (*freq >> 1).max(1);
+        }
+    }
+}
+
+/// Get the depths of the leaves of a Huffman tree.
+fn tree_depths(freqs: &[u32]) -> Vec<u8> {
+    let mut parents = vec![usize::MAX; freqs.len()];
+    let mut heap = freqs
+        .iter()
+        .enumerate()
+        .filter(|(_, &f)| f > 0)
+        .map(|(idx, &f)| Reverse((u64::from(f), idx)))
+        .collect::<BinaryHeap<_>>();
+    while heap.len() > 1 {
+        let Reverse((f1, n1)) = heap.pop().unwrap();
+        let Reverse((f2, n2)) = heap.pop().unwrap();
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[n1] = node;
+        parents[n2] = node;
+        heap.push(Reverse((f1 + f2, node)));
+    }
+    (0..freqs.len())
+        .map(|idx| {
+            if freqs[idx] == 0 {
+                return 0;
+            }
+            let mut depth = 0;
+            let mut node = idx;
+            while parents[node] != usize::MAX {
+                node = parents[node];
+                depth += 1;
+            }
+            depth
+        })
+        .collect()
+}
+
+/// Get the bit-reversed canonical codes and their lengths.
+fn canonical_codes(lengths: &[u8]) -> Vec<(u32, u32)> {
+    let mut counts = [0u32; 16];
+    for &len in lengths {
+        counts[len as usize] += 1;
+    }
+    counts[0] = 0;
+    let mut next = [0u32; 16];
+    let mut code = 0;
+    for len in 1..16 {
+        code = (code + counts[len - 1]) << 1;
+        next[len] = code;
+    }
+    lengths
+        .iter()
+        .map(|&len| {
+            if len == 0 {
+                return (0, 0);
+            }
+            let code = next[len as usize];
+            next[len as usize] += 1;
+            (code.reverse_bits() >> (32 - u32::from(len)), u32::from(len))
+        })
+        .collect()
+}
+
+/// Run-length encode code lengths as (symbol, extra bits, number of extra
+/// bits).
+fn run_lengths(lengths: &[u8]) -> Vec<(u8, u32, u32)> {
+    let mut rle = Vec::new();
+    let mut idx = 0;
+    while idx < lengths.len() {
+        let len = lengths[idx];
+        let run = lengths[idx..].iter().take_while(|&&l| l == len).count();
+        if len == 0 && run >= 11 {
+            let run = run.min(138);
+            rle.push((18, run as u32 - 11, 7));
+            idx += run;
+        } else if len == 0 && run >= 3 {
+            rle.push((17, run as u32 - 3, 3));
+            idx += run;
+        } else if len != 0 && run >= 4 {
+            rle.push((len, 0, 0));
+            let run = (run - 1).min(6);
+            rle.push((16, run as u32 - 3, 2));
+            idx += run + 1;
+        } else {
+            rle.push((len, 0, 0));
+            idx += 1;
+        }
+    }
+    rle
+}
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+/// Update a CRC-32 checksum with `data`.
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut c = !crc;
+    for &byte in data {
+        c = CRC_TABLE[((c ^ u32::from(byte)) & 0xff) as usize] ^ (c >> 8);
+    }
+    !c
+}
+
+/// Fuzzing entry point.
+///
+/// Decoding arbitrary bytes as gzip or raw DEFLATE has to fail with an
+/// error instead of panicking, and the bytes have to survive a round trip
+/// through `GzEncoder` and `GzDecoder`.
+#[doc(hidden)]
+pub fn fuzz_gzip(data: &[u8]) {
+    // Bound the output of streams that decode to a lot of data.
+    let mut decoded = Vec::new();
+    let _ = GzDecoder::new(data).take(1 << 20).read_to_end(&mut decoded);
+    decoded.clear();
+    let _ = DeflateDecoder::new(data)
+        .take(1 << 20)
+        .read_to_end(&mut decoded);
+
+    let mut encoder = GzEncoder::new(Vec::new());
+    encoder.write_all(data).unwrap();
+    encoder.try_finish().unwrap();
+    decoded.clear();
+    GzDecoder::new(&encoder.get_ref()[..])
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert!(decoded == data, "round trip of {} bytes", data.len());
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
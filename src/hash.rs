@@ -0,0 +1,162 @@
+//! Salted token hashes.
+//!
+//! Tokens are hashed with HMAC-SHA256 keyed by a salt, so that the owner of
+//! the salt can look up the hashes of known tokens with standard tools,
+//! e.g. `printf token | openssl dgst -sha256 -hmac SALT`, while the hashes
+//! do not reveal the tokens to anyone else.
+
+use std::fmt::Write;
+
+use crate::privacy;
+
+const BLOCK_LEN: usize = 64;
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Incremental SHA-256.
+#[derive(Clone)]
+struct Sha256 {
+    state: [u32; 8],
+    block: Vec<u8>,
+    len: u64,
+}
+
+impl Sha256 {
+    fn new() -> Self {
+        Sha256 {
+            state: INITIAL_STATE,
+            block: Vec::with_capacity(BLOCK_LEN),
+            len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        while !data.is_empty() {
+            let take = (BLOCK_LEN - self.block.len()).min(data.len());
+            self.block.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.block.len() == BLOCK_LEN {
+                self.compress();
+                self.block.clear();
+            }
+        }
+    }
+
+    fn finish(mut self) -> [u8; 32] {
+        let bits = self.len * 8;
+        self.block.push(0x80);
+        if self.block.len() > BLOCK_LEN - 8 {
+            self.block.resize(BLOCK_LEN, 0);
+            self.compress();
+            self.block.clear();
+        }
+        self.block.resize(BLOCK_LEN - 8, 0);
+        self.block.extend_from_slice(&bits.to_be_bytes());
+        self.compress();
+        let mut digest = [0; 32];
+        for (chunk, word) in digest.chunks_mut(4).zip(&self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 64];
+        for (word, chunk) in w.iter_mut().zip(self.block.chunks(4)) {
+            *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for (&k, &w) in ROUND_CONSTANTS.iter().zip(&w) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in self.state.iter_mut().zip(&[a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(*value);
+        }
+    }
+}
+
+/// Generate a random salt of 32 hex digits.
+pub fn random_salt() -> String {
+    format!(
+        "{:016x}{:016x}",
+        privacy::random_seed(),
+        privacy::random_seed()
+    )
+}
+
+/// HMAC-SHA256 of tokens with a salt as key.
+#[derive(Clone)]
+pub struct TokenHasher {
+    /// Hash states after the inner and outer padded keys.
+    inner: Sha256,
+    outer: Sha256,
+}
+
+impl TokenHasher {
+    pub fn new(salt: &str) -> Self {
+        let mut key = [0; BLOCK_LEN];
+        if salt.len() > BLOCK_LEN {
+            let mut hash = Sha256::new();
+            hash.update(salt.as_bytes());
+            key[..32].copy_from_slice(&hash.finish());
+        } else {
+            key[..salt.len()].copy_from_slice(salt.as_bytes());
+        }
+        let mut inner = Sha256::new();
+        inner.update(&key.map(|b| b ^ 0x36));
+        let mut outer = Sha256::new();
+        outer.update(&key.map(|b| b ^ 0x5c));
+        TokenHasher { inner, outer }
+    }
+
+    /// Get the hash of a token as hex digits.
+    pub fn hash(&self, token: &str) -> String {
+        let mut inner = self.inner.clone();
+        inner.update(token.as_bytes());
+        let mut outer = self.outer.clone();
+        outer.update(&inner.finish());
+        outer.finish().iter().fold(String::new(), |mut hex, b| {
+            write!(hex, "{:02x}", b).unwrap();
+            hex
+        })
+    }
+}
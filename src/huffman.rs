@@ -0,0 +1,80 @@
+//! Huffman coding of tokens for hierarchical softmax.
+//!
+//! The tree is built as by `CreateBinaryTree` of word2vec, so that codes
+//! and inner nodes are the same as in models trained with word2vec.
+
+/// Path of a token in the Huffman tree.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HuffmanPath {
+    /// Branches from the root to the token, `true` for the right branch.
+    pub code: Vec<bool>,
+    /// Indices of the inner nodes from the root, the root has index
+    /// `vocab_len - 2`.
+    pub points: Vec<usize>,
+}
+
+impl HuffmanPath {
+    /// Get the code as a string of `0` and `1`.
+    pub fn code_string(&self) -> String {
+        self.code
+            .iter()
+            .map(|&bit| if bit { '1' } else { '0' })
+            .collect()
+    }
+}
+
+/// Build the Huffman tree of counts sorted by descending count.
+///
+/// Returns the path of every token. A single token has an empty path.
+pub fn huffman_paths(counts: &[usize]) -> Vec<HuffmanPath> {
+    let len = counts.len();
+    if len < 2 {
+        return vec![HuffmanPath::default(); len];
+    }
+    // Leaves are followed by the inner nodes in the order they are built,
+    // inner nodes that are not built yet have the highest count.
+    let mut node_counts = counts
+        .iter()
+        .map(|&count| count as u128)
+        .collect::<Vec<_>>();
+    node_counts.resize(2 * len, u128::MAX);
+    let mut parents = vec![0; 2 * len];
+    let mut right = vec![false; 2 * len];
+    // Next leaf from the least frequent one and next inner node.
+    let mut leaf = Some(len - 1);
+    let mut inner = len;
+    let mut take_min = |node_counts: &[u128]| match leaf {
+        Some(idx) if node_counts[idx] < node_counts[inner] => {
+            leaf = idx.checked_sub(1);
+            idx
+        }
+        _ => {
+            inner += 1;
+            inner - 1
+        }
+    };
+    for node in len..2 * len - 1 {
+        let min1 = take_min(&node_counts);
+        let min2 = take_min(&node_counts);
+        node_counts[node] = node_counts[min1] + node_counts[min2];
+        parents[min1] = node;
+        parents[min2] = node;
+        right[min2] = true;
+    }
+
+    let root = 2 * len - 2;
+    (0..len)
+        .map(|token| {
+            let mut path = HuffmanPath::default();
+            let mut node = token;
+            while node != root {
+                path.code.push(right[node]);
+                node = parents[node];
+                path.points.push(node - len);
+            }
+            path.code.reverse();
+            path.points.reverse();
+            path
+        })
+        .collect()
+}
@@ -0,0 +1,587 @@
+//! Corpus readers.
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::archive::{self, ArchiveKind};
+use crate::conllu::{self, Sentences};
+use crate::glob::{self, Glob};
+use crate::gzip::{self, GzDecoder};
+use crate::json::Value;
+use crate::time::TimeBucket;
+use crate::warc::{Record, Records};
+
+/// Format of the corpus.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum InputFormat {
+    /// Plain text, one sentence per line.
+    Text,
+    /// WARC web archive, text is extracted from HTTP responses.
+    Warc,
+    /// WET web archive, text is read from conversion records.
+    Wet,
+    /// CoNLL-U, every sentence is read as a line of its word forms.
+    Conllu,
+    /// JSON Lines, the text field of every record is read.
+    Jsonl(JsonFields),
+}
+
+impl InputFormat {
+    pub fn variants() -> &'static [&'static str] {
+        &["text", "warc", "wet", "conllu", "jsonl"]
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(InputFormat::Text),
+            "warc" => Ok(InputFormat::Warc),
+            "wet" => Ok(InputFormat::Wet),
+            "conllu" => Ok(InputFormat::Conllu),
+            "jsonl" => Ok(InputFormat::Jsonl(JsonFields::default())),
+            _ => Err(format!("Unknown input format: {}", s)),
+        }
+    }
+}
+
+/// Fields of JSON Lines records.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonFields {
+    /// Dot-separated path of the text field.
+    pub text: String,
+    /// Field whose value labels the lines of a record.
+    pub label: Option<JsonLabel>,
+}
+
+impl Default for JsonFields {
+    fn default() -> Self {
+        JsonFields {
+            text: "text".to_owned(),
+            label: None,
+        }
+    }
+}
+
+/// Labels of the lines of JSON Lines records.
+///
+/// If a field is given, the lines of a record are read as
+/// `label<TAB>text` pairs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonLabel {
+    /// Dot-separated path of the field.
+    pub field: String,
+    /// The label is the time bucket of the field value.
+    pub bucket: Option<TimeBucket>,
+}
+
+impl JsonLabel {
+    /// Get the label of a record.
+    ///
+    /// Tabs and line breaks are replaced by spaces, so that labels
+    /// cannot be confused with the text.
+    fn of(&self, record: &Value) -> Option<String> {
+        let value = record.path(&self.field)?;
+        let label = match self.bucket {
+            Some(bucket) => bucket.of(value)?,
+            None => value.as_text()?.to_owned(),
+        };
+        Some(label.replace(&['\t', '\n', '\r'][..], " "))
+    }
+}
+
+/// Boundaries of documents in the corpus.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DocumentBoundary {
+    /// Every line is a document.
+    Line,
+    /// Documents are separated by blank lines.
+    Blank,
+    /// Every file, archive member or web archive record is a document.
+    File,
+}
+
+impl DocumentBoundary {
+    pub fn variants() -> &'static [&'static str] {
+        &["line", "blank", "file"]
+    }
+}
+
+impl FromStr for DocumentBoundary {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "line" => Ok(DocumentBoundary::Line),
+            "blank" => Ok(DocumentBoundary::Blank),
+            "file" => Ok(DocumentBoundary::File),
+            _ => Err(format!("Unknown document boundary: {}", s)),
+        }
+    }
+}
+
+/// Item read from a corpus file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Item {
+    /// A line of text.
+    Line(String),
+    /// End of a web archive record.
+    RecordEnd,
+}
+
+/// Description of the corpus to read.
+#[derive(Clone, Debug)]
+pub struct Corpus {
+    /// Corpus files, directories and archives, stdin if empty.
+    pub paths: Vec<PathBuf>,
+    /// Globs for files in directories and archives.
+    pub include: Vec<Glob>,
+    pub format: InputFormat,
+    pub documents: DocumentBoundary,
+    /// Range of lines to read.
+    pub lines: LineRange,
+    /// Byte range to read, only supported for single plain text files.
+    pub bytes: Option<ByteRange>,
+}
+
+impl Corpus {
+    /// Call `f` with every line of the corpus.
+    ///
+    /// Only lines in the line and byte ranges are read, boundaries of files
+    /// and web archive records are not reported.
+    pub fn for_each_line<F>(&self, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&str) -> io::Result<()>,
+    {
+        let mut line_no = 0;
+        self.for_each_reader(|reader| {
+            if self.lines.is_past(line_no + 1) {
+                return Ok(());
+            }
+            for item in lines(reader, &self.format)? {
+                if let Item::Line(line) = item? {
+                    line_no += 1;
+                    if self.lines.is_past(line_no) {
+                        break;
+                    } else if self.lines.contains(line_no) {
+                        f(&line)?;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Call `f` with the lines of every document of the corpus.
+    ///
+    /// Documents are delimited according to `documents`, the blank lines
+    /// separating documents are not part of them.
+    pub fn for_each_document<F>(&self, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&[String]) -> io::Result<()>,
+    {
+        let mut line_no = 0;
+        let mut doc = Vec::new();
+        self.for_each_reader(|reader| {
+            if self.lines.is_past(line_no + 1) {
+                return Ok(());
+            }
+            for item in lines(reader, &self.format)? {
+                let line = match item? {
+                    Item::Line(line) => line,
+                    Item::RecordEnd => {
+                        if !doc.is_empty() {
+                            f(&doc)?;
+                            doc.clear();
+                        }
+                        continue;
+                    }
+                };
+                line_no += 1;
+                if self.lines.is_past(line_no) {
+                    break;
+                } else if !self.lines.contains(line_no) {
+                    continue;
+                }
+                match self.documents {
+                    DocumentBoundary::Blank if line.trim().is_empty() => {
+                        if !doc.is_empty() {
+                            f(&doc)?;
+                            doc.clear();
+                        }
+                    }
+                    DocumentBoundary::Line => f(&[line])?,
+                    _ => doc.push(line),
+                }
+            }
+            if !doc.is_empty() {
+                f(&doc)?;
+                doc.clear();
+            }
+            Ok(())
+        })
+    }
+
+    /// Call `f` with every sentence of a CoNLL-U corpus.
+    ///
+    /// The line range of the corpus is interpreted as range of sentences.
+    pub fn for_each_sentence<F>(&self, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&[conllu::Token]) -> io::Result<()>,
+    {
+        let mut sentence_no = 0;
+        self.for_each_reader(|reader| {
+            if self.lines.is_past(sentence_no + 1) {
+                return Ok(());
+            }
+            for sentence in Sentences::new(decompressed(reader)?) {
+                let sentence = sentence?;
+                sentence_no += 1;
+                if self.lines.is_past(sentence_no) {
+                    break;
+                } else if self.lines.contains(sentence_no) {
+                    f(&sentence)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Call `f` with a reader for every file of the corpus or the byte range.
+    fn for_each_reader<F>(&self, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&mut dyn BufRead) -> io::Result<()>,
+    {
+        if let Some(bytes) = self.bytes {
+            return f(&mut bytes.open(self.byte_range_file()?)?);
+        }
+        for_each_file(&self.paths, &self.include, |_, reader| f(reader))
+    }
+
+    /// Get the file that the byte range applies to.
+    pub fn byte_range_file(&self) -> io::Result<&Path> {
+        match self.paths.as_slice() {
+            [path] if path.is_file() => Ok(path),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "byte ranges require a single corpus file",
+            )),
+        }
+    }
+}
+
+/// Range of corpus lines, numbered from 1.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LineRange {
+    /// First line to read.
+    pub start: usize,
+    /// Last line to read, inclusive.
+    pub end: Option<usize>,
+}
+
+impl LineRange {
+    pub fn is_full(&self) -> bool {
+        self.start <= 1 && self.end.is_none()
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        line >= self.start && !self.is_past(line)
+    }
+
+    /// Check whether `line` lies beyond the end of the range.
+    pub fn is_past(&self, line: usize) -> bool {
+        self.end.map(|end| line > end).unwrap_or(false)
+    }
+}
+
+impl Default for LineRange {
+    fn default() -> Self {
+        LineRange {
+            start: 1,
+            end: None,
+        }
+    }
+}
+
+/// Byte range of a corpus file.
+///
+/// The range contains all lines which start at offsets in `[start, end)`,
+/// so consecutive ranges partition the lines of a file.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl ByteRange {
+    /// Open the byte range of a plain text file.
+    pub fn open(&self, path: &Path) -> io::Result<impl BufRead> {
+        let mut reader = BufReader::new(File::open(path)?);
+        if gzip::is_gzip(&mut reader)? || ArchiveKind::from_path(&path.to_string_lossy()).is_some()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "byte ranges require uncompressed plain text files",
+            ));
+        }
+        let len = reader.seek(SeekFrom::End(0))?;
+        let start = align_to_line(&mut reader, self.start.min(len))?;
+        let end = match self.end {
+            Some(end) => align_to_line(&mut reader, end.min(len))?,
+            None => len,
+        };
+        reader.seek(SeekFrom::Start(start))?;
+        Ok(reader.take(end.saturating_sub(start)))
+    }
+}
+
+/// Get the offset of the first line starting at or after `pos`.
+fn align_to_line(reader: &mut BufReader<File>, pos: u64) -> io::Result<u64> {
+    if pos == 0 {
+        return Ok(0);
+    }
+    reader.seek(SeekFrom::Start(pos - 1))?;
+    let mut skipped = Vec::new();
+    let n = reader.read_until(b'\n', &mut skipped)?;
+    Ok(pos - 1 + n as u64)
+}
+
+/// Call `f` with a reader for every file of the corpus.
+///
+/// The corpus is read from stdin if no paths are given. Directories are
+/// traversed recursively in lexicographic order, the members of tar and zip
+/// archives are read without extracting them. Files in directories and
+/// archives are only read if they match one of the `include` patterns.
+pub fn for_each_file<F>(paths: &[PathBuf], include: &[Glob], mut f: F) -> io::Result<()>
+where
+    F: FnMut(&str, &mut dyn BufRead) -> io::Result<()>,
+{
+    if paths.is_empty() {
+        return f("-", &mut io::stdin().lock());
+    }
+    for file in corpus_files(paths, include)? {
+        for_each_member(&file, include, &mut f)?;
+    }
+    Ok(())
+}
+
+/// Expand the corpus paths to the list of files to read.
+///
+/// Directories are replaced by the included files below them, archives are
+/// kept as single files.
+pub fn corpus_files(paths: &[PathBuf], include: &[Glob]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut dir_files = Vec::new();
+            walk_dir(path, &mut dir_files)?;
+            files.extend(dir_files.into_iter().filter(|file| {
+                let name = file.strip_prefix(path).unwrap_or(file).to_string_lossy();
+                glob::any_matches(include, &name)
+            }));
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+/// Call `f` with a reader for a file, or every included member of an archive.
+pub fn for_each_member<F>(path: &Path, include: &[Glob], mut f: F) -> io::Result<()>
+where
+    F: FnMut(&str, &mut dyn BufRead) -> io::Result<()>,
+{
+    let name = path.to_string_lossy();
+    let file = File::open(path)?;
+    let mut member = |member: &str, reader: &mut dyn BufRead| {
+        if glob::any_matches(include, member) {
+            f(&format!("{}/{}", name, member), reader)
+        } else {
+            Ok(())
+        }
+    };
+    match ArchiveKind::from_path(&name) {
+        Some(ArchiveKind::Tar) => {
+            archive::for_each_tar_member(decompressed(BufReader::new(file))?, &mut member)
+        }
+        Some(ArchiveKind::Zip) => archive::for_each_zip_member(file, &mut member),
+        None => f(&name, &mut BufReader::new(file)),
+    }
+}
+
+/// Read a newline-delimited list of corpus paths.
+///
+/// Empty lines are ignored.
+pub fn read_file_list(reader: impl BufRead) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if !line.is_empty() {
+            paths.push(PathBuf::from(line));
+        }
+    }
+    Ok(paths)
+}
+
+fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            walk_dir(&entry, files)?;
+        } else {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Wrap gzip compressed readers in a decoder.
+pub fn decompressed<'a, R>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>>
+where
+    R: BufRead + 'a,
+{
+    if gzip::is_gzip(&mut reader)? {
+        Ok(Box::new(BufReader::new(GzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Get an iterator over the text lines of a corpus.
+///
+/// Gzip compressed input is decompressed transparently.
+pub fn lines<'a, R>(
+    reader: R,
+    format: &InputFormat,
+) -> io::Result<Box<dyn Iterator<Item = io::Result<Item>> + 'a>>
+where
+    R: BufRead + 'a,
+{
+    let reader = decompressed(reader)?;
+    Ok(match format {
+        InputFormat::Text => Box::new(reader.lines().map(|line| line.map(Item::Line))),
+        InputFormat::Warc => Box::new(RecordLines::new(Records::new(reader), Record::warc_text)),
+        InputFormat::Wet => Box::new(RecordLines::new(Records::new(reader), |record| {
+            Ok(record.wet_text())
+        })),
+        InputFormat::Conllu => Box::new(Sentences::new(reader).map(|sentence| {
+            sentence.map(|tokens| {
+                let forms = tokens.iter().map(|t| t.form.as_str()).collect::<Vec<_>>();
+                Item::Line(forms.join(" "))
+            })
+        })),
+        InputFormat::Jsonl(fields) => Box::new(JsonLines {
+            lines: reader.lines(),
+            fields: fields.clone(),
+            line_no: 0,
+            items: VecDeque::new(),
+        }),
+    })
+}
+
+/// Iterator over the text lines of JSON Lines records.
+///
+/// The lines of every record are followed by `Item::RecordEnd`, records
+/// without a string in the text field are skipped.
+struct JsonLines<L> {
+    lines: L,
+    fields: JsonFields,
+    line_no: usize,
+    items: VecDeque<Item>,
+}
+
+impl<L> Iterator for JsonLines<L>
+where
+    L: Iterator<Item = io::Result<String>>,
+{
+    type Item = io::Result<Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.items.is_empty() {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+            self.line_no += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = match Value::parse(&line) {
+                Ok(record) => record,
+                Err(err) => {
+                    return Some(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid JSON in line {}: {}", self.line_no, err),
+                    )))
+                }
+            };
+            let text = match record.path(&self.fields.text).and_then(Value::as_str) {
+                Some(text) => text,
+                None => continue,
+            };
+            // Lines of records without a label get an empty label.
+            let label = self
+                .fields
+                .label
+                .as_ref()
+                .map(|label| label.of(&record).unwrap_or_default());
+            self.items.extend(text.lines().map(|line| match &label {
+                Some(label) => Item::Line(format!("{}\t{}", label, line)),
+                None => Item::Line(line.to_owned()),
+            }));
+            self.items.push_back(Item::RecordEnd);
+        }
+        self.items.pop_front().map(Ok)
+    }
+}
+
+/// Iterator over the text lines of WARC records.
+///
+/// The lines of every record are followed by `Item::RecordEnd`.
+struct RecordLines<R> {
+    records: Records<R>,
+    extract: fn(&Record) -> io::Result<Option<String>>,
+    lines: VecDeque<Item>,
+}
+
+impl<R: BufRead> RecordLines<R> {
+    fn new(records: Records<R>, extract: fn(&Record) -> io::Result<Option<String>>) -> Self {
+        RecordLines {
+            records,
+            extract,
+            lines: VecDeque::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for RecordLines<R> {
+    type Item = io::Result<Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.lines.is_empty() {
+            let record = match self.records.next()? {
+                Ok(record) => record,
+                Err(err) => return Some(Err(err)),
+            };
+            let text = match (self.extract)(&record) {
+                Ok(text) => text,
+                Err(err) => return Some(Err(err)),
+            };
+            if let Some(text) = text {
+                self.lines
+                    .extend(text.lines().map(|line| Item::Line(line.to_owned())));
+                self.lines.push_back(Item::RecordEnd);
+            }
+        }
+        self.lines.pop_front().map(Ok)
+    }
+}
@@ -0,0 +1,98 @@
+//! Counting words and character n-grams in corpora.
+//!
+//! The library exposes the parts of `corpus-count` that determine the
+//! output of count tables, so that other tools can reproduce it. The
+//! counting pipeline itself is in `count`, `count::count_corpus` counts
+//! any reader, e.g. an in-memory corpus.
+
+pub mod archive;
+
+pub mod arrow;
+
+pub mod audit;
+
+pub mod bins;
+
+pub mod bloom;
+
+pub mod boilerplate;
+
+pub mod charlm;
+
+pub mod command;
+
+pub mod conllu;
+
+pub mod count;
+
+pub mod dawg;
+
+pub mod dispersion;
+
+pub mod embeddings;
+
+pub mod emoji;
+
+pub mod fasttext;
+
+pub mod features;
+
+pub mod filter;
+
+pub mod fst;
+
+pub mod glob;
+
+pub mod gzip;
+
+pub mod hash;
+
+pub mod huffman;
+
+pub mod input;
+
+pub mod json;
+
+pub mod minhash;
+
+pub mod ngrams;
+
+pub mod normalize;
+
+pub mod options;
+
+pub mod output;
+
+pub mod pii;
+
+pub mod privacy;
+
+pub mod projection;
+
+pub mod regex;
+
+pub mod rewrite;
+
+pub mod rng;
+
+pub mod roaring;
+
+pub mod sampling;
+
+pub mod script;
+
+pub mod sort;
+
+pub mod subword;
+
+pub mod temp;
+
+pub mod time;
+
+pub mod tokenize;
+
+pub mod trends;
+
+pub mod vocab;
+
+pub mod warc;
@@ -1,267 +1,3480 @@
-use std::cmp;
-use std::cmp::Ordering;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt::Display;
 use std::fs::File;
-use std::io::{BufRead, BufWriter, Write};
+use std::io::{self, BufReader, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
-use clap::{App, AppSettings, Arg, ArgMatches};
-use stdinout::{Input, Output};
+use clap::{value_t, values_t, App, AppSettings, Arg, ArgMatches, ErrorKind};
+use corpus_count::arrow::ArrowCountWriter;
+use corpus_count::audit::AuditTerms;
+use corpus_count::bins;
+use corpus_count::bloom::BloomFilter;
+use corpus_count::boilerplate::{self, Boilerplate};
+use corpus_count::charlm::CharLm;
+use corpus_count::command::TokenizerCommand;
+use corpus_count::count::{
+    self, counted_into_sorted, CountOptions, Counts, DocumentTerms, LabelCounts, NGramLengths,
+};
+use corpus_count::dawg::DawgBuilder;
+use corpus_count::dispersion::Dispersion;
+use corpus_count::embeddings;
+use corpus_count::emoji::EmojiPolicy;
+use corpus_count::features::{self, FeatureMeasure};
+use corpus_count::filter::{self, TokenFilter};
+use corpus_count::fst::MapBuilder;
+use corpus_count::glob::Glob;
+use corpus_count::hash::{self, TokenHasher};
+use corpus_count::huffman;
+use corpus_count::input::{
+    self, ByteRange, Corpus, DocumentBoundary, InputFormat, JsonLabel, LineRange,
+};
+use corpus_count::minhash;
+use corpus_count::ngrams;
+use corpus_count::normalize::{self, Normalizer};
+use corpus_count::options::Options;
+use corpus_count::output::{self, Compression, OutputFile, OutputOptions, TableFormat};
+use corpus_count::pii::{self, PiiPatterns};
+use corpus_count::privacy::{self, DpParams};
+use corpus_count::projection::HeapsFit;
+use corpus_count::regex::Regex;
+use corpus_count::rewrite::Rewriter;
+use corpus_count::rng;
+use corpus_count::roaring::RoaringBitmap;
+use corpus_count::sampling;
+use corpus_count::script::{self, Script, TokenScript};
+use corpus_count::sort::{self, Collation, ExternalSort};
+use corpus_count::temp;
+use corpus_count::time::TimeBucket;
+use corpus_count::tokenize::{self, Tokenizer};
+use corpus_count::trends::{self, Period};
+use stdinout::Input;
+
+mod subcommands;
+use subcommands::{
+    AffixesApp, AlignedApp, BoilerplateApp, BucketReportApp, CheckEmbeddingsApp, CompoundsApp,
+    CooccurrenceApp, CorpusCountApp, EncodeApp, FastTextVocabApp, FilterLinesApp, KwicApp,
+    LearnMorphsApp, OcrReportApp, PhrasesApp, QueryApp, ServeApp, ShuffleApp, SplitApp, StreamApp,
+    SubsampleApp, UpdateApp, VariantsApp, WordNGramsApp,
+};
 
 static DEFAULT_CLAP_SETTINGS: &[AppSettings] = &[
     AppSettings::DontCollapseArgsInUsage,
     AppSettings::UnifiedHelpMessage,
 ];
 
+static ARROW: &str = "ARROW";
+static AUDIT_CONTEXT: &str = "AUDIT_CONTEXT";
+static AUDIT_EXAMPLES: &str = "AUDIT_EXAMPLES";
+static AUDIT_REPORT: &str = "AUDIT_REPORT";
+static AUDIT_TERMS: &str = "AUDIT_TERMS";
+static BITEXT: &str = "BITEXT";
+static BLOOM_FPR: &str = "BLOOM_FPR";
+static BLOOM_OUT: &str = "BLOOM_OUT";
+static BUCKET_COUNTS: &str = "BUCKET_COUNTS";
+static BUCKET_FORMAT: &str = "BUCKET_FORMAT";
+static COLLATE: &str = "COLLATE";
+static COMPRESS: &str = "COMPRESS";
 static CORPUS: &str = "CORPUS";
+static COUNT_ONLY: &str = "COUNT_ONLY";
+static DAWG_OUT: &str = "DAWG_OUT";
+static DEDUP_LINES: &str = "DEDUP_LINES";
+static DTM_DOCS: &str = "DTM_DOCS";
+static DTM_OUT: &str = "DTM_OUT";
+static DTM_VOCAB: &str = "DTM_VOCAB";
+static DROP_NON_ALPHA: &str = "DROP_NON_ALPHA";
+static DROP_NUMERIC: &str = "DROP_NUMERIC";
+static DROP_PATTERN: &str = "DROP_PATTERN";
+static DISPERSION: &str = "DISPERSION";
+static DOCUMENTS: &str = "DOCUMENTS";
+static DP_DELTA: &str = "DP_DELTA";
+static DP_EPSILON: &str = "DP_EPSILON";
+static DP_SEED: &str = "DP_SEED";
+static DP_SENSITIVITY: &str = "DP_SENSITIVITY";
+static EMIT_FF_CONFIG: &str = "EMIT_FF_CONFIG";
+static EMOJI: &str = "EMOJI";
+static EMOJI_COUNTS: &str = "EMOJI_COUNTS";
+static END_BYTE: &str = "END_BYTE";
+static END_LINE: &str = "END_LINE";
+static FACET_COUNTS: &str = "FACET_COUNTS";
+static FACET_FIELD: &str = "FACET_FIELD";
+static FACET_FORMAT: &str = "FACET_FORMAT";
+static FACET_SUMMARY: &str = "FACET_SUMMARY";
+static FEATURE_MEASURE: &str = "FEATURE_MEASURE";
+static FEATURE_RANKING: &str = "FEATURE_RANKING";
+static FF_BUCKETS_EXP: &str = "FF_BUCKETS_EXP";
+static FILES_FROM: &str = "FILES_FROM";
 static FILTER_FIRST: &str = "FILTER_FIRST";
+static FOLLOW: &str = "FOLLOW";
+static FREQ_BIN_BASE: &str = "FREQ_BIN_BASE";
+static FREQ_BINS: &str = "FREQ_BINS";
+static CCFS_COUNTS: &str = "CCFS_COUNTS";
+static CCFS_OUT: &str = "CCFS_OUT";
+static GROWTH: &str = "GROWTH";
+static GROWTH_STEP: &str = "GROWTH_STEP";
+static HASH_SALT: &str = "HASH_SALT";
+static HASH_TOKENS: &str = "HASH_TOKENS";
+static HUFFMAN: &str = "HUFFMAN";
+static IDF: &str = "IDF";
+static IDS_OUT: &str = "IDS_OUT";
+static INCLUDE: &str = "INCLUDE";
+static INDEX_DOCS: &str = "INDEX_DOCS";
+static INPUT_FORMAT: &str = "INPUT_FORMAT";
+static INVERTED_INDEX: &str = "INVERTED_INDEX";
+static LABEL_COUNTS: &str = "LABEL_COUNTS";
+static LABELED: &str = "LABELED";
+static LOG_ODDS: &str = "LOG_ODDS";
 static NO_BRACKET: &str = "NO_BRACKET";
+static NO_VOCAB: &str = "NO_VOCAB";
+static NORMALIZED: &str = "NORMALIZED";
+static NORMALIZER: &str = "NORMALIZER";
+static ONLY_SCRIPT: &str = "ONLY_SCRIPT";
+static PII_COUNTS: &str = "PII_COUNTS";
+static POSITIONS: &str = "POSITIONS";
+static PROJECT_COVERAGE: &str = "PROJECT_COVERAGE";
+static RESERVED: &str = "RESERVED";
+static MASK_PII: &str = "MASK_PII";
+static MAX_DOC_FREQ: &str = "MAX_DOC_FREQ";
 static MAX_N: &str = "MAX_N";
+static MAX_VOCAB_COVERAGE: &str = "MAX_VOCAB_COVERAGE";
+static MIN_DOCS: &str = "MIN_DOCS";
 static MIN_N: &str = "MIN_N";
 static NGRAM_MIN: &str = "NGRAM_MIN";
+static NO_ATOMIC: &str = "NO_ATOMIC";
 static NGRAM_COUNTS: &str = "NGRAM_COUNTS";
+static NGRAM_IDS: &str = "NGRAM_IDS";
+static NGRAM_REDUNDANCY: &str = "NGRAM_REDUNDANCY";
+static NGRAM_TARGET_SIZE: &str = "NGRAM_TARGET_SIZE";
+static NEAR_DUP_REPORT: &str = "NEAR_DUP_REPORT";
+static NEAR_DUP_THRESHOLD: &str = "NEAR_DUP_THRESHOLD";
+static REWRITE: &str = "REWRITE";
+static SHINGLE_SIZE: &str = "SHINGLE_SIZE";
+static SNAPSHOT_INTERVAL: &str = "SNAPSHOT_INTERVAL";
+static SOFTMAX_CLUSTERS: &str = "SOFTMAX_CLUSTERS";
+static SOFTMAX_CUTOFFS: &str = "SOFTMAX_CUTOFFS";
+static SORT_BUFFER: &str = "SORT_BUFFER";
+static SPECIAL_TOKENS: &str = "SPECIAL_TOKENS";
+static STATS: &str = "STATS";
+static START_BYTE: &str = "START_BYTE";
+static START_LINE: &str = "START_LINE";
+static STRIP_BOILERPLATE: &str = "STRIP_BOILERPLATE";
+static SWEEP: &str = "SWEEP";
+static TEXT_FIELD: &str = "TEXT_FIELD";
+static THREADS: &str = "THREADS";
+static TIME_BUCKET: &str = "TIME_BUCKET";
+static TIME_FIELD: &str = "TIME_FIELD";
+static TARGET_COVERAGE: &str = "TARGET_COVERAGE";
+static TARGET_NGRAM_COUNTS: &str = "TARGET_NGRAM_COUNTS";
+static TARGET_TOKEN_COUNTS: &str = "TARGET_TOKEN_COUNTS";
+static TARGET_TYPES: &str = "TARGET_TYPES";
+static TMP_DIR: &str = "TMP_DIR";
+static TOKEN_LOGPROB: &str = "TOKEN_LOGPROB";
 static TOKEN_MIN: &str = "TOKEN_MIN";
+static TOKEN_MIN_QUANTILE: &str = "TOKEN_MIN_QUANTILE";
+static TOKENIZER: &str = "TOKENIZER";
+static TOKENIZER_CMD: &str = "TOKENIZER_CMD";
 static TOKEN_COUNTS: &str = "TOKEN_COUNTS";
+static TREND_AFTER: &str = "TREND_AFTER";
+static TREND_BEFORE: &str = "TREND_BEFORE";
+static TREND_MAX_P: &str = "TREND_MAX_P";
+static TRENDS: &str = "TRENDS";
+static UNIGRAM_POWER: &str = "UNIGRAM_POWER";
+static UNIGRAM_PROBS: &str = "UNIGRAM_PROBS";
+static UNIGRAM_TABLE: &str = "UNIGRAM_TABLE";
+static UNIGRAM_TABLE_SIZE: &str = "UNIGRAM_TABLE_SIZE";
+static WRITE_BUFFER: &str = "WRITE_BUFFER";
 
 fn main() {
     let matches = parse_args();
-    let corpus = Input::from(matches.value_of(CORPUS));
-    let output = Output::from(matches.value_of(TOKEN_COUNTS));
-    let mut output = output
-        .write()
+    match matches.subcommand() {
+        ("affixes", Some(matches)) => AffixesApp::parse(matches).run(),
+        ("aligned", Some(matches)) => AlignedApp::parse(matches).run(),
+        ("boilerplate", Some(matches)) => BoilerplateApp::parse(matches).run(),
+        ("bucket-report", Some(matches)) => BucketReportApp::parse(matches).run(),
+        ("check-embeddings", Some(matches)) => CheckEmbeddingsApp::parse(matches).run(),
+        ("compounds", Some(matches)) => CompoundsApp::parse(matches).run(),
+        ("cooccurrence", Some(matches)) => CooccurrenceApp::parse(matches).run(),
+        ("encode", Some(matches)) => EncodeApp::parse(matches).run(),
+        ("fasttext-vocab", Some(matches)) => FastTextVocabApp::parse(matches).run(),
+        ("filter-lines", Some(matches)) => FilterLinesApp::parse(matches).run(),
+        ("kwic", Some(matches)) => KwicApp::parse(matches).run(),
+        ("learn-morphs", Some(matches)) => LearnMorphsApp::parse(matches).run(),
+        ("ocr-report", Some(matches)) => OcrReportApp::parse(matches).run(),
+        ("phrases", Some(matches)) => PhrasesApp::parse(matches).run(),
+        ("query", Some(matches)) => QueryApp::parse(matches).run(),
+        ("serve", Some(matches)) => ServeApp::parse(matches).run(),
+        ("shuffle", Some(matches)) => ShuffleApp::parse(matches).run(),
+        ("split", Some(matches)) => SplitApp::parse(matches).run(),
+        ("stream", Some(matches)) => StreamApp::parse(matches).run(),
+        ("subsample", Some(matches)) => SubsampleApp::parse(matches).run(),
+        ("update", Some(matches)) => UpdateApp::parse(matches).run(),
+        ("variants", Some(matches)) => VariantsApp::parse(matches).run(),
+        ("word-ngrams", Some(matches)) => WordNGramsApp::parse(matches).run(),
+        _ => count_tokens(&matches),
+    }
+}
+
+fn count_tokens(matches: &ArgMatches) {
+    let (corpus, labeled) = labeled_corpus_from_matches(matches);
+    let output_opts = output_options_from_matches(matches);
+    let mut output = OutputFile::create(matches.value_of(TOKEN_COUNTS), &output_opts)
         .expect("Can't open output to write token counts.");
-    let reader = corpus.buf_read().expect("Can't open corpus for reading");
+    let sweep = matches.value_of(SWEEP).map(|v| parse_sweep(v).unwrap());
     let ngram_writer = matches.value_of(NGRAM_COUNTS).map(|s| {
-        let f = File::create(s).expect("Can't create file to write ngram counts.");
-        BufWriter::new(f)
+        let path = match &sweep {
+            Some(sweep) => sweep_path(s, sweep[0]),
+            None => s.to_owned(),
+        };
+        OutputFile::create(Some(&path), &output_opts)
+            .expect("Can't create file to write ngram counts.")
     });
-    let bracket = !matches.is_present(NO_BRACKET);
-    let filter_first = matches.is_present(FILTER_FIRST);
-    let token_min = matches
-        .value_of(TOKEN_MIN)
-        .map(|v| v.parse::<usize>().expect("Can't parse token min"))
-        .unwrap();
-    let ngram_min = matches
-        .value_of(NGRAM_MIN)
-        .map(|v| v.parse::<usize>().expect("Can't parse ngram min"))
-        .unwrap();
-    let min_n = matches
-        .value_of(MIN_N)
-        .map(|v| v.parse::<usize>().expect("Can't parse min_n"))
-        .unwrap();
-    let max_n = matches
-        .value_of(MAX_N)
-        .map(|v| v.parse::<usize>().expect("Can't parse max_n"))
-        .unwrap();
-    assert_ne!(min_n, 0, "The minimum n-gram length cannot be zero.");
-    assert!(
-        min_n <= max_n,
-        "The maximum length should be equal to or greater than the minimum length."
-    );
+    let count_opts = count_options_from_matches(matches);
+    if matches.is_present(COUNT_ONLY) {
+        let count_opts = CountOptions {
+            vocab: !matches.is_present(NO_VOCAB),
+            ..count_opts
+        };
+        let counts = count::count_files(&corpus, &count_opts).expect("Can't read corpus");
+        write_growth_from_matches(matches, &counts, &output_opts);
+        write_projection_from_matches(matches, &counts, &output_opts);
+        write_pii_counts_from_matches(matches, &counts, &output_opts);
+        write_totals(&mut output, &counts, &count_opts).expect("Can't write corpus totals.");
+        output.finish().expect("Can't write corpus totals.");
+        return;
+    }
+    let table = table_options_from_matches(matches);
+    // The n-grams of the first lengths of a sweep are written with the
+    // token counts.
+    let table = match &sweep {
+        Some(sweep) => table
+            .to_builder()
+            .min_n(sweep[0].0)
+            .max_n(sweep[0].1)
+            .build()
+            .unwrap_or_else(|err| usage_error(&err)),
+        None => table,
+    };
+    let mut ngram_opts = ngram_options_from_matches(matches, table.clone());
 
-    let mut token_counts = HashMap::new();
-    for line in reader.lines() {
-        let line = line.expect("Can't read line");
-        for part in line.split_whitespace() {
-            if let Some(cnt) = token_counts.get_mut(part) {
-                *cnt += 1;
-            } else {
-                token_counts.insert(part.to_string(), 1);
-            }
+    if matches.is_present(FOLLOW) {
+        let count_opts = CountOptions {
+            threads: 1,
+            growth_step: None,
+            ..count_opts
+        };
+        follow_tokens(
+            matches,
+            &corpus,
+            &count_opts,
+            (output, ngram_writer),
+            &mut ngram_opts,
+            &output_opts,
+        );
+        return;
+    }
+
+    let near_dup_report = matches.value_of(NEAR_DUP_REPORT);
+    let shingle_size = value_t!(matches, SHINGLE_SIZE, usize).unwrap();
+    let max_doc_freq = value_t!(matches, MAX_DOC_FREQ, f64).ok();
+    let min_docs = value_t!(matches, MIN_DOCS, usize).ok();
+    let token_hasher = Some(())
+        .filter(|_| matches.is_present(HASH_TOKENS))
+        .map(|_| match salt_from_matches(matches) {
+            Ok(salt) => TokenHasher::new(&salt),
+            Err(err) => usage_error(&format!(
+                "Can't read or write the salt file {}: {}",
+                matches.value_of(HASH_SALT).unwrap(),
+                err
+            )),
+        });
+    let audit = matches.value_of(AUDIT_TERMS).map(|path| {
+        let examples = value_t!(matches, AUDIT_EXAMPLES, usize).unwrap();
+        let context = value_t!(matches, AUDIT_CONTEXT, usize).unwrap();
+        Arc::new(
+            AuditTerms::read(
+                BufReader::new(File::open(path).expect("Can't open audit terms.")),
+                examples,
+                context,
+            )
+            .expect("Can't read audit terms."),
+        )
+    });
+    let count_opts = CountOptions {
+        sketch_shingle_size: near_dup_report.map(|_| shingle_size),
+        doc_freqs: max_doc_freq.is_some() || min_docs.is_some() || matches.is_present(IDF),
+        ngram_doc_freqs: min_docs.filter(|_| matches.is_present(NGRAM_COUNTS)).map(
+            |_| match &sweep {
+                Some(sweep) => NGramLengths {
+                    min_n: sweep.iter().map(|&(min_n, _)| min_n).min().unwrap(),
+                    max_n: sweep.iter().map(|&(_, max_n)| max_n).max().unwrap(),
+                    bracket: table.bracket(),
+                },
+                None => NGramLengths {
+                    min_n: table.min_n(),
+                    max_n: table.max_n(),
+                    bracket: table.bracket(),
+                },
+            },
+        ),
+        vocab: true,
+        audit,
+        positions: matches.is_present(POSITIONS),
+        dispersion: matches.is_present(DISPERSION),
+        document_terms: matches.is_present(DTM_OUT) || matches.is_present(INVERTED_INDEX),
+        bitext: matches.is_present(BITEXT),
+        labeled,
+        max_contributions: Some(())
+            .filter(|_| matches.is_present(DP_EPSILON))
+            .map(|_| value_t!(matches, DP_SENSITIVITY, usize).unwrap()),
+        ..count_opts
+    };
+    let mut counts = count::count_files(&corpus, &count_opts).expect("Can't read corpus");
+    release_private_counts_from_matches(matches, &mut counts);
+    if count_opts.dedup_lines {
+        eprintln!(
+            "Skipped {} duplicate lines of {} lines ({:.2}%)",
+            counts.duplicate_lines,
+            counts.lines,
+            100. * counts.duplicate_lines as f64 / counts.lines.max(1) as f64
+        );
+    }
+    write_corpus_reports_from_matches(matches, &mut counts, &count_opts, &output_opts);
+    write_label_reports_from_matches(matches, &counts.labels, &output_opts);
+
+    let num_documents = counts.num_documents;
+    let num_tokens = counts.num_tokens;
+    let dispersion = std::mem::take(&mut counts.dispersion);
+    let mut pruning = pruning_from_counts(&mut counts, max_doc_freq, min_docs);
+    ngram_opts.table = with_token_min(
+        &ngram_opts.table,
+        derived_token_min(matches, &counts.tokens).unwrap_or(table.token_min()),
+    );
+    let token_counts = match &token_hasher {
+        Some(hasher) => {
+            // Tokens are pruned while their surface forms are known.
+            let hashed = counts
+                .tokens
+                .into_iter()
+                .filter(|(token, _)| !pruning.prune_token(token))
+                .map(|(token, count)| (hasher.hash(&token), count))
+                .collect();
+            pruning = Pruning::default();
+            hashed
         }
+        None => counts.tokens,
+    };
+
+    let token_counts =
+        count::token_table(token_counts, &ngram_opts.table, &ngram_opts.special_tokens);
+    let kept_tokens = token_counts
+        .iter()
+        .filter(|(token, _)| !pruning.prune_token(token))
+        .collect::<Vec<_>>();
+
+    write_idf_from_matches(
+        matches,
+        &kept_tokens,
+        &pruning.doc_freqs,
+        num_documents,
+        &output_opts,
+    );
+    write_dispersion_from_matches(
+        matches,
+        &kept_tokens,
+        &dispersion,
+        num_documents,
+        num_tokens,
+        &output_opts,
+    );
+    write_ids_from_matches(matches, &kept_tokens, &ngram_opts, &output_opts);
+    write_freq_bins_from_matches(matches, &kept_tokens, &output_opts);
+    write_softmax_cutoffs_from_matches(matches, &kept_tokens, &output_opts);
+    if matches.is_present(UNIGRAM_PROBS) || matches.is_present(UNIGRAM_TABLE) {
+        write_unigram_from_matches(matches, kept_tokens.iter().copied(), &output_opts);
     }
+    write_huffman_from_matches(matches, &kept_tokens, &output_opts);
+    write_token_sets_from_matches(matches, &kept_tokens, &output_opts);
 
-    let token_counts = if filter_first {
-        counted_into_sorted(token_counts, Some(token_min))
-    } else {
-        counted_into_sorted(token_counts, None)
+    let mut documents = counts.document_terms;
+    documents
+        .sort_unstable_by(|doc1, doc2| (&doc1.file, doc1.index).cmp(&(&doc2.file, doc2.index)));
+    write_dtm_from_matches(matches, &documents, &kept_tokens, &output_opts);
+    write_inverted_index_from_matches(matches, &documents, &kept_tokens, &output_opts);
+
+    let ngram_ids = matches.value_of(NGRAM_IDS).map(|s| {
+        OutputFile::create(Some(s), &output_opts).expect("Can't create file to write ngram ids.")
+    });
+    if let Some(sweep) = &sweep {
+        write_sweep_from_matches(
+            matches,
+            &sweep[1..],
+            &token_counts,
+            &ngram_opts,
+            &pruning,
+            &output_opts,
+        );
+    }
+
+    let sizes = write_token_counts(
+        output,
+        ngram_writer,
+        ngram_ids,
+        token_counts,
+        num_tokens,
+        &ngram_opts,
+        &pruning,
+    );
+
+    if let Some(ff_config) = matches.value_of(EMIT_FF_CONFIG) {
+        let buckets_exp = value_t!(matches, FF_BUCKETS_EXP, u32).unwrap();
+        let mut writer = OutputFile::create(Some(ff_config), &output_opts)
+            .expect("Can't create finalfrontier config.");
+        write_ff_config(&mut writer, &ngram_opts, &sizes, num_tokens, buckets_exp)
+            .expect("Can't write finalfrontier config.");
+        writer.finish().expect("Can't write finalfrontier config.");
+    }
+
+    write_target_counts_from_matches(
+        matches,
+        counts.target_tokens,
+        table.token_min(),
+        &mut ngram_opts,
+        &output_opts,
+    );
+}
+
+/// Get the document frequency pruning of the tokens and n-grams.
+///
+/// The document frequencies are taken from the counts.
+fn pruning_from_counts(
+    counts: &mut Counts,
+    max_doc_freq: Option<f64>,
+    min_docs: Option<usize>,
+) -> Pruning {
+    let num_documents = counts.num_documents;
+    let pruning = Pruning {
+        doc_freqs: std::mem::take(&mut counts.doc_freqs),
+        ngram_doc_freqs: std::mem::take(&mut counts.ngram_doc_freqs),
+        max_docs: max_doc_freq.map(|f| (f * num_documents as f64) as usize),
+        min_docs,
     };
+    if let Some(max_docs) = pruning.max_docs {
+        eprintln!(
+            "Pruned {} tokens occurring in more than {} of {} documents",
+            counts
+                .tokens
+                .keys()
+                .filter(|token| pruning.doc_freq(token) > max_docs)
+                .count(),
+            max_docs,
+            num_documents
+        );
+    }
+    if let Some(min_docs) = min_docs {
+        eprintln!(
+            "Suppressed {} tokens occurring in fewer than {} documents",
+            counts
+                .tokens
+                .keys()
+                .filter(|token| pruning.doc_freq(token) < min_docs)
+                .count(),
+            min_docs
+        );
+    }
+    pruning
+}
 
-    if let Some(mut ngram_writer) = ngram_writer {
-        let mut ngram_counts = HashMap::new();
-        for (token, count) in token_counts {
-            if filter_first && count < token_min {
-                continue;
-            }
-            let token = if bracket {
-                let mut b_token = String::with_capacity(token.len() + 2);
-                b_token.push('<');
-                b_token.push_str(&token);
-                b_token.push('>');
-                b_token
-            } else {
-                token
+/// Get the corpus and whether its lines are labeled.
+///
+/// JSONL corpora are labeled by their time buckets or facets.
+fn labeled_corpus_from_matches(matches: &ArgMatches) -> (Corpus, bool) {
+    let mut corpus = corpus_from_matches(matches);
+    let time_bucket = value_t!(matches, TIME_BUCKET, TimeBucket).ok();
+    let json_label = match (time_bucket, matches.value_of(FACET_FIELD)) {
+        (Some(bucket), _) => Some(JsonLabel {
+            field: matches.value_of(TIME_FIELD).unwrap().to_owned(),
+            bucket: Some(bucket),
+        }),
+        (None, Some(field)) => Some(JsonLabel {
+            field: field.to_owned(),
+            bucket: None,
+        }),
+        (None, None) => None,
+    };
+    let labeled = matches.is_present(LABELED) || json_label.is_some();
+    if let Some(label) = json_label {
+        match &mut corpus.format {
+            InputFormat::Jsonl(fields) => fields.label = Some(label),
+            _ => usage_error("Time buckets and facets require --input_format jsonl."),
+        }
+    }
+    (corpus, labeled)
+}
+
+/// Get the count options that are shared by all ways of counting tokens.
+fn count_options_from_matches(matches: &ArgMatches) -> CountOptions {
+    let tokenizer_cmd = matches.value_of(TOKENIZER_CMD).map(|command| {
+        if matches.occurrences_of(TOKENIZER) != 0 {
+            usage_error(
+                "--tokenizer_cmd output is split at whitespace, --tokenizer cannot be used.",
+            );
+        }
+        TokenizerCommand::new(command)
+    });
+    let normalizer = matches
+        .value_of(NORMALIZER)
+        .map(|names| Arc::<dyn Normalizer>::from(normalizer_registry().create(names).unwrap()));
+    let rewriter = matches.value_of(REWRITE).map(|path| {
+        Rewriter::read(BufReader::new(
+            File::open(path).expect("Can't open rewrite rules."),
+        ))
+        .expect("Can't read rewrite rules.")
+    });
+    let boilerplate = matches.value_of(STRIP_BOILERPLATE).map(|path| {
+        Arc::new(
+            Boilerplate::read(BufReader::new(
+                File::open(path).expect("Can't open boilerplate n-grams."),
+            ))
+            .expect("Can't read boilerplate n-grams."),
+        )
+    });
+    let pii = Some(())
+        .filter(|_| matches.is_present(MASK_PII) || matches.is_present(PII_COUNTS))
+        .map(|_| Arc::new(PiiPatterns::new(matches.is_present(MASK_PII))));
+    let record_growth = matches.is_present(GROWTH) || matches.is_present(PROJECT_COVERAGE);
+    CountOptions {
+        threads: value_t!(matches, THREADS, usize)
+            .ok()
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get())),
+        dedup_lines: matches.is_present(DEDUP_LINES),
+        filter: filter_from_matches(matches),
+        emoji: value_t!(matches, EMOJI, EmojiPolicy).unwrap(),
+        tokenizer: tokenizer_from_matches(matches),
+        tokenizer_cmd,
+        normalizer,
+        rewriter,
+        boilerplate,
+        pii,
+        growth_step: Some(())
+            .filter(|_| record_growth)
+            .map(|_| value_t!(matches, GROWTH_STEP, usize).unwrap()),
+        ..CountOptions::default()
+    }
+}
+
+fn ngram_options_from_matches(matches: &ArgMatches, table: Options) -> NGramOptions {
+    NGramOptions {
+        table,
+        sort_buffer: sort_buffer_from_matches(matches),
+        tmp_dir: tmp_dir_from_matches(matches),
+        normalized: matches.is_present(NORMALIZED),
+        token_logprob: matches.is_present(TOKEN_LOGPROB),
+        arrow: matches.is_present(ARROW),
+        special_tokens: matches
+            .value_of(SPECIAL_TOKENS)
+            .map(|v| {
+                v.split(',')
+                    .filter(|s| !s.is_empty())
+                    .map(ToOwned::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Follow the corpus and write the token and n-gram counts of every
+/// snapshot.
+///
+/// The outputs in `writers` are used for the first snapshot.
+fn follow_tokens(
+    matches: &ArgMatches,
+    corpus: &Corpus,
+    count_opts: &CountOptions,
+    writers: (OutputFile, Option<OutputFile>),
+    ngram_opts: &mut NGramOptions,
+    output_opts: &OutputOptions,
+) {
+    if corpus.paths.is_empty() {
+        usage_error("Following requires corpus files, stdin is not supported.");
+    }
+    if !corpus.lines.is_full() || corpus.bytes.is_some() {
+        usage_error("Following requires complete corpus files.");
+    }
+    let interval = value_t!(matches, SNAPSHOT_INTERVAL, u64).unwrap();
+    let token_min = ngram_opts.table.token_min();
+    let mut writers = Some(writers);
+    count::follow_corpus(
+        corpus,
+        count_opts,
+        Duration::from_secs(interval),
+        |counts| {
+            let (output, ngram_writer) = match writers.take() {
+                Some(writers) => writers,
+                None => (
+                    OutputFile::create(matches.value_of(TOKEN_COUNTS), output_opts)?,
+                    matches
+                        .value_of(NGRAM_COUNTS)
+                        .map(|s| OutputFile::create(Some(s), output_opts))
+                        .transpose()?,
+                ),
             };
-            for ngram in NGrams::new(&token, min_n, max_n) {
-                if let Some(idx) = ngram_counts.get_mut(&*ngram) {
-                    *idx += count;
-                } else {
-                    ngram_counts.insert(ngram.to_string(), count);
-                }
-            }
-            writeln!(output, "{}\t{}", token, count).expect("Can't write token counts.");
+            ngram_opts.table = with_token_min(
+                &ngram_opts.table,
+                derived_token_min(matches, &counts.tokens).unwrap_or(token_min),
+            );
+            let token_counts = count::token_table(
+                counts.tokens.clone(),
+                &ngram_opts.table,
+                &ngram_opts.special_tokens,
+            );
+            write_token_counts(
+                output,
+                ngram_writer,
+                None,
+                token_counts,
+                counts.num_tokens,
+                ngram_opts,
+                &Pruning::default(),
+            );
+            Ok(())
+        },
+    )
+    .expect("Can't follow corpus");
+}
+
+/// Replace the token counts by the differentially private release of the
+/// document contributions.
+fn release_private_counts_from_matches(matches: &ArgMatches, counts: &mut Counts) {
+    let params = match value_t!(matches, DP_EPSILON, f64) {
+        Ok(epsilon) => DpParams {
+            epsilon,
+            delta: value_t!(matches, DP_DELTA, f64).unwrap(),
+            sensitivity: value_t!(matches, DP_SENSITIVITY, usize).unwrap(),
+        },
+        Err(_) => return,
+    };
+    let seed = value_t!(matches, DP_SEED, u64)
+        .ok()
+        .unwrap_or_else(privacy::random_seed);
+    let types = counts.tokens.len();
+    let contributions = std::mem::take(&mut counts.contributions);
+    counts.tokens = privacy::release(contributions, &params, &mut rng::Rng::new(seed));
+    counts.num_tokens = counts.tokens.values().sum();
+    eprintln!(
+        "Released {} of {} types with noisy counts of at least {:.1}",
+        counts.tokens.len(),
+        types,
+        params.threshold()
+    );
+}
+
+/// Write the reports about the corpus that do not depend on the pruned
+/// token counts.
+fn write_corpus_reports_from_matches(
+    matches: &ArgMatches,
+    counts: &mut Counts,
+    count_opts: &CountOptions,
+    output_opts: &OutputOptions,
+) {
+    if let Some(report) = matches.value_of(NEAR_DUP_REPORT) {
+        let threshold = value_t!(matches, NEAR_DUP_THRESHOLD, f64).unwrap();
+        let mut writer = OutputFile::create(Some(report), output_opts)
+            .expect("Can't create near-duplicate report.");
+        // Threads finish in any order, cluster the documents in corpus order.
+        counts
+            .documents
+            .sort_unstable_by(|doc1, doc2| (&doc1.file, doc1.index).cmp(&(&doc2.file, doc2.index)));
+        write_near_duplicates(&mut writer, &counts.documents, threshold)
+            .expect("Can't write near-duplicate report.");
+        writer.finish().expect("Can't write near-duplicate report.");
+    }
+    write_growth_from_matches(matches, counts, output_opts);
+    write_projection_from_matches(matches, counts, output_opts);
+    write_pii_counts_from_matches(matches, counts, output_opts);
+    if let Some(emoji_counts) = matches.value_of(EMOJI_COUNTS) {
+        let mut writer = OutputFile::create(Some(emoji_counts), output_opts)
+            .expect("Can't create emoji counts.");
+        for (emoji, count) in counted_into_sorted(std::mem::take(&mut counts.emoji), None) {
+            writeln!(writer, "{}\t{}", emoji, count).expect("Can't write emoji counts.");
         }
-        counted_into_sorted(ngram_counts, Some(ngram_min))
-            .into_iter()
-            .for_each(|(ngram, count)| {
-                writeln!(ngram_writer, "{}\t{}", ngram, count).expect("Can't write ngram counts.");
-            });
-    } else {
-        token_counts.into_iter().for_each(|(token, count)| {
-            writeln!(output, "{}\t{}", token, count).expect("Can't write token counts.");
-        });
+        writer.finish().expect("Can't write emoji counts.");
+    }
+    if let Some(positions) = matches.value_of(POSITIONS) {
+        let mut writer = OutputFile::create(Some(positions), output_opts)
+            .expect("Can't create positional counts.");
+        write_positions(&mut writer, std::mem::take(&mut counts.positions))
+            .expect("Can't write positional counts.");
+        writer.finish().expect("Can't write positional counts.");
+    }
+    if let Some(stats) = matches.value_of(STATS) {
+        let mut writer =
+            OutputFile::create(Some(stats), output_opts).expect("Can't create token statistics.");
+        write_stats(&mut writer, &counts.tokens).expect("Can't write token statistics.");
+        writer.finish().expect("Can't write token statistics.");
+    }
+    if let Some(audit) = &count_opts.audit {
+        let mut writer = OutputFile::create(matches.value_of(AUDIT_REPORT), output_opts)
+            .expect("Can't create audit report.");
+        audit
+            .write_report(&mut writer, &counts.audit)
+            .expect("Can't write audit report.");
+        writer.finish().expect("Can't write audit report.");
     }
 }
 
-fn counted_into_sorted(
-    iter: impl IntoIterator<Item = (String, usize)>,
-    filter: Option<usize>,
-) -> Vec<(String, usize)> {
-    let mut items: Vec<_> = if let Some(min_freq) = filter {
-        iter.into_iter()
-            .filter(|(_, cnt)| *cnt >= min_freq)
-            .collect()
+/// Write the tables of the counts per label, e.g. per time bucket.
+fn write_label_reports_from_matches(
+    matches: &ArgMatches,
+    labels: &HashMap<String, LabelCounts>,
+    output_opts: &OutputOptions,
+) {
+    if let Some(label_counts) = matches.value_of(LABEL_COUNTS) {
+        let mut writer = OutputFile::create(Some(label_counts), output_opts)
+            .expect("Can't create label counts.");
+        write_label_counts(&mut writer, labels).expect("Can't write label counts.");
+        writer.finish().expect("Can't write label counts.");
+    }
+    if let Some(bucket_counts) = matches.value_of(BUCKET_COUNTS) {
+        let format = value_t!(matches, BUCKET_FORMAT, TableFormat).unwrap();
+        let mut writer = OutputFile::create(Some(bucket_counts), output_opts)
+            .expect("Can't create bucket counts.");
+        write_label_table(&mut writer, labels, "bucket", format)
+            .expect("Can't write bucket counts.");
+        writer.finish().expect("Can't write bucket counts.");
+    }
+    if let Some(facet_counts) = matches.value_of(FACET_COUNTS) {
+        let format = value_t!(matches, FACET_FORMAT, TableFormat).unwrap();
+        let mut writer = OutputFile::create(Some(facet_counts), output_opts)
+            .expect("Can't create facet counts.");
+        write_label_table(&mut writer, labels, "facet", format).expect("Can't write facet counts.");
+        writer.finish().expect("Can't write facet counts.");
+    }
+    if let Some(facet_summary) = matches.value_of(FACET_SUMMARY) {
+        let mut writer = OutputFile::create(Some(facet_summary), output_opts)
+            .expect("Can't create facet summary.");
+        write_facet_summary(&mut writer, labels).expect("Can't write facet summary.");
+        writer.finish().expect("Can't write facet summary.");
+    }
+    if let Some(trends) = matches.value_of(TRENDS) {
+        write_trends_from_matches(matches, labels, trends, output_opts);
+    }
+    if let Some(log_odds) = matches.value_of(LOG_ODDS) {
+        let mut writer =
+            OutputFile::create(Some(log_odds), output_opts).expect("Can't create log-odds.");
+        write_log_odds(&mut writer, labels).expect("Can't write log-odds.");
+        writer.finish().expect("Can't write log-odds.");
+    }
+    if let Some(ranking) = matches.value_of(FEATURE_RANKING) {
+        let measure = value_t!(matches, FEATURE_MEASURE, FeatureMeasure).unwrap();
+        let mut writer =
+            OutputFile::create(Some(ranking), output_opts).expect("Can't create feature ranking.");
+        features::write_ranking(&mut writer, labels, measure)
+            .expect("Can't write feature ranking.");
+        writer.finish().expect("Can't write feature ranking.");
+    }
+}
+
+fn write_idf_from_matches(
+    matches: &ArgMatches,
+    tokens: &[&(String, usize)],
+    doc_freqs: &HashMap<String, usize>,
+    num_documents: usize,
+    output_opts: &OutputOptions,
+) {
+    let path = match matches.value_of(IDF) {
+        Some(path) => path,
+        None => return,
+    };
+    let mut writer = OutputFile::create(Some(path), output_opts).expect("Can't create IDF file.");
+    write_idf(
+        &mut writer,
+        tokens.iter().copied(),
+        doc_freqs,
+        num_documents,
+    )
+    .expect("Can't write IDF file.");
+    writer.finish().expect("Can't write IDF file.");
+}
+
+fn write_dispersion_from_matches(
+    matches: &ArgMatches,
+    tokens: &[&(String, usize)],
+    dispersion: &HashMap<String, Vec<(usize, usize)>>,
+    num_documents: usize,
+    num_tokens: usize,
+    output_opts: &OutputOptions,
+) {
+    let path = match matches.value_of(DISPERSION) {
+        Some(path) => path,
+        None => return,
+    };
+    let mut writer =
+        OutputFile::create(Some(path), output_opts).expect("Can't create dispersion file.");
+    write_dispersion(
+        &mut writer,
+        tokens.iter().copied(),
+        dispersion,
+        num_documents,
+        num_tokens,
+    )
+    .expect("Can't write dispersion file.");
+    writer.finish().expect("Can't write dispersion file.");
+}
+
+fn write_ids_from_matches(
+    matches: &ArgMatches,
+    tokens: &[&(String, usize)],
+    ngram_opts: &NGramOptions,
+    output_opts: &OutputOptions,
+) {
+    let path = match matches.value_of(IDS_OUT) {
+        Some(path) => path,
+        None => return,
+    };
+    // Special tokens lead the token counts and take the reserved ids.
+    let reserved = if ngram_opts.special_tokens.is_empty() {
+        matches
+            .value_of(RESERVED)
+            .map(|v| v.split(',').filter(|s| !s.is_empty()).collect::<Vec<_>>())
+            .unwrap()
     } else {
-        iter.into_iter().collect()
+        ngram_opts
+            .special_tokens
+            .iter()
+            .map(String::as_str)
+            .collect()
     };
-    items.sort_unstable_by(|(t1, c1), (t2, c2)| match c2.cmp(c1) {
-        Ordering::Equal => t1.cmp(t2),
-        o => o,
-    });
-    items
+    let mut writer =
+        OutputFile::create(Some(path), output_opts).expect("Can't create token id file.");
+    write_ids(
+        &mut writer,
+        tokens_by_id(&reserved, tokens.iter().map(|(token, _)| token.as_str())),
+    )
+    .expect("Can't write token id file.");
+    writer.finish().expect("Can't write token id file.");
 }
 
-fn parse_args() -> ArgMatches<'static> {
-    App::new("corpus-count")
-        .author("Sebastian Pütz")
-        .version("0.1.1")
-        .settings(DEFAULT_CLAP_SETTINGS)
-        .arg(
-            Arg::with_name(CORPUS)
-                .help("Corpus file")
-                .long("corpus")
-                .short("c")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name(TOKEN_COUNTS)
-                .long("token_counts")
-                .short("t")
-                .help("Token count file")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name(NGRAM_COUNTS)
-                .long("ngram_counts")
-                .short("n")
-                .help("File for ngram counts")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name(TOKEN_MIN)
-                .long("token_min")
-                .default_value("1")
-                .help("Word min count"),
-        )
-        .arg(
-            Arg::with_name(NGRAM_MIN)
-                .long("ngram_min")
-                .default_value("1")
-                .help("Ngram min count"),
-        )
-        .arg(
-            Arg::with_name(MIN_N)
-                .long(MIN_N)
-                .default_value("3")
-                .help("Minimal ngram length to be used.")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name(MAX_N)
-                .long(MAX_N)
-                .default_value("6")
-                .help("Maximum ngram length to be used.")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name(FILTER_FIRST)
-                .long("filter_first")
-                .help("Filter tokens before counting ngrams."),
-        )
-        .arg(
-            Arg::with_name(NO_BRACKET)
-                .long("no_bracket")
-                .takes_value(false),
-        )
-        .get_matches()
+fn write_freq_bins_from_matches(
+    matches: &ArgMatches,
+    tokens: &[&(String, usize)],
+    output_opts: &OutputOptions,
+) {
+    let path = match matches.value_of(FREQ_BINS) {
+        Some(path) => path,
+        None => return,
+    };
+    let base = value_t!(matches, FREQ_BIN_BASE, usize).unwrap();
+    let mut writer =
+        OutputFile::create(Some(path), output_opts).expect("Can't create frequency bins.");
+    write_freq_bins(&mut writer, tokens.iter().copied(), base)
+        .expect("Can't write frequency bins.");
+    writer.finish().expect("Can't write frequency bins.");
 }
 
-/// Taken from finalfrontier::subtokens
-pub struct NGrams<'a> {
-    max_n: usize,
-    min_n: usize,
-    string: &'a str,
-    char_offsets: VecDeque<usize>,
-    ngram_len: usize,
+fn write_softmax_cutoffs_from_matches(
+    matches: &ArgMatches,
+    tokens: &[&(String, usize)],
+    output_opts: &OutputOptions,
+) {
+    let path = match matches.value_of(SOFTMAX_CUTOFFS) {
+        Some(path) => path,
+        None => return,
+    };
+    let clusters = value_t!(matches, SOFTMAX_CLUSTERS, usize).unwrap();
+    let counts = tokens.iter().map(|(_, count)| *count).collect::<Vec<_>>();
+    let mut writer =
+        OutputFile::create(Some(path), output_opts).expect("Can't create softmax cutoffs.");
+    write_softmax_cutoffs(&mut writer, &counts, clusters).expect("Can't write softmax cutoffs.");
+    writer.finish().expect("Can't write softmax cutoffs.");
+}
+
+fn write_huffman_from_matches(
+    matches: &ArgMatches,
+    tokens: &[&(String, usize)],
+    output_opts: &OutputOptions,
+) {
+    let path = match matches.value_of(HUFFMAN) {
+        Some(path) => path,
+        None => return,
+    };
+    let mut writer =
+        OutputFile::create(Some(path), output_opts).expect("Can't create Huffman codes.");
+    write_huffman(&mut writer, tokens.iter().copied()).expect("Can't write Huffman codes.");
+    writer.finish().expect("Can't write Huffman codes.");
 }
 
-impl<'a> NGrams<'a> {
-    /// Create a new n-ngram iterator.
-    ///
-    /// The iterator will create n-ngrams of length *[min_n, max_n]*
-    pub fn new(string: &'a str, min_n: usize, max_n: usize) -> Self {
-        // Get the byte offsets of the characters in `string`.
-        let char_offsets = string
-            .char_indices()
-            .map(|(idx, _)| idx)
-            .collect::<VecDeque<_>>();
+/// Write the Bloom filter, token map and DAWG of the tokens.
+fn write_token_sets_from_matches(
+    matches: &ArgMatches,
+    tokens: &[&(String, usize)],
+    output_opts: &OutputOptions,
+) {
+    if let Some(path) = matches.value_of(BLOOM_OUT) {
+        let fpr = value_t!(matches, BLOOM_FPR, f64).unwrap();
+        let mut filter = BloomFilter::with_fpr(tokens.len(), fpr);
+        for (token, _) in tokens {
+            filter.insert(token);
+        }
+        let mut writer =
+            OutputFile::create(Some(path), output_opts).expect("Can't create Bloom filter.");
+        filter
+            .write(&mut writer)
+            .expect("Can't write Bloom filter.");
+        writer.finish().expect("Can't write Bloom filter.");
+    }
+
+    if !matches.is_present(CCFS_OUT) && !matches.is_present(DAWG_OUT) {
+        return;
+    }
+    let mut tokens = tokens.to_vec();
+    tokens.sort_unstable_by(|(token1, _), (token2, _)| token1.cmp(token2));
 
-        let ngram_len = cmp::min(max_n, char_offsets.len());
+    if let Some(path) = matches.value_of(CCFS_OUT) {
+        let counts = matches.is_present(CCFS_COUNTS);
+        let mut builder = MapBuilder::new();
+        for (token, count) in &tokens {
+            builder
+                .insert(token, if counts { *count as u64 } else { 0 })
+                .expect("Can't build token map.");
+        }
+        let mut writer =
+            OutputFile::create(Some(path), output_opts).expect("Can't create token map.");
+        writer
+            .write_all(&builder.into_bytes())
+            .expect("Can't write token map.");
+        writer.finish().expect("Can't write token map.");
+    }
 
-        NGrams {
-            min_n,
-            max_n,
-            string,
-            char_offsets,
-            ngram_len,
+    if let Some(path) = matches.value_of(DAWG_OUT) {
+        let mut builder = DawgBuilder::new();
+        for (token, count) in &tokens {
+            builder
+                .insert(token, *count as u64)
+                .expect("Can't build DAWG.");
         }
+        let mut writer = OutputFile::create(Some(path), output_opts).expect("Can't create DAWG.");
+        writer
+            .write_all(&builder.into_bytes())
+            .expect("Can't write DAWG.");
+        writer.finish().expect("Can't write DAWG.");
+    }
+}
+
+/// Write the document-term matrix with its vocabulary and documents.
+fn write_dtm_from_matches(
+    matches: &ArgMatches,
+    documents: &[DocumentTerms],
+    tokens: &[&(String, usize)],
+    output_opts: &OutputOptions,
+) {
+    let path = match matches.value_of(DTM_OUT) {
+        Some(path) => path,
+        None => return,
+    };
+    let vocab = tokens
+        .iter()
+        .map(|(token, _)| token.as_str())
+        .collect::<Vec<_>>();
+    let mut writer =
+        OutputFile::create(Some(path), output_opts).expect("Can't create document-term matrix.");
+    write_dtm(&mut writer, documents, &vocab).expect("Can't write document-term matrix.");
+    writer.finish().expect("Can't write document-term matrix.");
+    let mut writer = OutputFile::create(matches.value_of(DTM_VOCAB), output_opts)
+        .expect("Can't create document-term matrix vocabulary.");
+    vocab
+        .iter()
+        .try_for_each(|token| writeln!(writer, "{}", token))
+        .expect("Can't write document-term matrix vocabulary.");
+    writer
+        .finish()
+        .expect("Can't write document-term matrix vocabulary.");
+    if let Some(dtm_docs) = matches.value_of(DTM_DOCS) {
+        let mut writer = OutputFile::create(Some(dtm_docs), output_opts)
+            .expect("Can't create document-term matrix documents.");
+        documents
+            .iter()
+            .try_for_each(|doc| writeln!(writer, "{}:{}", doc.file, doc.index))
+            .expect("Can't write document-term matrix documents.");
+        writer
+            .finish()
+            .expect("Can't write document-term matrix documents.");
     }
 }
 
-impl<'a> Iterator for NGrams<'a> {
-    type Item = &'a str;
+/// Write the inverted index with its documents.
+fn write_inverted_index_from_matches(
+    matches: &ArgMatches,
+    documents: &[DocumentTerms],
+    tokens: &[&(String, usize)],
+    output_opts: &OutputOptions,
+) {
+    let path = match matches.value_of(INVERTED_INDEX) {
+        Some(path) => path,
+        None => return,
+    };
+    let mut writer =
+        OutputFile::create(Some(path), output_opts).expect("Can't create inverted index.");
+    write_inverted_index(&mut writer, documents, tokens.iter().copied())
+        .expect("Can't write inverted index.");
+    writer.finish().expect("Can't write inverted index.");
+    if let Some(index_docs) = matches.value_of(INDEX_DOCS) {
+        let mut writer = OutputFile::create(Some(index_docs), output_opts)
+            .expect("Can't create inverted index documents.");
+        documents
+            .iter()
+            .try_for_each(|doc| writeln!(writer, "{}:{}", doc.file, doc.index))
+            .expect("Can't write inverted index documents.");
+        writer
+            .finish()
+            .expect("Can't write inverted index documents.");
+    }
+}
 
-    #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        // If the n-grams for the current suffix are exhausted,
-        // move to the next suffix.
-        if self.ngram_len < self.min_n {
-            // Remove first character, to get the next suffix.
-            self.char_offsets.pop_front();
+/// Write the n-gram counts of the n-gram lengths of a sweep.
+fn write_sweep_from_matches(
+    matches: &ArgMatches,
+    lengths: &[(usize, usize)],
+    token_counts: &[(String, usize)],
+    ngram_opts: &NGramOptions,
+    pruning: &Pruning,
+    output_opts: &OutputOptions,
+) {
+    let ngram_counts = matches.value_of(NGRAM_COUNTS).unwrap();
+    let mut sweep_opts = ngram_opts.clone();
+    for &(min_n, max_n) in lengths {
+        sweep_opts.table = ngram_opts
+            .table
+            .to_builder()
+            .min_n(min_n)
+            .max_n(max_n)
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err));
+        let ngram_writer =
+            OutputFile::create(Some(&sweep_path(ngram_counts, (min_n, max_n))), output_opts)
+                .expect("Can't create file to write ngram counts.");
+        let counts = count_ngrams(token_counts, &sweep_opts, pruning);
+        write_ngram_counts(ngram_writer, None, counts, 0, &sweep_opts, pruning);
+    }
+}
 
-            // If the suffix is smaller than the minimal n-gram
-            // length, the iterator is exhausted.
-            if self.char_offsets.len() < self.min_n {
-                return None;
-            }
+/// Write the token and n-gram counts of the target side of bitext.
+fn write_target_counts_from_matches(
+    matches: &ArgMatches,
+    target_tokens: HashMap<String, usize>,
+    token_min: usize,
+    ngram_opts: &mut NGramOptions,
+    output_opts: &OutputOptions,
+) {
+    let path = match matches.value_of(TARGET_TOKEN_COUNTS) {
+        Some(path) => path,
+        None => return,
+    };
+    let output = OutputFile::create(Some(path), output_opts)
+        .expect("Can't open output to write target token counts.");
+    let ngram_writer = matches.value_of(TARGET_NGRAM_COUNTS).map(|s| {
+        OutputFile::create(Some(s), output_opts)
+            .expect("Can't create file to write target ngram counts.")
+    });
+    let num_tokens = target_tokens.values().sum();
+    ngram_opts.table = with_token_min(
+        &ngram_opts.table,
+        derived_token_min(matches, &target_tokens).unwrap_or(token_min),
+    );
+    let token_counts =
+        count::token_table(target_tokens, &ngram_opts.table, &ngram_opts.special_tokens);
+    write_token_counts(
+        output,
+        ngram_writer,
+        None,
+        token_counts,
+        num_tokens,
+        ngram_opts,
+        &Pruning::default(),
+    );
+}
+
+/// Get the salt of token hashes.
+///
+/// The salt is read from `--hash_salt`, a random salt is generated and
+/// written to the file if it does not exist.
+fn salt_from_matches(matches: &ArgMatches) -> io::Result<String> {
+    let path = match matches.value_of(HASH_SALT) {
+        Some(path) => path,
+        None => return Ok(hash::random_salt()),
+    };
+    match std::fs::read_to_string(path) {
+        Ok(salt) if salt.trim().is_empty() => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the salt file is empty",
+        )),
+        Ok(salt) => Ok(salt.trim().to_owned()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let salt = hash::random_salt();
+            std::fs::write(path, format!("{}\n", salt))?;
+            Ok(salt)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Get the min counts and n-gram lengths of the count tables.
+fn table_options_from_matches(matches: &ArgMatches) -> Options {
+    Options::builder()
+        .token_min(value_t!(matches, TOKEN_MIN, usize).unwrap())
+        .ngram_min(value_t!(matches, NGRAM_MIN, usize).unwrap())
+        .min_n(value_t!(matches, MIN_N, usize).unwrap())
+        .max_n(value_t!(matches, MAX_N, usize).unwrap())
+        .bracket(!matches.is_present(NO_BRACKET))
+        .filter_first(matches.is_present(FILTER_FIRST))
+        .ngram_redundancy(value_t!(matches, NGRAM_REDUNDANCY, f64).ok())
+        .ngram_target_size(value_t!(matches, NGRAM_TARGET_SIZE, usize).ok())
+        .collation(value_t!(matches, COLLATE, Collation).unwrap())
+        .build()
+        .unwrap_or_else(|err| usage_error(&err))
+}
+
+/// Replace the token min count of count table options.
+fn with_token_min(table: &Options, token_min: usize) -> Options {
+    table
+        .to_builder()
+        .token_min(token_min)
+        .build()
+        .unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Get the token min count from `--token_min_quantile` or
+/// `--max_vocab_coverage`.
+fn derived_token_min(matches: &ArgMatches, counts: &HashMap<String, usize>) -> Option<usize> {
+    let mut sorted = counts.values().copied().collect::<Vec<_>>();
+    sorted.sort_unstable();
+    let token_min = if let Ok(quantile) = value_t!(matches, TOKEN_MIN_QUANTILE, f64) {
+        quantile_min_count(&sorted, quantile)
+    } else if let Ok(coverage) = value_t!(matches, MAX_VOCAB_COVERAGE, f64) {
+        coverage_min_count(&sorted, coverage)
+    } else {
+        return None;
+    };
+    eprintln!("Using token min count {}", token_min);
+    Some(token_min)
+}
+
+/// Get the count at a quantile of ascending type counts.
+fn quantile_min_count(sorted: &[usize], quantile: f64) -> usize {
+    if sorted.is_empty() {
+        return 1;
+    }
+    let idx = ((quantile * sorted.len() as f64) as usize).min(sorted.len() - 1);
+    sorted[idx]
+}
 
-            // Get the maximum n-gram length for this suffix.
-            self.ngram_len = cmp::min(self.max_n, self.char_offsets.len());
+/// Get the smallest count such that the types with at least that count
+/// make up `coverage` of the tokens.
+fn coverage_min_count(sorted: &[usize], coverage: f64) -> usize {
+    let total = sorted.iter().sum::<usize>() as f64;
+    let mut covered = 0;
+    for &count in sorted.iter().rev() {
+        covered += count;
+        if covered as f64 >= coverage * total {
+            return count;
         }
+    }
+    1
+}
 
-        let ngram = if self.ngram_len == self.char_offsets.len() {
-            &self.string[self.char_offsets[0]..]
-        } else {
-            &self.string[self.char_offsets[0]..self.char_offsets[self.ngram_len]]
+/// Parse the n-gram lengths of a sweep such as `min_n=2..4,max_n=4..7`.
+///
+/// Ranges are inclusive, a single length is also a range. Returns all
+/// combinations with `min_n <= max_n`.
+fn parse_sweep(spec: &str) -> Result<Vec<(usize, usize)>, String> {
+    let mut min_ns = None;
+    let mut max_ns = None;
+    for param in spec.split(',') {
+        let (name, range) = param
+            .split_once('=')
+            .ok_or_else(|| format!("Can't parse sweep parameter: {}", param))?;
+        let (start, end) = range.split_once("..").unwrap_or((range, range));
+        let parse_len = |len: &str| {
+            len.parse::<usize>()
+                .map_err(|_| format!("Can't parse sweep range: {}", range))
         };
+        let (start, end) = (parse_len(start)?, parse_len(end)?);
+        if start > end {
+            return Err("Sweep ranges cannot be empty.".to_owned());
+        }
+        match name {
+            "min_n" => min_ns = Some(start..=end),
+            "max_n" => max_ns = Some(start..=end),
+            _ => return Err(format!("Unknown sweep parameter: {}", name)),
+        }
+    }
+    let min_ns = min_ns.ok_or("The sweep needs a min_n range.")?;
+    let max_ns = max_ns.ok_or("The sweep needs a max_n range.")?;
+    if *min_ns.start() == 0 {
+        return Err("The minimum n-gram length cannot be zero.".to_owned());
+    }
+    let sweep = min_ns
+        .flat_map(|min_n| {
+            max_ns
+                .clone()
+                .filter(move |&max_n| min_n <= max_n)
+                .map(move |max_n| (min_n, max_n))
+        })
+        .collect::<Vec<_>>();
+    if sweep.is_empty() {
+        return Err("The sweep has no lengths with min_n <= max_n.".to_owned());
+    }
+    Ok(sweep)
+}
+
+/// Get the n-gram count file of sweep lengths.
+///
+/// `.min<min_n>_max<max_n>` is inserted before the extensions of the file
+/// name, e.g. `ngrams.min3_max6.tsv` for `ngrams.tsv`.
+fn sweep_path(path: &str, (min_n, max_n): (usize, usize)) -> String {
+    let name_start = path.rfind('/').map_or(0, |idx| idx + 1);
+    let ext_start = path[name_start..]
+        .find('.')
+        .map_or(path.len(), |idx| name_start + idx);
+    format!(
+        "{}.min{}_max{}{}",
+        &path[..ext_start],
+        min_n,
+        max_n,
+        &path[ext_start..]
+    )
+}
+
+/// Write a `token<TAB>count` line.
+///
+/// With the corpus size `total`, the frequency per million tokens and the
+/// Zipf value `log10(fpm) + 3` are added as columns. `logprob` is added as
+/// the last column.
+fn write_count(
+    mut writer: impl Write,
+    token: &str,
+    count: usize,
+    total: Option<usize>,
+    logprob: Option<f64>,
+) -> io::Result<()> {
+    write!(writer, "{}\t{}", token, count)?;
+    if let Some(total) = total {
+        let fpm = count as f64 * 1e6 / total as f64;
+        write!(writer, "\t{:.4}\t{:.4}", fpm, fpm.log10() + 3.)?;
+    }
+    if let Some(logprob) = logprob {
+        write!(writer, "\t{:.4}", logprob)?;
+    }
+    writeln!(writer)
+}
+
+/// Options for writing token and n-gram counts.
+#[derive(Clone)]
+struct NGramOptions {
+    /// Min counts and n-gram lengths of the count tables.
+    table: Options,
+    sort_buffer: Option<usize>,
+    tmp_dir: PathBuf,
+    normalized: bool,
+    token_logprob: bool,
+    arrow: bool,
+    /// Tokens leading the token counts, see `count::token_table`.
+    special_tokens: Vec<String>,
+}
+
+/// Pruning of tokens and n-grams by their document frequencies.
+#[derive(Default)]
+struct Pruning {
+    doc_freqs: HashMap<String, usize>,
+    ngram_doc_freqs: HashMap<String, usize>,
+    /// Tokens in more documents are pruned.
+    max_docs: Option<usize>,
+    /// Tokens and n-grams in fewer documents are pruned.
+    min_docs: Option<usize>,
+}
 
-        self.ngram_len -= 1;
+impl Pruning {
+    fn doc_freq(&self, token: &str) -> usize {
+        self.doc_freqs.get(token).copied().unwrap_or(0)
+    }
+
+    fn prune_token(&self, token: &str) -> bool {
+        let docs = self.doc_freq(token);
+        self.max_docs.map(|max| docs > max).unwrap_or(false)
+            || self.min_docs.map(|min| docs < min).unwrap_or(false)
+    }
+
+    fn prune_ngram(&self, ngram: &str) -> bool {
+        match self.min_docs {
+            Some(min_docs) => self.ngram_doc_freqs.get(ngram).copied().unwrap_or(0) < min_docs,
+            None => false,
+        }
+    }
+}
+
+/// Writer of a count table as TSV or Arrow IPC stream.
+enum CountTable {
+    Tsv(OutputFile),
+    Arrow(ArrowCountWriter<OutputFile>),
+}
+
+impl CountTable {
+    /// Create a table of `key` counts, normalized tables have frequency
+    /// per million and Zipf value columns, followed by a log-probability
+    /// column if `logprob` is set.
+    fn new(
+        output: OutputFile,
+        arrow: bool,
+        key: &str,
+        normalized: bool,
+        logprob: bool,
+    ) -> io::Result<Self> {
+        if !arrow {
+            return Ok(CountTable::Tsv(output));
+        }
+        let mut values = Vec::new();
+        if normalized {
+            values.extend(&["fpm", "zipf"]);
+        }
+        if logprob {
+            values.push("logprob");
+        }
+        Ok(CountTable::Arrow(ArrowCountWriter::new(
+            output, key, &values,
+        )?))
+    }
 
-        Some(ngram)
+    /// Write a count, `total` is the corpus size of normalized tables.
+    fn write(
+        &mut self,
+        item: &str,
+        count: usize,
+        total: Option<usize>,
+        logprob: Option<f64>,
+    ) -> io::Result<()> {
+        match self {
+            CountTable::Tsv(output) => write_count(output, item, count, total, logprob),
+            CountTable::Arrow(writer) => {
+                let mut values = Vec::with_capacity(3);
+                if let Some(total) = total {
+                    let fpm = count as f64 * 1e6 / total as f64;
+                    values.extend(&[fpm, fpm.log10() + 3.]);
+                }
+                values.extend(logprob);
+                writer.write(item, count, &values)
+            }
+        }
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let cap_approx = (self.max_n - self.min_n + 1) * self.char_offsets.len();
-        (cap_approx, Some(cap_approx))
+    fn finish(self) -> io::Result<()> {
+        match self {
+            CountTable::Tsv(output) => output.finish(),
+            CountTable::Arrow(writer) => writer.finish()?.finish(),
+        }
     }
 }
+
+/// Write sorted token counts and the n-gram counts of the tokens.
+///
+/// Pruned tokens are not written, their n-grams are only counted if the
+/// tokens are not filtered first. Pruned n-grams are counted, but not
+/// written. `num_tokens` is
+/// the corpus size for normalized frequencies. With `token_logprob`, the
+/// tokens are written with their log-probability per character under a
+/// language model of the n-gram counts, with one occurrence of the token
+/// left out, once all n-grams are counted. Returns the numbers of written
+/// tokens and n-grams.
+fn write_token_counts(
+    output: OutputFile,
+    ngram_writer: Option<OutputFile>,
+    ngram_ids: Option<OutputFile>,
+    token_counts: Vec<(String, usize)>,
+    num_tokens: usize,
+    opts: &NGramOptions,
+    pruning: &Pruning,
+) -> VocabSizes {
+    let total = Some(num_tokens).filter(|_| opts.normalized);
+    let mut output = CountTable::new(
+        output,
+        opts.arrow,
+        "token",
+        opts.normalized,
+        opts.token_logprob && ngram_writer.is_some(),
+    )
+    .expect("Can't write token counts.");
+    if let Some(ngram_writer) = ngram_writer {
+        let ngram_counts = count_ngrams(&token_counts, opts, pruning);
+        let lm = if opts.token_logprob {
+            Some(CharLm::from_ngram_counts(
+                ngram_counts.iter().map(|(ngram, &count)| (ngram, count)),
+                opts.table.min_n(),
+                opts.table.max_n(),
+                opts.table.bracket(),
+            ))
+        } else {
+            None
+        };
+        let mut n_tokens = 0;
+        let mut token_counts = token_counts.into_iter();
+        // Special tokens are neither pruned nor split into n-grams.
+        for (token, count) in token_counts.by_ref().take(opts.special_tokens.len()) {
+            output
+                .write(&token, count, total, None)
+                .expect("Can't write token counts.");
+            n_tokens += 1;
+        }
+        for (token, count) in token_counts {
+            if pruning.prune_token(&token)
+                || (opts.table.filter_first() && count < opts.table.token_min())
+            {
+                continue;
+            }
+            let token = if opts.table.bracket() {
+                let mut b_token = String::with_capacity(token.len() + 2);
+                b_token.push('<');
+                b_token.push_str(&token);
+                b_token.push('>');
+                b_token
+            } else {
+                token
+            };
+            let logprob = lm.as_ref().map(|lm| {
+                let unbracketed = if opts.table.bracket() {
+                    &token[1..token.len() - 1]
+                } else {
+                    &token
+                };
+                lm.held_out_log_prob_per_char(unbracketed)
+            });
+            output
+                .write(&token, count, total, logprob)
+                .expect("Can't write token counts.");
+            n_tokens += 1;
+        }
+        let (n_ngrams, ngram_min) = write_ngram_counts(
+            ngram_writer,
+            ngram_ids,
+            ngram_counts,
+            n_tokens,
+            opts,
+            pruning,
+        );
+        output.finish().expect("Can't write token counts.");
+        VocabSizes {
+            tokens: n_tokens,
+            ngrams: Some(n_ngrams),
+            ngram_min,
+        }
+    } else {
+        let special = opts.special_tokens.len();
+        let mut n_tokens = 0;
+        token_counts
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, (token, _))| *idx < special || !pruning.prune_token(token))
+            .for_each(|(_, (token, count))| {
+                output
+                    .write(&token, count, total, None)
+                    .expect("Can't write token counts.");
+                n_tokens += 1;
+            });
+        output.finish().expect("Can't write token counts.");
+        VocabSizes {
+            tokens: n_tokens,
+            ngrams: None,
+            ngram_min: opts.table.ngram_min(),
+        }
+    }
+}
+
+/// Count the n-grams of the tokens with the counts of the tokens.
+///
+/// Special tokens are not split into n-grams, pruned tokens only if they
+/// are not filtered first.
+fn count_ngrams(
+    token_counts: &[(String, usize)],
+    opts: &NGramOptions,
+    pruning: &Pruning,
+) -> HashMap<String, usize> {
+    count::table_ngram_counts(
+        token_counts,
+        &opts.table,
+        opts.special_tokens.len(),
+        |token| opts.table.filter_first() && pruning.prune_token(token),
+    )
+}
+
+/// Write sorted n-gram counts.
+///
+/// N-grams are pruned by `ngram_redundancy` and the n-gram min count,
+/// which is derived from `ngram_target_size` if set. Pruned n-grams are
+/// not written. N-gram ids follow the ids of the `n_tokens` tokens.
+/// Returns the number of written n-grams and the n-gram min count.
+fn write_ngram_counts(
+    ngram_writer: OutputFile,
+    ngram_ids: Option<OutputFile>,
+    mut ngram_counts: HashMap<String, usize>,
+    n_tokens: usize,
+    opts: &NGramOptions,
+    pruning: &Pruning,
+) -> (usize, usize) {
+    let mut ngram_writer = CountTable::new(ngram_writer, opts.arrow, "ngram", false, false)
+        .expect("Can't write ngram counts.");
+    let ngram_min = ngrams::prune_ngrams(&mut ngram_counts, &opts.table);
+    if opts.table.ngram_target_size().is_some() {
+        eprintln!("Using ngram min count {}", ngram_min);
+    }
+    // N-grams are indexed by rank after the tokens.
+    let mut ngram_ids = ngram_ids;
+    let mut next_id = n_tokens;
+    let mut n_ngrams = 0;
+    let mut write_ngram = |ngram: &str, count: usize| {
+        if pruning.prune_ngram(ngram) {
+            return Ok(());
+        }
+        ngram_writer.write(ngram, count, None, None)?;
+        n_ngrams += 1;
+        if let Some(ngram_ids) = ngram_ids.as_mut() {
+            writeln!(ngram_ids, "{}\t{}", ngram, next_id)?;
+            next_id += 1;
+        }
+        Ok(())
+    };
+    if let Some(run_len) = opts.sort_buffer {
+        let sort = ExternalSort::new(run_len, &opts.tmp_dir).collation(opts.table.collation());
+        sort.for_each_sorted(ngram_counts, ngram_min, write_ngram)
+            .expect("Can't write ngram counts.");
+    } else {
+        count::collated_into_sorted(ngram_counts, Some(ngram_min), opts.table.collation())
+            .into_iter()
+            .try_for_each(|(ngram, count)| write_ngram(&ngram, count))
+            .expect("Can't write ngram counts.");
+    }
+    ngram_writer.finish().expect("Can't write ngram counts.");
+    if let Some(ngram_ids) = ngram_ids {
+        ngram_ids.finish().expect("Can't write ngram ids.");
+    }
+    (n_ngrams, ngram_min)
+}
+
+/// Write a finalfrontier training configuration for the counts.
+///
+/// With n-gram counts, the configuration uses an explicit n-gram
+/// vocabulary, otherwise n-grams are hashed into `2^buckets_exp` buckets.
+/// The other settings are finalfrontier's defaults.
+fn write_ff_config(
+    mut writer: impl Write,
+    opts: &NGramOptions,
+    sizes: &VocabSizes,
+    num_tokens: usize,
+    buckets_exp: u32,
+) -> io::Result<()> {
+    let subwords = match sizes.ngrams {
+        Some(_) => format!("--subwords ngrams --ngram-mincount {}", sizes.ngram_min),
+        None => format!("--subwords buckets --buckets {}", buckets_exp),
+    };
+    writeln!(
+        writer,
+        "# finalfrontier configuration written by corpus-count."
+    )?;
+    writeln!(writer, "#")?;
+    writeln!(
+        writer,
+        "# finalfrontier skipgram --mincount {} --minn {} --maxn {} {} CORPUS OUTPUT",
+        opts.table.token_min(),
+        opts.table.min_n(),
+        opts.table.max_n(),
+        subwords
+    )?;
+    writeln!(writer)?;
+    writeln!(writer, "[corpus_count]")?;
+    writeln!(writer, "corpus_tokens = {}", num_tokens)?;
+    writeln!(writer, "vocab_size = {}", sizes.tokens)?;
+    if let Some(ngrams) = sizes.ngrams {
+        writeln!(writer, "ngram_vocab_size = {}", ngrams)?;
+    }
+    writeln!(writer)?;
+    writeln!(writer, "[common]")?;
+    writeln!(writer, "dims = 300")?;
+    writeln!(writer, "epochs = 15")?;
+    writeln!(writer, "loss = \"LogisticNegativeSampling\"")?;
+    writeln!(writer, "lr = 0.05")?;
+    writeln!(writer, "negative_samples = 5")?;
+    writeln!(writer, "zipf_exponent = 0.5")?;
+    writeln!(writer)?;
+    writeln!(writer, "[model]")?;
+    writeln!(writer, "context_size = 10")?;
+    writeln!(writer, "model = \"SkipGram\"")?;
+    writeln!(writer, "type = \"SkipGramModel\"")?;
+    writeln!(writer)?;
+    writeln!(writer, "[vocab]")?;
+    writeln!(writer, "discard_threshold = 1e-4")?;
+    writeln!(writer, "min_count = {}", opts.table.token_min())?;
+    writeln!(writer, "min_n = {}", opts.table.min_n())?;
+    writeln!(writer, "max_n = {}", opts.table.max_n())?;
+    match sizes.ngrams {
+        Some(_) => {
+            writeln!(writer, "min_ngram_count = {}", sizes.ngram_min)?;
+            writeln!(writer, "type = \"NGramVocab\"")?;
+        }
+        None => {
+            writeln!(writer, "buckets_exp = {}", buckets_exp)?;
+            writeln!(writer, "indexer_type = \"finalfusion\"")?;
+            writeln!(writer, "type = \"SubwordVocab\"")?;
+        }
+    }
+    writer.flush()
+}
+
+/// Numbers of written tokens and n-grams.
+struct VocabSizes {
+    tokens: usize,
+    /// `None` if n-grams are not counted.
+    ngrams: Option<usize>,
+    /// The n-gram min count, which may be derived from
+    /// `--ngram_target_size`.
+    ngram_min: usize,
+}
+
+/// Tokenizers of `--tokenizer` that take no argument.
+const TOKENIZERS: &[&str] = &["whitespace", "unicode_words", "chars", "script_runs"];
+
+/// Get the tokenizer selected by `--tokenizer`.
+fn tokenizer_from_matches(matches: &ArgMatches) -> Arc<dyn Tokenizer> {
+    let name = matches.value_of(TOKENIZER).unwrap();
+    if let Some(pattern) = name.strip_prefix("regex:") {
+        return Arc::new(Regex::new(pattern).unwrap());
+    }
+    if let Some(path) = name.strip_prefix("dict:") {
+        let segmenter = File::open(path)
+            .and_then(|words| tokenize::DictSegmenter::read_word_list(BufReader::new(words)));
+        return match segmenter {
+            Ok(segmenter) => Arc::new(segmenter),
+            Err(err) => usage_error(&format!(
+                "Can't read segmentation word list {}: {}",
+                path, err
+            )),
+        };
+    }
+    if let Some(path) = name.strip_prefix("jieba:") {
+        let segmenter =
+            File::open(path).and_then(|dict| tokenize::DictSegmenter::read(BufReader::new(dict)));
+        return match segmenter {
+            Ok(segmenter) => Arc::new(segmenter),
+            Err(err) => usage_error(&format!(
+                "Can't read segmentation dictionary {}: {}",
+                path, err
+            )),
+        };
+    }
+    match name {
+        "whitespace" => Arc::new(tokenize::Whitespace),
+        "unicode_words" => Arc::new(tokenize::UnicodeWords),
+        "chars" => Arc::new(tokenize::Chars),
+        "script_runs" => Arc::new(tokenize::ScriptRuns),
+        _ => unreachable!("Unknown tokenizer: {}", name),
+    }
+}
+
+/// Validate the name of a tokenizer and the pattern of `regex:` tokenizers.
+fn valid_tokenizer(v: String) -> Result<(), String> {
+    if let Some(pattern) = v.strip_prefix("regex:") {
+        return Regex::new(pattern).map(|_| ());
+    }
+    if v.starts_with("dict:") || v.starts_with("jieba:") || TOKENIZERS.contains(&v.as_str()) {
+        Ok(())
+    } else {
+        Err(format!("Unknown tokenizer: {}", v))
+    }
+}
+
+/// Get the normalizers available to `--normalizer`.
+///
+/// Custom normalizers are compiled in by registering them here.
+fn normalizer_registry() -> normalize::Registry {
+    normalize::Registry::builtin()
+}
+
+fn corpus_from_matches(matches: &ArgMatches) -> Corpus {
+    let mut paths = matches
+        .values_of(CORPUS)
+        .map(|v| v.map(PathBuf::from).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if let Some(files_from) = matches.value_of(FILES_FROM) {
+        let list = Input::from(Some(files_from).filter(|&f| f != "-"));
+        let list = list.buf_read().expect("Can't open file list for reading");
+        paths.extend(input::read_file_list(list).expect("Can't read file list"));
+    }
+    let mut format = value_t!(matches, INPUT_FORMAT, InputFormat).unwrap();
+    if let InputFormat::Jsonl(fields) = &mut format {
+        fields.text = matches.value_of(TEXT_FIELD).unwrap().to_owned();
+    }
+    let documents = value_t!(matches, DOCUMENTS, DocumentBoundary).unwrap();
+    let include = values_t!(matches, INCLUDE, Glob).unwrap_or_default();
+
+    let mut lines = LineRange::default();
+    if let Ok(start) = value_t!(matches, START_LINE, usize) {
+        lines.start = start;
+    }
+    lines.end = value_t!(matches, END_LINE, usize).ok();
+    if lines.end.map(|end| end < lines.start).unwrap_or(false) {
+        usage_error("The end line should be equal to or greater than the start line.");
+    }
+
+    let bytes = if matches.is_present(START_BYTE) || matches.is_present(END_BYTE) {
+        let start = value_t!(matches, START_BYTE, u64).unwrap_or(0);
+        let end = value_t!(matches, END_BYTE, u64).ok();
+        if end.map(|end| end < start).unwrap_or(false) {
+            usage_error("The end byte should be equal to or greater than the start byte.");
+        }
+        Some(ByteRange { start, end })
+    } else {
+        None
+    };
+
+    Corpus {
+        paths,
+        include,
+        format,
+        documents,
+        lines,
+        bytes,
+    }
+}
+
+/// Write clusters of near-duplicate documents and report their share of
+/// tokens.
+fn write_near_duplicates(
+    mut writer: impl Write,
+    documents: &[minhash::Document],
+    threshold: f64,
+) -> io::Result<()> {
+    let clusters = minhash::near_duplicates(documents, threshold);
+    let total_tokens = documents.iter().map(|doc| doc.tokens).sum::<usize>();
+    let mut duplicate_docs = 0;
+    let mut duplicate_tokens = 0;
+    writeln!(writer, "cluster\tdocument\ttokens\tsimilarity")?;
+    for (cluster, members) in clusters.iter().enumerate() {
+        let first = &documents[members[0]];
+        for (idx, &member) in members.iter().enumerate() {
+            let doc = &documents[member];
+            let similarity = minhash::similarity(&first.signature, &doc.signature);
+            writeln!(
+                writer,
+                "{}\t{}:{}\t{}\t{:.4}",
+                cluster, doc.file, doc.index, doc.tokens, similarity
+            )?;
+            // All but the first document of a cluster are redundant.
+            if idx > 0 {
+                duplicate_docs += 1;
+                duplicate_tokens += doc.tokens;
+            }
+        }
+    }
+    writer.flush()?;
+    eprintln!(
+        "Found {} near-duplicate clusters, {} redundant documents of {} contain {} of {} tokens ({:.2}%)",
+        clusters.len(),
+        duplicate_docs,
+        documents.len(),
+        duplicate_tokens,
+        total_tokens,
+        100. * duplicate_tokens as f64 / total_tokens.max(1) as f64
+    );
+    Ok(())
+}
+
+/// Write the vocabulary growth curve if requested.
+fn write_growth_from_matches(matches: &ArgMatches, counts: &Counts, output_opts: &OutputOptions) {
+    let path = match matches.value_of(GROWTH) {
+        Some(path) => path,
+        None => return,
+    };
+    let mut writer =
+        OutputFile::create(Some(path), output_opts).expect("Can't create growth curve.");
+    write_growth(&mut writer, counts).expect("Can't write growth curve.");
+    writer.finish().expect("Can't write growth curve.");
+}
+
+/// Write the counts of personal data if requested.
+fn write_pii_counts_from_matches(
+    matches: &ArgMatches,
+    counts: &Counts,
+    output_opts: &OutputOptions,
+) {
+    let path = match matches.value_of(PII_COUNTS) {
+        Some(path) => path,
+        None => return,
+    };
+    let mut writer =
+        OutputFile::create(Some(path), output_opts).expect("Can't create personal data counts.");
+    pii::write_counts(&mut writer, &counts.pii).expect("Can't write personal data counts.");
+    writer.finish().expect("Can't write personal data counts.");
+}
+
+/// Write the coverage projection if requested.
+fn write_projection_from_matches(
+    matches: &ArgMatches,
+    counts: &Counts,
+    output_opts: &OutputOptions,
+) {
+    let path = match matches.value_of(PROJECT_COVERAGE) {
+        Some(path) => path,
+        None => return,
+    };
+    let target_types = values_t!(matches, TARGET_TYPES, usize).unwrap_or_default();
+    let target_coverage = values_t!(matches, TARGET_COVERAGE, f64).unwrap_or_default();
+    if target_types.is_empty() && target_coverage.is_empty() {
+        usage_error("The coverage projection requires --target_types or --target_coverage.");
+    }
+    let mut points = counts.growth.clone();
+    points.push((counts.num_tokens, counts.tokens.len()));
+    let fit =
+        HeapsFit::fit(&points).expect("Can't fit vocabulary growth, use a smaller --growth_step.");
+    let hapax = counts.tokens.values().filter(|&&count| count == 1).count();
+    eprintln!(
+        "Heaps' law fit: k = {:.4}, beta = {:.4}, coverage: {:.4} (Good-Turing: {:.4})",
+        fit.k,
+        fit.beta,
+        fit.coverage(counts.num_tokens as f64),
+        1. - hapax as f64 / counts.num_tokens.max(1) as f64
+    );
+    let mut writer =
+        OutputFile::create(Some(path), output_opts).expect("Can't create coverage projection.");
+    write_projection(
+        &mut writer,
+        &fit,
+        counts.num_tokens,
+        &target_types,
+        &target_coverage,
+    )
+    .expect("Can't write coverage projection.");
+    writer.finish().expect("Can't write coverage projection.");
+}
+
+/// Write the number of tokens needed to reach the targets.
+///
+/// Targets that cannot be reached under the fitted growth are written as
+/// `inf`.
+fn write_projection(
+    mut writer: impl Write,
+    fit: &HeapsFit,
+    num_tokens: usize,
+    target_types: &[usize],
+    target_coverage: &[f64],
+) -> io::Result<()> {
+    writeln!(writer, "target\tvalue\ttokens_needed\tadditional_tokens")?;
+    let targets = target_types
+        .iter()
+        .map(|&types| {
+            (
+                "types",
+                types.to_string(),
+                Some(fit.tokens_for_types(types as f64)),
+            )
+        })
+        .chain(target_coverage.iter().map(|&coverage| {
+            (
+                "coverage",
+                coverage.to_string(),
+                fit.tokens_for_coverage(coverage),
+            )
+        }));
+    for (target, value, needed) in targets {
+        match needed.filter(|needed| needed.is_finite()) {
+            Some(needed) => writeln!(
+                writer,
+                "{}\t{}\t{:.0}\t{:.0}",
+                target,
+                value,
+                needed,
+                (needed - num_tokens as f64).max(0.)
+            )?,
+            None => writeln!(writer, "{}\t{}\tinf\tinf", target, value)?,
+        }
+    }
+    writer.flush()
+}
+
+/// Write the number of types after every growth step.
+///
+/// The curve ends with the totals of the corpus.
+fn write_growth(mut writer: impl Write, counts: &Counts) -> io::Result<()> {
+    writeln!(writer, "tokens\ttypes")?;
+    for (tokens, types) in &counts.growth {
+        writeln!(writer, "{}\t{}", tokens, types)?;
+    }
+    if counts.growth.last().map(|&(tokens, _)| tokens) != Some(counts.num_tokens) {
+        writeln!(writer, "{}\t{}", counts.num_tokens, counts.tokens.len())?;
+    }
+    writer.flush()
+}
+
+/// Write the corpus totals.
+///
+/// Types are only written if the vocabulary was stored, duplicate lines
+/// only if lines were deduplicated.
+fn write_totals(mut writer: impl Write, counts: &Counts, opts: &CountOptions) -> io::Result<()> {
+    writeln!(writer, "lines\t{}", counts.lines)?;
+    if opts.dedup_lines {
+        writeln!(writer, "duplicate_lines\t{}", counts.duplicate_lines)?;
+    }
+    writeln!(writer, "tokens\t{}", counts.num_tokens)?;
+    if opts.vocab {
+        writeln!(writer, "types\t{}", counts.tokens.len())?;
+    }
+    writeln!(writer, "bytes\t{}", counts.bytes)?;
+    writer.flush()
+}
+
+/// Write the initial, medial and final counts of tokens.
+///
+/// Tokens are sorted by their total count, a token that forms a whole line
+/// is counted as initial and final.
+fn write_positions(
+    mut writer: impl Write,
+    positions: HashMap<String, [usize; 3]>,
+) -> io::Result<()> {
+    let mut positions = positions.into_iter().collect::<Vec<_>>();
+    positions.sort_unstable_by(|(token1, counts1), (token2, counts2)| {
+        sort::count_order(token1, counts1.iter().sum(), token2, counts2.iter().sum())
+    });
+    writeln!(writer, "token\tinitial\tmedial\tfinal")?;
+    for (token, [initial, medial, last]) in positions {
+        writeln!(writer, "{}\t{}\t{}\t{}", token, initial, medial, last)?;
+    }
+    writer.flush()
+}
+
+/// Write the token length distribution and token counts per script.
+///
+/// Every row has the statistic, its value, the number of types and the
+/// number of tokens. Lengths are in characters.
+fn write_stats(mut writer: impl Write, tokens: &HashMap<String, usize>) -> io::Result<()> {
+    let mut lengths = BTreeMap::new();
+    let mut scripts = BTreeMap::new();
+    for (token, &count) in tokens {
+        let length = lengths.entry(token.chars().count()).or_insert((0, 0));
+        length.0 += 1;
+        length.1 += count;
+        let script = scripts.entry(Script::of_token(token)).or_insert((0, 0));
+        script.0 += 1;
+        script.1 += count;
+    }
+    writeln!(writer, "stat\tvalue\ttypes\ttokens")?;
+    for (length, (types, count)) in lengths {
+        writeln!(writer, "length\t{}\t{}\t{}", length, types, count)?;
+    }
+    let mut scripts = scripts.into_iter().collect::<Vec<(TokenScript, _)>>();
+    scripts.sort_by(|(_, (_, count1)), (_, (_, count2))| count2.cmp(count1));
+    for (script, (types, count)) in scripts {
+        writeln!(writer, "script\t{}\t{}\t{}", script, types, count)?;
+    }
+    writer.flush()
+}
+
+/// Write document frequencies, IDF and TF-IDF of tokens.
+///
+/// IDF is `ln(documents / doc_freq)`, TF-IDF multiplies it with the corpus
+/// count of the token.
+fn write_idf<'a>(
+    mut writer: impl Write,
+    tokens: impl IntoIterator<Item = &'a (String, usize)>,
+    doc_freqs: &HashMap<String, usize>,
+    num_documents: usize,
+) -> io::Result<()> {
+    writeln!(writer, "token\tcount\tdoc_freq\tidf\ttf_idf")?;
+    for (token, count) in tokens {
+        let doc_freq = doc_freqs.get(token).copied().unwrap_or(0);
+        let idf = (num_documents as f64 / doc_freq.max(1) as f64).ln();
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{:.6}\t{:.6}",
+            token,
+            count,
+            doc_freq,
+            idf,
+            *count as f64 * idf
+        )?;
+    }
+    writer.flush()
+}
+
+/// Write the token counts per label.
+///
+/// Labels are sorted by name, the tokens of a label by their counts.
+fn write_label_counts(
+    mut writer: impl Write,
+    labels: &HashMap<String, LabelCounts>,
+) -> io::Result<()> {
+    writeln!(writer, "label\ttoken\tcount")?;
+    write_label_rows(writer, labels)
+}
+
+/// Write `label<TAB>token<TAB>count` lines, labels sorted by name.
+fn write_label_rows(
+    mut writer: impl Write,
+    labels: &HashMap<String, LabelCounts>,
+) -> io::Result<()> {
+    let mut labels = labels.iter().collect::<Vec<_>>();
+    labels.sort_unstable_by_key(|(label, _)| *label);
+    for (label, label_counts) in labels {
+        let tokens = counted_into_sorted(
+            label_counts
+                .tokens
+                .iter()
+                .map(|(token, &count)| (token.clone(), count)),
+            None,
+        );
+        for (token, count) in tokens {
+            writeln!(writer, "{}\t{}\t{}", label, token, count)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Write the token counts per label, e.g. per time bucket.
+///
+/// Labels are sorted by name, which is chronological for time buckets.
+/// `key` is the header of the label column in the long format. In the
+/// wide format, tokens are sorted by their total counts and have a column
+/// of counts per label after the total.
+fn write_label_table(
+    mut writer: impl Write,
+    labels: &HashMap<String, LabelCounts>,
+    key: &str,
+    format: TableFormat,
+) -> io::Result<()> {
+    if format == TableFormat::Long {
+        writeln!(writer, "{}\ttoken\tcount", key)?;
+        return write_label_rows(writer, labels);
+    }
+    let mut labels = labels.iter().collect::<Vec<_>>();
+    labels.sort_unstable_by_key(|(label, _)| *label);
+    let mut totals: HashMap<&str, usize> = HashMap::new();
+    for (_, label_counts) in &labels {
+        for (token, &count) in &label_counts.tokens {
+            *totals.entry(token.as_str()).or_insert(0) += count;
+        }
+    }
+    let mut tokens = totals.into_iter().collect::<Vec<_>>();
+    tokens.sort_unstable_by(|(token1, count1), (token2, count2)| {
+        count2.cmp(count1).then(token1.cmp(token2))
+    });
+    write!(writer, "token\ttotal")?;
+    for (label, _) in &labels {
+        write!(writer, "\t{}", label)?;
+    }
+    writeln!(writer)?;
+    for (token, total) in tokens {
+        write!(writer, "{}\t{}", token, total)?;
+        for (_, label_counts) in &labels {
+            write!(
+                writer,
+                "\t{}",
+                label_counts.tokens.get(token).copied().unwrap_or(0)
+            )?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// Write the composition of the corpus by facet values.
+///
+/// Facets are sorted by their numbers of tokens, the share is the fraction
+/// of the tokens of all records with a facet value.
+fn write_facet_summary(
+    mut writer: impl Write,
+    facets: &HashMap<String, LabelCounts>,
+) -> io::Result<()> {
+    let mut facets = facets
+        .iter()
+        .map(|(facet, counts)| (facet, counts, counts.tokens.values().sum::<usize>()))
+        .collect::<Vec<_>>();
+    facets.sort_unstable_by(|(facet1, _, tokens1), (facet2, _, tokens2)| {
+        tokens2.cmp(tokens1).then(facet1.cmp(facet2))
+    });
+    let total = facets.iter().map(|(_, _, tokens)| tokens).sum::<usize>();
+    writeln!(writer, "facet\tlines\ttokens\ttypes\tshare")?;
+    for (facet, counts, tokens) in facets {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{:.6}",
+            facet,
+            counts.lines,
+            tokens,
+            counts.tokens.len(),
+            tokens as f64 / total.max(1) as f64
+        )?;
+    }
+    writer.flush()
+}
+
+/// Write the trends of tokens between two periods of time buckets.
+///
+/// The periods default to the first and the last bucket.
+fn write_trends_from_matches(
+    matches: &ArgMatches,
+    buckets: &HashMap<String, LabelCounts>,
+    path: &str,
+    output_opts: &OutputOptions,
+) {
+    let period = |arg, default: Option<&String>| {
+        value_t!(matches, arg, Period)
+            .ok()
+            .or_else(|| default.map(|bucket| Period::bucket(bucket)))
+    };
+    let before = period(TREND_BEFORE, buckets.keys().min());
+    let after = period(TREND_AFTER, buckets.keys().max());
+    let max_p = value_t!(matches, TREND_MAX_P, f64).unwrap();
+    let trends = match (before, after) {
+        (Some(before), Some(after)) => trends::trends(buckets, &before, &after, max_p),
+        _ => Vec::new(),
+    };
+    let mut writer = OutputFile::create(Some(path), output_opts).expect("Can't create trends.");
+    trends::write_trends(&mut writer, &trends).expect("Can't write trends.");
+    writer.finish().expect("Can't write trends.");
+}
+
+/// Write the log-odds ratios of the tokens of every label.
+///
+/// The ratio compares the lines with the label to the other labeled lines,
+/// with the counts of all labeled lines as informative Dirichlet prior
+/// (Monroe et al., 2008). The z-score divides the ratio by its estimated
+/// standard deviation. Tokens of a label are sorted by their z-scores.
+fn write_log_odds(mut writer: impl Write, labels: &HashMap<String, LabelCounts>) -> io::Result<()> {
+    let mut totals = HashMap::new();
+    for label_counts in labels.values() {
+        for (token, &count) in &label_counts.tokens {
+            *totals.entry(token.as_str()).or_insert(0) += count;
+        }
+    }
+    let total = totals.values().sum::<usize>() as f64;
+    let mut labels = labels.iter().collect::<Vec<_>>();
+    labels.sort_unstable_by_key(|(label, _)| *label);
+    writeln!(writer, "label\ttoken\tcount\tlog_odds\tz")?;
+    for (label, label_counts) in labels {
+        let label_total = label_counts.tokens.values().sum::<usize>() as f64;
+        let rest_total = total - label_total;
+        let mut scores = label_counts
+            .tokens
+            .iter()
+            .map(|(token, &count)| {
+                let prior = totals[token.as_str()] as f64;
+                let in_label = count as f64 + prior;
+                let in_rest = (totals[token.as_str()] - count) as f64 + prior;
+                let log_odds = (in_label / (label_total + total - in_label)).ln()
+                    - (in_rest / (rest_total + total - in_rest)).ln();
+                let z = log_odds / (1. / in_label + 1. / in_rest).sqrt();
+                (token, count, log_odds, z)
+            })
+            .collect::<Vec<_>>();
+        scores.sort_unstable_by(|(token1, _, _, z1), (token2, _, _, z2)| {
+            z2.total_cmp(z1).then(token1.cmp(token2))
+        });
+        for (token, count, log_odds, z) in scores {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{:.6}\t{:.6}",
+                label, token, count, log_odds, z
+            )?;
+        }
+    }
+    writer.flush()
+}
+
+/// Write the token counts of the documents as sparse Matrix Market
+/// matrix.
+///
+/// Rows are the documents and columns the tokens of the vocabulary, both
+/// numbered from 1. Tokens that are not in the vocabulary are skipped.
+fn write_dtm(
+    mut writer: impl Write,
+    documents: &[DocumentTerms],
+    vocab: &[&str],
+) -> io::Result<()> {
+    let columns = vocab
+        .iter()
+        .enumerate()
+        .map(|(idx, &token)| (token, idx + 1))
+        .collect::<HashMap<_, _>>();
+    let rows = documents
+        .iter()
+        .map(|doc| {
+            let mut row = doc
+                .terms
+                .iter()
+                .filter_map(|(token, &count)| columns.get(token.as_str()).map(|&col| (col, count)))
+                .collect::<Vec<_>>();
+            row.sort_unstable();
+            row
+        })
+        .collect::<Vec<_>>();
+    writeln!(writer, "%%MatrixMarket matrix coordinate integer general")?;
+    writeln!(
+        writer,
+        "{} {} {}",
+        documents.len(),
+        vocab.len(),
+        rows.iter().map(Vec::len).sum::<usize>()
+    )?;
+    for (idx, row) in rows.iter().enumerate() {
+        for (col, count) in row {
+            writeln!(writer, "{} {} {}", idx + 1, col, count)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Encode bytes as base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (idx, &byte)| {
+            bits | u32::from(byte) << (16 - 8 * idx)
+        });
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * idx) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Write `token<TAB>doc_freq<TAB>bitmap` lines of the documents of every
+/// token.
+///
+/// Documents are numbered from 0 in the order of `documents`, bitmaps are
+/// base64-encoded portable Roaring bitmaps.
+fn write_inverted_index<'a>(
+    mut writer: impl Write,
+    documents: &[DocumentTerms],
+    tokens: impl IntoIterator<Item = &'a (String, usize)>,
+) -> io::Result<()> {
+    assert!(
+        documents.len() <= u32::MAX as usize,
+        "The inverted index holds at most 2^32 documents."
+    );
+    let mut postings: HashMap<&str, RoaringBitmap> = HashMap::new();
+    for (idx, doc) in documents.iter().enumerate() {
+        for token in doc.terms.keys() {
+            postings.entry(token).or_default().insert(idx as u32);
+        }
+    }
+    let mut data = Vec::new();
+    for (token, _) in tokens {
+        let bitmap = match postings.get(token.as_str()) {
+            Some(bitmap) => bitmap,
+            None => continue,
+        };
+        data.clear();
+        bitmap.serialize(&mut data)?;
+        writeln!(writer, "{}\t{}\t{}", token, bitmap.len(), base64(&data))?;
+    }
+    writer.flush()
+}
+
+/// Write dispersion measures of tokens over the documents.
+///
+/// Juilland's D is `1 - (sd / mean) / sqrt(n - 1)` of the relative
+/// frequencies of a token in the `n` documents. Gries' DP is half the sum
+/// of the absolute differences between the share of a token's occurrences
+/// in a document and the share of the document in the corpus.
+fn write_dispersion<'a>(
+    mut writer: impl Write,
+    tokens: impl IntoIterator<Item = &'a (String, usize)>,
+    dispersion: &HashMap<String, Vec<(usize, usize)>>,
+    num_documents: usize,
+    num_tokens: usize,
+) -> io::Result<()> {
+    writeln!(writer, "token\tcount\tdoc_freq\tjuilland_d\tdp")?;
+    for (token, count) in tokens {
+        let docs = dispersion.get(token).map(Vec::as_slice).unwrap_or(&[]);
+        let dispersion = Dispersion::new(*count, docs, num_documents, num_tokens);
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{:.6}\t{:.6}",
+            token,
+            count,
+            docs.len(),
+            dispersion.juilland_d,
+            dispersion.dp
+        )?;
+    }
+    writer.flush()
+}
+
+/// Write `token<TAB>count<TAB>bin` lines of log-frequency bins.
+///
+/// The bins are relative to the highest count of the tokens.
+fn write_freq_bins<'a>(
+    mut writer: impl Write,
+    tokens: impl IntoIterator<Item = &'a (String, usize)>,
+    base: usize,
+) -> io::Result<()> {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let max_count = tokens.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    for (token, count) in tokens {
+        writeln!(
+            writer,
+            "{}\t{}\t{}",
+            token,
+            count,
+            bins::log_freq_bin(*count, max_count, base)
+        )?;
+    }
+    writer.flush()
+}
+
+/// Write the clusters of balanced adaptive softmax cutoffs.
+///
+/// Every `cluster<TAB>first_rank<TAB>tokens<TAB>count<TAB>share` line has
+/// the rank of the first token of the cluster, which are the cutoffs after
+/// the first cluster, and the tokens and token mass of the cluster.
+fn write_softmax_cutoffs(
+    mut writer: impl Write,
+    counts: &[usize],
+    clusters: usize,
+) -> io::Result<()> {
+    let total = counts.iter().sum::<usize>();
+    let mut bounds = vec![0];
+    bounds.extend(bins::balanced_cutoffs(counts, clusters));
+    bounds.push(counts.len());
+    writeln!(writer, "cluster\tfirst_rank\ttokens\tcount\tshare")?;
+    for (cluster, range) in bounds.windows(2).enumerate() {
+        let count = counts[range[0]..range[1]].iter().sum::<usize>();
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{:.6}",
+            cluster,
+            range[0],
+            range[1] - range[0],
+            count,
+            count as f64 / total.max(1) as f64
+        )?;
+    }
+    writer.flush()
+}
+
+/// Write the negative sampling distribution of the tokens.
+///
+/// `--unigram_probs` gets `token<TAB>count<TAB>probability` lines,
+/// `--unigram_table` the token ranks of word2vec's unigram table as
+/// little-endian 32-bit integers.
+fn write_unigram_from_matches<'a>(
+    matches: &ArgMatches,
+    tokens: impl IntoIterator<Item = &'a (String, usize)>,
+    output_opts: &OutputOptions,
+) {
+    let power = value_t!(matches, UNIGRAM_POWER, f64).unwrap();
+    let (tokens, counts): (Vec<_>, Vec<_>) = tokens
+        .into_iter()
+        .map(|(token, count)| (token.as_str(), *count))
+        .unzip();
+    if let Some(path) = matches.value_of(UNIGRAM_PROBS) {
+        let mut writer = OutputFile::create(Some(path), output_opts)
+            .expect("Can't create unigram probabilities.");
+        tokens
+            .iter()
+            .zip(&counts)
+            .zip(sampling::smoothed_probs(&counts, power))
+            .try_for_each(|((token, count), prob)| {
+                writeln!(writer, "{}\t{}\t{:.6e}", token, count, prob)
+            })
+            .expect("Can't write unigram probabilities.");
+        writer.finish().expect("Can't write unigram probabilities.");
+    }
+    if let Some(path) = matches.value_of(UNIGRAM_TABLE) {
+        let size = value_t!(matches, UNIGRAM_TABLE_SIZE, usize).unwrap();
+        assert!(
+            counts.len() <= u32::MAX as usize,
+            "The unigram table holds at most 2^32 tokens."
+        );
+        let mut writer =
+            OutputFile::create(Some(path), output_opts).expect("Can't create unigram table.");
+        sampling::unigram_table(&counts, power, size)
+            .into_iter()
+            .try_for_each(|idx| writer.write_all(&idx.to_le_bytes()))
+            .expect("Can't write unigram table.");
+        writer.finish().expect("Can't write unigram table.");
+    }
+}
+
+/// Write `token<TAB>count<TAB>code<TAB>points` lines of Huffman codes.
+///
+/// Points are the comma-separated inner nodes on the path from the root.
+fn write_huffman<'a>(
+    mut writer: impl Write,
+    tokens: impl IntoIterator<Item = &'a (String, usize)>,
+) -> io::Result<()> {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let counts = tokens.iter().map(|(_, count)| *count).collect::<Vec<_>>();
+    for ((token, count), path) in tokens.into_iter().zip(huffman::huffman_paths(&counts)) {
+        let points = path
+            .points
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}",
+            token,
+            count,
+            path.code_string(),
+            points.join(",")
+        )?;
+    }
+    writer.flush()
+}
+
+/// Get the tokens in id order.
+///
+/// The reserved symbols get the first ids, the remaining ids are assigned
+/// in the order of `tokens`.
+fn tokens_by_id<'a>(
+    reserved: &'a [&'a str],
+    tokens: impl IntoIterator<Item = &'a str>,
+) -> impl Iterator<Item = &'a str> {
+    reserved.iter().copied().chain(
+        tokens
+            .into_iter()
+            .filter(move |token| !reserved.contains(token)),
+    )
+}
+
+/// Write the token to id mapping.
+fn write_ids<'a>(
+    mut writer: impl Write,
+    tokens_by_id: impl IntoIterator<Item = &'a str>,
+) -> io::Result<()> {
+    for (id, token) in tokens_by_id.into_iter().enumerate() {
+        writeln!(writer, "{}\t{}", token, id)?;
+    }
+    writer.flush()
+}
+
+/// Arguments describing the corpus to read, shared by all subcommands.
+fn corpus_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name(CORPUS)
+            .help("Corpus files, directories or tar/zip archives")
+            .long("corpus")
+            .short("c")
+            .takes_value(true)
+            .multiple(true),
+        Arg::with_name(FILES_FROM)
+            .long("files_from")
+            .help("File with a list of corpus paths, one per line, '-' for stdin")
+            .takes_value(true),
+        Arg::with_name(INCLUDE)
+            .long("include")
+            .validator(is_valid::<Glob>)
+            .help("Glob for files to read from corpus directories and archives")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+        Arg::with_name(INPUT_FORMAT)
+            .long("input_format")
+            .validator(is_valid::<InputFormat>)
+            .possible_values(InputFormat::variants())
+            .default_value("text")
+            .help("Corpus format"),
+        Arg::with_name(TEXT_FIELD)
+            .long("text_field")
+            .default_value("text")
+            .help("Dot-separated path of the text field of jsonl records"),
+        Arg::with_name(START_LINE)
+            .long("start_line")
+            .validator(non_zero("Lines are numbered starting at 1."))
+            .help("First corpus line to read, starting at 1")
+            .takes_value(true),
+        Arg::with_name(END_LINE)
+            .long("end_line")
+            .validator(is_valid::<usize>)
+            .help("Last corpus line to read")
+            .takes_value(true),
+        Arg::with_name(START_BYTE)
+            .long("start_byte")
+            .validator(is_valid::<u64>)
+            .help("Read lines starting at or after this byte offset")
+            .takes_value(true),
+        Arg::with_name(END_BYTE)
+            .long("end_byte")
+            .validator(is_valid::<u64>)
+            .help("Read lines starting before this byte offset")
+            .takes_value(true),
+        Arg::with_name(DOCUMENTS)
+            .long("documents")
+            .validator(is_valid::<DocumentBoundary>)
+            .possible_values(DocumentBoundary::variants())
+            .default_value("blank")
+            .help("Document boundaries: every line, blank lines or every file/record"),
+    ]
+}
+
+/// Arguments for writing outputs, shared by all subcommands.
+fn output_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name(NO_ATOMIC)
+            .long("no_atomic")
+            .help("Write outputs directly instead of renaming temporary files, e.g. for FIFOs"),
+        Arg::with_name(COMPRESS)
+            .long("compress")
+            .validator(is_valid::<Compression>)
+            .possible_values(Compression::variants())
+            .help("Compression of all outputs (default: gz for .gz files, none otherwise)")
+            .takes_value(true),
+        Arg::with_name(WRITE_BUFFER)
+            .long("write_buffer")
+            .validator(is_valid::<usize>)
+            .default_value("1048576")
+            .help("Size of output write buffers in bytes"),
+    ]
+}
+
+/// Arguments for sorting large tables, shared by subcommands.
+fn sort_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name(SORT_BUFFER)
+            .long("sort_buffer")
+            .validator(non_zero("The sort buffer cannot be zero."))
+            .help("Sort in runs of this many items in temporary files to bound memory use")
+            .takes_value(true),
+        tmp_dir_arg(),
+    ]
+}
+
+fn tmp_dir_arg() -> Arg<'static, 'static> {
+    Arg::with_name(TMP_DIR)
+        .long("tmp_dir")
+        .help("Directory for temporary files (default: system temp directory)")
+        .takes_value(true)
+}
+
+/// Validator of arguments that parse as `T`.
+fn is_valid<T>(v: String) -> Result<(), String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    v.parse::<T>().map(|_| ()).map_err(|err| err.to_string())
+}
+
+/// Validate that an argument parses as `T` and passes `check`, `msg` is the
+/// error otherwise.
+fn validate<T>(v: &str, check: impl FnOnce(&T) -> bool, msg: &str) -> Result<(), String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = v.parse::<T>().map_err(|err| err.to_string())?;
+    if check(&value) {
+        Ok(())
+    } else {
+        Err(msg.to_owned())
+    }
+}
+
+/// Validator of non-zero integer arguments, `msg` is the error for zero.
+fn non_zero(msg: &'static str) -> impl Fn(String) -> Result<(), String> {
+    move |v| validate(&v, |&n: &usize| n != 0, msg)
+}
+
+/// Validate that the special tokens are unique.
+fn unique_special_tokens(v: String) -> Result<(), String> {
+    let mut tokens = v.split(',').filter(|s| !s.is_empty()).collect::<Vec<_>>();
+    let n_tokens = tokens.len();
+    tokens.sort_unstable();
+    tokens.dedup();
+    if tokens.len() == n_tokens {
+        Ok(())
+    } else {
+        Err("Special tokens should be unique.".to_owned())
+    }
+}
+
+/// Exit with a usage error, e.g. for arguments that are invalid together.
+fn usage_error(msg: &str) -> ! {
+    clap::Error::with_description(msg, ErrorKind::ValueValidation).exit()
+}
+
+fn sort_buffer_from_matches(matches: &ArgMatches) -> Option<usize> {
+    value_t!(matches, SORT_BUFFER, usize).ok()
+}
+
+fn tmp_dir_from_matches(matches: &ArgMatches) -> PathBuf {
+    matches
+        .value_of(TMP_DIR)
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+}
+
+fn output_options_from_matches(matches: &ArgMatches) -> OutputOptions {
+    OutputOptions {
+        atomic: !matches.is_present(NO_ATOMIC),
+        compression: value_t!(matches, COMPRESS, Compression).ok(),
+        buffer_size: value_t!(matches, WRITE_BUFFER, usize).unwrap(),
+    }
+}
+
+fn filter_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name(ONLY_SCRIPT)
+            .long("only_script")
+            .validator(is_valid::<Script>)
+            .help("Only count tokens of these Unicode scripts, e.g. Latin")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+        Arg::with_name(DROP_NON_ALPHA)
+            .long("drop_non_alpha")
+            .help("Do not count tokens with non-alphabetic characters"),
+        Arg::with_name(DROP_NUMERIC)
+            .long("drop_numeric")
+            .help("Do not count numbers"),
+        Arg::with_name(DROP_PATTERN)
+            .long("drop_pattern")
+            .validator(is_valid::<Regex>)
+            .help("Do not count tokens that match this regular expression")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+    ]
+}
+
+fn filter_from_matches(matches: &ArgMatches) -> TokenFilter {
+    TokenFilter {
+        scripts: values_t!(matches, ONLY_SCRIPT, Script).unwrap_or_default(),
+        drop_non_alpha: matches.is_present(DROP_NON_ALPHA),
+        drop_numeric: matches.is_present(DROP_NUMERIC),
+        drop_patterns: values_t!(matches, DROP_PATTERN, Regex).unwrap_or_default(),
+    }
+}
+
+fn parse_args() -> ArgMatches<'static> {
+    App::new("corpus-count")
+        .author("Sebastian Pütz")
+        .version("0.1.1")
+        .settings(DEFAULT_CLAP_SETTINGS)
+        .args(&corpus_args())
+        .args(&output_args())
+        .args(&sort_args())
+        .args(&filter_args())
+        .arg(
+            Arg::with_name(THREADS)
+                .long("threads")
+                .validator(is_valid::<usize>)
+                .help("Number of threads for reading multiple files (default: all cores)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(TOKEN_COUNTS)
+                .long("token_counts")
+                .short("t")
+                .help("Token count file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(NGRAM_COUNTS)
+                .long("ngram_counts")
+                .short("n")
+                .help("File for ngram counts")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(NGRAM_IDS)
+                .long("ngram_ids")
+                .requires(NGRAM_COUNTS)
+                .help("File for ngram ids, numbered after the tokens")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(BITEXT)
+                .long("bitext")
+                .requires(TARGET_TOKEN_COUNTS)
+                .help("Lines are source<TAB>target pairs, count the target side separately"),
+        )
+        .arg(
+            Arg::with_name(LABELED)
+                .long("labeled")
+                .conflicts_with(BITEXT)
+                .help("Lines are label<TAB>text pairs, count the tokens per label"),
+        )
+        .arg(
+            Arg::with_name(TIME_BUCKET)
+                .long("time_bucket")
+                .validator(is_valid::<TimeBucket>)
+                .possible_values(TimeBucket::variants())
+                .conflicts_with_all(&[BITEXT, LABELED])
+                .help("Count the tokens of jsonl records per month or year of --time_field")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(TIME_FIELD)
+                .long("time_field")
+                .default_value("timestamp")
+                .help("Dot-separated path of the ISO 8601 or Unix timestamp of jsonl records"),
+        )
+        .arg(
+            Arg::with_name(BUCKET_COUNTS)
+                .long("bucket_counts")
+                .requires(TIME_BUCKET)
+                .help("File for token counts per bucket of --time_bucket")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(BUCKET_FORMAT)
+                .long("bucket_format")
+                .validator(is_valid::<TableFormat>)
+                .possible_values(TableFormat::variants())
+                .default_value("long")
+                .help("Write bucket counts as bucket<TAB>token<TAB>count lines or one column per bucket"),
+        )
+        .arg(
+            Arg::with_name(FACET_FIELD)
+                .long("facet_field")
+                .conflicts_with_all(&[BITEXT, LABELED, TIME_BUCKET])
+                .help("Count the tokens of jsonl records per value of this dot-separated field")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(FACET_COUNTS)
+                .long("facet_counts")
+                .requires(FACET_FIELD)
+                .help("File for token counts per value of --facet_field")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(FACET_FORMAT)
+                .long("facet_format")
+                .validator(is_valid::<TableFormat>)
+                .possible_values(TableFormat::variants())
+                .default_value("long")
+                .help("Write facet counts as facet<TAB>token<TAB>count lines or one column per facet"),
+        )
+        .arg(
+            Arg::with_name(FACET_SUMMARY)
+                .long("facet_summary")
+                .requires(FACET_FIELD)
+                .help("File for the lines, tokens, types and token share of every facet value")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(TRENDS)
+                .long("trends")
+                .requires(TIME_BUCKET)
+                .help("File for tokens with significant frequency changes between two periods")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(TREND_BEFORE)
+                .long("trend_before")
+                .validator(is_valid::<Period>)
+                .help("Earlier period of --trends as bucket or FIRST..LAST range [default: first bucket]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(TREND_AFTER)
+                .long("trend_after")
+                .validator(is_valid::<Period>)
+                .help("Later period of --trends as bucket or FIRST..LAST range [default: last bucket]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(TREND_MAX_P)
+                .long("trend_max_p")
+                .validator(|v| validate(&v, |&f: &f64| f > 0. && f <= 1., "The maximum p-value should be in (0, 1]."))
+                .default_value("0.001")
+                .help("Maximum p-value of the log-likelihood test of --trends"),
+        )
+        .arg(
+            Arg::with_name(LABEL_COUNTS)
+                .long("label_counts")
+                .requires(LABELED)
+                .help("File for label<TAB>token<TAB>count lines of --labeled")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(LOG_ODDS)
+                .long("log_odds")
+                .requires(LABELED)
+                .help("File for log-odds ratios of tokens per label of --labeled")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(FEATURE_RANKING)
+                .long("feature_ranking")
+                .requires(LABELED)
+                .help("File for token<TAB>lines<TAB>score<TAB>label feature ranking of --labeled")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(FEATURE_MEASURE)
+                .long("feature_measure")
+                .validator(is_valid::<FeatureMeasure>)
+                .possible_values(FeatureMeasure::variants())
+                .default_value("chi2")
+                .help("Rank features by maximum chi-square over the labels or information gain"),
+        )
+        .arg(
+            Arg::with_name(TARGET_TOKEN_COUNTS)
+                .long("target_token_counts")
+                .requires(BITEXT)
+                .help("Token count file for the target side of --bitext")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(TARGET_NGRAM_COUNTS)
+                .long("target_ngram_counts")
+                .requires(BITEXT)
+                .help("File for ngram counts of the target side of --bitext")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARROW)
+                .long("arrow")
+                .help("Write token and ngram counts as Arrow IPC streams"),
+        )
+        .arg(
+            Arg::with_name(NORMALIZED)
+                .long("normalized")
+                .help("Add frequency per million tokens and Zipf value columns to token counts"),
+        )
+        .arg(
+            Arg::with_name(TOKEN_LOGPROB)
+                .long("token_logprob")
+                .requires(NGRAM_COUNTS)
+                .help("Add the log-probability per character under a language model of the ngram counts to token counts"),
+        )
+        .arg(
+            Arg::with_name(TOKEN_MIN)
+                .long("token_min")
+                .validator(non_zero("The token min count cannot be zero."))
+                .default_value("1")
+                .help("Word min count"),
+        )
+        .arg(
+            Arg::with_name(TOKEN_MIN_QUANTILE)
+                .long("token_min_quantile")
+                .validator(|v| validate(&v, |q: &f64| (0. ..=1.).contains(q), "The token min quantile should be in [0, 1]."))
+                .conflicts_with(MAX_VOCAB_COVERAGE)
+                .help("Use the count at this quantile of type counts as token min")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(MAX_VOCAB_COVERAGE)
+                .long("max_vocab_coverage")
+                .validator(|v| validate(&v, |&f: &f64| f > 0. && f <= 1., "The maximum vocabulary coverage should be in (0, 1]."))
+                .help("Use the smallest token min whose tokens cover this fraction of the corpus")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(NGRAM_MIN)
+                .long("ngram_min")
+                .validator(non_zero("The ngram min count cannot be zero."))
+                .default_value("1")
+                .help("Ngram min count"),
+        )
+        .arg(
+            Arg::with_name(NGRAM_REDUNDANCY)
+                .long("ngram_redundancy")
+                .validator(|v| validate(&v, |&f: &f64| f > 0. && f <= 1., "The n-gram redundancy ratio should be between 0 and 1."))
+                .requires(NGRAM_COUNTS)
+                .takes_value(true)
+                .help("Prune n-grams if a one character longer n-gram has at least this share of their count"),
+        )
+        .arg(
+            Arg::with_name(NGRAM_TARGET_SIZE)
+                .long("ngram_target_size")
+                .validator(non_zero("The n-gram target size cannot be zero."))
+                .requires(NGRAM_COUNTS)
+                .takes_value(true)
+                .help("Use the ngram min count that keeps about this many ngrams"),
+        )
+        .arg(
+            Arg::with_name(SWEEP)
+                .long("sweep")
+                .validator(|v| parse_sweep(&v).map(|_| ()))
+                .requires(NGRAM_COUNTS)
+                .conflicts_with_all(&[
+                    COUNT_ONLY,
+                    EMIT_FF_CONFIG,
+                    FOLLOW,
+                    NGRAM_IDS,
+                    TARGET_NGRAM_COUNTS,
+                    TOKEN_LOGPROB,
+                ])
+                .takes_value(true)
+                .help("Write ngram counts for all ngram lengths of ranges, e.g. \"min_n=2..4,max_n=4..7\""),
+        )
+        .arg(
+            Arg::with_name(MIN_N)
+                .long("min_n")
+                .alias("MIN_N")
+                .validator(non_zero("The minimum n-gram length cannot be zero."))
+                .default_value("3")
+                .help("Minimal ngram length to be used.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(MAX_N)
+                .long("max_n")
+                .alias("MAX_N")
+                .validator(is_valid::<usize>)
+                .default_value("6")
+                .help("Maximum ngram length to be used.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(COLLATE)
+                .long("collate")
+                .validator(is_valid::<Collation>)
+                .default_value("codepoint")
+                .help("Order of tokens and ngrams with equal counts: codepoint or caseless"),
+        )
+        .arg(
+            Arg::with_name(MAX_DOC_FREQ)
+                .long("max_doc_freq")
+                .validator(|v| validate(&v, |&f: &f64| f > 0. && f <= 1., "The maximum document frequency should be in (0, 1]."))
+                .help("Prune tokens occurring in more than this fraction of documents")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(MIN_DOCS)
+                .long("min_docs")
+                .validator(is_valid::<usize>)
+                .conflicts_with_all(&[BITEXT, COUNT_ONLY, FOLLOW])
+                .help("Suppress tokens and n-grams occurring in fewer documents")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(IDF)
+                .long("idf")
+                .help("File for document frequencies, IDF and TF-IDF of tokens")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(IDS_OUT)
+                .long("ids_out")
+                .help("File for the token to id mapping")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(FREQ_BINS)
+                .long("freq_bins")
+                .conflicts_with_all(&[COUNT_ONLY, FOLLOW])
+                .help("File for token<TAB>count<TAB>bin lines of log-frequency bins")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(FREQ_BIN_BASE)
+                .long("freq_bin_base")
+                .validator(|v| validate(&v, |&base: &usize| base > 1, "The bin base should be greater than 1."))
+                .default_value("2")
+                .help("Factor between the counts of consecutive --freq_bins"),
+        )
+        .arg(
+            Arg::with_name(SOFTMAX_CUTOFFS)
+                .long("softmax_cutoffs")
+                .conflicts_with_all(&[COUNT_ONLY, FOLLOW])
+                .help("File for adaptive softmax clusters with balanced token mass")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(SOFTMAX_CLUSTERS)
+                .long("softmax_clusters")
+                .validator(non_zero("The number of clusters cannot be zero."))
+                .default_value("4")
+                .help("Number of clusters of --softmax_cutoffs, including the head"),
+        )
+        .arg(
+            Arg::with_name(UNIGRAM_PROBS)
+                .long("unigram_probs")
+                .conflicts_with_all(&[COUNT_ONLY, FOLLOW])
+                .help("File for token<TAB>count<TAB>probability lines of the negative sampling distribution")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(UNIGRAM_TABLE)
+                .long("unigram_table")
+                .conflicts_with_all(&[COUNT_ONLY, FOLLOW])
+                .help("File for word2vec's unigram table of token ranks as little-endian u32")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(UNIGRAM_TABLE_SIZE)
+                .long("unigram_table_size")
+                .validator(non_zero("The unigram table size cannot be zero."))
+                .default_value("100000000")
+                .help("Number of entries of --unigram_table"),
+        )
+        .arg(
+            Arg::with_name(UNIGRAM_POWER)
+                .long("unigram_power")
+                .validator(is_valid::<f64>)
+                .default_value("0.75")
+                .help("Power of the counts in the negative sampling distribution"),
+        )
+        .arg(
+            Arg::with_name(HUFFMAN)
+                .long("huffman")
+                .conflicts_with_all(&[COUNT_ONLY, FOLLOW])
+                .help("File for token<TAB>count<TAB>code<TAB>points lines of the Huffman tree for hierarchical softmax")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(BLOOM_OUT)
+                .long("bloom_out")
+                .conflicts_with_all(&[COUNT_ONLY, FOLLOW])
+                .help("File for a Bloom filter of the vocabulary")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(BLOOM_FPR)
+                .long("bloom_fpr")
+                .validator(|v| validate(&v, |&f: &f64| f > 0. && f < 1., "The false positive rate should be between 0 and 1."))
+                .default_value("0.001")
+                .help("False positive rate of the Bloom filter"),
+        )
+        .arg(
+            Arg::with_name(CCFS_OUT)
+                .long("ccfs_out")
+                .conflicts_with_all(&[COUNT_ONLY, FOLLOW])
+                .help("File for a transducer of the sorted vocabulary in CCFS format")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(CCFS_COUNTS)
+                .long("ccfs_counts")
+                .requires(CCFS_OUT)
+                .help("Map the tokens of the transducer to their counts"),
+        )
+        .arg(
+            Arg::with_name(DAWG_OUT)
+                .long("dawg_out")
+                .conflicts_with_all(&[COUNT_ONLY, FOLLOW])
+                .help("File for a DAWG of the vocabulary and its counts")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(EMIT_FF_CONFIG)
+                .long("emit_ff_config")
+                .conflicts_with_all(&[COUNT_ONLY, FOLLOW])
+                .help("File for a finalfrontier training configuration matching the counts")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(FF_BUCKETS_EXP)
+                .long("ff_buckets_exp")
+                .validator(|v| validate(&v, |&exp: &u32| exp < 64, "The bucket exponent should be smaller than 64."))
+                .default_value("21")
+                .help("Exponent of the number of n-gram buckets of --emit_ff_config without --ngram_counts"),
+        )
+        .arg(
+            Arg::with_name(SPECIAL_TOKENS)
+                .long("special_tokens")
+                .validator(unique_special_tokens)
+                .conflicts_with_all(&[COUNT_ONLY, HASH_TOKENS, TOKEN_LOGPROB])
+                .help("Comma-separated symbols leading the token counts regardless of their counts, e.g. \"<unk>,<pad>,<s>,</s>\"")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(RESERVED)
+                .long("reserved")
+                .default_value("<pad>,<unk>")
+                .empty_values(true)
+                .help("Comma-separated symbols with reserved leading ids"),
+        )
+        .arg(
+            Arg::with_name(COUNT_ONLY)
+                .long("count_only")
+                .conflicts_with_all(&[
+                    IDF,
+                    IDS_OUT,
+                    MAX_DOC_FREQ,
+                    NEAR_DUP_REPORT,
+                    NGRAM_COUNTS,
+                    STATS,
+                    EMOJI_COUNTS,
+                    POSITIONS,
+                    DISPERSION,
+                    BITEXT,
+                    LABELED,
+                    TIME_BUCKET,
+                    FACET_FIELD,
+                    NORMALIZED,
+                    ARROW,
+                    AUDIT_TERMS,
+                    TOKEN_MIN_QUANTILE,
+                    MAX_VOCAB_COVERAGE,
+                ])
+                .help("Only write the total number of lines, tokens, types and bytes."),
+        )
+        .arg(
+            Arg::with_name(FOLLOW)
+                .long("follow")
+                .requires(TOKEN_COUNTS)
+                .conflicts_with_all(&[
+                    COUNT_ONLY,
+                    IDF,
+                    IDS_OUT,
+                    MAX_DOC_FREQ,
+                    NEAR_DUP_REPORT,
+                    NGRAM_IDS,
+                    STATS,
+                    EMOJI_COUNTS,
+                    POSITIONS,
+                    DISPERSION,
+                    BITEXT,
+                    LABELED,
+                    TIME_BUCKET,
+                    FACET_FIELD,
+                    GROWTH,
+                    PROJECT_COVERAGE,
+                ])
+                .help("Keep reading the growing corpus, writing count snapshots periodically"),
+        )
+        .arg(
+            Arg::with_name(SNAPSHOT_INTERVAL)
+                .long("snapshot_interval")
+                .validator(is_valid::<u64>)
+                .default_value("60")
+                .help("Seconds between count snapshots with --follow"),
+        )
+        .arg(
+            Arg::with_name(NO_VOCAB)
+                .long("no_vocab")
+                .requires(COUNT_ONLY)
+                .help("Do not store the vocabulary with --count_only, types are not reported."),
+        )
+        .arg(
+            Arg::with_name(TOKENIZER)
+                .long("tokenizer")
+                .validator(valid_tokenizer)
+                .default_value("whitespace")
+                .help(
+                    "Tokenizer: whitespace, unicode_words, chars, script_runs, regex:PATTERN \
+                     matching the tokens, jieba:DICT segmenting with a jieba dictionary or \
+                     dict:WORDS segmenting into the fewest words of a word list",
+                ),
+        )
+        .arg(
+            Arg::with_name(TOKENIZER_CMD)
+                .long("tokenizer_cmd")
+                .help("Shell command printing the whitespace-separated tokens of every input line")
+                .takes_value(true)
+                .conflicts_with_all(&[BITEXT, FOLLOW]),
+        )
+        .arg(
+            Arg::with_name(NORMALIZER)
+                .long("normalizer")
+                .validator(|v| normalizer_registry().create(&v).map(|_| ()))
+                .help(
+                    "Comma-separated normalizers applied before rewriting: digits, lowercase, \
+                     strip_punct",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(REWRITE)
+                .long("rewrite")
+                .help("File with from<TAB>to token rewrite rules, /regex/ for patterns")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(AUDIT_TERMS)
+                .long("audit_terms")
+                .requires(AUDIT_REPORT)
+                .conflicts_with(FOLLOW)
+                .help("File with terms to audit, one per line, /regex/ for patterns")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(AUDIT_REPORT)
+                .long("audit_report")
+                .requires(AUDIT_TERMS)
+                .help("File for term<TAB>count<TAB>examples lines of the audited terms")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(AUDIT_EXAMPLES)
+                .long("audit_examples")
+                .validator(is_valid::<usize>)
+                .default_value("3")
+                .help("Number of example contexts per audited term"),
+        )
+        .arg(
+            Arg::with_name(AUDIT_CONTEXT)
+                .long("audit_context")
+                .validator(is_valid::<usize>)
+                .default_value("5")
+                .help("Number of tokens on either side of audited terms in examples"),
+        )
+        .arg(
+            Arg::with_name(DTM_OUT)
+                .long("dtm_out")
+                .requires(DTM_VOCAB)
+                .conflicts_with_all(&[COUNT_ONLY, DP_EPSILON, FOLLOW, HASH_TOKENS])
+                .help("File for the document-term matrix in Matrix Market format")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(DTM_VOCAB)
+                .long("dtm_vocab")
+                .requires(DTM_OUT)
+                .help("File for the tokens of the matrix columns, one per line")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(DTM_DOCS)
+                .long("dtm_docs")
+                .requires(DTM_OUT)
+                .help("File for the file:index names of the matrix rows, one per line")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(INVERTED_INDEX)
+                .long("inverted_index")
+                .conflicts_with_all(&[COUNT_ONLY, DP_EPSILON, FOLLOW, HASH_TOKENS])
+                .help("File for token<TAB>doc_freq<TAB>bitmap lines of the documents of the tokens")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(INDEX_DOCS)
+                .long("index_docs")
+                .requires(INVERTED_INDEX)
+                .help("File for the file:index names of the documents of the inverted index, one per line")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(HASH_TOKENS)
+                .long("hash_tokens")
+                .conflicts_with_all(&[
+                    AUDIT_TERMS,
+                    BITEXT,
+                    COUNT_ONLY,
+                    DISPERSION,
+                    EMOJI_COUNTS,
+                    FOLLOW,
+                    IDF,
+                    IDS_OUT,
+                    NEAR_DUP_REPORT,
+                    NGRAM_COUNTS,
+                    POSITIONS,
+                ])
+                .help("Write salted hashes of the tokens instead of the tokens"),
+        )
+        .arg(
+            Arg::with_name(HASH_SALT)
+                .long("hash_salt")
+                .requires(HASH_TOKENS)
+                .help("File with the salt of token hashes, a random salt is written if it does not exist")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(DP_EPSILON)
+                .long("dp_epsilon")
+                .validator(|v| validate(&v, |&e: &f64| e > 0., "Epsilon should be positive."))
+                .conflicts_with_all(&[
+                    AUDIT_TERMS,
+                    BITEXT,
+                    COUNT_ONLY,
+                    DISPERSION,
+                    EMOJI_COUNTS,
+                    FOLLOW,
+                    GROWTH,
+                    IDF,
+                    MAX_DOC_FREQ,
+                    MIN_DOCS,
+                    NEAR_DUP_REPORT,
+                    PII_COUNTS,
+                    POSITIONS,
+                    PROJECT_COVERAGE,
+                ])
+                .help(
+                    "Release the number of documents containing every token with differential \
+                     privacy with this epsilon",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(DP_DELTA)
+                .long("dp_delta")
+                .validator(|v| validate(&v, |&f: &f64| f > 0. && f < 1., "Delta should be in (0, 1)."))
+                .default_value("1e-6")
+                .help("Probability of releasing a token that only one document contributes"),
+        )
+        .arg(
+            Arg::with_name(DP_SENSITIVITY)
+                .long("dp_sensitivity")
+                .validator(non_zero("The sensitivity cannot be zero."))
+                .default_value("1")
+                .help("Maximum number of distinct tokens counted per document"),
+        )
+        .arg(
+            Arg::with_name(DP_SEED)
+                .long("dp_seed")
+                .validator(is_valid::<u64>)
+                .help("Seed of the noise, random if not given")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(MASK_PII)
+                .long("mask_pii")
+                .help("Replace emails, IBANs, card numbers, IP addresses and phone numbers by placeholder tokens"),
+        )
+        .arg(
+            Arg::with_name(PII_COUNTS)
+                .long("pii_counts")
+                .conflicts_with(FOLLOW)
+                .help("File for kind<TAB>count lines of the personal data in the corpus")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(STRIP_BOILERPLATE)
+                .long("strip_boilerplate")
+                .help("File with ngram<TAB>documents boilerplate n-grams, the tokens they cover are not counted")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(EMOJI)
+                .long("emoji")
+                .validator(is_valid::<EmojiPolicy>)
+                .possible_values(EmojiPolicy::variants())
+                .default_value("keep")
+                .help("Keep emoji in tokens, drop them or count them separately"),
+        )
+        .arg(
+            Arg::with_name(EMOJI_COUNTS)
+                .long("emoji_counts")
+                .help("File for emoji counts with --emoji separate")
+                .takes_value(true)
+                .required_if(EMOJI, "separate"),
+        )
+        .arg(
+            Arg::with_name(GROWTH)
+                .long("growth")
+                .help("File for the vocabulary growth curve, reads the corpus with one thread")
+                .takes_value(true)
+                .conflicts_with(NO_VOCAB),
+        )
+        .arg(
+            Arg::with_name(GROWTH_STEP)
+                .long("growth_step")
+                .validator(non_zero("The growth step cannot be zero."))
+                .default_value("100000")
+                .help("Number of tokens between points of the growth curve"),
+        )
+        .arg(
+            Arg::with_name(PROJECT_COVERAGE)
+                .long("project_coverage")
+                .help("File for the corpus size needed to reach target types or coverage")
+                .takes_value(true)
+                .conflicts_with(NO_VOCAB),
+        )
+        .arg(
+            Arg::with_name(TARGET_TYPES)
+                .long("target_types")
+                .validator(is_valid::<usize>)
+                .help("Target vocabulary sizes of the coverage projection")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .requires(PROJECT_COVERAGE),
+        )
+        .arg(
+            Arg::with_name(TARGET_COVERAGE)
+                .long("target_coverage")
+                .validator(|v| validate(&v, |&f: &f64| f > 0. && f < 1., "The target coverage should be in (0, 1)."))
+                .help("Target coverages in (0, 1) of the coverage projection")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .requires(PROJECT_COVERAGE),
+        )
+        .arg(
+            Arg::with_name(DISPERSION)
+                .long("dispersion")
+                .help("File for Juilland's D and Gries' DP of tokens over documents")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(POSITIONS)
+                .long("positions")
+                .help("File for counts of tokens at the start, middle and end of lines")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(STATS)
+                .long("stats")
+                .help("File for token length and script statistics")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(DEDUP_LINES)
+                .long("dedup_lines")
+                .help("Count only the first occurrence of every line."),
+        )
+        .arg(
+            Arg::with_name(NEAR_DUP_REPORT)
+                .long("near_dup_report")
+                .help("File for clusters of near-duplicate documents")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(NEAR_DUP_THRESHOLD)
+                .long("near_dup_threshold")
+                .validator(|v| validate(&v, |&f: &f64| f > 0. && f <= 1., "The near-duplicate threshold should be in (0, 1]."))
+                .default_value("0.8")
+                .help("Minimum estimated Jaccard similarity of near-duplicates"),
+        )
+        .arg(
+            Arg::with_name(SHINGLE_SIZE)
+                .long("shingle_size")
+                .validator(non_zero("The shingle size cannot be zero."))
+                .default_value("5")
+                .help("Number of tokens per shingle for near-duplicate detection"),
+        )
+        .arg(
+            Arg::with_name(FILTER_FIRST)
+                .long("filter_first")
+                .help("Filter tokens before counting ngrams."),
+        )
+        .arg(
+            Arg::with_name(NO_BRACKET)
+                .long("no_bracket")
+                .takes_value(false),
+        )
+        .subcommand(AffixesApp::app())
+        .subcommand(AlignedApp::app())
+        .subcommand(BoilerplateApp::app())
+        .subcommand(BucketReportApp::app())
+        .subcommand(CheckEmbeddingsApp::app())
+        .subcommand(CompoundsApp::app())
+        .subcommand(CooccurrenceApp::app())
+        .subcommand(EncodeApp::app())
+        .subcommand(FastTextVocabApp::app())
+        .subcommand(FilterLinesApp::app())
+        .subcommand(KwicApp::app())
+        .subcommand(LearnMorphsApp::app())
+        .subcommand(OcrReportApp::app())
+        .subcommand(PhrasesApp::app())
+        .subcommand(QueryApp::app())
+        .subcommand(ServeApp::app())
+        .subcommand(ShuffleApp::app())
+        .subcommand(SplitApp::app())
+        .subcommand(StreamApp::app())
+        .subcommand(SubsampleApp::app())
+        .subcommand(UpdateApp::app())
+        .subcommand(VariantsApp::app())
+        .subcommand(WordNGramsApp::app())
+        .get_matches()
+}
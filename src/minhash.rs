@@ -0,0 +1,194 @@
+//! MinHash sketches for near-duplicate document detection.
+//!
+//! Documents are represented by the set of their word shingles (token
+//! n-grams). The MinHash signature of a document estimates the Jaccard
+//! similarity of shingle sets, locality sensitive hashing over bands of
+//! the signature finds candidate pairs without comparing all documents.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+/// Number of hash functions in a signature.
+pub const NUM_HASHES: usize = 128;
+
+/// A sketched document.
+#[derive(Clone, Debug)]
+pub struct Document {
+    /// Source file of the document.
+    pub file: String,
+    /// Index of the document within the file.
+    pub index: usize,
+    /// Number of tokens in the document.
+    pub tokens: usize,
+    pub signature: Vec<u32>,
+}
+
+/// Incremental MinHash computation for a single document.
+#[derive(Clone, Debug)]
+pub struct Sketch {
+    shingle_size: usize,
+    window: VecDeque<u64>,
+    mins: Vec<u32>,
+    shingles: usize,
+}
+
+impl Sketch {
+    pub fn new(shingle_size: usize) -> Self {
+        Sketch {
+            shingle_size: shingle_size.max(1),
+            window: VecDeque::with_capacity(shingle_size),
+            mins: vec![u32::MAX; NUM_HASHES],
+            shingles: 0,
+        }
+    }
+
+    /// Add the next token of the document.
+    pub fn add_token(&mut self, token: &str) {
+        let mut hasher = DefaultHasher::new();
+        token.hash(&mut hasher);
+        if self.window.len() == self.shingle_size {
+            self.window.pop_front();
+        }
+        self.window.push_back(hasher.finish());
+        if self.window.len() == self.shingle_size {
+            self.add_shingle();
+        }
+    }
+
+    /// Finish the signature of the document.
+    ///
+    /// Documents shorter than the shingle size are represented by a single
+    /// shingle of all their tokens. Returns `None` for empty documents.
+    pub fn finish(mut self) -> Option<Vec<u32>> {
+        if self.shingles == 0 {
+            if self.window.is_empty() {
+                return None;
+            }
+            self.add_shingle();
+        }
+        Some(self.mins)
+    }
+
+    fn add_shingle(&mut self) {
+        let shingle = self
+            .window
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325u64, |acc, &h| mix(acc ^ h));
+        for (i, min) in self.mins.iter_mut().enumerate() {
+            let h = (mix(shingle ^ SEEDS[i]) >> 32) as u32;
+            if h < *min {
+                *min = h;
+            }
+        }
+        self.shingles += 1;
+    }
+}
+
+/// Estimate the Jaccard similarity of two signatures.
+pub fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    let equal = a.iter().zip(b).filter(|(a, b)| a == b).count();
+    equal as f64 / a.len().max(1) as f64
+}
+
+/// Find clusters of near-duplicate documents.
+///
+/// Documents are clustered if their estimated similarity is at least
+/// `threshold`. Only clusters with more than one document are returned,
+/// the largest clusters first.
+pub fn near_duplicates(docs: &[Document], threshold: f64) -> Vec<Vec<usize>> {
+    let (bands, rows) = banding(threshold);
+    let mut clusters = UnionFind::new(docs.len());
+    for band in 0..bands {
+        let mut buckets: HashMap<&[u32], usize> = HashMap::new();
+        for (idx, doc) in docs.iter().enumerate() {
+            let key = &doc.signature[band * rows..(band + 1) * rows];
+            // Compare with the first document of the bucket only, which keeps
+            // large buckets of exact duplicates linear.
+            match buckets.get(key) {
+                Some(&first) => {
+                    if similarity(&docs[first].signature, &doc.signature) >= threshold {
+                        clusters.union(first, idx);
+                    }
+                }
+                None => {
+                    buckets.insert(key, idx);
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for idx in 0..docs.len() {
+        groups.entry(clusters.find(idx)).or_default().push(idx);
+    }
+    let mut groups = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect::<Vec<_>>();
+    groups.sort_unstable_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+    groups
+}
+
+/// Choose the band layout whose LSH threshold is closest to `threshold`.
+///
+/// Pairs with similarity `s` become candidates with probability
+/// `1 - (1 - s^rows)^bands`, which has its steepest point at roughly
+/// `(1 / bands)^(1 / rows)`.
+fn banding(threshold: f64) -> (usize, usize) {
+    (1..=NUM_HASHES)
+        .filter(|rows| NUM_HASHES.is_multiple_of(*rows))
+        .map(|rows| (NUM_HASHES / rows, rows))
+        .min_by(|&(b1, r1), &(b2, r2)| {
+            let t1 = (1. / b1 as f64).powf(1. / r1 as f64);
+            let t2 = (1. / b2 as f64).powf(1. / r2 as f64);
+            (t1 - threshold).abs().total_cmp(&(t2 - threshold).abs())
+        })
+        .unwrap()
+}
+
+struct UnionFind {
+    parents: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        UnionFind {
+            parents: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut idx: usize) -> usize {
+        while self.parents[idx] != idx {
+            self.parents[idx] = self.parents[self.parents[idx]];
+            idx = self.parents[idx];
+        }
+        idx
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parents[a.max(b)] = a.min(b);
+        }
+    }
+}
+
+/// SplitMix64 finalizer.
+const fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+static SEEDS: [u64; NUM_HASHES] = seeds();
+
+const fn seeds() -> [u64; NUM_HASHES] {
+    let mut seeds = [0u64; NUM_HASHES];
+    let mut i = 0;
+    while i < NUM_HASHES {
+        seeds[i] = mix((i as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        i += 1;
+    }
+    seeds
+}
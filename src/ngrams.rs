@@ -0,0 +1,305 @@
+//! Character n-grams.
+
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasher;
+use std::iter::FusedIterator;
+
+use crate::options::Options;
+
+/// Taken from finalfrontier::subtokens
+///
+/// N-grams are returned by start offset, the n-grams of a start offset by
+/// descending length. The iterator is double-ended, n-grams are taken from
+/// the back in the reverse order.
+#[derive(Clone, Debug)]
+pub struct NGrams<'a> {
+    max_n: usize,
+    min_n: usize,
+    string: &'a str,
+    // Byte offsets of the characters in `string`, followed by its length.
+    char_offsets: Vec<usize>,
+    // Start (in characters) and length of the next n-gram from the front.
+    front: (usize, usize),
+    // Start and length of the next n-gram from the back.
+    back: (usize, usize),
+    remaining: usize,
+}
+
+impl<'a> NGrams<'a> {
+    /// Create a new n-ngram iterator.
+    ///
+    /// The iterator will create n-ngrams of length *[min_n, max_n]*
+    pub fn new(string: &'a str, min_n: usize, max_n: usize) -> Self {
+        assert_ne!(min_n, 0, "The minimum n-gram length cannot be zero.");
+        assert!(
+            min_n <= max_n,
+            "The maximum length should be equal to or greater than the minimum length."
+        );
+
+        let char_offsets = string
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .chain(Some(string.len()))
+            .collect::<Vec<_>>();
+        let n_chars = char_offsets.len() - 1;
+
+        // Every suffix of at least min_n characters has one n-gram per
+        // length in [min_n, min(max_n, suffix length)].
+        let remaining = if n_chars < min_n {
+            0
+        } else {
+            let short = cmp::min(n_chars, max_n) - min_n + 1;
+            let long = n_chars.saturating_sub(max_n) * (max_n - min_n + 1);
+            short * (short + 1) / 2 + long
+        };
+
+        NGrams {
+            min_n,
+            max_n,
+            string,
+            char_offsets,
+            front: (0, cmp::min(max_n, n_chars)),
+            back: (n_chars.saturating_sub(min_n), min_n),
+            remaining,
+        }
+    }
+
+    fn n_chars(&self) -> usize {
+        self.char_offsets.len() - 1
+    }
+
+    fn ngram(&self, (start, len): (usize, usize)) -> &'a str {
+        &self.string[self.char_offsets[start]..self.char_offsets[start + len]]
+    }
+}
+
+impl<'a> Iterator for NGrams<'a> {
+    type Item = &'a str;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let ngram = self.ngram(self.front);
+        let (start, len) = self.front;
+        self.front = if len > self.min_n {
+            (start, len - 1)
+        } else {
+            // Move to the next suffix, starting with its longest n-gram.
+            let start = start + 1;
+            (start, cmp::min(self.max_n, self.n_chars() - start))
+        };
+
+        Some(ngram)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> DoubleEndedIterator for NGrams<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let ngram = self.ngram(self.back);
+        let (start, len) = self.back;
+        self.back = if len < cmp::min(self.max_n, self.n_chars() - start) {
+            (start, len + 1)
+        } else {
+            // Move to the previous suffix, starting with its shortest n-gram.
+            (start.saturating_sub(1), self.min_n)
+        };
+
+        Some(ngram)
+    }
+}
+
+impl<'a> ExactSizeIterator for NGrams<'a> {}
+
+impl<'a> FusedIterator for NGrams<'a> {}
+
+/// Get the n-grams whose counts are explained by longer n-grams.
+///
+/// An n-gram is redundant if an n-gram that is one character longer and
+/// contains it has at least `ratio` times its count, e.g. `<th` is
+/// redundant if nearly all of its occurrences are in `<the`. Every longer
+/// n-gram containing an n-gram contains such an extension, so only
+/// extensions by one character are compared. Only n-grams with at least
+/// `min_count` occurrences explain shorter ones, so that n-grams are not
+/// pruned in favor of n-grams below the count threshold.
+pub fn redundant_ngrams<S: BuildHasher>(
+    counts: &HashMap<String, usize, S>,
+    ratio: f64,
+    min_count: usize,
+) -> HashSet<String> {
+    // The highest count of an extension of every n-gram.
+    let mut extensions: HashMap<&str, usize> = HashMap::new();
+    for (ngram, &count) in counts {
+        if count < min_count {
+            continue;
+        }
+        let first = match ngram.char_indices().nth(1) {
+            Some((idx, _)) => idx,
+            None => continue,
+        };
+        let last = ngram.char_indices().last().unwrap().0;
+        for shorter in [&ngram[first..], &ngram[..last]] {
+            let max = extensions.entry(shorter).or_insert(0);
+            *max = cmp::max(*max, count);
+        }
+    }
+    extensions
+        .into_iter()
+        .filter(|(ngram, max)| {
+            counts
+                .get(*ngram)
+                .map(|&count| *max as f64 >= ratio * count as f64)
+                .unwrap_or(false)
+        })
+        .map(|(ngram, _)| ngram.to_owned())
+        .collect()
+}
+
+/// Count the n-grams of tokens with the counts of the tokens.
+///
+/// Tokens are bracketed and split into n-grams as set in the options,
+/// with `filter_first`, tokens below the token min count are skipped.
+pub fn count_ngrams<'a>(
+    token_counts: impl IntoIterator<Item = (&'a str, usize)>,
+    opts: &Options,
+) -> HashMap<String, usize> {
+    let mut ngram_counts = HashMap::new();
+    for (token, count) in token_counts {
+        if opts.filter_first() && count < opts.token_min() {
+            continue;
+        }
+        let token = if opts.bracket() {
+            format!("<{}>", token)
+        } else {
+            token.to_owned()
+        };
+        for ngram in NGrams::new(&token, opts.min_n(), opts.max_n()) {
+            if let Some(cnt) = ngram_counts.get_mut(ngram) {
+                *cnt += count;
+            } else {
+                ngram_counts.insert(ngram.to_string(), count);
+            }
+        }
+    }
+    ngram_counts
+}
+
+/// Prune n-gram counts as set in the options.
+///
+/// Redundant n-grams are removed, see `redundant_ngrams`. Returns the
+/// n-gram min count, which is the count that keeps the number of n-grams
+/// closest to the n-gram target size if there is one.
+pub fn prune_ngrams<S: BuildHasher>(
+    ngram_counts: &mut HashMap<String, usize, S>,
+    opts: &Options,
+) -> usize {
+    if let Some(ratio) = opts.ngram_redundancy() {
+        let redundant = redundant_ngrams(ngram_counts, ratio, opts.ngram_min());
+        ngram_counts.retain(|ngram, _| !redundant.contains(ngram));
+    }
+    match opts.ngram_target_size() {
+        Some(target_size) => {
+            let mut sorted = ngram_counts.values().copied().collect::<Vec<_>>();
+            sorted.sort_unstable();
+            target_size_min_count(&sorted, target_size)
+        }
+        None => opts.ngram_min(),
+    }
+}
+
+/// Get the min count of ascending n-gram counts that keeps the number of
+/// n-grams closest to `target_size`.
+fn target_size_min_count(sorted: &[usize], target_size: usize) -> usize {
+    if target_size >= sorted.len() {
+        return 1;
+    }
+    let count = sorted[sorted.len() - target_size];
+    // Ties make the number of kept n-grams jump at `count`.
+    let at_least = sorted.len() - sorted.partition_point(|&c| c < count);
+    let above = sorted.len() - sorted.partition_point(|&c| c <= count);
+    if at_least - target_size <= target_size - above {
+        count
+    } else {
+        count + 1
+    }
+}
+
+/// Get the n-grams of `string` by slicing every length at every offset.
+///
+/// Reference implementation of `NGrams` for tests, n-grams are in the same
+/// order.
+#[doc(hidden)]
+pub fn naive_ngrams(string: &str, min_n: usize, max_n: usize) -> Vec<&str> {
+    let offsets = string
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .chain(Some(string.len()))
+        .collect::<Vec<_>>();
+    let n_chars = offsets.len() - 1;
+    let mut ngrams = Vec::new();
+    for start in 0..n_chars {
+        for len in (min_n..=max_n).rev() {
+            if start + len <= n_chars {
+                ngrams.push(&string[offsets[start]..offsets[start + len]]);
+            }
+        }
+    }
+    ngrams
+}
+
+/// Fuzzing entry point.
+///
+/// The first two bytes determine `min_n` and `max_n`, the remaining bytes
+/// are the string. Panics if `NGrams` differs from `naive_ngrams` in either
+/// direction.
+#[doc(hidden)]
+pub fn fuzz_ngrams(data: &[u8]) {
+    if data.len() < 2 {
+        return;
+    }
+    let min_n = 1 + data[0] as usize % 8;
+    let max_n = min_n + data[1] as usize % 8;
+    let string = String::from_utf8_lossy(&data[2..]);
+    let ngrams = NGrams::new(&string, min_n, max_n);
+    assert_eq!(
+        ngrams.len(),
+        naive_ngrams(&string, min_n, max_n).len(),
+        "length of n-grams of {:?} with min_n {} and max_n {}",
+        string,
+        min_n,
+        max_n
+    );
+    let mut expected = naive_ngrams(&string, min_n, max_n);
+    assert_eq!(
+        ngrams.clone().collect::<Vec<_>>(),
+        expected,
+        "n-grams of {:?} with min_n {} and max_n {}",
+        string,
+        min_n,
+        max_n
+    );
+    expected.reverse();
+    assert_eq!(
+        ngrams.rev().collect::<Vec<_>>(),
+        expected,
+        "reversed n-grams of {:?} with min_n {} and max_n {}",
+        string,
+        min_n,
+        max_n
+    );
+}
@@ -0,0 +1,148 @@
+//! Token normalization.
+//!
+//! Normalizers map tokens to their normalized form before they are
+//! rewritten, filtered and counted. Domain-specific normalizers implement
+//! `Normalizer` and are made available to `--normalizer` by registering
+//! them in a `Registry` under a name.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Normalization of tokens.
+pub trait Normalizer: fmt::Debug + Send + Sync {
+    /// Normalize a token, tokens that are normalized to the empty string
+    /// are dropped.
+    fn normalize<'a>(&self, token: &'a str) -> Cow<'a, str>;
+}
+
+/// Lowercase tokens.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lowercase;
+
+impl Normalizer for Lowercase {
+    fn normalize<'a>(&self, token: &'a str) -> Cow<'a, str> {
+        if token.chars().any(|c| c.is_uppercase()) {
+            Cow::Owned(token.to_lowercase())
+        } else {
+            Cow::Borrowed(token)
+        }
+    }
+}
+
+/// Replace every decimal digit with `0`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Digits;
+
+impl Normalizer for Digits {
+    fn normalize<'a>(&self, token: &'a str) -> Cow<'a, str> {
+        if token.chars().any(|c| c.is_numeric() && c != '0') {
+            Cow::Owned(
+                token
+                    .chars()
+                    .map(|c| if c.is_numeric() { '0' } else { c })
+                    .collect(),
+            )
+        } else {
+            Cow::Borrowed(token)
+        }
+    }
+}
+
+/// Strip leading and trailing punctuation.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StripPunctuation;
+
+impl Normalizer for StripPunctuation {
+    fn normalize<'a>(&self, token: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(token.trim_matches(|c: char| c.is_ascii_punctuation() || is_punctuation(c)))
+    }
+}
+
+/// Check whether a non-ASCII character is common punctuation.
+pub(crate) fn is_punctuation(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00a1}'
+            | '\u{00ab}'
+            | '\u{00bb}'
+            | '\u{00bf}'
+            | '\u{2010}'..='\u{2027}'
+            | '\u{2030}'..='\u{205e}'
+            | '\u{3001}'..='\u{3003}'
+            | '\u{3008}'..='\u{3011}'
+    )
+}
+
+/// Normalizers applied one after the other.
+#[derive(Debug)]
+pub struct Chain(pub Vec<Box<dyn Normalizer>>);
+
+impl Normalizer for Chain {
+    fn normalize<'a>(&self, token: &'a str) -> Cow<'a, str> {
+        let mut token = Cow::Borrowed(token);
+        for normalizer in &self.0 {
+            let normalized = normalizer.normalize(&token);
+            if normalized != token {
+                token = Cow::Owned(normalized.into_owned());
+            }
+        }
+        token
+    }
+}
+
+/// Constructor of a normalizer.
+pub type NormalizerFactory = fn() -> Box<dyn Normalizer>;
+
+/// Normalizers by name.
+#[derive(Clone, Default)]
+pub struct Registry {
+    factories: BTreeMap<String, NormalizerFactory>,
+}
+
+impl Registry {
+    /// Get a registry of the built-in normalizers `lowercase`, `digits`
+    /// and `strip_punct`.
+    pub fn builtin() -> Self {
+        let mut registry = Registry::default();
+        registry.register("digits", || Box::new(Digits));
+        registry.register("lowercase", || Box::new(Lowercase));
+        registry.register("strip_punct", || Box::new(StripPunctuation));
+        registry
+    }
+
+    /// Register a normalizer, replaces normalizers of the same name.
+    pub fn register(&mut self, name: &str, factory: NormalizerFactory) {
+        self.factories.insert(name.to_owned(), factory);
+    }
+
+    /// Get the names of the registered normalizers.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+
+    /// Create the normalizers of a comma-separated list of names, which
+    /// are applied from left to right.
+    pub fn create(&self, names: &str) -> Result<Box<dyn Normalizer>, String> {
+        let mut normalizers = names
+            .split(',')
+            .map(|name| {
+                self.factories
+                    .get(name.trim())
+                    .map(|factory| factory())
+                    .ok_or_else(|| format!("Unknown normalizer: {}", name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if normalizers.len() == 1 {
+            Ok(normalizers.remove(0))
+        } else {
+            Ok(Box::new(Chain(normalizers)))
+        }
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
@@ -0,0 +1,188 @@
+//! Options of token and n-gram count tables.
+//!
+//! The min counts and n-gram lengths determine which tokens and n-grams
+//! are written, the collation how ties of their counts are ordered. They are built with `OptionsBuilder`, which checks that
+//! they are consistent, so the command line and library users get the
+//! same validation.
+
+use crate::sort::Collation;
+
+/// Min counts, n-gram lengths and collation of count tables.
+///
+/// The defaults are the defaults of `corpus-count`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    token_min: usize,
+    ngram_min: usize,
+    min_n: usize,
+    max_n: usize,
+    bracket: bool,
+    filter_first: bool,
+    ngram_redundancy: Option<f64>,
+    ngram_target_size: Option<usize>,
+    collation: Collation,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            token_min: 1,
+            ngram_min: 1,
+            min_n: 3,
+            max_n: 6,
+            bracket: true,
+            filter_first: false,
+            ngram_redundancy: None,
+            ngram_target_size: None,
+            collation: Collation::Codepoint,
+        }
+    }
+}
+
+impl Options {
+    /// Get a builder of the default options.
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::new()
+    }
+
+    /// Get a builder of these options, e.g. to change a min count.
+    pub fn to_builder(&self) -> OptionsBuilder {
+        OptionsBuilder {
+            options: self.clone(),
+        }
+    }
+
+    /// Get the min count of tokens.
+    pub fn token_min(&self) -> usize {
+        self.token_min
+    }
+
+    /// Get the min count of n-grams without an n-gram target size.
+    pub fn ngram_min(&self) -> usize {
+        self.ngram_min
+    }
+
+    /// Get the minimum n-gram length.
+    pub fn min_n(&self) -> usize {
+        self.min_n
+    }
+
+    /// Get the maximum n-gram length.
+    pub fn max_n(&self) -> usize {
+        self.max_n
+    }
+
+    /// Check whether tokens are bracketed with `<` and `>` before their
+    /// n-grams are extracted.
+    pub fn bracket(&self) -> bool {
+        self.bracket
+    }
+
+    /// Check whether tokens below the token min count are removed before
+    /// their n-grams are counted.
+    pub fn filter_first(&self) -> bool {
+        self.filter_first
+    }
+
+    /// Get the share of the count of an n-gram that a one character longer
+    /// n-gram needs to make it redundant.
+    pub fn ngram_redundancy(&self) -> Option<f64> {
+        self.ngram_redundancy
+    }
+
+    /// Get the number of n-grams that the n-gram min count should keep.
+    pub fn ngram_target_size(&self) -> Option<usize> {
+        self.ngram_target_size
+    }
+
+    /// Get the order of tokens and n-grams with equal counts.
+    pub fn collation(&self) -> Collation {
+        self.collation
+    }
+}
+
+/// Builder of `Options`.
+#[derive(Clone, Debug, Default)]
+pub struct OptionsBuilder {
+    options: Options,
+}
+
+impl OptionsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn token_min(mut self, token_min: usize) -> Self {
+        self.options.token_min = token_min;
+        self
+    }
+
+    pub fn ngram_min(mut self, ngram_min: usize) -> Self {
+        self.options.ngram_min = ngram_min;
+        self
+    }
+
+    pub fn min_n(mut self, min_n: usize) -> Self {
+        self.options.min_n = min_n;
+        self
+    }
+
+    pub fn max_n(mut self, max_n: usize) -> Self {
+        self.options.max_n = max_n;
+        self
+    }
+
+    pub fn bracket(mut self, bracket: bool) -> Self {
+        self.options.bracket = bracket;
+        self
+    }
+
+    pub fn filter_first(mut self, filter_first: bool) -> Self {
+        self.options.filter_first = filter_first;
+        self
+    }
+
+    pub fn ngram_redundancy(mut self, ratio: Option<f64>) -> Self {
+        self.options.ngram_redundancy = ratio;
+        self
+    }
+
+    pub fn ngram_target_size(mut self, target_size: Option<usize>) -> Self {
+        self.options.ngram_target_size = target_size;
+        self
+    }
+
+    pub fn collation(mut self, collation: Collation) -> Self {
+        self.options.collation = collation;
+        self
+    }
+
+    /// Check the options and build them.
+    pub fn build(self) -> Result<Options, String> {
+        let options = self.options;
+        if options.token_min == 0 {
+            return Err("The token min count cannot be zero.".to_owned());
+        }
+        if options.ngram_min == 0 {
+            return Err("The ngram min count cannot be zero.".to_owned());
+        }
+        if options.min_n == 0 {
+            return Err("The minimum n-gram length cannot be zero.".to_owned());
+        }
+        if options.min_n > options.max_n {
+            return Err(
+                "The maximum length should be equal to or greater than the minimum length."
+                    .to_owned(),
+            );
+        }
+        if let Some(ratio) = options.ngram_redundancy {
+            if !(ratio > 0. && ratio <= 1.) {
+                return Err("The n-gram redundancy ratio should be between 0 and 1.".to_owned());
+            }
+        }
+        if options.ngram_target_size == Some(0) {
+            return Err("The n-gram target size cannot be zero.".to_owned());
+        }
+        Ok(options)
+    }
+}
@@ -0,0 +1,282 @@
+//! Output files.
+//!
+//! Files are written to a temporary file next to the target and renamed
+//! once writing finished, so crashed runs never leave partial outputs
+//! behind that look complete.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::gzip::GzEncoder;
+use crate::temp;
+
+/// Options for writing output files.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct OutputOptions {
+    /// Write to a temporary file and rename it on success.
+    pub atomic: bool,
+    /// Compression of all outputs, determined by the file extension if
+    /// `None`.
+    pub compression: Option<Compression>,
+    /// Capacity of the write buffer in bytes.
+    pub buffer_size: usize,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        OutputOptions {
+            atomic: true,
+            compression: None,
+            buffer_size: 1 << 20,
+        }
+    }
+}
+
+/// Output compression.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+}
+
+impl Compression {
+    pub fn variants() -> &'static [&'static str] {
+        &["none", "gz"]
+    }
+
+    /// Get the compression for a file name.
+    fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext == "gz" => Compression::Gzip,
+            _ => Compression::None,
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Compression::None),
+            "gz" => Ok(Compression::Gzip),
+            _ => Err(format!("Unknown compression: {}", s)),
+        }
+    }
+}
+
+/// Layout of count tables per label, e.g. per time bucket.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TableFormat {
+    /// One `label<TAB>token<TAB>count` line per token and label.
+    Long,
+    /// One line per token with a column of counts per label.
+    Wide,
+}
+
+impl TableFormat {
+    pub fn variants() -> &'static [&'static str] {
+        &["long", "wide"]
+    }
+}
+
+impl FromStr for TableFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "long" => Ok(TableFormat::Long),
+            "wide" => Ok(TableFormat::Wide),
+            _ => Err(format!("Unknown table format: {}", s)),
+        }
+    }
+}
+
+/// An output file or stdout.
+///
+/// Outputs have to be completed through `finish`, atomic outputs that are
+/// dropped without finishing are removed.
+pub struct OutputFile {
+    writer: BufWriter<Encoder>,
+    /// Temporary path and target of atomic outputs.
+    rename: Option<(PathBuf, PathBuf)>,
+}
+
+impl OutputFile {
+    /// Create an output, `None` or `-` write to stdout and `tcp://ADDR`
+    /// connects to `ADDR`.
+    pub fn create(path: Option<&str>, opts: &OutputOptions) -> io::Result<Self> {
+        let path = match path {
+            None | Some("-") => {
+                let compression = opts.compression.unwrap_or(Compression::None);
+                return Ok(OutputFile {
+                    writer: BufWriter::with_capacity(
+                        opts.buffer_size,
+                        Encoder::new(Sink::Stdout(io::stdout()), compression),
+                    ),
+                    rename: None,
+                });
+            }
+            Some(path) => match path.strip_prefix("tcp://") {
+                Some(addr) => {
+                    let compression = opts.compression.unwrap_or(Compression::None);
+                    let sink = Sink::Tcp(TcpStream::connect(addr)?);
+                    return Ok(OutputFile {
+                        writer: BufWriter::with_capacity(
+                            opts.buffer_size,
+                            Encoder::new(sink, compression),
+                        ),
+                        rename: None,
+                    });
+                }
+                None => Path::new(path),
+            },
+        };
+        let compression = opts
+            .compression
+            .unwrap_or_else(|| Compression::from_path(path));
+        // Symlinks are resolved, so that the linked file is replaced
+        // instead of the link. Special files like `/dev/null` or FIFOs and
+        // dangling links are written in place, renaming would replace them
+        // by a regular file.
+        let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        let regular = fs::symlink_metadata(&target)
+            .map(|metadata| metadata.file_type().is_file())
+            .unwrap_or(true);
+        if !opts.atomic || !regular {
+            let sink = Sink::File(File::create(path)?);
+            return Ok(OutputFile {
+                writer: BufWriter::with_capacity(opts.buffer_size, Encoder::new(sink, compression)),
+                rename: None,
+            });
+        }
+
+        let dir = match target.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let name = target
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let tmp = temp::unique_path(dir, &name);
+        let sink = Sink::File(File::create(&tmp)?);
+        Ok(OutputFile {
+            writer: BufWriter::with_capacity(opts.buffer_size, Encoder::new(sink, compression)),
+            rename: Some((tmp, target)),
+        })
+    }
+
+    /// Flush and complete the output and move atomic outputs to their
+    /// target.
+    ///
+    /// Regular files are synced to disk before they are renamed, so a finished
+    /// output is complete even if the system crashes afterwards.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.writer.get_mut().finish()?;
+        if let Sink::File(file) = self.writer.get_ref().sink() {
+            if file.metadata()?.file_type().is_file() {
+                file.sync_all()?;
+            }
+        }
+        if let Some((tmp, target)) = self.rename.take() {
+            fs::rename(tmp, target)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.writer.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        if let Some((tmp, _)) = self.rename.take() {
+            let _ = fs::remove_file(tmp);
+        }
+    }
+}
+
+/// Optional compression of the output.
+enum Encoder {
+    Plain(Sink),
+    Gzip(GzEncoder<Sink>),
+}
+
+impl Encoder {
+    fn new(sink: Sink, compression: Compression) -> Self {
+        match compression {
+            Compression::None => Encoder::Plain(sink),
+            Compression::Gzip => Encoder::Gzip(GzEncoder::new(sink)),
+        }
+    }
+
+    fn sink(&self) -> &Sink {
+        match self {
+            Encoder::Plain(sink) => sink,
+            Encoder::Gzip(encoder) => encoder.get_ref(),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(sink) => sink.flush(),
+            Encoder::Gzip(encoder) => encoder.try_finish(),
+        }
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(sink) => sink.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(sink) => sink.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+enum Sink {
+    Stdout(io::Stdout),
+    File(File),
+    Tcp(TcpStream),
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Stdout(stdout) => stdout.write(buf),
+            Sink::File(file) => file.write(buf),
+            Sink::Tcp(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Stdout(stdout) => stdout.flush(),
+            Sink::File(file) => file.flush(),
+            Sink::Tcp(stream) => stream.flush(),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Personal data in lines.
+//!
+//! Emails, IBANs, card numbers, IP addresses and phone numbers are found
+//! with built-in patterns. Matches of IBANs and card numbers must have
+//! valid checksums, so that arbitrary long numbers are not reported.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::regex::Regex;
+
+/// Kinds of personal data, in the order they are matched.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PiiKind {
+    Email,
+    Iban,
+    Card,
+    Ip,
+    Phone,
+}
+
+impl PiiKind {
+    pub fn all() -> &'static [PiiKind] {
+        &[
+            PiiKind::Email,
+            PiiKind::Iban,
+            PiiKind::Card,
+            PiiKind::Ip,
+            PiiKind::Phone,
+        ]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PiiKind::Email => "email",
+            PiiKind::Iban => "iban",
+            PiiKind::Card => "card",
+            PiiKind::Ip => "ip",
+            PiiKind::Phone => "phone",
+        }
+    }
+
+    /// Get the token that replaces masked matches.
+    pub fn placeholder(self) -> &'static str {
+        match self {
+            PiiKind::Email => "<EMAIL>",
+            PiiKind::Iban => "<IBAN>",
+            PiiKind::Card => "<CARD>",
+            PiiKind::Ip => "<IP>",
+            PiiKind::Phone => "<PHONE>",
+        }
+    }
+
+    fn pattern(self) -> &'static str {
+        match self {
+            PiiKind::Email => r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}",
+            PiiKind::Iban => r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b",
+            PiiKind::Card => r"\b\d(?:[ -]?\d){12,18}\b",
+            PiiKind::Ip => {
+                r"\b(?:(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\.){3}(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\b"
+            }
+            PiiKind::Phone => r"(?:\+\d{1,3}[ .-]?|\(\d{1,4}\) ?)?\d{2,4}(?:[ ./-]\d{2,8}){1,4}\b",
+        }
+    }
+
+    /// Check a match beyond its pattern.
+    fn is_valid(self, text: &str) -> bool {
+        let digits = text.chars().filter(char::is_ascii_digit).count();
+        match self {
+            PiiKind::Email | PiiKind::Ip => true,
+            PiiKind::Iban => iban_checksum(text),
+            PiiKind::Card => luhn_checksum(text),
+            // Numbers with dots or spaces only are more often quantities
+            // or dates than phone numbers, unless they have a prefix.
+            PiiKind::Phone if text.starts_with('+') || text.starts_with('(') => {
+                (7..=15).contains(&digits)
+            }
+            PiiKind::Phone => (9..=15).contains(&digits) && text.contains(&['-', '/'][..]),
+        }
+    }
+}
+
+/// Check the mod-97 checksum of an IBAN.
+fn iban_checksum(iban: &str) -> bool {
+    let iban = iban.replace(' ', "");
+    if !(15..=34).contains(&iban.len()) {
+        return false;
+    }
+    let (country, account) = iban.split_at(4);
+    let mut remainder = 0;
+    for c in account.chars().chain(country.chars()) {
+        let value = c
+            .to_digit(36)
+            .expect("IBAN pattern admits non-alphanumeric");
+        remainder = if value < 10 {
+            (remainder * 10 + value) % 97
+        } else {
+            (remainder * 100 + value) % 97
+        };
+    }
+    remainder == 1
+}
+
+/// Check the Luhn checksum of a card number.
+fn luhn_checksum(number: &str) -> bool {
+    let sum = number
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(idx, digit)| match (idx % 2, digit * 2) {
+            (0, _) => digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum::<u32>();
+    sum % 10 == 0
+}
+
+/// Built-in patterns of personal data.
+#[derive(Clone, Debug)]
+pub struct PiiPatterns {
+    patterns: Vec<(PiiKind, Regex)>,
+    mask: bool,
+}
+
+impl PiiPatterns {
+    /// Construct the patterns, matches are replaced by placeholders if
+    /// `mask` is set.
+    pub fn new(mask: bool) -> Self {
+        PiiPatterns {
+            patterns: PiiKind::all()
+                .iter()
+                .map(|&kind| {
+                    let regex = Regex::new(kind.pattern()).expect("Invalid built-in PII pattern");
+                    (kind, regex)
+                })
+                .collect(),
+            mask,
+        }
+    }
+
+    /// Count the personal data in a line.
+    ///
+    /// Returns the line with masked matches if masking is enabled. Every
+    /// kind is matched after the previous kinds are masked, so a match is
+    /// only counted once.
+    pub fn apply<'a>(&self, line: &'a str, counts: &mut HashMap<PiiKind, usize>) -> Cow<'a, str> {
+        let mut masked = Cow::Borrowed(line);
+        for (kind, regex) in &self.patterns {
+            let candidate = match kind {
+                PiiKind::Email => masked.contains('@'),
+                _ => masked.bytes().any(|b| b.is_ascii_digit()),
+            };
+            if !candidate {
+                continue;
+            }
+            let mut result = String::new();
+            let mut last = 0;
+            let mut pos = 0;
+            while let Some((start, end)) = regex.find_at(&masked, pos) {
+                if end > start && kind.is_valid(&masked[start..end]) {
+                    *counts.entry(*kind).or_insert(0) += 1;
+                    result.push_str(&masked[last..start]);
+                    result.push_str(kind.placeholder());
+                    last = end;
+                    pos = end;
+                } else {
+                    match masked[start..].chars().next() {
+                        Some(c) => pos = start + c.len_utf8(),
+                        None => break,
+                    }
+                }
+            }
+            if last > 0 {
+                result.push_str(&masked[last..]);
+                masked = Cow::Owned(result);
+            }
+        }
+        if self.mask {
+            masked
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+}
+
+/// Write `kind<TAB>count` lines for all kinds of personal data.
+pub fn write_counts(mut writer: impl Write, counts: &HashMap<PiiKind, usize>) -> io::Result<()> {
+    for kind in PiiKind::all() {
+        writeln!(
+            writer,
+            "{}\t{}",
+            kind.name(),
+            counts.get(kind).copied().unwrap_or(0)
+        )?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,83 @@
+//! Differentially private release of counts.
+//!
+//! Counts are released with the Laplace mechanism and only keys whose
+//! noisy count reaches a threshold are kept, so that the presence of rare
+//! keys does not reveal the individual they stem from. Every individual
+//! has to contribute at most one to the counts of at most `sensitivity`
+//! keys, e.g. the `Counts::contributions` of documents.
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+
+use crate::rng::Rng;
+
+/// Parameters of the release.
+#[derive(Clone, Copy, Debug)]
+pub struct DpParams {
+    pub epsilon: f64,
+    pub delta: f64,
+    /// Maximum number of keys that a single individual contributes to,
+    /// adding one to each.
+    pub sensitivity: usize,
+}
+
+impl DpParams {
+    /// Get the scale of the Laplace noise.
+    pub fn scale(&self) -> f64 {
+        self.sensitivity as f64 / self.epsilon
+    }
+
+    /// Get the minimum noisy count of released keys.
+    ///
+    /// An individual contributes to at most `sensitivity` keys, the
+    /// threshold bounds the probability that any of the keys that only
+    /// they contribute is released by `delta`.
+    pub fn threshold(&self) -> f64 {
+        1. + self.scale() * (self.sensitivity as f64 / (2. * self.delta)).ln()
+    }
+}
+
+/// Get a seed from the randomly keyed hasher of the standard library.
+pub fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Sample from the Laplace distribution with the given scale.
+fn laplace(rng: &mut Rng, scale: f64) -> f64 {
+    // Uniform in (-0.5, 0.5), zero is excluded to avoid ln(0).
+    let u = loop {
+        let u = rng.next_f64() - 0.5;
+        if u != -0.5 {
+            break u;
+        }
+    };
+    -scale * u.signum() * (1. - 2. * u.abs()).ln()
+}
+
+/// Release counts with Laplace noise, dropping keys below the threshold.
+///
+/// Noisy counts are rounded to the nearest integer.
+pub fn release(
+    counts: HashMap<String, usize>,
+    params: &DpParams,
+    rng: &mut Rng,
+) -> HashMap<String, usize> {
+    let scale = params.scale();
+    let threshold = params.threshold();
+    // Keys are noised in a fixed order, so that a seed reproduces the
+    // release.
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_unstable();
+    counts
+        .into_iter()
+        .filter_map(|(key, count)| {
+            let noisy = count as f64 + laplace(rng, scale);
+            if noisy >= threshold {
+                Some((key, noisy.round() as usize))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
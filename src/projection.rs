@@ -0,0 +1,64 @@
+//! Projection of vocabulary growth.
+//!
+//! The vocabulary size is modeled with Heaps' law `V = k * N^beta`, fitted
+//! by least squares in log-log space to the points of the growth curve.
+
+/// Heaps' law fitted to a growth curve.
+#[derive(Clone, Copy, Debug)]
+pub struct HeapsFit {
+    pub k: f64,
+    pub beta: f64,
+}
+
+impl HeapsFit {
+    /// Fit Heaps' law to `(tokens, types)` points.
+    ///
+    /// Returns `None` if there are fewer than two distinct points.
+    pub fn fit(points: &[(usize, usize)]) -> Option<Self> {
+        let points = points
+            .iter()
+            .filter(|&&(tokens, types)| tokens > 0 && types > 0)
+            .map(|&(tokens, types)| ((tokens as f64).ln(), (types as f64).ln()))
+            .collect::<Vec<_>>();
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let (mut cov, mut var) = (0., 0.);
+        for (x, y) in &points {
+            cov += (x - mean_x) * (y - mean_y);
+            var += (x - mean_x) * (x - mean_x);
+        }
+        if points.len() < 2 || var == 0. {
+            return None;
+        }
+        let beta = cov / var;
+        Some(HeapsFit {
+            k: (mean_y - beta * mean_x).exp(),
+            beta,
+        })
+    }
+
+    /// Get the number of tokens needed for a vocabulary of `types` types.
+    pub fn tokens_for_types(&self, types: f64) -> f64 {
+        (types / self.k).powf(1. / self.beta)
+    }
+
+    /// Get the coverage after `tokens` tokens.
+    ///
+    /// The coverage is the probability that the next token was seen
+    /// before, i.e. one minus the rate of new types `k * beta * N^(beta - 1)`.
+    pub fn coverage(&self, tokens: f64) -> f64 {
+        1. - self.k * self.beta * tokens.powf(self.beta - 1.)
+    }
+
+    /// Get the number of tokens needed to reach a coverage.
+    ///
+    /// Returns `None` if the coverage cannot be reached, i.e. if the
+    /// vocabulary does not saturate.
+    pub fn tokens_for_coverage(&self, coverage: f64) -> Option<f64> {
+        if self.beta <= 0. || self.beta >= 1. {
+            return None;
+        }
+        Some(((1. - coverage) / (self.k * self.beta)).powf(1. / (self.beta - 1.)))
+    }
+}
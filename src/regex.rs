@@ -0,0 +1,709 @@
+//! Regular expressions.
+//!
+//! Patterns are compiled to a program for a backtracking machine that
+//! remembers visited states, so matching is linear in the product of the
+//! program and text length. Matches follow the leftmost-first semantics of
+//! Perl-style engines. Repetitions of sub-patterns that match the empty
+//! string, like `(a|)*`, can end at a different iteration than in Perl or
+//! Python, which changes the match or the captured spans.
+//!
+//! Supported syntax:
+//!
+//! * Literals, `.` (any character but newline) and escapes `\.`, `\t`,
+//!   `\n`, `\\` etc.
+//! * Classes `[a-z]`, `[^0-9]` and the ASCII classes `\d`, `\w`, `\s`
+//!   with their negations `\D`, `\W`, `\S`.
+//! * Groups `(...)`, non-capturing groups `(?:...)` and alternation `|`.
+//! * Greedy and lazy repetition `*`, `+`, `?`, `{n}`, `{n,}`, `{n,m}`.
+//! * Anchors `^`, `$` and word boundaries `\b`, `\B`.
+//! * Case-insensitive matching through a leading `(?i)`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::tokenize::Tokenizer;
+
+/// Maximum number of repetitions in counted repetitions.
+const MAX_REPEAT: usize = 1000;
+
+/// A compiled regular expression.
+#[derive(Clone)]
+pub struct Regex {
+    pattern: String,
+    prog: Vec<Inst>,
+    /// Number of capture groups, including the whole match.
+    groups: usize,
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Regex").field(&self.pattern).finish()
+    }
+}
+
+impl FromStr for Regex {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Regex::new(s)
+    }
+}
+
+/// Spans of the capture groups of a match.
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let (fold, rest) = match pattern.strip_prefix("(?i)") {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let mut parser = Parser {
+            chars: rest.chars().collect(),
+            pos: 0,
+            fold,
+            groups: 1,
+        };
+        let node = parser.parse_alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err(format!("Unmatched ')' in pattern: {}", pattern));
+        }
+        let mut compiler = Compiler { prog: Vec::new() };
+        compiler.emit(Inst::Save(0));
+        compiler.compile(&node);
+        compiler.emit(Inst::Save(1));
+        compiler.emit(Inst::Match);
+        Ok(Regex {
+            pattern: pattern.to_owned(),
+            prog: compiler.prog,
+            groups: parser.groups,
+        })
+    }
+
+    /// Check whether the expression matches all of `text`.
+    pub fn is_full_match(&self, text: &str) -> bool {
+        self.full_captures(text).is_some()
+    }
+
+    /// Get the capture groups if the expression matches all of `text`.
+    pub fn full_captures(&self, text: &str) -> Option<Captures> {
+        Matcher::new(self, text, true).run(0)
+    }
+
+    /// Find the leftmost match starting at or after byte offset `start`.
+    pub fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+        let mut matcher = Matcher::new(self, text, false);
+        let mut pos = start;
+        loop {
+            if let Some(caps) = matcher.run(pos) {
+                return caps[0];
+            }
+            pos += text[pos..].chars().next()?.len_utf8();
+        }
+    }
+}
+
+/// Non-overlapping, non-empty matches are the tokens.
+impl Tokenizer for Regex {
+    fn tokenize(&self, line: &str, token: &mut dyn FnMut(&str)) {
+        let mut pos = 0;
+        while let Some((start, end)) = self.find_at(line, pos) {
+            if end > start {
+                token(&line[start..end]);
+                pos = end;
+            } else {
+                match line[end..].chars().next() {
+                    Some(c) => pos = end + c.len_utf8(),
+                    None => break,
+                }
+            }
+        }
+    }
+}
+
+/// Expand a replacement with the capture groups of a match in `text`.
+///
+/// `$1` or `${1}` in the replacement is substituted with the text of the
+/// capture group, `$$` is a literal `$`.
+pub fn expand(replacement: &str, text: &str, caps: &Captures, result: &mut String) {
+    let mut rest = replacement;
+    while let Some(idx) = rest.find('$') {
+        result.push_str(&rest[..idx]);
+        rest = &rest[idx + 1..];
+        if let Some(stripped) = rest.strip_prefix('$') {
+            result.push('$');
+            rest = stripped;
+            continue;
+        }
+        let (digits, after) = if let Some(braced) = rest.strip_prefix('{') {
+            match braced.find('}') {
+                Some(close) => (&braced[..close], &braced[close + 1..]),
+                None => ("", rest),
+            }
+        } else {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            (&rest[..len], &rest[len..])
+        };
+        match digits.parse::<usize>() {
+            Ok(group) => {
+                if let Some(Some((start, end))) = caps.get(group) {
+                    result.push_str(&text[*start..*end]);
+                }
+                rest = after;
+            }
+            Err(_) => result.push('$'),
+        }
+    }
+    result.push_str(rest);
+}
+
+/// Fuzzing entry point.
+///
+/// The bytes up to the first zero byte are the pattern, the remaining bytes
+/// are the text. Compiling and matching must not panic, and matches have to
+/// be spans of the text on character boundaries.
+#[doc(hidden)]
+pub fn fuzz_regex(data: &[u8]) {
+    let split = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    let pattern = String::from_utf8_lossy(&data[..split]);
+    let text = String::from_utf8_lossy(data.get(split + 1..).unwrap_or_default());
+    let regex = match Regex::new(&pattern) {
+        Ok(regex) => regex,
+        Err(_) => return,
+    };
+    if let Some(caps) = regex.full_captures(&text) {
+        assert_eq!(
+            caps[0],
+            Some((0, text.len())),
+            "full match of {:?}",
+            pattern
+        );
+    }
+    for (start, _) in text.char_indices().chain(Some((text.len(), ' '))) {
+        if let Some((match_start, end)) = regex.find_at(&text, start) {
+            assert!(start <= match_start && match_start <= end && end <= text.len());
+            assert!(text.is_char_boundary(match_start) && text.is_char_boundary(end));
+        }
+    }
+    regex.tokenize(&text, &mut |token| assert!(!token.is_empty()));
+}
+
+/// Set of characters.
+#[derive(Clone, Debug)]
+struct Class {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+    fold: bool,
+}
+
+impl Class {
+    fn single(c: char, fold: bool) -> Self {
+        Class {
+            ranges: vec![(c, c)],
+            negated: false,
+            fold,
+        }
+    }
+
+    fn contains(&self, c: char) -> bool {
+        let in_ranges = |c: char| self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+        let mut found = in_ranges(c);
+        if !found && self.fold {
+            found = c.to_lowercase().any(in_ranges) || c.to_uppercase().any(in_ranges);
+        }
+        found != self.negated
+    }
+}
+
+fn digit_ranges() -> Vec<(char, char)> {
+    vec![('0', '9')]
+}
+
+fn word_ranges() -> Vec<(char, char)> {
+    vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')]
+}
+
+fn space_ranges() -> Vec<(char, char)> {
+    vec![('\t', '\r'), (' ', ' ')]
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Empty,
+    Class(Class),
+    Any,
+    Start,
+    End,
+    WordBoundary(bool),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    fold: bool,
+    groups: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, String> {
+        let mut alternatives = vec![self.parse_concat()?];
+        while self.eat('|') {
+            alternatives.push(self.parse_concat()?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            Node::Alt(alternatives)
+        })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_repeat(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap(),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn parse_repeat(&mut self, mut node: Node) -> Result<Node, String> {
+        loop {
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => match self.parse_counts()? {
+                    Some(counts) => counts,
+                    None => return Ok(node),
+                },
+                _ => return Ok(node),
+            };
+            self.pos += 1;
+            let greedy = !self.eat('?');
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+                greedy,
+            };
+        }
+    }
+
+    /// Parse `{n}`, `{n,}` or `{n,m}`, returns `None` and treats the brace
+    /// as literal if it does not start a counted repetition.
+    fn parse_counts(&mut self) -> Result<Option<(usize, Option<usize>)>, String> {
+        let start = self.pos;
+        self.pos += 1;
+        let min = self.parse_number();
+        let max = if self.eat(',') {
+            self.parse_number()
+        } else {
+            min
+        };
+        let (min, max) = match (min, self.peek()) {
+            (Some(min), Some('}')) => (min, max),
+            _ => {
+                self.pos = start;
+                return Ok(None);
+            }
+        };
+        if max.map(|max| max < min).unwrap_or(false) {
+            return Err(format!("Invalid repetition {{{},{}}}", min, max.unwrap()));
+        }
+        if max.unwrap_or(min) > MAX_REPEAT {
+            return Err(format!("Repetition exceeds {}", MAX_REPEAT));
+        }
+        // Leave the position on the closing brace, it is consumed as the
+        // quantifier.
+        Ok(Some((min, max)))
+    }
+
+    fn parse_number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some('(') => {
+                let index = if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups - 1)
+                };
+                let node = self.parse_alternation()?;
+                if !self.eat(')') {
+                    return Err("Unclosed group".to_string());
+                }
+                Ok(Node::Group(Box::new(node), index))
+            }
+            Some('[') => self.parse_class(),
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Start),
+            Some('$') => Ok(Node::End),
+            Some('\\') => self.parse_escape(),
+            Some(c) if c == '*' || c == '+' || c == '?' => {
+                Err(format!("Repetition operator '{}' without operand", c))
+            }
+            Some(c) => Ok(Node::Class(Class::single(c, self.fold))),
+            None => Err("Unexpected end of pattern".to_string()),
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, String> {
+        let c = self.next().ok_or("Trailing backslash")?;
+        let (ranges, negated) = match c {
+            'b' => return Ok(Node::WordBoundary(true)),
+            'B' => return Ok(Node::WordBoundary(false)),
+            'd' => (digit_ranges(), false),
+            'D' => (digit_ranges(), true),
+            'w' => (word_ranges(), false),
+            'W' => (word_ranges(), true),
+            's' => (space_ranges(), false),
+            'S' => (space_ranges(), true),
+            c => {
+                let c = escaped_char(c)?;
+                return Ok(Node::Class(Class::single(c, self.fold)));
+            }
+        };
+        Ok(Node::Class(Class {
+            ranges,
+            negated,
+            fold: false,
+        }))
+    }
+
+    fn parse_class(&mut self) -> Result<Node, String> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.next().ok_or("Unclosed character class")?;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let lo = if c == '\\' {
+                let c = self.next().ok_or("Trailing backslash")?;
+                match c {
+                    'd' => {
+                        ranges.extend(digit_ranges());
+                        continue;
+                    }
+                    'w' => {
+                        ranges.extend(word_ranges());
+                        continue;
+                    }
+                    's' => {
+                        ranges.extend(space_ranges());
+                        continue;
+                    }
+                    c => escaped_char(c)?,
+                }
+            } else {
+                c
+            };
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                let hi = match self.next().ok_or("Unclosed character class")? {
+                    '\\' => escaped_char(self.next().ok_or("Trailing backslash")?)?,
+                    c => c,
+                };
+                if hi < lo {
+                    return Err(format!("Invalid class range {}-{}", lo, hi));
+                }
+                ranges.push((lo, hi));
+            } else {
+                ranges.push((lo, lo));
+            }
+        }
+        Ok(Node::Class(Class {
+            ranges,
+            negated,
+            fold: self.fold,
+        }))
+    }
+}
+
+fn escaped_char(c: char) -> Result<char, String> {
+    match c {
+        't' => Ok('\t'),
+        'n' => Ok('\n'),
+        'r' => Ok('\r'),
+        c if c.is_ascii_alphanumeric() => Err(format!("Unknown escape \\{}", c)),
+        c => Ok(c),
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Inst {
+    Class(Class),
+    Any,
+    Start,
+    End,
+    WordBoundary(bool),
+    /// Continue at both targets, preferring the first.
+    Split(usize, usize),
+    Jmp(usize),
+    Save(usize),
+    Match,
+}
+
+struct Compiler {
+    prog: Vec<Inst>,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> usize {
+        self.prog.push(inst);
+        self.prog.len() - 1
+    }
+
+    fn compile(&mut self, node: &Node) {
+        match node {
+            Node::Empty => (),
+            Node::Class(class) => {
+                self.emit(Inst::Class(class.clone()));
+            }
+            Node::Any => {
+                self.emit(Inst::Any);
+            }
+            Node::Start => {
+                self.emit(Inst::Start);
+            }
+            Node::End => {
+                self.emit(Inst::End);
+            }
+            Node::WordBoundary(boundary) => {
+                self.emit(Inst::WordBoundary(*boundary));
+            }
+            Node::Group(node, index) => match index {
+                Some(index) => {
+                    self.emit(Inst::Save(2 * index));
+                    self.compile(node);
+                    self.emit(Inst::Save(2 * index + 1));
+                }
+                None => self.compile(node),
+            },
+            Node::Concat(nodes) => nodes.iter().for_each(|node| self.compile(node)),
+            Node::Alt(alternatives) => {
+                let mut jumps = Vec::new();
+                for (idx, alternative) in alternatives.iter().enumerate() {
+                    if idx + 1 < alternatives.len() {
+                        let split = self.emit(Inst::Split(0, 0));
+                        self.compile(alternative);
+                        jumps.push(self.emit(Inst::Jmp(0)));
+                        let next = self.prog.len();
+                        self.prog[split] = Inst::Split(split + 1, next);
+                    } else {
+                        self.compile(alternative);
+                    }
+                }
+                let end = self.prog.len();
+                for jump in jumps {
+                    self.prog[jump] = Inst::Jmp(end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.compile(node);
+                }
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0));
+                        self.compile(node);
+                        self.emit(Inst::Jmp(split));
+                        let next = self.prog.len();
+                        self.prog[split] = self.split(split + 1, next, *greedy);
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.emit(Inst::Split(0, 0)));
+                            self.compile(node);
+                        }
+                        let end = self.prog.len();
+                        for split in splits {
+                            self.prog[split] = self.split(split + 1, end, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn split(&self, body: usize, next: usize, greedy: bool) -> Inst {
+        if greedy {
+            Inst::Split(body, next)
+        } else {
+            Inst::Split(next, body)
+        }
+    }
+}
+
+enum Job {
+    Explore(usize, usize),
+    Restore(usize, Option<usize>),
+}
+
+/// Backtracking matcher with a bitset of visited states.
+///
+/// A state that was visited before failed already, so the bitset can be
+/// shared between start positions.
+struct Matcher<'a> {
+    regex: &'a Regex,
+    text: &'a str,
+    anchored: bool,
+    visited: Vec<u64>,
+    slots: Vec<Option<usize>>,
+    jobs: Vec<Job>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(regex: &'a Regex, text: &'a str, anchored: bool) -> Self {
+        let states = regex.prog.len() * (text.len() + 1);
+        Matcher {
+            regex,
+            text,
+            anchored,
+            visited: vec![0; states.div_ceil(64)],
+            slots: vec![None; 2 * regex.groups],
+            jobs: Vec::new(),
+        }
+    }
+
+    fn visit(&mut self, pc: usize, pos: usize) -> bool {
+        let state = pc * (self.text.len() + 1) + pos;
+        let (word, bit) = (state / 64, 1 << (state % 64));
+        let visited = self.visited[word] & bit != 0;
+        self.visited[word] |= bit;
+        !visited
+    }
+
+    fn run(&mut self, start: usize) -> Option<Captures> {
+        self.jobs.clear();
+        self.jobs.push(Job::Explore(0, start));
+        while let Some(job) = self.jobs.pop() {
+            let (mut pc, mut pos) = match job {
+                Job::Explore(pc, pos) => (pc, pos),
+                Job::Restore(slot, value) => {
+                    self.slots[slot] = value;
+                    continue;
+                }
+            };
+            loop {
+                if !self.visit(pc, pos) {
+                    break;
+                }
+                match &self.regex.prog[pc] {
+                    Inst::Class(class) => match self.text[pos..].chars().next() {
+                        Some(c) if class.contains(c) => {
+                            pos += c.len_utf8();
+                            pc += 1;
+                        }
+                        _ => break,
+                    },
+                    Inst::Any => match self.text[pos..].chars().next() {
+                        Some(c) if c != '\n' => {
+                            pos += c.len_utf8();
+                            pc += 1;
+                        }
+                        _ => break,
+                    },
+                    Inst::Start if pos == 0 => pc += 1,
+                    Inst::End if pos == self.text.len() => pc += 1,
+                    Inst::Start | Inst::End => break,
+                    Inst::WordBoundary(boundary) => {
+                        let before = self.text[..pos].chars().next_back().map(is_word_char);
+                        let after = self.text[pos..].chars().next().map(is_word_char);
+                        let at_boundary = before.unwrap_or(false) != after.unwrap_or(false);
+                        if at_boundary != *boundary {
+                            break;
+                        }
+                        pc += 1;
+                    }
+                    Inst::Split(first, second) => {
+                        self.jobs.push(Job::Explore(*second, pos));
+                        pc = *first;
+                    }
+                    Inst::Jmp(target) => pc = *target,
+                    Inst::Save(slot) => {
+                        self.jobs.push(Job::Restore(*slot, self.slots[*slot]));
+                        self.slots[*slot] = Some(pos);
+                        pc += 1;
+                    }
+                    Inst::Match => {
+                        if self.anchored && pos != self.text.len() {
+                            break;
+                        }
+                        return Some(
+                            self.slots
+                                .chunks(2)
+                                .map(|span| match (span[0], span[1]) {
+                                    (Some(start), Some(end)) => Some((start, end)),
+                                    _ => None,
+                                })
+                                .collect(),
+                        );
+                    }
+                }
+            }
+        }
+        None
+    }
+}
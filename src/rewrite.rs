@@ -0,0 +1,72 @@
+//! Token rewrite rules.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+use crate::regex::{self, Regex};
+
+/// Rewrite rules for tokens.
+///
+/// Tokens are looked up in the mapping first, otherwise the first regular
+/// expression matching the whole token is applied.
+#[derive(Clone, Debug, Default)]
+pub struct Rewriter {
+    mapping: HashMap<String, String>,
+    rules: Vec<(Regex, String)>,
+}
+
+impl Rewriter {
+    /// Read rewrite rules.
+    ///
+    /// Every line is a `from<TAB>to` pair. If `from` is enclosed in slashes,
+    /// it is a regular expression and `to` can refer to its groups as `$1`.
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        let mut rewriter = Rewriter::default();
+        for line in reader.lines() {
+            let line = line?;
+            let mut parts = line.splitn(2, '\t');
+            let (from, to) = match (parts.next(), parts.next()) {
+                (Some(from), Some(to)) if !from.is_empty() => (from, to),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid rewrite rule: {}", line),
+                    ))
+                }
+            };
+            match from
+                .strip_prefix('/')
+                .and_then(|from| from.strip_suffix('/'))
+            {
+                Some(pattern) => {
+                    let regex = Regex::new(pattern)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    rewriter.rules.push((regex, to.to_owned()));
+                }
+                None => {
+                    rewriter.mapping.insert(from.to_owned(), to.to_owned());
+                }
+            }
+        }
+        Ok(rewriter)
+    }
+
+    /// Rewrite a token.
+    ///
+    /// Returns `None` if no rule applies, the result is empty if the token
+    /// is removed.
+    pub fn rewrite(&self, token: &str) -> Option<Cow<'_, str>> {
+        if let Some(to) = self.mapping.get(token) {
+            return Some(Cow::Borrowed(to));
+        }
+        for (regex, replacement) in &self.rules {
+            if let Some(caps) = regex.full_captures(token) {
+                let mut rewritten = String::new();
+                regex::expand(replacement, token, &caps, &mut rewritten);
+                return Some(Cow::Owned(rewritten));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,252 @@
+//! Compressed bitmaps of document ids.
+//!
+//! Bitmaps are Roaring bitmaps (Lemire et al., 2016): ids are split into
+//! chunks of their 16 high bits, and the low bits of a chunk are stored as
+//! a sorted array of at most 4096 values or as a bitmap of 2^16 bits.
+//!
+//! Bitmaps are serialized in the portable format of the Roaring
+//! implementations without run containers, so that they can be read by
+//! CRoaring, pyroaring or the `roaring` crate: the cookie 12346 and the
+//! number of containers as little-endian 32-bit integers, the key and the
+//! cardinality minus 1 of every container as little-endian 16-bit
+//! integers, the byte offset of every container as a little-endian 32-bit
+//! integer, followed by the containers. Array containers are their values
+//! as little-endian 16-bit integers, bitmap containers 1024 little-endian
+//! 64-bit words.
+
+use std::io::{self, Read, Write};
+
+use crate::vocab::invalid_data;
+
+const SERIAL_COOKIE_NO_RUNCONTAINER: u32 = 12346;
+const SERIAL_COOKIE: u16 = 12347;
+
+/// Maximum cardinality of array containers.
+const ARRAY_MAX: usize = 4096;
+const BITMAP_WORDS: usize = 1024;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Container {
+    Array(Vec<u16>),
+    Bitmap(Box<[u64; BITMAP_WORDS]>, usize),
+}
+
+impl Container {
+    fn len(&self) -> usize {
+        match self {
+            Container::Array(values) => values.len(),
+            Container::Bitmap(_, len) => *len,
+        }
+    }
+
+    fn insert(&mut self, value: u16) -> bool {
+        match self {
+            Container::Array(values) => match values.binary_search(&value) {
+                Ok(_) => false,
+                Err(idx) => {
+                    values.insert(idx, value);
+                    if values.len() > ARRAY_MAX {
+                        let mut words = Box::new([0; BITMAP_WORDS]);
+                        for &value in values.iter() {
+                            words[value as usize / 64] |= 1 << (value % 64);
+                        }
+                        *self = Container::Bitmap(words, ARRAY_MAX + 1);
+                    }
+                    true
+                }
+            },
+            Container::Bitmap(words, len) => {
+                let word = &mut words[value as usize / 64];
+                let bit = 1 << (value % 64);
+                if *word & bit != 0 {
+                    return false;
+                }
+                *word |= bit;
+                *len += 1;
+                true
+            }
+        }
+    }
+
+    fn contains(&self, value: u16) -> bool {
+        match self {
+            Container::Array(values) => values.binary_search(&value).is_ok(),
+            Container::Bitmap(words, _) => words[value as usize / 64] & (1 << (value % 64)) != 0,
+        }
+    }
+
+    fn values(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Container::Array(values) => Box::new(values.iter().copied()),
+            Container::Bitmap(words, _) => Box::new(
+                (0..=u16::MAX)
+                    .filter(move |&value| words[value as usize / 64] & (1 << (value % 64)) != 0),
+            ),
+        }
+    }
+
+    fn serialized_len(&self) -> usize {
+        match self {
+            Container::Array(values) => 2 * values.len(),
+            Container::Bitmap(..) => 8 * BITMAP_WORDS,
+        }
+    }
+}
+
+/// A compressed bitmap of 32-bit integers.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RoaringBitmap {
+    containers: Vec<(u16, Container)>,
+}
+
+impl RoaringBitmap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a value, returns `false` if it was already present.
+    ///
+    /// Inserting values in ascending order is fastest.
+    pub fn insert(&mut self, value: u32) -> bool {
+        let (key, low) = ((value >> 16) as u16, value as u16);
+        let idx = match self.containers.last() {
+            Some((last, _)) if *last == key => self.containers.len() - 1,
+            _ => match self.containers.binary_search_by_key(&key, |(key, _)| *key) {
+                Ok(idx) => idx,
+                Err(idx) => {
+                    self.containers
+                        .insert(idx, (key, Container::Array(Vec::new())));
+                    idx
+                }
+            },
+        };
+        self.containers[idx].1.insert(low)
+    }
+
+    /// Check whether the bitmap contains a value.
+    pub fn contains(&self, value: u32) -> bool {
+        let (key, low) = ((value >> 16) as u16, value as u16);
+        match self.containers.binary_search_by_key(&key, |(key, _)| *key) {
+            Ok(idx) => self.containers[idx].1.contains(low),
+            Err(_) => false,
+        }
+    }
+
+    /// Get the number of values.
+    pub fn len(&self) -> u64 {
+        self.containers
+            .iter()
+            .map(|(_, container)| container.len() as u64)
+            .sum()
+    }
+
+    /// Check whether the bitmap has no values.
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+
+    /// Get the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.containers.iter().flat_map(|(key, container)| {
+            container
+                .values()
+                .map(move |low| (u32::from(*key) << 16) | u32::from(low))
+        })
+    }
+
+    /// Get the length of the serialization in bytes.
+    pub fn serialized_len(&self) -> usize {
+        8 + self
+            .containers
+            .iter()
+            .map(|(_, container)| 8 + container.serialized_len())
+            .sum::<usize>()
+    }
+
+    /// Write the bitmap in the portable format.
+    pub fn serialize(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(&SERIAL_COOKIE_NO_RUNCONTAINER.to_le_bytes())?;
+        writer.write_all(&(self.containers.len() as u32).to_le_bytes())?;
+        for (key, container) in &self.containers {
+            writer.write_all(&key.to_le_bytes())?;
+            writer.write_all(&((container.len() - 1) as u16).to_le_bytes())?;
+        }
+        let mut offset = 8 + 8 * self.containers.len();
+        for (_, container) in &self.containers {
+            writer.write_all(&(offset as u32).to_le_bytes())?;
+            offset += container.serialized_len();
+        }
+        for (_, container) in &self.containers {
+            match container {
+                Container::Array(values) => {
+                    for value in values {
+                        writer.write_all(&value.to_le_bytes())?;
+                    }
+                }
+                Container::Bitmap(words, _) => {
+                    for word in words.iter() {
+                        writer.write_all(&word.to_le_bytes())?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a bitmap in the portable format without run containers.
+    pub fn deserialize(mut reader: impl Read) -> io::Result<Self> {
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf)?;
+        let cookie = u32::from_le_bytes(buf);
+        if cookie as u16 == SERIAL_COOKIE {
+            return Err(invalid_data(
+                "Roaring bitmaps with run containers are not supported".to_owned(),
+            ));
+        } else if cookie != SERIAL_COOKIE_NO_RUNCONTAINER {
+            return Err(invalid_data("Not a Roaring bitmap".to_owned()));
+        }
+        reader.read_exact(&mut buf)?;
+        let n_containers = u32::from_le_bytes(buf) as usize;
+        if n_containers > 1 << 16 {
+            return Err(invalid_data("Too many Roaring containers".to_owned()));
+        }
+        let mut header = vec![0; 4 * n_containers];
+        reader.read_exact(&mut header)?;
+        // The offsets are implied by the cardinalities.
+        let mut offsets = vec![0; 4 * n_containers];
+        reader.read_exact(&mut offsets)?;
+
+        let mut containers = Vec::with_capacity(n_containers);
+        for entry in header.chunks_exact(4) {
+            let key = u16::from_le_bytes([entry[0], entry[1]]);
+            let len = u16::from_le_bytes([entry[2], entry[3]]) as usize + 1;
+            let container = if len <= ARRAY_MAX {
+                let mut bytes = vec![0; 2 * len];
+                reader.read_exact(&mut bytes)?;
+                Container::Array(
+                    bytes
+                        .chunks_exact(2)
+                        .map(|value| u16::from_le_bytes([value[0], value[1]]))
+                        .collect(),
+                )
+            } else {
+                let mut words = Box::new([0; BITMAP_WORDS]);
+                let mut word = [0; 8];
+                for slot in words.iter_mut() {
+                    reader.read_exact(&mut word)?;
+                    *slot = u64::from_le_bytes(word);
+                }
+                Container::Bitmap(words, len)
+            };
+            if containers
+                .last()
+                .map(|(last, _)| *last >= key)
+                .unwrap_or(false)
+            {
+                return Err(invalid_data("Unsorted Roaring containers".to_owned()));
+            }
+            containers.push((key, container));
+        }
+        Ok(RoaringBitmap { containers })
+    }
+}
@@ -0,0 +1,48 @@
+//! Noise distributions for negative sampling.
+//!
+//! Follows word2vec, which samples negative tokens from the unigram
+//! distribution raised to a power, usually 0.75.
+
+/// Get the smoothed unigram probabilities of counts.
+///
+/// The probabilities are proportional to `count^power`.
+pub fn smoothed_probs(counts: &[usize], power: f64) -> Vec<f64> {
+    let weights = counts
+        .iter()
+        .map(|&count| (count as f64).powf(power))
+        .collect::<Vec<_>>();
+    let total = weights.iter().sum::<f64>();
+    weights
+        .into_iter()
+        .map(|weight| if total > 0. { weight / total } else { 0. })
+        .collect()
+}
+
+/// Build word2vec's unigram table of `size` token indices.
+///
+/// Every index occurs about `size` times its smoothed probability, so that
+/// negative tokens are sampled by drawing uniform positions of the table.
+/// The table is filled exactly as by `InitUnigramTable` of word2vec.
+pub fn unigram_table(counts: &[usize], power: f64, size: usize) -> Vec<u32> {
+    if counts.is_empty() {
+        return Vec::new();
+    }
+    let total = counts
+        .iter()
+        .map(|&count| (count as f64).powf(power))
+        .sum::<f64>();
+    let mut table = Vec::with_capacity(size);
+    let mut idx = 0;
+    let mut cumulative = (counts[0] as f64).powf(power) / total;
+    for pos in 0..size {
+        table.push(idx as u32);
+        if pos as f64 / size as f64 > cumulative {
+            idx += 1;
+            if idx < counts.len() {
+                cumulative += (counts[idx] as f64).powf(power) / total;
+            }
+        }
+        idx = idx.min(counts.len() - 1);
+    }
+    table
+}
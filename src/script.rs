@@ -0,0 +1,260 @@
+//! Unicode scripts of characters and tokens.
+//!
+//! The table covers the blocks of the major scripts, which is sufficient
+//! to spot tokens of unexpected scripts. Characters outside these blocks,
+//! such as digits, punctuation and symbols, belong to the common script.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Unicode script.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Script {
+    Arabic,
+    Armenian,
+    Bengali,
+    Bopomofo,
+    Cherokee,
+    Cyrillic,
+    Devanagari,
+    Ethiopic,
+    Georgian,
+    Greek,
+    Gujarati,
+    Gurmukhi,
+    Han,
+    Hangul,
+    Hebrew,
+    Hiragana,
+    Kannada,
+    Katakana,
+    Khmer,
+    Lao,
+    Latin,
+    Malayalam,
+    Mongolian,
+    Myanmar,
+    Oriya,
+    Sinhala,
+    Syriac,
+    Tamil,
+    Telugu,
+    Thaana,
+    Thai,
+    Tibetan,
+    /// Combining marks, which take the script of their base character.
+    Inherited,
+    /// Characters shared between scripts.
+    Common,
+}
+
+/// Script of a token.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TokenScript {
+    /// All characters that are not common or inherited belong to the
+    /// script.
+    Single(Script),
+    /// The token contains characters of multiple scripts.
+    Mixed,
+    /// The token only contains common and inherited characters.
+    Common,
+}
+
+impl fmt::Display for TokenScript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenScript::Single(script) => script.fmt(f),
+            TokenScript::Mixed => f.write_str("Mixed"),
+            TokenScript::Common => f.write_str("Common"),
+        }
+    }
+}
+
+use Script::*;
+
+/// Character ranges of the scripts, sorted by start.
+static RANGES: &[(u32, u32, Script)] = &[
+    (0x0041, 0x005A, Latin),
+    (0x0061, 0x007A, Latin),
+    (0x00AA, 0x00AA, Latin),
+    (0x00BA, 0x00BA, Latin),
+    (0x00C0, 0x00D6, Latin),
+    (0x00D8, 0x00F6, Latin),
+    (0x00F8, 0x02AF, Latin),
+    (0x0300, 0x036F, Inherited),
+    (0x0370, 0x03FF, Greek),
+    (0x0400, 0x052F, Cyrillic),
+    (0x0531, 0x058F, Armenian),
+    (0x0591, 0x05FF, Hebrew),
+    (0x0600, 0x06FF, Arabic),
+    (0x0700, 0x074F, Syriac),
+    (0x0750, 0x077F, Arabic),
+    (0x0780, 0x07BF, Thaana),
+    (0x08A0, 0x08FF, Arabic),
+    (0x0900, 0x097F, Devanagari),
+    (0x0980, 0x09FF, Bengali),
+    (0x0A00, 0x0A7F, Gurmukhi),
+    (0x0A80, 0x0AFF, Gujarati),
+    (0x0B00, 0x0B7F, Oriya),
+    (0x0B80, 0x0BFF, Tamil),
+    (0x0C00, 0x0C7F, Telugu),
+    (0x0C80, 0x0CFF, Kannada),
+    (0x0D00, 0x0D7F, Malayalam),
+    (0x0D80, 0x0DFF, Sinhala),
+    (0x0E00, 0x0E7F, Thai),
+    (0x0E80, 0x0EFF, Lao),
+    (0x0F00, 0x0FFF, Tibetan),
+    (0x1000, 0x109F, Myanmar),
+    (0x10A0, 0x10FF, Georgian),
+    (0x1100, 0x11FF, Hangul),
+    (0x1200, 0x139F, Ethiopic),
+    (0x13A0, 0x13FF, Cherokee),
+    (0x1780, 0x17FF, Khmer),
+    (0x1800, 0x18AF, Mongolian),
+    (0x19E0, 0x19FF, Khmer),
+    (0x1AB0, 0x1AFF, Inherited),
+    (0x1C80, 0x1C8F, Cyrillic),
+    (0x1C90, 0x1CBF, Georgian),
+    (0x1D00, 0x1D7F, Latin),
+    (0x1DC0, 0x1DFF, Inherited),
+    (0x1E00, 0x1EFF, Latin),
+    (0x1F00, 0x1FFF, Greek),
+    (0x20D0, 0x20FF, Inherited),
+    (0x2C60, 0x2C7F, Latin),
+    (0x2D00, 0x2D2F, Georgian),
+    (0x2DE0, 0x2DFF, Cyrillic),
+    (0x2E80, 0x2FDF, Han),
+    (0x3005, 0x3005, Han),
+    (0x3007, 0x3007, Han),
+    (0x3021, 0x3029, Han),
+    (0x3038, 0x303B, Han),
+    (0x3041, 0x3096, Hiragana),
+    (0x309D, 0x309F, Hiragana),
+    (0x30A1, 0x30FA, Katakana),
+    (0x30FD, 0x30FF, Katakana),
+    (0x3100, 0x312F, Bopomofo),
+    (0x3130, 0x318F, Hangul),
+    (0x31A0, 0x31BF, Bopomofo),
+    (0x31F0, 0x31FF, Katakana),
+    (0x3400, 0x4DBF, Han),
+    (0x4E00, 0x9FFF, Han),
+    (0xA640, 0xA69F, Cyrillic),
+    (0xA720, 0xA7FF, Latin),
+    (0xA8E0, 0xA8FF, Devanagari),
+    (0xA960, 0xA97F, Hangul),
+    (0xAB30, 0xAB6F, Latin),
+    (0xAC00, 0xD7FF, Hangul),
+    (0xF900, 0xFAFF, Han),
+    (0xFB00, 0xFB06, Latin),
+    (0xFB1D, 0xFB4F, Hebrew),
+    (0xFB50, 0xFDFF, Arabic),
+    (0xFE00, 0xFE0F, Inherited),
+    (0xFE20, 0xFE2F, Inherited),
+    (0xFE70, 0xFEFF, Arabic),
+    (0xFF21, 0xFF3A, Latin),
+    (0xFF41, 0xFF5A, Latin),
+    (0xFF66, 0xFF6F, Katakana),
+    (0xFF71, 0xFF9D, Katakana),
+    (0xFFA0, 0xFFDC, Hangul),
+    (0x20000, 0x2FA1F, Han),
+    (0x30000, 0x323AF, Han),
+    (0xE0100, 0xE01EF, Inherited),
+];
+
+impl Script {
+    /// All scripts.
+    pub const ALL: &'static [Script] = &[
+        Arabic, Armenian, Bengali, Bopomofo, Cherokee, Cyrillic, Devanagari, Ethiopic, Georgian,
+        Greek, Gujarati, Gurmukhi, Han, Hangul, Hebrew, Hiragana, Kannada, Katakana, Khmer, Lao,
+        Latin, Malayalam, Mongolian, Myanmar, Oriya, Sinhala, Syriac, Tamil, Telugu, Thaana, Thai,
+        Tibetan, Inherited, Common,
+    ];
+
+    /// Get the script of a character.
+    pub fn of(c: char) -> Script {
+        let c = c as u32;
+        match RANGES.binary_search_by(|&(start, _, _)| start.cmp(&c)) {
+            Ok(idx) => RANGES[idx].2,
+            Err(0) => Common,
+            Err(idx) => {
+                let (_, end, script) = RANGES[idx - 1];
+                if c <= end {
+                    script
+                } else {
+                    Common
+                }
+            }
+        }
+    }
+
+    /// Get the script of a token.
+    pub fn of_token(token: &str) -> TokenScript {
+        let mut token_script = TokenScript::Common;
+        for c in token.chars() {
+            match (Script::of(c), token_script) {
+                (Common, _) | (Inherited, _) => (),
+                (script, TokenScript::Common) => token_script = TokenScript::Single(script),
+                (script, TokenScript::Single(prev)) if script == prev => (),
+                _ => return TokenScript::Mixed,
+            }
+        }
+        token_script
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Arabic => "Arabic",
+            Armenian => "Armenian",
+            Bengali => "Bengali",
+            Bopomofo => "Bopomofo",
+            Cherokee => "Cherokee",
+            Cyrillic => "Cyrillic",
+            Devanagari => "Devanagari",
+            Ethiopic => "Ethiopic",
+            Georgian => "Georgian",
+            Greek => "Greek",
+            Gujarati => "Gujarati",
+            Gurmukhi => "Gurmukhi",
+            Han => "Han",
+            Hangul => "Hangul",
+            Hebrew => "Hebrew",
+            Hiragana => "Hiragana",
+            Kannada => "Kannada",
+            Katakana => "Katakana",
+            Khmer => "Khmer",
+            Lao => "Lao",
+            Latin => "Latin",
+            Malayalam => "Malayalam",
+            Mongolian => "Mongolian",
+            Myanmar => "Myanmar",
+            Oriya => "Oriya",
+            Sinhala => "Sinhala",
+            Syriac => "Syriac",
+            Tamil => "Tamil",
+            Telugu => "Telugu",
+            Thaana => "Thaana",
+            Thai => "Thai",
+            Tibetan => "Tibetan",
+            Inherited => "Inherited",
+            Common => "Common",
+        }
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Script {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Script::ALL
+            .iter()
+            .copied()
+            .find(|script| script.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown script: {}", s))
+    }
+}
@@ -0,0 +1,324 @@
+//! Sorting of count tables.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::temp::TempDir;
+
+/// Order of items in count tables: by descending count, ties by item.
+///
+/// Ties are broken by the bytes of the items, so the order is total and
+/// output is byte-identical across runs. Items are not normalized, e.g.
+/// the NFC and NFD forms of a token are distinct items ordered by their
+/// encodings.
+pub fn count_order(item1: &str, count1: usize, item2: &str, count2: usize) -> Ordering {
+    count2.cmp(&count1).then_with(|| item1.cmp(item2))
+}
+
+/// Order of items with equal counts in count tables.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Collation {
+    /// Order by codepoints, the order of the UTF-8 bytes of `count_order`.
+    #[default]
+    Codepoint,
+    /// Order by the lowercase forms of the items, items with the same
+    /// lowercase form by codepoints, e.g. `apple`, `Banana`, `banana`.
+    Caseless,
+}
+
+impl FromStr for Collation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "codepoint" => Ok(Collation::Codepoint),
+            "caseless" => Ok(Collation::Caseless),
+            _ if s.starts_with("icu:") => {
+                Err("ICU collation is not supported, use codepoint or caseless".to_owned())
+            }
+            _ => Err(format!("Unknown collation: {}", s)),
+        }
+    }
+}
+
+impl Collation {
+    /// Order of items in count tables: by descending count, ties by the
+    /// collation of the items.
+    pub fn count_order(self, item1: &str, count1: usize, item2: &str, count2: usize) -> Ordering {
+        count2.cmp(&count1).then_with(|| match self {
+            Collation::Codepoint => item1.cmp(item2),
+            Collation::Caseless => {
+                let lower1 = item1.chars().flat_map(char::to_lowercase);
+                let lower2 = item2.chars().flat_map(char::to_lowercase);
+                lower1.cmp(lower2).then_with(|| item1.cmp(item2))
+            }
+        })
+    }
+}
+
+/// External sort of count tables.
+///
+/// Items are sorted in runs of at most `run_len` items which are spilled to
+/// temporary files in `tmp_dir`, the runs are merged while writing. Peak
+/// memory is bounded by the run length rather than the table size.
+pub struct ExternalSort<'a> {
+    run_len: usize,
+    tmp_dir: &'a Path,
+    collation: Collation,
+}
+
+impl<'a> ExternalSort<'a> {
+    /// Sort in runs of `run_len` items.
+    ///
+    /// Panics if `run_len` is zero, no items would fit into a run.
+    pub fn new(run_len: usize, tmp_dir: &'a Path) -> Self {
+        assert!(
+            run_len > 0,
+            "The run length of external sorts cannot be zero."
+        );
+        ExternalSort {
+            run_len,
+            tmp_dir,
+            collation: Collation::default(),
+        }
+    }
+
+    /// Order the items of equal counts by `collation`.
+    pub fn collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
+
+    /// Call `f` with all items with at least `min_count` in count order.
+    pub fn for_each_sorted<I, F>(&self, items: I, min_count: usize, mut f: F) -> io::Result<()>
+    where
+        I: IntoIterator<Item = (String, usize)>,
+        F: FnMut(&str, usize) -> io::Result<()>,
+    {
+        let dir = TempDir::new(self.tmp_dir, "corpus-count-sort")?;
+        let mut runs = Vec::new();
+        let mut run = Vec::with_capacity(self.run_len.min(1 << 20));
+        let mut items = items
+            .into_iter()
+            .filter(|(_, count)| *count >= min_count)
+            .peekable();
+        while items.peek().is_some() {
+            run.extend(items.by_ref().take(self.run_len));
+            run.sort_unstable_by(|(i1, c1), (i2, c2)| self.collation.count_order(i1, *c1, i2, *c2));
+            if runs.is_empty() && items.peek().is_none() {
+                // Everything fits into a single run.
+                return run.iter().try_for_each(|(item, count)| f(item, *count));
+            }
+            let path = dir.path().join(format!("run-{}", runs.len()));
+            let mut writer = BufWriter::new(File::create(&path)?);
+            for (item, count) in run.drain(..) {
+                writeln!(writer, "{}\t{}", count, item)?;
+            }
+            writer.flush()?;
+            runs.push(Run::open(&path, self.collation)?);
+        }
+
+        let mut heap = BinaryHeap::new();
+        for (idx, run) in runs.iter_mut().enumerate() {
+            if let Some(head) = run.next_head(idx)? {
+                heap.push(head);
+            }
+        }
+        while let Some(head) = heap.pop() {
+            f(&head.item, head.count)?;
+            if let Some(next) = runs[head.run].next_head(head.run)? {
+                heap.push(next);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sorted run in a temporary file.
+struct Run {
+    lines: Lines<BufReader<File>>,
+    collation: Collation,
+}
+
+impl Run {
+    fn open(path: &Path, collation: Collation) -> io::Result<Self> {
+        Ok(Run {
+            lines: BufReader::new(File::open(path)?).lines(),
+            collation,
+        })
+    }
+
+    fn next_head(&mut self, run: usize) -> io::Result<Option<RunHead>> {
+        let line = match self.lines.next() {
+            Some(line) => line?,
+            None => return Ok(None),
+        };
+        let (count, item) = line
+            .split_once('\t')
+            .and_then(|(count, item)| Some((count.parse().ok()?, item)))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid sorted run"))?;
+        Ok(Some(RunHead {
+            item: item.to_owned(),
+            count,
+            run,
+            collation: self.collation,
+        }))
+    }
+}
+
+/// Smallest remaining item of a run.
+#[derive(Eq, PartialEq)]
+struct RunHead {
+    item: String,
+    count: usize,
+    run: usize,
+    collation: Collation,
+}
+
+impl Ord for RunHead {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap, reverse to pop the first item in count
+        // order.
+        self.collation
+            .count_order(&other.item, other.count, &self.item, self.count)
+    }
+}
+
+impl PartialOrd for RunHead {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::env;
+
+    use super::{count_order, Collation, ExternalSort};
+
+    fn sorted(mut items: Vec<(String, usize)>) -> Vec<(String, usize)> {
+        items.sort_unstable_by(|(i1, c1), (i2, c2)| count_order(i1, *c1, i2, *c2));
+        items
+    }
+
+    fn items() -> Vec<(String, usize)> {
+        vec![
+            ("b".to_string(), 2),
+            ("a".to_string(), 2),
+            ("c".to_string(), 3),
+            // NFC and NFD forms of "é".
+            ("\u{e9}".to_string(), 1),
+            ("e\u{301}".to_string(), 1),
+            ("d".to_string(), 1),
+        ]
+    }
+
+    #[test]
+    fn descending_count_ties_by_bytes() {
+        assert_eq!(
+            sorted(items()),
+            vec![
+                ("c".to_string(), 3),
+                ("a".to_string(), 2),
+                ("b".to_string(), 2),
+                ("d".to_string(), 1),
+                ("e\u{301}".to_string(), 1),
+                ("\u{e9}".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn order_is_total() {
+        let items = items();
+        for (i1, c1) in &items {
+            for (i2, c2) in &items {
+                let order = count_order(i1, *c1, i2, *c2);
+                assert_eq!(order, count_order(i2, *c2, i1, *c1).reverse());
+                assert_eq!(order == Ordering::Equal, i1 == i2 && c1 == c2);
+            }
+        }
+    }
+
+    #[test]
+    fn order_is_independent_of_input_order() {
+        let expected = sorted(items());
+        let mut items = items();
+        for _ in 0..items.len() {
+            items.rotate_left(1);
+            assert_eq!(sorted(items.clone()), expected);
+            items.reverse();
+            assert_eq!(sorted(items.clone()), expected);
+        }
+    }
+
+    #[test]
+    fn external_sort_matches_in_memory_sort() {
+        let tmp_dir = env::temp_dir();
+        for run_len in 1..=items().len() {
+            let sort = ExternalSort::new(run_len, &tmp_dir);
+            let mut merged = Vec::new();
+            sort.for_each_sorted(items(), 1, |item, count| {
+                merged.push((item.to_string(), count));
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(merged, sorted(items()));
+        }
+    }
+
+    #[test]
+    fn caseless_ties_by_lowercase_forms() {
+        let mut items = ["banana", "Banana", "apple", "Cherry", "b"]
+            .iter()
+            .map(|item| (item.to_string(), 1))
+            .collect::<Vec<_>>();
+        items.push(("zebra".to_string(), 2));
+        let collation = Collation::Caseless;
+        items.sort_unstable_by(|(i1, c1), (i2, c2)| collation.count_order(i1, *c1, i2, *c2));
+        let items = items
+            .iter()
+            .map(|(item, _)| item.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(items, ["zebra", "apple", "b", "Banana", "banana", "Cherry"]);
+    }
+
+    #[test]
+    fn external_sort_uses_the_collation() {
+        let tmp_dir = env::temp_dir();
+        let items = || {
+            ["b", "A", "a", "B"]
+                .iter()
+                .map(|item| (item.to_string(), 1))
+                .collect::<Vec<_>>()
+        };
+        for run_len in 1..=4 {
+            let sort = ExternalSort::new(run_len, &tmp_dir).collation(Collation::Caseless);
+            let mut merged = Vec::new();
+            sort.for_each_sorted(items(), 1, |item, _| {
+                merged.push(item.to_string());
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(merged, ["A", "a", "B", "b"]);
+        }
+    }
+
+    #[test]
+    fn icu_collation_is_rejected() {
+        assert_eq!("codepoint".parse(), Ok(Collation::Codepoint));
+        assert_eq!("caseless".parse(), Ok(Collation::Caseless));
+        assert!("icu:de".parse::<Collation>().is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn external_sort_rejects_empty_runs() {
+        ExternalSort::new(0, &env::temp_dir());
+    }
+}
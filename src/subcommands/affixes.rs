@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Write};
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+use corpus_count::vocab::Vocab;
+
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    is_valid, non_zero, output_args, output_options_from_matches, usage_error,
+    DEFAULT_CLAP_SETTINGS,
+};
+
+static COUNTS: &str = "COUNTS";
+static MAX_LEN: &str = "MAX_LEN";
+static MIN_COUNT: &str = "MIN_COUNT";
+static MIN_LEN: &str = "MIN_LEN";
+static MIN_STEM: &str = "MIN_STEM";
+static MIN_TYPES: &str = "MIN_TYPES";
+static OUTPUT: &str = "OUTPUT";
+static TOP: &str = "TOP";
+
+/// Frequencies of an affix.
+#[derive(Clone, Copy, Debug, Default)]
+struct AffixStats {
+    /// Number of word types with the affix.
+    types: usize,
+    /// Number of word tokens with the affix.
+    tokens: usize,
+    /// Number of word types with the affix that occur once.
+    hapaxes: usize,
+}
+
+impl AffixStats {
+    fn add(&mut self, count: usize) {
+        self.types += 1;
+        self.tokens += count;
+        if count == 1 {
+            self.hapaxes += 1;
+        }
+    }
+}
+
+/// Report the prefixes and suffixes of the vocabulary.
+pub struct AffixesApp {
+    output_opts: OutputOptions,
+    counts: String,
+    output: Option<String>,
+    min_count: usize,
+    min_len: usize,
+    max_len: usize,
+    min_stem: usize,
+    min_types: usize,
+    top: Option<usize>,
+}
+
+impl AffixesApp {
+    /// Count the affixes of the words, returns the prefix and suffix
+    /// statistics.
+    fn count<'a>(
+        &self,
+        words: impl Iterator<Item = (&'a str, usize)>,
+    ) -> [HashMap<&'a str, AffixStats>; 2] {
+        let mut prefixes = HashMap::new();
+        let mut suffixes = HashMap::new();
+        for (word, count) in words {
+            let bounds = word
+                .char_indices()
+                .map(|(idx, _)| idx)
+                .chain(Some(word.len()))
+                .collect::<Vec<_>>();
+            let n_chars = bounds.len() - 1;
+            let max_len = self.max_len.min(n_chars.saturating_sub(self.min_stem));
+            for len in self.min_len..=max_len {
+                prefixes
+                    .entry(&word[..bounds[len]])
+                    .or_insert_with(AffixStats::default)
+                    .add(count);
+                suffixes
+                    .entry(&word[bounds[n_chars - len]..])
+                    .or_insert_with(AffixStats::default)
+                    .add(count);
+            }
+        }
+        [prefixes, suffixes]
+    }
+}
+
+impl CorpusCountApp for AffixesApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("affixes")
+            .about("Report the most productive prefixes and suffixes of token counts")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&output_args())
+            .arg(
+                Arg::with_name(COUNTS)
+                    .long("counts")
+                    .help("Token count file of the vocabulary")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("File for kind<TAB>affix<TAB>types<TAB>tokens<TAB>hapaxes lines")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT)
+                    .long("min_count")
+                    .validator(is_valid::<usize>)
+                    .default_value("1")
+                    .help("Token min count"),
+            )
+            .arg(
+                Arg::with_name(MIN_LEN)
+                    .long("min_len")
+                    .validator(non_zero("The minimum affix length should be positive."))
+                    .default_value("1")
+                    .help("Minimum affix length in characters"),
+            )
+            .arg(
+                Arg::with_name(MAX_LEN)
+                    .long("max_len")
+                    .validator(is_valid::<usize>)
+                    .default_value("4")
+                    .help("Maximum affix length in characters"),
+            )
+            .arg(
+                Arg::with_name(MIN_STEM)
+                    .long("min_stem")
+                    .validator(is_valid::<usize>)
+                    .default_value("3")
+                    .help("Minimum number of characters left of a word besides the affix"),
+            )
+            .arg(
+                Arg::with_name(MIN_TYPES)
+                    .long("min_types")
+                    .validator(is_valid::<usize>)
+                    .default_value("2")
+                    .help("Minimum number of word types with an affix"),
+            )
+            .arg(
+                Arg::with_name(TOP)
+                    .long("top")
+                    .validator(is_valid::<usize>)
+                    .help("Only report the top prefixes and suffixes each")
+                    .takes_value(true),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let min_len = value_t!(matches, MIN_LEN, usize).unwrap();
+        let max_len = value_t!(matches, MAX_LEN, usize).unwrap();
+        if min_len > max_len {
+            usage_error("The minimum affix length should not exceed the maximum length.");
+        }
+        AffixesApp {
+            output_opts: output_options_from_matches(matches),
+            counts: matches.value_of(COUNTS).map(ToOwned::to_owned).unwrap(),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            min_count: value_t!(matches, MIN_COUNT, usize).unwrap(),
+            min_len,
+            max_len,
+            min_stem: value_t!(matches, MIN_STEM, usize).unwrap(),
+            min_types: value_t!(matches, MIN_TYPES, usize).unwrap(),
+            top: value_t!(matches, TOP, usize).ok(),
+        }
+    }
+
+    fn run(&self) {
+        let vocab = Vocab::read(BufReader::new(
+            File::open(&self.counts).expect("Can't open token counts."),
+        ))
+        .expect("Can't read token counts.");
+        let affixes = self.count(vocab.iter().filter(|&(_, count)| count >= self.min_count));
+
+        let mut writer = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't create output.");
+        for (kind, affixes) in ["prefix", "suffix"].iter().zip(affixes) {
+            let mut affixes = affixes
+                .into_iter()
+                .filter(|(_, stats)| stats.types >= self.min_types)
+                .collect::<Vec<_>>();
+            // Productive affixes attach to many types.
+            affixes.sort_unstable_by(|(a1, s1), (a2, s2)| {
+                s2.types
+                    .cmp(&s1.types)
+                    .then(s2.tokens.cmp(&s1.tokens))
+                    .then(a1.cmp(a2))
+            });
+            for (affix, stats) in affixes.into_iter().take(self.top.unwrap_or(usize::MAX)) {
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}\t{}",
+                    kind, affix, stats.types, stats.tokens, stats.hapaxes
+                )
+                .expect("Can't write affixes.");
+            }
+        }
+        writer.finish().expect("Can't write affixes.");
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+
+use crate::input;
+use crate::output::{OutputFile, OutputOptions};
+use crate::sort::ExternalSort;
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    counted_into_sorted, is_valid, output_args, output_options_from_matches, sort_args,
+    sort_buffer_from_matches, tmp_dir_from_matches, DEFAULT_CLAP_SETTINGS,
+};
+
+static ALIGNMENTS: &str = "ALIGNMENTS";
+static MIN_COUNT: &str = "MIN_COUNT";
+static OUTPUT: &str = "OUTPUT";
+static SOURCE: &str = "SOURCE";
+static TARGET: &str = "TARGET";
+
+/// Count token pairs of line-aligned parallel files.
+pub struct AlignedApp {
+    source: PathBuf,
+    target: PathBuf,
+    alignments: Option<PathBuf>,
+    output_opts: OutputOptions,
+    output: Option<String>,
+    min_count: usize,
+    sort_buffer: Option<usize>,
+    tmp_dir: PathBuf,
+}
+
+fn open_lines(path: &PathBuf) -> io::Result<io::Lines<Box<dyn BufRead>>> {
+    Ok(input::decompressed(BufReader::new(File::open(path)?))?.lines())
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Parse a line of Pharaoh-style `i-j` alignments.
+fn parse_alignments(line: &str, line_no: usize) -> io::Result<Vec<(usize, usize)>> {
+    line.split_whitespace()
+        .map(|pair| {
+            pair.split_once('-')
+                .and_then(|(src, tgt)| Some((src.parse().ok()?, tgt.parse().ok()?)))
+                .ok_or_else(|| {
+                    invalid_data(format!("Invalid alignment {} in line {}", pair, line_no))
+                })
+        })
+        .collect()
+}
+
+impl AlignedApp {
+    /// Count the token pairs.
+    ///
+    /// Pairs are keyed by `source\ttarget`. Without alignments, every
+    /// source token is paired with every target token of the aligned line.
+    fn count(&self) -> io::Result<HashMap<String, usize>> {
+        let mut counts = HashMap::new();
+        let mut source = open_lines(&self.source)?;
+        let mut target = open_lines(&self.target)?;
+        let mut alignments = self.alignments.as_ref().map(open_lines).transpose()?;
+        let mut key = String::new();
+        let mut line_no = 0;
+        loop {
+            line_no += 1;
+            let (src_line, tgt_line) = match (source.next(), target.next()) {
+                (Some(src), Some(tgt)) => (src?, tgt?),
+                (None, None) => break,
+                _ => {
+                    return Err(invalid_data(
+                        "Source and target have different numbers of lines".to_string(),
+                    ))
+                }
+            };
+            let src_tokens = src_line.split_whitespace().collect::<Vec<_>>();
+            let tgt_tokens = tgt_line.split_whitespace().collect::<Vec<_>>();
+            let alignment = match alignments.as_mut() {
+                Some(alignments) => {
+                    let line = alignments.next().ok_or_else(|| {
+                        invalid_data(format!("Missing alignments for line {}", line_no))
+                    })??;
+                    Some(parse_alignments(&line, line_no)?)
+                }
+                None => None,
+            };
+            let mut count_pair = |src: &str, tgt: &str| {
+                key.clear();
+                key.push_str(src);
+                key.push('\t');
+                key.push_str(tgt);
+                if let Some(cnt) = counts.get_mut(&key) {
+                    *cnt += 1;
+                } else {
+                    counts.insert(key.clone(), 1);
+                }
+            };
+            match alignment {
+                Some(alignment) => {
+                    for (src, tgt) in alignment {
+                        match (src_tokens.get(src), tgt_tokens.get(tgt)) {
+                            (Some(src), Some(tgt)) => count_pair(src, tgt),
+                            _ => {
+                                return Err(invalid_data(format!(
+                                    "Alignment {}-{} out of range in line {}",
+                                    src, tgt, line_no
+                                )))
+                            }
+                        }
+                    }
+                }
+                None => {
+                    for src in &src_tokens {
+                        for tgt in &tgt_tokens {
+                            count_pair(src, tgt);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(counts)
+    }
+}
+
+impl CorpusCountApp for AlignedApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("aligned")
+            .about("Count token pairs of line-aligned parallel files")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&output_args())
+            .args(&sort_args())
+            .arg(
+                Arg::with_name(SOURCE)
+                    .long("source")
+                    .help("Source side of the parallel corpus")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(TARGET)
+                    .long("target")
+                    .help("Target side of the parallel corpus")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(ALIGNMENTS)
+                    .long("alignments")
+                    .help("Word alignments in i-j format, pairs all tokens of a line if absent")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("Pair count file")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT)
+                    .long("min_count")
+                    .validator(is_valid::<usize>)
+                    .default_value("1")
+                    .help("Pair min count"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        AlignedApp {
+            source: matches.value_of(SOURCE).map(PathBuf::from).unwrap(),
+            target: matches.value_of(TARGET).map(PathBuf::from).unwrap(),
+            alignments: matches.value_of(ALIGNMENTS).map(PathBuf::from),
+            output_opts: output_options_from_matches(matches),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            min_count: value_t!(matches, MIN_COUNT, usize).unwrap(),
+            sort_buffer: sort_buffer_from_matches(matches),
+            tmp_dir: tmp_dir_from_matches(matches),
+        }
+    }
+
+    fn run(&self) {
+        let counts = self.count().expect("Can't read parallel corpus");
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't open output to write pair counts.");
+        if let Some(run_len) = self.sort_buffer {
+            let sort = ExternalSort::new(run_len, &self.tmp_dir);
+            sort.for_each_sorted(counts, self.min_count, |pair, count| {
+                writeln!(output, "{}\t{}", pair, count)
+            })
+            .expect("Can't write pair counts.");
+        } else {
+            for (pair, count) in counted_into_sorted(counts, Some(self.min_count)) {
+                writeln!(output, "{}\t{}", pair, count).expect("Can't write pair counts.");
+            }
+        }
+        output.finish().expect("Can't write pair counts.");
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+
+use crate::boilerplate::ngram_hash;
+use crate::input::Corpus;
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    corpus_args, corpus_from_matches, is_valid, non_zero, output_args, output_options_from_matches,
+    DEFAULT_CLAP_SETTINGS,
+};
+
+static MIN_DOCS: &str = "MIN_DOCS";
+static NGRAM_LEN: &str = "NGRAM_LEN";
+static OUTPUT: &str = "OUTPUT";
+
+/// Find long word n-grams that repeat across many documents.
+pub struct BoilerplateApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    output: Option<String>,
+    min_docs: usize,
+    ngram_len: usize,
+}
+
+impl BoilerplateApp {
+    /// Call `f` with the distinct n-grams of every document.
+    fn for_each_document<F>(&self, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&HashSet<u64>, &[String]),
+    {
+        let mut hashes = HashSet::new();
+        self.corpus.for_each_document(|lines| {
+            hashes.clear();
+            for line in lines {
+                let tokens = line.split_whitespace().collect::<Vec<_>>();
+                hashes.extend(tokens.windows(self.ngram_len).map(ngram_hash));
+            }
+            f(&hashes, lines);
+            Ok(())
+        })
+    }
+
+    /// Get the document frequencies of the n-grams in at least `min_docs`
+    /// documents.
+    fn doc_freqs(&self) -> io::Result<HashMap<u64, usize>> {
+        let mut doc_freqs = HashMap::new();
+        self.for_each_document(|hashes, _| {
+            for &hash in hashes {
+                *doc_freqs.entry(hash).or_insert(0) += 1;
+            }
+        })?;
+        doc_freqs.retain(|_, &mut docs| docs >= self.min_docs);
+        Ok(doc_freqs)
+    }
+
+    /// Get the text of the n-grams and the number of documents with and
+    /// without them, the corpus is read again.
+    fn texts(
+        &self,
+        doc_freqs: &HashMap<u64, usize>,
+    ) -> io::Result<(HashMap<u64, String>, usize, usize)> {
+        let mut texts = HashMap::new();
+        let mut documents = 0;
+        let mut affected = 0;
+        self.for_each_document(|hashes, lines| {
+            documents += 1;
+            if !hashes.iter().any(|hash| doc_freqs.contains_key(hash)) {
+                return;
+            }
+            affected += 1;
+            for line in lines {
+                let tokens = line.split_whitespace().collect::<Vec<_>>();
+                for ngram in tokens.windows(self.ngram_len) {
+                    let hash = ngram_hash(ngram);
+                    if doc_freqs.contains_key(&hash) {
+                        texts.entry(hash).or_insert_with(|| ngram.join(" "));
+                    }
+                }
+            }
+        })?;
+        Ok((texts, documents, affected))
+    }
+}
+
+impl CorpusCountApp for BoilerplateApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("boilerplate")
+            .about("Find long word n-grams that repeat across many documents")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("File for ngram<TAB>documents lines")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(MIN_DOCS)
+                    .long("min_docs")
+                    .validator(is_valid::<usize>)
+                    .default_value("10")
+                    .help("Minimum number of documents of boilerplate n-grams"),
+            )
+            .arg(
+                Arg::with_name(NGRAM_LEN)
+                    .long("ngram_len")
+                    .validator(non_zero("The n-gram length cannot be zero."))
+                    .short("n")
+                    .default_value("8")
+                    .help("Number of tokens of boilerplate n-grams"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let ngram_len = value_t!(matches, NGRAM_LEN, usize).unwrap();
+        BoilerplateApp {
+            corpus: corpus_from_matches(matches),
+            output_opts: output_options_from_matches(matches),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            min_docs: value_t!(matches, MIN_DOCS, usize).unwrap(),
+            ngram_len,
+        }
+    }
+
+    fn run(&self) {
+        assert!(
+            !self.corpus.paths.is_empty(),
+            "Finding boilerplate requires corpus files, stdin can only be read once."
+        );
+        let doc_freqs = self.doc_freqs().expect("Can't read corpus");
+        let (texts, documents, affected) = self.texts(&doc_freqs).expect("Can't read corpus");
+        let mut ngrams = texts
+            .into_iter()
+            .map(|(hash, text)| (text, doc_freqs[&hash]))
+            .collect::<Vec<_>>();
+        ngrams.sort_unstable_by(|(t1, d1), (t2, d2)| d2.cmp(d1).then(t1.cmp(t2)));
+        eprintln!(
+            "{} boilerplate n-grams in {} of {} documents ({:.2}%)",
+            ngrams.len(),
+            affected,
+            documents,
+            100. * affected as f64 / documents.max(1) as f64
+        );
+
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't create output.");
+        for (ngram, docs) in ngrams {
+            writeln!(output, "{}\t{}", ngram, docs).expect("Can't write boilerplate n-grams.");
+        }
+        output.finish().expect("Can't write boilerplate n-grams.");
+    }
+}
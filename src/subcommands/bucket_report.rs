@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Write};
+
+use clap::{value_t, values_t, App, Arg, ArgMatches, SubCommand};
+use corpus_count::ngrams;
+use corpus_count::options::Options;
+use corpus_count::subword::NGramIndexer;
+use corpus_count::vocab::Vocab;
+
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    is_valid, non_zero, output_args, output_options_from_matches, usage_error, validate,
+    DEFAULT_CLAP_SETTINGS,
+};
+
+static BUCKETS: &str = "BUCKETS";
+static COUNTS: &str = "COUNTS";
+static DIMS: &str = "DIMS";
+static MAX_N: &str = "MAX_N";
+static MIN_COUNT: &str = "MIN_COUNT";
+static MIN_N: &str = "MIN_N";
+static NO_BRACKET: &str = "NO_BRACKET";
+static OUTPUT: &str = "OUTPUT";
+
+/// Report n-gram collisions and embedding matrix sizes of bucket counts.
+pub struct BucketReportApp {
+    output_opts: OutputOptions,
+    buckets: Vec<u32>,
+    counts: String,
+    dims: usize,
+    options: Options,
+    output: Option<String>,
+}
+
+impl BucketReportApp {
+    /// Count the n-grams of the tokens with at least the min count.
+    ///
+    /// N-grams are counted with the counts of the tokens they occur in.
+    /// Returns the number of tokens and the n-gram counts.
+    fn ngram_counts(&self, vocab: &Vocab) -> (usize, HashMap<String, usize>) {
+        let n_tokens = vocab
+            .iter()
+            .take_while(|&(_, count)| count >= self.options.token_min())
+            .count();
+        (n_tokens, ngrams::count_ngrams(vocab.iter(), &self.options))
+    }
+}
+
+impl CorpusCountApp for BucketReportApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("bucket-report")
+            .about("Report n-gram collisions and embedding matrix sizes of bucket counts")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&output_args())
+            .arg(
+                Arg::with_name(BUCKETS)
+                    .long("buckets")
+                    .validator(|v| {
+                        validate(
+                            &v,
+                            |&exp: &u32| exp < 64,
+                            "The bucket exponents should be smaller than 64.",
+                        )
+                    })
+                    .default_value("16,18,19,20,21,22,24")
+                    .use_delimiter(true)
+                    .help("Comma-separated exponents of the number of buckets"),
+            )
+            .arg(
+                Arg::with_name(COUNTS)
+                    .long("counts")
+                    .help("Token count file")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(DIMS)
+                    .long("dims")
+                    .validator(is_valid::<usize>)
+                    .default_value("300")
+                    .help("Embedding dimensionality"),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT)
+                    .long("min_count")
+                    .validator(non_zero("The token min count cannot be zero."))
+                    .default_value("1")
+                    .help("Minimum count of tokens in the vocabulary"),
+            )
+            .arg(
+                Arg::with_name(MIN_N)
+                    .long("min_n")
+                    .validator(non_zero("The minimum n-gram length cannot be zero."))
+                    .default_value("3")
+                    .help("Minimal ngram length to be used."),
+            )
+            .arg(
+                Arg::with_name(MAX_N)
+                    .long("max_n")
+                    .validator(is_valid::<usize>)
+                    .default_value("6")
+                    .help("Maximum ngram length to be used."),
+            )
+            .arg(
+                Arg::with_name(NO_BRACKET)
+                    .long("no_bracket")
+                    .help("Do not bracket tokens before extracting ngrams"),
+            )
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("File for the report, stdout is used otherwise")
+                    .takes_value(true),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let buckets = values_t!(matches, BUCKETS, u32).unwrap();
+        let options = Options::builder()
+            .token_min(value_t!(matches, MIN_COUNT, usize).unwrap())
+            .filter_first(true)
+            .min_n(value_t!(matches, MIN_N, usize).unwrap())
+            .max_n(value_t!(matches, MAX_N, usize).unwrap())
+            .bracket(!matches.is_present(NO_BRACKET))
+            .build()
+            .unwrap_or_else(|err| usage_error(&err));
+        BucketReportApp {
+            output_opts: output_options_from_matches(matches),
+            buckets,
+            counts: matches.value_of(COUNTS).map(ToOwned::to_owned).unwrap(),
+            dims: value_t!(matches, DIMS, usize).unwrap(),
+            options,
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+        }
+    }
+
+    fn run(&self) {
+        let vocab = Vocab::read(BufReader::new(
+            File::open(&self.counts).expect("Can't open token counts."),
+        ))
+        .expect("Can't read token counts.");
+        let (n_tokens, ngram_counts) = self.ngram_counts(&vocab);
+        let n_ngrams = ngram_counts.len();
+        let total = ngram_counts.values().sum::<usize>();
+        eprintln!(
+            "{} tokens, {} ngram types, {} ngram occurrences",
+            n_tokens, n_ngrams, total
+        );
+
+        let mut writer = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't create bucket report.");
+        writeln!(
+            writer,
+            "buckets_exp\tbuckets\tused_buckets\tcolliding_types\texpected_colliding_types\tcolliding_occurrences\tmatrix_mib"
+        )
+        .expect("Can't write bucket report.");
+        for &buckets_exp in &self.buckets {
+            let indexer = NGramIndexer::Hashed { buckets_exp };
+            let mut bucket_types = HashMap::new();
+            for ngram in ngram_counts.keys() {
+                *bucket_types.entry(indexer.idx(ngram).unwrap()).or_insert(0) += 1;
+            }
+            // N-grams sharing their bucket with another n-gram.
+            let (colliding, colliding_count) = ngram_counts
+                .iter()
+                .filter(|(ngram, _)| bucket_types[&indexer.idx(ngram).unwrap()] > 1)
+                .fold((0, 0), |(types, occurrences), (_, &count)| {
+                    (types + 1, occurrences + count)
+                });
+            // Probability that one of the other n-grams hits the bucket of
+            // an n-gram under uniform hashing.
+            let n_buckets = indexer.len() as f64;
+            let expected = 1. - (1. - 1. / n_buckets).powf(n_ngrams.saturating_sub(1) as f64);
+            let matrix_bytes = (n_tokens + indexer.len()) as f64 * (self.dims * 4) as f64;
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{:.4}\t{:.4}\t{:.4}\t{:.1}",
+                buckets_exp,
+                indexer.len(),
+                bucket_types.len(),
+                colliding as f64 / n_ngrams.max(1) as f64,
+                expected,
+                colliding_count as f64 / total.max(1) as f64,
+                matrix_bytes / f64::from(1 << 20)
+            )
+            .expect("Can't write bucket report.");
+        }
+        writer.finish().expect("Can't write bucket report.");
+    }
+}
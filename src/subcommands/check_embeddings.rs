@@ -0,0 +1,132 @@
+use std::fs::File;
+use std::io::{BufReader, Write};
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+use corpus_count::vocab::Vocab;
+
+use crate::embeddings::{self, EmbeddingFormat};
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{is_valid, output_args, output_options_from_matches, DEFAULT_CLAP_SETTINGS};
+
+static COUNTS: &str = "COUNTS";
+static EMBEDDINGS: &str = "EMBEDDINGS";
+static FORMAT: &str = "FORMAT";
+static MIN_COUNT: &str = "MIN_COUNT";
+static OUTPUT: &str = "OUTPUT";
+
+/// Report corpus tokens missing from embeddings.
+pub struct CheckEmbeddingsApp {
+    output_opts: OutputOptions,
+    counts: String,
+    embeddings: String,
+    format: EmbeddingFormat,
+    min_count: usize,
+    output: Option<String>,
+}
+
+impl CorpusCountApp for CheckEmbeddingsApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("check-embeddings")
+            .about("Report corpus tokens missing from embeddings")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&output_args())
+            .arg(
+                Arg::with_name(COUNTS)
+                    .long("counts")
+                    .help("Token count file of the corpus")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(EMBEDDINGS)
+                    .long("embeddings")
+                    .help("Embedding file")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(FORMAT)
+                    .long("format")
+                    .validator(is_valid::<EmbeddingFormat>)
+                    .possible_values(EmbeddingFormat::variants())
+                    .default_value("finalfusion")
+                    .help("Format of the embedding file"),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT)
+                    .long("min_count")
+                    .validator(is_valid::<usize>)
+                    .default_value("1")
+                    .help("Minimum count of checked tokens"),
+            )
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("File for token<TAB>count<TAB>coverage lines of tokens without an embedding in the vocabulary")
+                    .takes_value(true),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        CheckEmbeddingsApp {
+            output_opts: output_options_from_matches(matches),
+            counts: matches.value_of(COUNTS).map(ToOwned::to_owned).unwrap(),
+            embeddings: matches.value_of(EMBEDDINGS).map(ToOwned::to_owned).unwrap(),
+            format: value_t!(matches, FORMAT, EmbeddingFormat).unwrap(),
+            min_count: value_t!(matches, MIN_COUNT, usize).unwrap(),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+        }
+    }
+
+    fn run(&self) {
+        let vocab = Vocab::read(BufReader::new(
+            File::open(&self.counts).expect("Can't open token counts."),
+        ))
+        .expect("Can't read token counts.");
+        let embeddings = embeddings::read_vocab(
+            BufReader::new(File::open(&self.embeddings).expect("Can't open embeddings.")),
+            self.format,
+        )
+        .expect("Can't read embeddings.");
+
+        let mut writer = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't create output.");
+        // Types and tokens that are known, only have subword embeddings
+        // or are missing.
+        let mut coverage = [(0, 0); 3];
+        let mut tokens = 0;
+        for (token, count) in vocab
+            .iter()
+            .take_while(|&(_, count)| count >= self.min_count)
+        {
+            tokens += count;
+            let idx = if embeddings.words.contains(token) {
+                0
+            } else if embeddings.has_subwords(token) {
+                1
+            } else {
+                2
+            };
+            coverage[idx].0 += 1;
+            coverage[idx].1 += count;
+            if idx != 0 {
+                let name = if idx == 1 { "subwords" } else { "none" };
+                writeln!(writer, "{}\t{}\t{}", token, count, name)
+                    .expect("Can't write missing tokens.");
+            }
+        }
+        writer.finish().expect("Can't write missing tokens.");
+
+        for (name, (types, covered)) in ["known", "subwords", "missing"].iter().zip(&coverage) {
+            eprintln!(
+                "{}: {} types, {} tokens ({:.2}% of tokens)",
+                name,
+                types,
+                covered,
+                100. * *covered as f64 / tokens.max(1) as f64
+            );
+        }
+    }
+}
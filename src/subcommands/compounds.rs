@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Write};
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+use corpus_count::vocab::Vocab;
+
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{is_valid, non_zero, output_args, output_options_from_matches, DEFAULT_CLAP_SETTINGS};
+
+static CANDIDATES: &str = "CANDIDATES";
+static COUNTS: &str = "COUNTS";
+static FILLERS: &str = "FILLERS";
+static IGNORE_CASE: &str = "IGNORE_CASE";
+static MAX_PARTS: &str = "MAX_PARTS";
+static MIN_COUNT: &str = "MIN_COUNT";
+static MIN_LEN: &str = "MIN_LEN";
+static MIN_PART: &str = "MIN_PART";
+static OUTPUT: &str = "OUTPUT";
+
+/// A part of a compound with the linking element following it.
+#[derive(Clone, Debug)]
+struct Part<'a> {
+    part: &'a str,
+    filler: &'a str,
+    count: usize,
+}
+
+/// Propose compound splits of long tokens.
+pub struct CompoundsApp {
+    output_opts: OutputOptions,
+    counts: String,
+    output: Option<String>,
+    candidates: usize,
+    fillers: Vec<String>,
+    ignore_case: bool,
+    max_parts: usize,
+    min_count: usize,
+    min_len: usize,
+    min_part: usize,
+}
+
+/// Part counts, keyed by lowercased parts if case is ignored.
+struct Parts {
+    counts: HashMap<String, usize>,
+    ignore_case: bool,
+}
+
+impl Parts {
+    fn count(&self, part: &str) -> Option<usize> {
+        if self.ignore_case {
+            self.counts.get(&part.to_lowercase()).copied()
+        } else {
+            self.counts.get(part).copied()
+        }
+    }
+}
+
+impl CompoundsApp {
+    /// Enumerate the splits of `rest` into known parts, `f` is called with
+    /// the parts of every split.
+    fn splits<'a>(
+        &'a self,
+        parts: &Parts,
+        rest: &'a str,
+        split: &mut Vec<Part<'a>>,
+        f: &mut dyn FnMut(&[Part<'a>]),
+    ) {
+        if split.len() == self.max_parts {
+            return;
+        }
+        for (n_chars, (end, _)) in rest
+            .char_indices()
+            .skip(1)
+            .chain(Some((rest.len(), ' ')))
+            .enumerate()
+        {
+            if n_chars + 1 < self.min_part {
+                continue;
+            }
+            let part = &rest[..end];
+            let count = match parts.count(part) {
+                Some(count) => count,
+                None => continue,
+            };
+            split.push(Part {
+                part,
+                filler: "",
+                count,
+            });
+            if end == rest.len() {
+                f(split);
+            } else {
+                self.splits(parts, &rest[end..], split, f);
+                for filler in &self.fillers {
+                    if rest[end..].starts_with(filler.as_str()) && end + filler.len() < rest.len() {
+                        split.last_mut().unwrap().filler = filler;
+                        self.splits(parts, &rest[end + filler.len()..], split, f);
+                    }
+                }
+            }
+            split.pop();
+        }
+    }
+
+    /// Get the splits of a word that score higher than the word, ordered
+    /// by their score.
+    fn propose<'a>(&'a self, parts: &Parts, word: &'a str, count: usize) -> Vec<(String, f64)> {
+        let mut proposals = Vec::new();
+        self.splits(parts, word, &mut Vec::new(), &mut |split| {
+            if split.len() < 2 {
+                return;
+            }
+            // Geometric mean of the part counts.
+            let score = (split
+                .iter()
+                .map(|part| (part.count as f64).ln())
+                .sum::<f64>()
+                / split.len() as f64)
+                .exp();
+            if score > count as f64 {
+                let split = split
+                    .iter()
+                    .map(|part| {
+                        if part.filler.is_empty() {
+                            part.part.to_owned()
+                        } else {
+                            format!("{}+{}", part.part, part.filler)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                proposals.push((split, score));
+            }
+        });
+        proposals.sort_by(|(s1, score1), (s2, score2)| {
+            score2.partial_cmp(score1).unwrap().then(s1.cmp(s2))
+        });
+        proposals.truncate(self.candidates);
+        proposals
+    }
+}
+
+impl CorpusCountApp for CompoundsApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("compounds")
+            .about("Propose compound splits of long tokens from token counts")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&output_args())
+            .arg(
+                Arg::with_name(COUNTS)
+                    .long("counts")
+                    .help("Token count file of the vocabulary")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("File for word<TAB>split<TAB>score lines")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(CANDIDATES)
+                    .long("candidates")
+                    .validator(is_valid::<usize>)
+                    .default_value("1")
+                    .help("Maximum number of splits per word"),
+            )
+            .arg(
+                Arg::with_name(FILLERS)
+                    .long("fillers")
+                    .default_value("s,es,n,en,e")
+                    .help("Comma-separated linking elements allowed between parts"),
+            )
+            .arg(
+                Arg::with_name(IGNORE_CASE)
+                    .long("ignore_case")
+                    .help("Look up parts case-insensitively"),
+            )
+            .arg(
+                Arg::with_name(MAX_PARTS)
+                    .long("max_parts")
+                    .validator(is_valid::<usize>)
+                    .default_value("3")
+                    .help("Maximum number of parts of a compound"),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT)
+                    .long("min_count")
+                    .validator(is_valid::<usize>)
+                    .default_value("1")
+                    .help("Minimum count of parts"),
+            )
+            .arg(
+                Arg::with_name(MIN_LEN)
+                    .long("min_len")
+                    .validator(is_valid::<usize>)
+                    .default_value("8")
+                    .help("Minimum length of words to split in characters"),
+            )
+            .arg(
+                Arg::with_name(MIN_PART)
+                    .long("min_part")
+                    .validator(non_zero("The minimum part length should be positive."))
+                    .default_value("3")
+                    .help("Minimum length of parts in characters"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let min_part = value_t!(matches, MIN_PART, usize).unwrap();
+        CompoundsApp {
+            output_opts: output_options_from_matches(matches),
+            counts: matches.value_of(COUNTS).map(ToOwned::to_owned).unwrap(),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            candidates: value_t!(matches, CANDIDATES, usize).unwrap(),
+            fillers: matches
+                .value_of(FILLERS)
+                .unwrap()
+                .split(',')
+                .filter(|filler| !filler.is_empty())
+                .map(ToOwned::to_owned)
+                .collect(),
+            ignore_case: matches.is_present(IGNORE_CASE),
+            max_parts: value_t!(matches, MAX_PARTS, usize).unwrap(),
+            min_count: value_t!(matches, MIN_COUNT, usize).unwrap(),
+            min_len: value_t!(matches, MIN_LEN, usize).unwrap(),
+            min_part,
+        }
+    }
+
+    fn run(&self) {
+        let vocab = Vocab::read(BufReader::new(
+            File::open(&self.counts).expect("Can't open token counts."),
+        ))
+        .expect("Can't read token counts.");
+        let mut counts = HashMap::new();
+        for (token, count) in vocab.iter() {
+            if count < self.min_count {
+                continue;
+            }
+            if self.ignore_case {
+                *counts.entry(token.to_lowercase()).or_insert(0) += count;
+            } else {
+                counts.insert(token.to_owned(), count);
+            }
+        }
+        let parts = Parts {
+            counts,
+            ignore_case: self.ignore_case,
+        };
+
+        let mut writer = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't create output.");
+        for (word, count) in vocab.iter() {
+            if word.chars().count() < self.min_len {
+                continue;
+            }
+            for (split, score) in self.propose(&parts, word, count) {
+                writeln!(writer, "{}\t{}\t{:.2}", word, split, score)
+                    .expect("Can't write compound splits.");
+            }
+        }
+        writer.finish().expect("Can't write compound splits.");
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+
+use crate::input::{Corpus, InputFormat};
+use crate::output::{OutputFile, OutputOptions};
+use crate::sort::ExternalSort;
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    corpus_args, corpus_from_matches, counted_into_sorted, is_valid, non_zero, output_args,
+    output_options_from_matches, sort_args, sort_buffer_from_matches, tmp_dir_from_matches,
+    usage_error, DEFAULT_CLAP_SETTINGS,
+};
+
+static DEPENDENCIES: &str = "DEPENDENCIES";
+static DIRECTIONAL: &str = "DIRECTIONAL";
+static MIN_COUNT: &str = "MIN_COUNT";
+static OUTPUT: &str = "OUTPUT";
+static WINDOW: &str = "WINDOW";
+
+/// Count co-occurrences of tokens within a window or dependency relations.
+pub struct CooccurrenceApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    output: Option<String>,
+    window: usize,
+    directional: bool,
+    dependencies: bool,
+    min_count: usize,
+    sort_buffer: Option<usize>,
+    tmp_dir: PathBuf,
+}
+
+impl CooccurrenceApp {
+    /// Count the co-occurrences of the corpus.
+    ///
+    /// Pairs are keyed by `word\tcontext`, directional contexts carry the
+    /// signed offset to the word as suffix, e.g. `context_-2`.
+    fn count(&self) -> io::Result<HashMap<String, usize>> {
+        let mut counts = HashMap::new();
+        let mut key = String::new();
+        self.corpus.for_each_line(|line| {
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            for (idx, word) in tokens.iter().enumerate() {
+                let start = idx.saturating_sub(self.window);
+                let end = (idx + self.window + 1).min(tokens.len());
+                for (ctx_idx, context) in tokens.iter().enumerate().take(end).skip(start) {
+                    if ctx_idx == idx {
+                        continue;
+                    }
+                    start_pair(&mut key, word, context);
+                    if self.directional {
+                        write!(key, "_{}", ctx_idx as isize - idx as isize).unwrap();
+                    }
+                    count_pair(&mut counts, &key);
+                }
+            }
+            Ok(())
+        })?;
+        Ok(counts)
+    }
+
+    /// Count dependency-based contexts of a CoNLL-U corpus.
+    ///
+    /// Following Levy & Goldberg (2014), a head gets the context
+    /// `modifier/deprel`, the modifier gets the inverse context
+    /// `head/deprel-1`. Root attachments are not counted.
+    fn count_dependencies(&self) -> io::Result<HashMap<String, usize>> {
+        let mut counts = HashMap::new();
+        let mut key = String::new();
+        self.corpus.for_each_sentence(|sentence| {
+            for token in sentence {
+                let head = match token.head {
+                    Some(head) if head > 0 => head,
+                    _ => continue,
+                };
+                let head = match sentence.get(head - 1).filter(|t| t.id == head) {
+                    Some(head) => head,
+                    None => continue,
+                };
+                start_pair(&mut key, &head.form, &token.form);
+                write!(key, "/{}", token.deprel).unwrap();
+                count_pair(&mut counts, &key);
+                start_pair(&mut key, &token.form, &head.form);
+                write!(key, "/{}-1", token.deprel).unwrap();
+                count_pair(&mut counts, &key);
+            }
+            Ok(())
+        })?;
+        Ok(counts)
+    }
+}
+
+fn start_pair(key: &mut String, word: &str, context: &str) {
+    key.clear();
+    key.push_str(word);
+    key.push('\t');
+    key.push_str(context);
+}
+
+fn count_pair(counts: &mut HashMap<String, usize>, key: &str) {
+    if let Some(cnt) = counts.get_mut(key) {
+        *cnt += 1;
+    } else {
+        counts.insert(key.to_owned(), 1);
+    }
+}
+
+impl CorpusCountApp for CooccurrenceApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("cooccurrence")
+            .about("Count co-occurrences of tokens within a window")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .args(&sort_args())
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("Co-occurrence count file")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(WINDOW)
+                    .long("window")
+                    .validator(non_zero("The window size cannot be zero."))
+                    .default_value("5")
+                    .help("Number of context tokens on each side of a word"),
+            )
+            .arg(
+                Arg::with_name(DIRECTIONAL)
+                    .long("directional")
+                    .help("Count contexts separately by their position relative to the word"),
+            )
+            .arg(
+                Arg::with_name(DEPENDENCIES)
+                    .long("dependencies")
+                    .conflicts_with(DIRECTIONAL)
+                    .help("Count dependency relations of CoNLL-U input as contexts"),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT)
+                    .long("min_count")
+                    .validator(is_valid::<usize>)
+                    .default_value("1")
+                    .help("Co-occurrence min count"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let window = value_t!(matches, WINDOW, usize).unwrap();
+        let corpus = corpus_from_matches(matches);
+        let dependencies = matches.is_present(DEPENDENCIES);
+        if dependencies && corpus.format != InputFormat::Conllu {
+            usage_error("Dependency contexts require CoNLL-U input.");
+        }
+        CooccurrenceApp {
+            corpus,
+            output_opts: output_options_from_matches(matches),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            window,
+            directional: matches.is_present(DIRECTIONAL),
+            dependencies,
+            sort_buffer: sort_buffer_from_matches(matches),
+            tmp_dir: tmp_dir_from_matches(matches),
+            min_count: value_t!(matches, MIN_COUNT, usize).unwrap(),
+        }
+    }
+
+    fn run(&self) {
+        let counts = if self.dependencies {
+            self.count_dependencies()
+        } else {
+            self.count()
+        }
+        .expect("Can't read corpus");
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't open output to write co-occurrence counts.");
+        if let Some(run_len) = self.sort_buffer {
+            let sort = ExternalSort::new(run_len, &self.tmp_dir);
+            sort.for_each_sorted(counts, self.min_count, |pair, count| {
+                writeln!(output, "{}\t{}", pair, count)
+            })
+            .expect("Can't write co-occurrence counts.");
+        } else {
+            for (pair, count) in counted_into_sorted(counts, Some(self.min_count)) {
+                writeln!(output, "{}\t{}", pair, count).expect("Can't write co-occurrence counts.");
+            }
+        }
+        output.finish().expect("Can't write co-occurrence counts.");
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::thread;
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+
+use crate::count::{self, CountOptions};
+use crate::input::Corpus;
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    corpus_args, corpus_from_matches, counted_into_sorted, is_valid, output_args,
+    output_options_from_matches, tokens_by_id, write_ids, DEFAULT_CLAP_SETTINGS,
+};
+
+static BINARY: &str = "BINARY";
+static MIN_COUNT: &str = "MIN_COUNT";
+static OUTPUT: &str = "OUTPUT";
+static RESERVED: &str = "RESERVED";
+static UNK: &str = "UNK";
+static VOCAB: &str = "VOCAB";
+static VOCAB_OUT: &str = "VOCAB_OUT";
+
+/// Rewrite the corpus as token ids.
+pub struct EncodeApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    output: Option<String>,
+    vocab: Option<String>,
+    vocab_out: Option<String>,
+    min_count: usize,
+    reserved: Vec<String>,
+    unk: String,
+    binary: bool,
+}
+
+impl EncodeApp {
+    /// Get the vocabulary, either read from a token id file or counted from
+    /// the corpus.
+    fn vocab(&self) -> io::Result<HashMap<String, u32>> {
+        if let Some(vocab) = &self.vocab {
+            return read_ids(BufReader::new(File::open(vocab)?));
+        }
+
+        let opts = CountOptions {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            ..CountOptions::default()
+        };
+        let counts = count::count_files(&self.corpus, &opts)?;
+        let tokens = counted_into_sorted(counts.tokens, Some(self.min_count));
+        let reserved = self.reserved.iter().map(String::as_str).collect::<Vec<_>>();
+        let by_id = tokens_by_id(&reserved, tokens.iter().map(|(token, _)| token.as_str()))
+            .collect::<Vec<_>>();
+        if let Some(vocab_out) = &self.vocab_out {
+            let mut writer = OutputFile::create(Some(vocab_out), &self.output_opts)?;
+            write_ids(&mut writer, by_id.iter().copied())?;
+            writer.finish()?;
+        }
+        Ok(by_id
+            .into_iter()
+            .enumerate()
+            .map(|(id, token)| (token.to_owned(), id as u32))
+            .collect())
+    }
+
+    fn encode(&self, vocab: &HashMap<String, u32>, mut writer: impl Write) -> io::Result<()> {
+        let unk = vocab.get(&self.unk).copied();
+        let mut unknown = 0usize;
+        self.corpus.for_each_line(|line| {
+            let ids = line.split_whitespace().map(|token| {
+                vocab.get(token).copied().or_else(|| {
+                    unknown += 1;
+                    unk
+                })
+            });
+            if self.binary {
+                for id in ids.flatten() {
+                    writer.write_all(&id.to_le_bytes())?;
+                }
+            } else {
+                let mut first = true;
+                for id in ids.flatten() {
+                    if !first {
+                        writer.write_all(b" ")?;
+                    }
+                    write!(writer, "{}", id)?;
+                    first = false;
+                }
+                writer.write_all(b"\n")?;
+            }
+            Ok(())
+        })?;
+        writer.flush()?;
+        if unknown != 0 && unk.is_none() {
+            eprintln!(
+                "Dropped {} unknown tokens, {} is not in the vocabulary",
+                unknown, self.unk
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Read a `token<TAB>id` table.
+fn read_ids(reader: impl BufRead) -> io::Result<HashMap<String, u32>> {
+    let mut ids = HashMap::new();
+    for line in reader.lines() {
+        let line = line?;
+        let mut parts = line.rsplitn(2, '\t');
+        let id = parts.next().and_then(|id| id.parse::<u32>().ok());
+        match (parts.next(), id) {
+            (Some(token), Some(id)) => {
+                ids.insert(token.to_owned(), id);
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid token id line: {}", line),
+                ))
+            }
+        }
+    }
+    Ok(ids)
+}
+
+impl CorpusCountApp for EncodeApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("encode")
+            .about("Rewrite the corpus as token ids")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("Encoded corpus file")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(VOCAB)
+                    .long("vocab")
+                    .help("Token id file as written by --ids_out, counted from the corpus otherwise")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(VOCAB_OUT)
+                    .long("vocab_out")
+                    .conflicts_with(VOCAB)
+                    .help("File for the token ids of the counted vocabulary")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT)
+                    .long("min_count")
+                    .validator(is_valid::<usize>)
+                    .default_value("1")
+                    .help("Min count of tokens in the counted vocabulary"),
+            )
+            .arg(
+                Arg::with_name(RESERVED)
+                    .long("reserved")
+                    .default_value("<pad>,<unk>")
+                    .empty_values(true)
+                    .help("Comma-separated symbols with reserved leading ids in the counted vocabulary"),
+            )
+            .arg(
+                Arg::with_name(UNK)
+                    .long("unk")
+                    .default_value("<unk>")
+                    .help("Symbol for unknown tokens, these are dropped if it is not in the vocabulary"),
+            )
+            .arg(
+                Arg::with_name(BINARY)
+                    .long("binary")
+                    .help("Write ids as a stream of little-endian u32 without line boundaries"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        EncodeApp {
+            corpus: corpus_from_matches(matches),
+            output_opts: output_options_from_matches(matches),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            vocab: matches.value_of(VOCAB).map(ToOwned::to_owned),
+            vocab_out: matches.value_of(VOCAB_OUT).map(ToOwned::to_owned),
+            min_count: value_t!(matches, MIN_COUNT, usize).unwrap(),
+            reserved: matches
+                .value_of(RESERVED)
+                .map(|v| {
+                    v.split(',')
+                        .filter(|s| !s.is_empty())
+                        .map(ToOwned::to_owned)
+                        .collect()
+                })
+                .unwrap(),
+            unk: matches.value_of(UNK).unwrap().to_owned(),
+            binary: matches.is_present(BINARY),
+        }
+    }
+
+    fn run(&self) {
+        assert!(
+            self.vocab.is_some() || !self.corpus.paths.is_empty(),
+            "Counting the vocabulary requires corpus files, stdin can only be read once."
+        );
+        let vocab = self.vocab().expect("Can't get vocabulary");
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't open output to write encoded corpus.");
+        self.encode(&vocab, &mut output)
+            .expect("Can't encode corpus");
+        output.finish().expect("Can't write encoded corpus.");
+    }
+}
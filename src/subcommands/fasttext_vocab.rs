@@ -0,0 +1,132 @@
+use std::io::{self, Write};
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+use corpus_count::fasttext::{Dictionary, Entry, EntryType};
+
+use crate::input::Corpus;
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    corpus_args, corpus_from_matches, is_valid, output_args, output_options_from_matches,
+    DEFAULT_CLAP_SETTINGS,
+};
+
+static LABEL: &str = "LABEL";
+static LABELS: &str = "LABELS";
+static MIN_COUNT: &str = "MIN_COUNT";
+static MIN_COUNT_LABEL: &str = "MIN_COUNT_LABEL";
+static OUTPUT: &str = "OUTPUT";
+
+/// Write the vocabulary in the order of fastText's dictionary.
+pub struct FastTextVocabApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    output: Option<String>,
+    labels: Option<String>,
+    label: String,
+    min_count: usize,
+    min_count_label: usize,
+}
+
+impl FastTextVocabApp {
+    fn dictionary(&self) -> io::Result<Dictionary> {
+        let mut dict = Dictionary::new(&self.label);
+        self.corpus.for_each_line(|line| {
+            dict.add_line(line);
+            Ok(())
+        })?;
+        dict.threshold(self.min_count, self.min_count_label);
+        Ok(dict)
+    }
+}
+
+/// Write `token<TAB>count` lines of the entries of a type.
+fn write_entries(
+    mut writer: impl Write,
+    entries: &[Entry],
+    entry_type: EntryType,
+) -> io::Result<()> {
+    for entry in entries
+        .iter()
+        .filter(|entry| entry.entry_type == entry_type)
+    {
+        writeln!(writer, "{}\t{}", entry.word, entry.count)?;
+    }
+    writer.flush()
+}
+
+impl CorpusCountApp for FastTextVocabApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("fasttext-vocab")
+            .about("Write the words of the corpus in the order of fastText's dictionary")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("File for token<TAB>count lines of the words")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(LABELS)
+                    .long("labels")
+                    .help("File for token<TAB>count lines of the labels")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(LABEL)
+                    .long("label")
+                    .default_value("__label__")
+                    .help("Prefix of labels, as fastText's -label"),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT)
+                    .long("min_count")
+                    .validator(is_valid::<usize>)
+                    .default_value("5")
+                    .help("Minimal count of words, as fastText's -minCount"),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT_LABEL)
+                    .long("min_count_label")
+                    .validator(is_valid::<usize>)
+                    .default_value("0")
+                    .help("Minimal count of labels, as fastText's -minCountLabel"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        FastTextVocabApp {
+            corpus: corpus_from_matches(matches),
+            output_opts: output_options_from_matches(matches),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            labels: matches.value_of(LABELS).map(ToOwned::to_owned),
+            label: matches.value_of(LABEL).unwrap().to_owned(),
+            min_count: value_t!(matches, MIN_COUNT, usize).unwrap(),
+            min_count_label: value_t!(matches, MIN_COUNT_LABEL, usize).unwrap(),
+        }
+    }
+
+    fn run(&self) {
+        let dict = self.dictionary().expect("Can't read corpus");
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't open output to write vocabulary.");
+        write_entries(&mut output, dict.entries(), EntryType::Word)
+            .expect("Can't write vocabulary.");
+        output.finish().expect("Can't write vocabulary.");
+        if let Some(labels) = &self.labels {
+            let mut output = OutputFile::create(Some(labels), &self.output_opts)
+                .expect("Can't open output to write labels.");
+            write_entries(&mut output, dict.entries(), EntryType::Label)
+                .expect("Can't write labels.");
+            output.finish().expect("Can't write labels.");
+        }
+        eprintln!(
+            "Read {} tokens, {} entries in the dictionary",
+            dict.tokens(),
+            dict.entries().len()
+        );
+    }
+}
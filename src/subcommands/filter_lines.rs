@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::str::FromStr;
+use std::thread;
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+use corpus_count::charlm::CharLm;
+use corpus_count::vocab::Vocab;
+
+use crate::count::{self, CountOptions};
+use crate::input::Corpus;
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    corpus_args, corpus_from_matches, is_valid, non_zero, output_args, output_options_from_matches,
+    DEFAULT_CLAP_SETTINGS,
+};
+
+static COUNTS: &str = "COUNTS";
+static MIN_LOGPROB: &str = "MIN_LOGPROB";
+static MODEL: &str = "MODEL";
+static ORDER: &str = "ORDER";
+static OUTPUT: &str = "OUTPUT";
+static SCORES: &str = "SCORES";
+
+/// Models scoring lines.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ModelType {
+    /// Token unigram model.
+    Unigram,
+    /// Character language model.
+    Char,
+}
+
+impl ModelType {
+    fn variants() -> &'static [&'static str] {
+        &["unigram", "char"]
+    }
+}
+
+impl FromStr for ModelType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unigram" => Ok(ModelType::Unigram),
+            "char" => Ok(ModelType::Char),
+            _ => Err(format!("Unknown model: {}", s)),
+        }
+    }
+}
+
+/// Line scoring model.
+enum Model {
+    /// Token counts with the log of the corpus size plus vocabulary size.
+    Unigram(HashMap<String, usize>, f64),
+    Char(CharLm),
+}
+
+impl Model {
+    /// Get the mean log-probability of the tokens of a line, per token for
+    /// unigram models and per character for character models.
+    ///
+    /// Returns `None` for lines without tokens.
+    fn score(&self, line: &str) -> Option<f64> {
+        let mut log_prob = 0.;
+        let mut n = 0;
+        for token in line.split_whitespace() {
+            match self {
+                // Add-one smoothed probabilities.
+                Model::Unigram(counts, ln_total) => {
+                    let count = counts.get(token).copied().unwrap_or(0);
+                    log_prob += ((count + 1) as f64).ln() - ln_total;
+                    n += 1;
+                }
+                Model::Char(lm) => {
+                    log_prob += lm.log_prob(token);
+                    // Characters and the end of the token.
+                    n += token.chars().count() + 1;
+                }
+            }
+        }
+        if n == 0 {
+            None
+        } else {
+            Some(log_prob / n as f64)
+        }
+    }
+}
+
+/// Write the lines of the corpus that are probable under a model.
+pub struct FilterLinesApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    output: Option<String>,
+    counts: Option<String>,
+    min_logprob: Option<f64>,
+    model: ModelType,
+    order: usize,
+}
+
+impl FilterLinesApp {
+    fn model(&self) -> io::Result<Model> {
+        let tokens = match &self.counts {
+            Some(path) => Vocab::read(BufReader::new(File::open(path)?))?
+                .iter()
+                .map(|(token, count)| (token.to_owned(), count))
+                .collect(),
+            None => {
+                let opts = CountOptions {
+                    threads: thread::available_parallelism().map_or(1, |n| n.get()),
+                    ..CountOptions::default()
+                };
+                count::count_files(&self.corpus, &opts)?.tokens
+            }
+        };
+        Ok(match self.model {
+            ModelType::Unigram => {
+                let total = tokens.values().sum::<usize>() + tokens.len() + 1;
+                Model::Unigram(tokens, (total as f64).ln())
+            }
+            ModelType::Char => Model::Char(CharLm::from_token_counts(
+                tokens.iter().map(|(token, &count)| (token.as_str(), count)),
+                self.order,
+                true,
+            )),
+        })
+    }
+
+    fn filter(&self, model: &Model, mut writer: impl Write) -> io::Result<()> {
+        let mut kept = 0usize;
+        let mut total = 0usize;
+        self.corpus.for_each_line(|line| {
+            // Blank lines are kept as document boundaries, without scores.
+            let score = match (model.score(line), self.min_logprob) {
+                (Some(score), _) => score,
+                (None, Some(_)) => return writeln!(writer, "{}", line),
+                (None, None) => return writeln!(writer, "\t{}", line),
+            };
+            total += 1;
+            match self.min_logprob {
+                Some(min_logprob) if score < min_logprob => Ok(()),
+                Some(_) => {
+                    kept += 1;
+                    writeln!(writer, "{}", line)
+                }
+                None => writeln!(writer, "{:.4}\t{}", score, line),
+            }
+        })?;
+        writer.flush()?;
+        if self.min_logprob.is_some() {
+            eprintln!(
+                "Kept {} of {} lines ({:.2}%)",
+                kept,
+                total,
+                100. * kept as f64 / total.max(1) as f64
+            );
+        }
+        Ok(())
+    }
+}
+
+impl CorpusCountApp for FilterLinesApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("filter-lines")
+            .about(
+                "Write the lines of the corpus that are probable under a token or character model",
+            )
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("Filtered corpus file")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(COUNTS)
+                    .long("counts")
+                    .help("Token count file to estimate the model from instead of the corpus")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(MIN_LOGPROB)
+                    .long("min_logprob")
+                    .validator(is_valid::<f64>)
+                    .allow_hyphen_values(true)
+                    .help("Minimum mean log-probability of kept lines")
+                    .takes_value(true)
+                    .required_unless(SCORES),
+            )
+            .arg(
+                Arg::with_name(SCORES)
+                    .long("scores")
+                    .conflicts_with(MIN_LOGPROB)
+                    .help("Write all lines with their scores as score<TAB>line"),
+            )
+            .arg(
+                Arg::with_name(MODEL)
+                    .long("model")
+                    .validator(is_valid::<ModelType>)
+                    .possible_values(ModelType::variants())
+                    .default_value("unigram")
+                    .help(
+                        "Score tokens with their frequencies or characters with a language model",
+                    ),
+            )
+            .arg(
+                Arg::with_name(ORDER)
+                    .long("order")
+                    .validator(non_zero("The order should be positive."))
+                    .default_value("4")
+                    .help("Order of the character language model"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let order = value_t!(matches, ORDER, usize).unwrap();
+        FilterLinesApp {
+            corpus: corpus_from_matches(matches),
+            output_opts: output_options_from_matches(matches),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            counts: matches.value_of(COUNTS).map(ToOwned::to_owned),
+            min_logprob: value_t!(matches, MIN_LOGPROB, f64).ok(),
+            model: value_t!(matches, MODEL, ModelType).unwrap(),
+            order,
+        }
+    }
+
+    fn run(&self) {
+        assert!(
+            self.counts.is_some() || !self.corpus.paths.is_empty(),
+            "Filtering stdin requires token counts, stdin can only be read once."
+        );
+        let model = self.model().expect("Can't estimate model");
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't open output to write filtered corpus.");
+        self.filter(&model, &mut output)
+            .expect("Can't filter corpus");
+        output.finish().expect("Can't write filtered corpus.");
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+
+use crate::input::Corpus;
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    corpus_args, corpus_from_matches, is_valid, output_args, output_options_from_matches,
+    DEFAULT_CLAP_SETTINGS,
+};
+
+static MAX_HITS: &str = "MAX_HITS";
+static OUTPUT: &str = "OUTPUT";
+static TARGETS: &str = "TARGETS";
+static TARGETS_FILE: &str = "TARGETS_FILE";
+static WIDTH: &str = "WIDTH";
+
+/// Extract keyword-in-context lines of target tokens.
+pub struct KwicApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    output: Option<String>,
+    targets: HashSet<String>,
+    width: usize,
+    max_hits: Option<usize>,
+}
+
+impl KwicApp {
+    /// Write a `line<TAB>left<TAB>keyword<TAB>right` row for every
+    /// occurrence of a target.
+    ///
+    /// Lines are numbered from 1 in the order they are read, the context
+    /// does not extend beyond the line.
+    fn kwic(&self, mut writer: impl Write) -> io::Result<()> {
+        let mut hits = HashMap::new();
+        let mut line_no = 0;
+        self.corpus.for_each_line(|line| {
+            line_no += 1;
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            for (idx, token) in tokens.iter().enumerate() {
+                let target = match self.targets.get(*token) {
+                    Some(target) => target,
+                    None => continue,
+                };
+                let target_hits = hits.entry(target).or_insert(0);
+                if self
+                    .max_hits
+                    .map(|max| *target_hits >= max)
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+                *target_hits += 1;
+                let left = &tokens[idx.saturating_sub(self.width)..idx];
+                let right = &tokens[idx + 1..(idx + 1 + self.width).min(tokens.len())];
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}",
+                    line_no,
+                    left.join(" "),
+                    token,
+                    right.join(" ")
+                )?;
+            }
+            Ok(())
+        })?;
+        writer.flush()
+    }
+}
+
+impl CorpusCountApp for KwicApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("kwic")
+            .about("Extract keyword-in-context lines of target tokens")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("Concordance file")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(TARGETS)
+                    .long("targets")
+                    .short("t")
+                    .help("Target tokens")
+                    .takes_value(true)
+                    .multiple(true)
+                    .use_delimiter(true)
+                    .required_unless(TARGETS_FILE),
+            )
+            .arg(
+                Arg::with_name(TARGETS_FILE)
+                    .long("targets_file")
+                    .help("File with target tokens, one per line")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(WIDTH)
+                    .long("width")
+                    .validator(is_valid::<usize>)
+                    .default_value("5")
+                    .help("Number of context tokens on each side of the keyword"),
+            )
+            .arg(
+                Arg::with_name(MAX_HITS)
+                    .long("max_hits")
+                    .validator(is_valid::<usize>)
+                    .help("Maximum number of lines per target")
+                    .takes_value(true),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let mut targets = matches
+            .values_of(TARGETS)
+            .map(|v| v.map(ToOwned::to_owned).collect::<HashSet<_>>())
+            .unwrap_or_default();
+        if let Some(path) = matches.value_of(TARGETS_FILE) {
+            let reader = BufReader::new(File::open(path).expect("Can't open targets file."));
+            for line in reader.lines() {
+                let line = line.expect("Can't read targets file.");
+                let target = line.trim();
+                if !target.is_empty() {
+                    targets.insert(target.to_owned());
+                }
+            }
+        }
+        KwicApp {
+            corpus: corpus_from_matches(matches),
+            output_opts: output_options_from_matches(matches),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            targets,
+            width: value_t!(matches, WIDTH, usize).unwrap(),
+            max_hits: value_t!(matches, MAX_HITS, usize).ok(),
+        }
+    }
+
+    fn run(&self) {
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't open output to write concordance.");
+        self.kwic(&mut output).expect("Can't write concordance.");
+        output.finish().expect("Can't write concordance.");
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::str::FromStr;
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+use corpus_count::vocab::Vocab;
+
+use crate::output::{OutputFile, OutputOptions};
+use crate::rng::Rng;
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    counted_into_sorted, is_valid, output_args, output_options_from_matches, validate,
+    DEFAULT_CLAP_SETTINGS,
+};
+
+static CORPUS_WEIGHT: &str = "CORPUS_WEIGHT";
+static COUNTS: &str = "COUNTS";
+static DAMPENING: &str = "DAMPENING";
+static EPOCHS: &str = "EPOCHS";
+static LEXICON: &str = "LEXICON";
+static MIN_COUNT: &str = "MIN_COUNT";
+static MODEL: &str = "MODEL";
+static OUTPUT: &str = "OUTPUT";
+static SEED: &str = "SEED";
+
+/// Relative cost decrease below which training stops.
+const CONVERGENCE: f64 = 1e-4;
+
+/// Weighting of word counts in training.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Dampening {
+    /// Words are weighted by their counts.
+    None,
+    /// Words are weighted by the logarithm of their counts.
+    Log,
+    /// All words have weight 1.
+    Ones,
+}
+
+impl Dampening {
+    fn variants() -> &'static [&'static str] {
+        &["none", "log", "ones"]
+    }
+
+    fn weight(self, count: usize) -> f64 {
+        match self {
+            Dampening::None => count as f64,
+            Dampening::Log => (1. + count as f64).ln(),
+            Dampening::Ones => 1.,
+        }
+    }
+}
+
+impl FromStr for Dampening {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Dampening::None),
+            "log" => Ok(Dampening::Log),
+            "ones" => Ok(Dampening::Ones),
+            _ => Err(format!("Unknown dampening: {}", s)),
+        }
+    }
+}
+
+/// Morph lexicon with the two-part cost of Morfessor Baseline.
+///
+/// The cost is the weighted corpus cost, the negative log likelihood of the
+/// morph tokens, plus the lexicon cost of spelling out every morph type
+/// with the character distribution of the vocabulary.
+struct Model {
+    counts: HashMap<String, f64>,
+    tokens: f64,
+    /// Sum of `c ln c` over the morph counts.
+    count_entropy: f64,
+    lexicon_cost: f64,
+    char_costs: HashMap<char, f64>,
+    end_cost: f64,
+    corpus_weight: f64,
+}
+
+fn c_ln_c(count: f64) -> f64 {
+    if count > 0. {
+        count * count.ln()
+    } else {
+        0.
+    }
+}
+
+impl Model {
+    fn new<'a>(words: impl Iterator<Item = &'a str>, corpus_weight: f64) -> Self {
+        let mut char_counts = HashMap::new();
+        let mut n_words = 0;
+        for word in words {
+            for c in word.chars() {
+                *char_counts.entry(c).or_insert(0usize) += 1;
+            }
+            n_words += 1;
+        }
+        let total = (char_counts.values().sum::<usize>() + n_words) as f64;
+        Model {
+            counts: HashMap::new(),
+            tokens: 0.,
+            count_entropy: 0.,
+            lexicon_cost: 0.,
+            char_costs: char_counts
+                .into_iter()
+                .map(|(c, count)| (c, (total / count as f64).ln()))
+                .collect(),
+            end_cost: (total / n_words.max(1) as f64).ln(),
+            corpus_weight,
+        }
+    }
+
+    /// Cost of spelling out a morph of the vocabulary.
+    fn morph_cost(&self, morph: &str) -> f64 {
+        morph.chars().map(|c| self.char_costs[&c]).sum::<f64>() + self.end_cost
+    }
+
+    fn cost(&self) -> f64 {
+        self.corpus_weight * (c_ln_c(self.tokens) - self.count_entropy) + self.lexicon_cost
+    }
+
+    fn add(&mut self, morph: &str, weight: f64) {
+        let old = self.counts.get(morph).copied().unwrap_or(0.);
+        if old == 0. {
+            self.lexicon_cost += self.morph_cost(morph);
+        }
+        self.counts.insert(morph.to_owned(), old + weight);
+        self.count_entropy += c_ln_c(old + weight) - c_ln_c(old);
+        self.tokens += weight;
+    }
+
+    fn remove(&mut self, morph: &str, weight: f64) {
+        let old = self.counts[morph];
+        // Dampened weights are not integral, avoid leftover rounding errors.
+        let new = if old - weight < 1e-9 {
+            0.
+        } else {
+            old - weight
+        };
+        if new == 0. {
+            self.counts.remove(morph);
+            self.lexicon_cost -= self.morph_cost(morph);
+        } else {
+            self.counts.insert(morph.to_owned(), new);
+        }
+        self.count_entropy += c_ln_c(new) - c_ln_c(old);
+        self.tokens -= old - new;
+    }
+
+    /// Split `segment` recursively where that lowers the cost, the morphs
+    /// of the segment are added to the model.
+    fn resolve(&mut self, segment: &str, weight: f64, morphs: &mut Vec<String>) {
+        self.add(segment, weight);
+        let mut best = (self.cost(), None);
+        self.remove(segment, weight);
+        for (idx, _) in segment.char_indices().skip(1) {
+            let (prefix, suffix) = segment.split_at(idx);
+            self.add(prefix, weight);
+            self.add(suffix, weight);
+            let cost = self.cost();
+            if cost < best.0 {
+                best = (cost, Some(idx));
+            }
+            self.remove(suffix, weight);
+            self.remove(prefix, weight);
+        }
+        match best.1 {
+            Some(idx) => {
+                let (prefix, suffix) = segment.split_at(idx);
+                self.resolve(prefix, weight, morphs);
+                self.resolve(suffix, weight, morphs);
+            }
+            None => {
+                self.add(segment, weight);
+                morphs.push(segment.to_owned());
+            }
+        }
+    }
+}
+
+/// Segment a word into the most probable morphs of a lexicon.
+///
+/// Characters that start no morph are morphs of their own with count 1.
+fn viterbi(lexicon: &Vocab, ln_total: f64, max_len: usize, word: &str) -> Vec<String> {
+    let bounds = word
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .chain(Some(word.len()))
+        .collect::<Vec<_>>();
+    let n = bounds.len() - 1;
+    // Cost of the best segmentation of the prefix and its last morph start.
+    let mut best = vec![(f64::INFINITY, 0); n + 1];
+    best[0].0 = 0.;
+    for end in 1..=n {
+        for start in end.saturating_sub(max_len)..end {
+            let count = lexicon.count(&word[bounds[start]..bounds[end]]);
+            let count = match count {
+                Some(count) => count,
+                None if end == start + 1 => 1,
+                None => continue,
+            };
+            let cost = best[start].0 + ln_total - (count.max(1) as f64).ln();
+            if cost < best[end].0 {
+                best[end] = (cost, start);
+            }
+        }
+    }
+    let mut morphs = Vec::new();
+    let mut end = n;
+    while end > 0 {
+        let start = best[end].1;
+        morphs.push(word[bounds[start]..bounds[end]].to_owned());
+        end = start;
+    }
+    morphs.reverse();
+    morphs
+}
+
+/// Learn a morph lexicon from token counts and segment the vocabulary.
+pub struct LearnMorphsApp {
+    output_opts: OutputOptions,
+    counts: String,
+    output: Option<String>,
+    lexicon: Option<String>,
+    model: Option<String>,
+    min_count: usize,
+    corpus_weight: f64,
+    dampening: Dampening,
+    epochs: usize,
+    seed: u64,
+}
+
+impl LearnMorphsApp {
+    /// Train the model, returns the segmentations of the words.
+    fn train(&self, words: &[(&str, usize)]) -> Vec<Vec<String>> {
+        let mut model = Model::new(words.iter().map(|(word, _)| *word), self.corpus_weight);
+        let weights = words
+            .iter()
+            .map(|&(_, count)| self.dampening.weight(count))
+            .collect::<Vec<_>>();
+        let mut analyses = words
+            .iter()
+            .zip(&weights)
+            .map(|(&(word, _), &weight)| {
+                model.add(word, weight);
+                vec![word.to_owned()]
+            })
+            .collect::<Vec<_>>();
+
+        let mut rng = Rng::new(self.seed);
+        let mut order = (0..words.len()).collect::<Vec<_>>();
+        let mut cost = model.cost();
+        eprintln!("Initial cost {:.1}", cost);
+        for epoch in 1..=self.epochs {
+            rng.shuffle(&mut order);
+            for &idx in &order {
+                for morph in &analyses[idx] {
+                    model.remove(morph, weights[idx]);
+                }
+                let mut morphs = Vec::new();
+                model.resolve(words[idx].0, weights[idx], &mut morphs);
+                analyses[idx] = morphs;
+            }
+            let new_cost = model.cost();
+            eprintln!(
+                "Epoch {}: cost {:.1}, {} morph types",
+                epoch,
+                new_cost,
+                model.counts.len()
+            );
+            let converged = cost - new_cost < CONVERGENCE * cost.abs();
+            cost = new_cost;
+            if converged {
+                break;
+            }
+        }
+        analyses
+    }
+
+    fn write_segmentations(
+        &self,
+        words: &[(&str, usize)],
+        analyses: &[Vec<String>],
+    ) -> io::Result<()> {
+        let mut writer = OutputFile::create(self.output.as_deref(), &self.output_opts)?;
+        for ((word, _), morphs) in words.iter().zip(analyses) {
+            writeln!(writer, "{}\t{}", word, morphs.join(" "))?;
+        }
+        writer.finish()
+    }
+}
+
+impl CorpusCountApp for LearnMorphsApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("learn-morphs")
+            .about("Learn a morph lexicon from token counts with Morfessor Baseline")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&output_args())
+            .arg(
+                Arg::with_name(COUNTS)
+                    .long("counts")
+                    .help("Token count file of the vocabulary")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("File for word<TAB>morphs segmentations of the vocabulary")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(LEXICON)
+                    .long("lexicon")
+                    .help("File for morph counts of the learned lexicon")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(MODEL)
+                    .long("model")
+                    .help(
+                        "Segment with the most probable morphs of this lexicon instead of training",
+                    )
+                    .takes_value(true)
+                    .conflicts_with(LEXICON),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT)
+                    .long("min_count")
+                    .validator(is_valid::<usize>)
+                    .default_value("1")
+                    .help("Token min count"),
+            )
+            .arg(
+                Arg::with_name(CORPUS_WEIGHT)
+                    .long("corpus_weight")
+                    .validator(|v| {
+                        validate(
+                            &v,
+                            |&f: &f64| f > 0.,
+                            "The corpus weight should be positive.",
+                        )
+                    })
+                    .default_value("1")
+                    .help("Weight of the corpus cost, higher weights give shorter morphs"),
+            )
+            .arg(
+                Arg::with_name(DAMPENING)
+                    .long("dampening")
+                    .validator(is_valid::<Dampening>)
+                    .possible_values(Dampening::variants())
+                    .default_value("none")
+                    .help("Weight words by their counts, log counts or uniformly"),
+            )
+            .arg(
+                Arg::with_name(EPOCHS)
+                    .long("epochs")
+                    .validator(is_valid::<usize>)
+                    .default_value("10")
+                    .help("Maximum number of training epochs"),
+            )
+            .arg(
+                Arg::with_name(SEED)
+                    .long("seed")
+                    .validator(is_valid::<u64>)
+                    .default_value("42")
+                    .help("Seed of the random number generator"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let corpus_weight = value_t!(matches, CORPUS_WEIGHT, f64).unwrap();
+        LearnMorphsApp {
+            output_opts: output_options_from_matches(matches),
+            counts: matches.value_of(COUNTS).map(ToOwned::to_owned).unwrap(),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            lexicon: matches.value_of(LEXICON).map(ToOwned::to_owned),
+            model: matches.value_of(MODEL).map(ToOwned::to_owned),
+            min_count: value_t!(matches, MIN_COUNT, usize).unwrap(),
+            corpus_weight,
+            dampening: value_t!(matches, DAMPENING, Dampening).unwrap(),
+            epochs: value_t!(matches, EPOCHS, usize).unwrap(),
+            seed: value_t!(matches, SEED, u64).unwrap(),
+        }
+    }
+
+    fn run(&self) {
+        let vocab = Vocab::read(BufReader::new(
+            File::open(&self.counts).expect("Can't open token counts."),
+        ))
+        .expect("Can't read token counts.");
+        let words = vocab
+            .iter()
+            .filter(|&(_, count)| count >= self.min_count)
+            .collect::<Vec<_>>();
+
+        if let Some(model) = &self.model {
+            let lexicon = Vocab::read(BufReader::new(
+                File::open(model).expect("Can't open morph lexicon."),
+            ))
+            .expect("Can't read morph lexicon.");
+            let total = lexicon.iter().map(|(_, count)| count).sum::<usize>();
+            let ln_total = (total.max(1) as f64).ln();
+            let max_len = lexicon
+                .iter()
+                .map(|(morph, _)| morph.chars().count())
+                .max()
+                .unwrap_or(1);
+            let analyses = words
+                .iter()
+                .map(|(word, _)| viterbi(&lexicon, ln_total, max_len, word))
+                .collect::<Vec<_>>();
+            self.write_segmentations(&words, &analyses)
+                .expect("Can't write segmentations.");
+            return;
+        }
+
+        let analyses = self.train(&words);
+        self.write_segmentations(&words, &analyses)
+            .expect("Can't write segmentations.");
+        if let Some(lexicon) = &self.lexicon {
+            let mut counts = HashMap::new();
+            for ((_, count), morphs) in words.iter().zip(&analyses) {
+                for morph in morphs {
+                    *counts.entry(morph.clone()).or_insert(0) += count;
+                }
+            }
+            let mut writer = OutputFile::create(Some(lexicon), &self.output_opts)
+                .expect("Can't create morph lexicon.");
+            for (morph, count) in counted_into_sorted(counts, None) {
+                writeln!(writer, "{}\t{}", morph, count).expect("Can't write morph lexicon.");
+            }
+            writer.finish().expect("Can't write morph lexicon.");
+        }
+    }
+}
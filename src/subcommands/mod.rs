@@ -0,0 +1,87 @@
+//! Subcommands besides token and ngram counting.
+
+use clap::{App, ArgMatches};
+
+mod affixes;
+pub use affixes::AffixesApp;
+
+mod aligned;
+pub use aligned::AlignedApp;
+
+mod boilerplate;
+pub use boilerplate::BoilerplateApp;
+
+mod bucket_report;
+pub use bucket_report::BucketReportApp;
+
+mod check_embeddings;
+pub use check_embeddings::CheckEmbeddingsApp;
+
+mod compounds;
+pub use compounds::CompoundsApp;
+
+mod cooccurrence;
+pub use cooccurrence::CooccurrenceApp;
+
+mod encode;
+pub use encode::EncodeApp;
+
+mod fasttext_vocab;
+pub use fasttext_vocab::FastTextVocabApp;
+
+mod filter_lines;
+pub use filter_lines::FilterLinesApp;
+
+mod kwic;
+pub use kwic::KwicApp;
+
+mod learn_morphs;
+pub use learn_morphs::LearnMorphsApp;
+
+mod ocr_report;
+pub use ocr_report::OcrReportApp;
+
+mod phrases;
+pub use phrases::PhrasesApp;
+
+mod query;
+pub use query::QueryApp;
+
+mod serve;
+pub use serve::ServeApp;
+
+mod shuffle;
+pub use shuffle::ShuffleApp;
+
+mod split;
+pub use split::SplitApp;
+
+mod stream;
+pub use stream::StreamApp;
+
+mod subsample;
+pub use subsample::SubsampleApp;
+
+mod update;
+pub use update::UpdateApp;
+
+mod variants;
+pub use variants::VariantsApp;
+
+mod word_ngrams;
+pub use word_ngrams::WordNGramsApp;
+
+/// A corpus-count subcommand.
+pub trait CorpusCountApp
+where
+    Self: Sized,
+{
+    /// Get the clap definition of the subcommand.
+    fn app() -> App<'static, 'static>;
+
+    /// Construct the subcommand from its arguments.
+    fn parse(matches: &ArgMatches) -> Self;
+
+    /// Run the subcommand.
+    fn run(&self);
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufReader, Write};
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+use corpus_count::charlm::CharLm;
+use corpus_count::vocab::Vocab;
+
+use crate::output::{OutputFile, OutputOptions};
+use crate::script::Script;
+use crate::subcommands::CorpusCountApp;
+use crate::{is_valid, non_zero, output_args, output_options_from_matches, DEFAULT_CLAP_SETTINGS};
+
+static COUNTS: &str = "COUNTS";
+static MAX_REPEAT: &str = "MAX_REPEAT";
+static MIN_LOGPROB: &str = "MIN_LOGPROB";
+static ORDER: &str = "ORDER";
+static OUTPUT: &str = "OUTPUT";
+
+/// Names of the heuristics, in the order of the flags.
+const HEURISTICS: [&str; 3] = ["mixed_script", "repeat", "improbable"];
+
+/// Check whether a token mixes scripts.
+///
+/// Han, hiragana and katakana are one script, since Japanese mixes them.
+fn mixed_script(token: &str) -> bool {
+    let scripts = token
+        .chars()
+        .map(|c| match Script::of(c) {
+            Script::Hiragana | Script::Katakana => Script::Han,
+            script => script,
+        })
+        .filter(|&script| script != Script::Common && script != Script::Inherited)
+        .collect::<BTreeSet<_>>();
+    scripts.len() > 1
+}
+
+/// Get the length of the longest run of a character.
+fn longest_run(token: &str) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev = None;
+    for c in token.chars() {
+        run = if prev == Some(c) { run + 1 } else { 1 };
+        longest = longest.max(run);
+        prev = Some(c);
+    }
+    longest
+}
+
+/// Report tokens that are likely OCR noise.
+pub struct OcrReportApp {
+    output_opts: OutputOptions,
+    counts: String,
+    output: Option<String>,
+    max_repeat: usize,
+    min_logprob: f64,
+    order: usize,
+}
+
+impl OcrReportApp {
+    /// Get the heuristics that flag a token.
+    fn flags(&self, lm: &CharLm, token: &str) -> ([bool; 3], f64) {
+        let logprob = lm.held_out_log_prob_per_char(token);
+        (
+            [
+                mixed_script(token),
+                longest_run(token) > self.max_repeat,
+                logprob < self.min_logprob,
+            ],
+            logprob,
+        )
+    }
+}
+
+impl CorpusCountApp for OcrReportApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("ocr-report")
+            .about("Report tokens that are likely OCR noise")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&output_args())
+            .arg(
+                Arg::with_name(COUNTS)
+                    .long("counts")
+                    .help("Token count file of the vocabulary")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("File for token<TAB>count<TAB>logprob<TAB>flags lines of flagged tokens")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(MAX_REPEAT)
+                    .long("max_repeat")
+                    .validator(is_valid::<usize>)
+                    .default_value("3")
+                    .help("Flag tokens that repeat a character more often"),
+            )
+            .arg(
+                Arg::with_name(MIN_LOGPROB)
+                    .long("min_logprob")
+                    .validator(is_valid::<f64>)
+                    .default_value("-4")
+                    .allow_hyphen_values(true)
+                    .help("Flag tokens with a lower log-probability per character"),
+            )
+            .arg(
+                Arg::with_name(ORDER)
+                    .long("order")
+                    .validator(non_zero("The order should be positive."))
+                    .default_value("4")
+                    .help("Order of the character language model"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let order = value_t!(matches, ORDER, usize).unwrap();
+        OcrReportApp {
+            output_opts: output_options_from_matches(matches),
+            counts: matches.value_of(COUNTS).map(ToOwned::to_owned).unwrap(),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            max_repeat: value_t!(matches, MAX_REPEAT, usize).unwrap(),
+            min_logprob: value_t!(matches, MIN_LOGPROB, f64).unwrap(),
+            order,
+        }
+    }
+
+    fn run(&self) {
+        let vocab = Vocab::read(BufReader::new(
+            File::open(&self.counts).expect("Can't open token counts."),
+        ))
+        .expect("Can't read token counts.");
+        let lm = CharLm::from_token_counts(vocab.iter(), self.order, true);
+
+        let mut writer = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't create output.");
+        // Flagged types and tokens per heuristic and in total.
+        let mut flagged = [(0, 0); 4];
+        let mut tokens = 0;
+        for (token, count) in vocab.iter() {
+            tokens += count;
+            let (flags, logprob) = self.flags(&lm, token);
+            if !flags.iter().any(|&flag| flag) {
+                continue;
+            }
+            let names = HEURISTICS
+                .iter()
+                .zip(&flags)
+                .filter(|(_, &flag)| flag)
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>();
+            writeln!(
+                writer,
+                "{}\t{}\t{:.3}\t{}",
+                token,
+                count,
+                logprob,
+                names.join(",")
+            )
+            .expect("Can't write OCR report.");
+            for (stats, &flag) in flagged.iter_mut().zip(flags.iter().chain(Some(&true))) {
+                if flag {
+                    stats.0 += 1;
+                    stats.1 += count;
+                }
+            }
+        }
+        writer.finish().expect("Can't write OCR report.");
+
+        for (name, (types, flagged_tokens)) in HEURISTICS.iter().chain(Some(&"total")).zip(&flagged)
+        {
+            eprintln!(
+                "{}: {} types, {} tokens ({:.2}% of tokens)",
+                name,
+                types,
+                flagged_tokens,
+                100. * *flagged_tokens as f64 / tokens.max(1) as f64
+            );
+        }
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use clap::{value_t, values_t, App, Arg, ArgMatches, SubCommand};
+
+use crate::input::Corpus;
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    corpus_args, corpus_from_matches, is_valid, output_args, output_options_from_matches,
+    DEFAULT_CLAP_SETTINGS,
+};
+
+static COLLOCATIONS: &str = "COLLOCATIONS";
+static COLLOCATION_MEASURES: &str = "COLLOCATION_MEASURES";
+static DELIMITER: &str = "DELIMITER";
+static MIN_COUNT: &str = "MIN_COUNT";
+static OUTPUT: &str = "OUTPUT";
+static REWRITE_OUT: &str = "REWRITE_OUT";
+static SCORING: &str = "SCORING";
+static THRESHOLD: &str = "THRESHOLD";
+
+/// Scoring function for phrase candidates.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Scoring {
+    /// Phrase score of word2phrase.
+    Word2Vec,
+    /// Normalized pointwise mutual information.
+    Npmi,
+}
+
+impl Scoring {
+    fn variants() -> &'static [&'static str] {
+        &["word2vec", "npmi"]
+    }
+
+    fn default_threshold(self) -> f64 {
+        match self {
+            Scoring::Word2Vec => 100.,
+            Scoring::Npmi => 0.5,
+        }
+    }
+}
+
+impl FromStr for Scoring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "word2vec" => Ok(Scoring::Word2Vec),
+            "npmi" => Ok(Scoring::Npmi),
+            _ => Err(format!("Unknown phrase scoring: {}", s)),
+        }
+    }
+}
+
+/// Unigram and bigram counts.
+struct Counts {
+    unigrams: HashMap<String, usize>,
+    bigrams: HashMap<String, usize>,
+    total: usize,
+}
+
+impl Counts {
+    /// Get the unigram counts of the tokens of a bigram.
+    fn unigram_counts(&self, bigram: &str) -> (usize, usize) {
+        let (first, second) = bigram.split_once(' ').expect("Bigram without space");
+        (self.unigrams[first], self.unigrams[second])
+    }
+}
+
+/// Association measure of a bigram.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Measure {
+    /// Pointwise mutual information in bits.
+    Pmi,
+    /// t-score of the observed against the expected count.
+    TScore,
+    /// logDice of Rychlý (2008).
+    LogDice,
+}
+
+impl Measure {
+    fn variants() -> &'static [&'static str] {
+        &["pmi", "tscore", "logdice"]
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Measure::Pmi => "pmi",
+            Measure::TScore => "tscore",
+            Measure::LogDice => "logdice",
+        }
+    }
+
+    fn compute(self, count: usize, first: usize, second: usize, total: usize) -> f64 {
+        let (count, first, second, total) =
+            (count as f64, first as f64, second as f64, total as f64);
+        match self {
+            Measure::Pmi => (count * total / (first * second)).log2(),
+            Measure::TScore => (count - first * second / total) / count.sqrt(),
+            Measure::LogDice => 14. + (2. * count / (first + second)).log2(),
+        }
+    }
+}
+
+impl FromStr for Measure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pmi" => Ok(Measure::Pmi),
+            "tscore" => Ok(Measure::TScore),
+            "logdice" => Ok(Measure::LogDice),
+            _ => Err(format!("Unknown collocation measure: {}", s)),
+        }
+    }
+}
+
+/// Detect phrases from unigram and bigram counts.
+pub struct PhrasesApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    output: Option<String>,
+    rewrite_out: Option<String>,
+    collocations: Option<String>,
+    measures: Vec<Measure>,
+    scoring: Scoring,
+    threshold: f64,
+    min_count: usize,
+    delimiter: String,
+}
+
+impl PhrasesApp {
+    /// Count unigrams and bigrams within lines.
+    ///
+    /// Bigrams are keyed by the tokens joined with a space, which cannot
+    /// occur within tokens.
+    fn count(&self) -> io::Result<Counts> {
+        let mut unigrams = HashMap::new();
+        let mut bigrams = HashMap::new();
+        let mut total = 0;
+        let mut bigram = String::new();
+        self.corpus.for_each_line(|line| {
+            let mut prev = None;
+            for token in line.split_whitespace() {
+                total += 1;
+                count(&mut unigrams, token);
+                if let Some(prev) = prev {
+                    join(&mut bigram, prev, token);
+                    count(&mut bigrams, &bigram);
+                }
+                prev = Some(token);
+            }
+            Ok(())
+        })?;
+        Ok(Counts {
+            unigrams,
+            bigrams,
+            total,
+        })
+    }
+
+    /// Score the bigrams, returns the phrases that pass the threshold.
+    fn score(&self, counts: &Counts) -> HashMap<String, (usize, f64)> {
+        let total = counts.total as f64;
+        let mut phrases = HashMap::new();
+        for (bigram, &count) in &counts.bigrams {
+            if count < self.min_count {
+                continue;
+            }
+            let (first, second) = counts.unigram_counts(bigram);
+            let (first, second, count_f) = (first as f64, second as f64, count as f64);
+            let score = match self.scoring {
+                Scoring::Word2Vec => (count_f - self.min_count as f64) / (first * second) * total,
+                Scoring::Npmi => {
+                    let p_pair = count_f / total;
+                    let pmi = (p_pair / ((first / total) * (second / total))).ln();
+                    pmi / -p_pair.ln()
+                }
+            };
+            if score > self.threshold {
+                phrases.insert(bigram.clone(), (count, score));
+            }
+        }
+        phrases
+    }
+
+    /// Write the association measures of the bigrams.
+    ///
+    /// Bigrams are ranked by the first measure.
+    fn write_collocations(&self, counts: &Counts, mut writer: impl Write) -> io::Result<()> {
+        let mut collocations = counts
+            .bigrams
+            .iter()
+            .filter(|(_, &count)| count >= self.min_count)
+            .map(|(bigram, &count)| {
+                let (first, second) = counts.unigram_counts(bigram);
+                let scores = self
+                    .measures
+                    .iter()
+                    .map(|measure| measure.compute(count, first, second, counts.total))
+                    .collect::<Vec<_>>();
+                (bigram, count, scores)
+            })
+            .collect::<Vec<_>>();
+        collocations.sort_unstable_by(|(bigram1, _, scores1), (bigram2, _, scores2)| {
+            scores2[0]
+                .partial_cmp(&scores1[0])
+                .unwrap()
+                .then_with(|| bigram1.cmp(bigram2))
+        });
+        write!(writer, "first\tsecond\tcount")?;
+        for measure in &self.measures {
+            write!(writer, "\t{}", measure.name())?;
+        }
+        writeln!(writer)?;
+        for (bigram, count, scores) in collocations {
+            write!(writer, "{}\t{}", bigram.replacen(' ', "\t", 1), count)?;
+            for score in scores {
+                write!(writer, "\t{:.6}", score)?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+
+    fn write_phrases(
+        &self,
+        phrases: &HashMap<String, (usize, f64)>,
+        mut writer: impl Write,
+    ) -> io::Result<()> {
+        let mut phrases = phrases.iter().collect::<Vec<_>>();
+        phrases.sort_unstable_by(|(phrase1, (_, score1)), (phrase2, (_, score2))| {
+            score2
+                .partial_cmp(score1)
+                .unwrap()
+                .then_with(|| phrase1.cmp(phrase2))
+        });
+        for (phrase, (count, score)) in phrases {
+            let phrase = phrase.replacen(' ', &self.delimiter, 1);
+            writeln!(writer, "{}\t{}\t{:.6}", phrase, count, score)?;
+        }
+        writer.flush()
+    }
+
+    /// Write the corpus with phrases joined by the delimiter.
+    ///
+    /// Phrases are joined greedily from left to right, a token is part of
+    /// at most one phrase per pass.
+    fn rewrite(
+        &self,
+        phrases: &HashMap<String, (usize, f64)>,
+        mut writer: impl Write,
+    ) -> io::Result<()> {
+        let mut bigram = String::new();
+        let mut joined = 0usize;
+        self.corpus.for_each_line(|line| {
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            let mut idx = 0;
+            while idx < tokens.len() {
+                if idx > 0 {
+                    writer.write_all(b" ")?;
+                }
+                if let Some(next) = tokens.get(idx + 1) {
+                    join(&mut bigram, tokens[idx], next);
+                    if phrases.contains_key(&bigram) {
+                        write!(writer, "{}{}{}", tokens[idx], self.delimiter, next)?;
+                        joined += 1;
+                        idx += 2;
+                        continue;
+                    }
+                }
+                writer.write_all(tokens[idx].as_bytes())?;
+                idx += 1;
+            }
+            writer.write_all(b"\n")
+        })?;
+        writer.flush()?;
+        eprintln!("Joined {} phrases", joined);
+        Ok(())
+    }
+}
+
+fn join(bigram: &mut String, first: &str, second: &str) {
+    bigram.clear();
+    bigram.push_str(first);
+    bigram.push(' ');
+    bigram.push_str(second);
+}
+
+fn count(counts: &mut HashMap<String, usize>, key: &str) {
+    if let Some(cnt) = counts.get_mut(key) {
+        *cnt += 1;
+    } else {
+        counts.insert(key.to_owned(), 1);
+    }
+}
+
+impl CorpusCountApp for PhrasesApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("phrases")
+            .about("Detect phrases from bigram counts and join them in the corpus")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("Phrase table with counts and scores")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(REWRITE_OUT)
+                    .long("rewrite_out")
+                    .help("File for the corpus with phrases joined")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(COLLOCATIONS)
+                    .long("collocations")
+                    .help("File for a ranked table of bigram association measures")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(COLLOCATION_MEASURES)
+                    .long("collocation_measures")
+                    .validator(is_valid::<Measure>)
+                    .possible_values(Measure::variants())
+                    .use_delimiter(true)
+                    .default_value("pmi,tscore,logdice")
+                    .help("Association measures of the collocation table, ranked by the first"),
+            )
+            .arg(
+                Arg::with_name(SCORING)
+                    .long("scoring")
+                    .validator(is_valid::<Scoring>)
+                    .possible_values(Scoring::variants())
+                    .default_value("word2vec")
+                    .help("Phrase scoring function"),
+            )
+            .arg(
+                Arg::with_name(THRESHOLD)
+                    .long("threshold")
+                    .validator(is_valid::<f64>)
+                    .help("Minimum phrase score (default: 100 for word2vec, 0.5 for npmi)")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT)
+                    .long("min_count")
+                    .validator(is_valid::<usize>)
+                    .default_value("5")
+                    .help("Bigram min count"),
+            )
+            .arg(
+                Arg::with_name(DELIMITER)
+                    .long("delimiter")
+                    .validator(|v| {
+                        if v.is_empty() || v.contains(char::is_whitespace) {
+                            Err(
+                                "The phrase delimiter cannot be empty or contain whitespace."
+                                    .to_owned(),
+                            )
+                        } else {
+                            Ok(())
+                        }
+                    })
+                    .default_value("_")
+                    .help("Delimiter joining the tokens of a phrase"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let scoring = value_t!(matches, SCORING, Scoring).unwrap();
+        let delimiter = matches.value_of(DELIMITER).unwrap().to_owned();
+        PhrasesApp {
+            corpus: corpus_from_matches(matches),
+            output_opts: output_options_from_matches(matches),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            rewrite_out: matches.value_of(REWRITE_OUT).map(ToOwned::to_owned),
+            collocations: matches.value_of(COLLOCATIONS).map(ToOwned::to_owned),
+            measures: values_t!(matches, COLLOCATION_MEASURES, Measure).unwrap(),
+            scoring,
+            threshold: value_t!(matches, THRESHOLD, f64)
+                .unwrap_or_else(|_| scoring.default_threshold()),
+            min_count: value_t!(matches, MIN_COUNT, usize).unwrap(),
+            delimiter,
+        }
+    }
+
+    fn run(&self) {
+        assert!(
+            self.rewrite_out.is_none() || !self.corpus.paths.is_empty(),
+            "Rewriting requires corpus files, stdin can only be read once."
+        );
+        let counts = self.count().expect("Can't read corpus");
+        if let Some(collocations) = &self.collocations {
+            let mut output = OutputFile::create(Some(collocations), &self.output_opts)
+                .expect("Can't open output to write collocations.");
+            self.write_collocations(&counts, &mut output)
+                .expect("Can't write collocations.");
+            output.finish().expect("Can't write collocations.");
+        }
+        let phrases = self.score(&counts);
+        eprintln!("Found {} phrases", phrases.len());
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't open output to write phrases.");
+        self.write_phrases(&phrases, &mut output)
+            .expect("Can't write phrases.");
+        output.finish().expect("Can't write phrases.");
+        if let Some(rewrite_out) = &self.rewrite_out {
+            let mut output = OutputFile::create(Some(rewrite_out), &self.output_opts)
+                .expect("Can't open output to write rewritten corpus.");
+            self.rewrite(&phrases, &mut output)
+                .expect("Can't rewrite corpus.");
+            output.finish().expect("Can't write rewritten corpus.");
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+use corpus_count::fst::{Map, MapBuilder};
+use corpus_count::sort::count_order;
+use corpus_count::vocab::Vocab;
+
+use crate::subcommands::CorpusCountApp;
+use crate::{is_valid, DEFAULT_CLAP_SETTINGS};
+
+static COUNTS: &str = "COUNTS";
+static CCFS: &str = "CCFS";
+static QUERIES: &str = "QUERIES";
+static TOP_K: &str = "TOP_K";
+
+/// Query token counts by token and prefix.
+pub struct QueryApp {
+    counts: Option<String>,
+    ccfs: Option<String>,
+    queries: Option<String>,
+    top_k: usize,
+}
+
+impl QueryApp {
+    fn load(&self) -> Map<Vec<u8>> {
+        if let Some(ccfs) = &self.ccfs {
+            return Map::new(fs::read(ccfs).expect("Can't read token map."))
+                .expect("Can't read token map.");
+        }
+        let vocab = Vocab::read(BufReader::new(
+            File::open(self.counts.as_ref().unwrap()).expect("Can't open token counts."),
+        ))
+        .expect("Can't read token counts.");
+        let mut tokens = vocab.iter().collect::<Vec<_>>();
+        tokens.sort_unstable_by_key(|&(token, _)| token);
+        let mut builder = MapBuilder::new();
+        for (token, count) in tokens {
+            builder
+                .insert(token, count as u64)
+                .expect("Can't build token map.");
+        }
+        builder.finish()
+    }
+
+    /// Answer a query, returns `false` for invalid queries.
+    fn answer(&self, map: &Map<Vec<u8>>, query: &str, mut writer: impl Write) -> io::Result<bool> {
+        let (command, arg) = query.split_once(' ').unwrap_or((query, ""));
+        match command {
+            "count" => writeln!(writer, "{}\t{}", arg, map.get(arg).unwrap_or(0))?,
+            "sum" => {
+                let entries = map.prefix(arg);
+                let sum = entries.iter().map(|(_, count)| count).sum::<u64>();
+                writeln!(writer, "{}\t{}\t{}", arg, entries.len(), sum)?;
+            }
+            "top" => {
+                let entries = map
+                    .prefix(arg)
+                    .into_iter()
+                    .map(|(token, count)| (String::from_utf8_lossy(&token).into_owned(), count))
+                    .collect::<Vec<_>>();
+                let mut top = entries.iter().collect::<Vec<_>>();
+                top.sort_unstable_by(|(t1, c1), (t2, c2)| {
+                    count_order(t1, *c1 as usize, t2, *c2 as usize)
+                });
+                for (token, count) in top.into_iter().take(self.top_k) {
+                    writeln!(writer, "{}\t{}", token, count)?;
+                }
+                writeln!(writer)?;
+            }
+            _ => return Ok(false),
+        }
+        writer.flush()?;
+        Ok(true)
+    }
+}
+
+impl CorpusCountApp for QueryApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("query")
+            .about("Query token counts by token and prefix")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .arg(
+                Arg::with_name(COUNTS)
+                    .long("counts")
+                    .help("Token count file")
+                    .takes_value(true)
+                    .required_unless(CCFS),
+            )
+            .arg(
+                Arg::with_name(CCFS)
+                    .long("ccfs")
+                    .help("Token map of --ccfs_out --ccfs_counts")
+                    .conflicts_with(COUNTS)
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(QUERIES)
+                    .long("queries")
+                    .help("File with a query per line, queries are read from stdin otherwise")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(TOP_K)
+                    .long("top_k")
+                    .validator(is_valid::<usize>)
+                    .default_value("10")
+                    .help("Number of completions of top queries"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        QueryApp {
+            counts: matches.value_of(COUNTS).map(ToOwned::to_owned),
+            ccfs: matches.value_of(CCFS).map(ToOwned::to_owned),
+            queries: matches.value_of(QUERIES).map(ToOwned::to_owned),
+            top_k: value_t!(matches, TOP_K, usize).unwrap(),
+        }
+    }
+
+    fn run(&self) {
+        let map = self.load();
+        let stdin = io::stdin();
+        let (reader, interactive): (Box<dyn BufRead>, bool) = match &self.queries {
+            Some(queries) => (
+                Box::new(BufReader::new(
+                    File::open(queries).expect("Can't open queries."),
+                )),
+                false,
+            ),
+            None => (Box::new(stdin.lock()), io::stdin().is_terminal()),
+        };
+        if interactive {
+            eprintln!(
+                "Loaded {} tokens. Queries: count TOKEN, sum PREFIX, top PREFIX",
+                map.len()
+            );
+        }
+
+        let stdout = io::stdout();
+        let mut writer = stdout.lock();
+        if interactive {
+            eprint!("> ");
+        }
+        for query in reader.lines() {
+            let query = query.expect("Can't read queries.");
+            if !self
+                .answer(&map, &query, &mut writer)
+                .expect("Can't write answers.")
+            {
+                eprintln!("Invalid query: {}", query);
+            }
+            if interactive {
+                eprint!("> ");
+            }
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::thread;
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+use corpus_count::ngrams;
+use corpus_count::options::Options;
+
+use crate::count::{CountOptions, StreamCounter};
+use crate::filter::TokenFilter;
+use crate::output::{OutputFile, OutputOptions};
+use crate::sort::count_order;
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    counted_into_sorted, filter_args, filter_from_matches, is_valid, non_zero, output_args,
+    output_options_from_matches, usage_error, DEFAULT_CLAP_SETTINGS,
+};
+
+static LISTEN: &str = "LISTEN";
+static MAX_N: &str = "MAX_N";
+static MIN_N: &str = "MIN_N";
+static NGRAM_COUNTS: &str = "NGRAM_COUNTS";
+static NO_BRACKET: &str = "NO_BRACKET";
+static TOKEN_COUNTS: &str = "TOKEN_COUNTS";
+
+/// Maximum size of a request body.
+const MAX_BODY: usize = 64 << 20;
+
+/// Serve token and n-gram counts over HTTP.
+pub struct ServeApp {
+    output_opts: OutputOptions,
+    listen: String,
+    token_counts: Option<String>,
+    ngram_counts: Option<String>,
+    options: Options,
+    filter: TokenFilter,
+}
+
+/// Counts of all submitted text.
+#[derive(Default)]
+struct State {
+    tokens: HashMap<String, usize>,
+    ngrams: HashMap<String, usize>,
+    num_tokens: usize,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn ok(body: String) -> Self {
+        Response {
+            status: "200 OK",
+            body,
+        }
+    }
+
+    fn error(status: &'static str, msg: &str) -> Self {
+        Response {
+            status,
+            body: format!("{}\n", msg),
+        }
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Decode a percent-encoded query component.
+fn percent_decode(s: &str) -> String {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                // Only two ASCII hex digits, `from_str_radix` would also
+                // accept a sign like in `%+f`.
+                let mut digits = iter.clone().map(|b| char::from(b).to_digit(16));
+                match (digits.next().flatten(), digits.next().flatten()) {
+                    (Some(high), Some(low)) => {
+                        bytes.push((high * 16 + low) as u8);
+                        iter.nth(1);
+                    }
+                    _ => bytes.push(b'%'),
+                }
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Read an HTTP/1.1 request.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+        _ => return Err(invalid_data("Invalid request line")),
+    };
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data("Incomplete request headers"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid_data("Invalid Content-Length"))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(invalid_data("Request body too large"));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        query,
+        body,
+    })
+}
+
+impl ServeApp {
+    fn handle(&self, state: &Mutex<State>, request: Request) -> Response {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/text") => {
+                let text = match String::from_utf8(request.body) {
+                    Ok(text) => text,
+                    Err(_) => return Response::error("400 Bad Request", "Text is not UTF-8"),
+                };
+                Response::ok(self.submit(state, &text))
+            }
+            ("GET", "/token") | ("GET", "/ngram") => {
+                let item = match request.query.get("q") {
+                    Some(item) => item,
+                    None => return Response::error("400 Bad Request", "Missing parameter q"),
+                };
+                let state = state.lock().unwrap();
+                let counts = if request.path == "/token" {
+                    &state.tokens
+                } else {
+                    &state.ngrams
+                };
+                Response::ok(format!("{}\n", counts.get(item).copied().unwrap_or(0)))
+            }
+            ("GET", "/top") => {
+                let k = match request.query.get("k").map(|k| k.parse::<usize>()) {
+                    Some(Ok(k)) => k,
+                    None => 10,
+                    Some(Err(_)) => return Response::error("400 Bad Request", "Invalid k"),
+                };
+                let state = state.lock().unwrap();
+                let mut top = state.tokens.iter().collect::<Vec<_>>();
+                top.sort_unstable_by(|(t1, c1), (t2, c2)| count_order(t1, **c1, t2, **c2));
+                let body = top
+                    .into_iter()
+                    .take(k)
+                    .map(|(token, count)| format!("{}\t{}\n", token, count))
+                    .collect();
+                Response::ok(body)
+            }
+            ("POST", "/snapshot") => match self.snapshot(state) {
+                Ok(()) => Response::ok("ok\n".to_string()),
+                Err(err) => Response::error("500 Internal Server Error", &err.to_string()),
+            },
+            (_, "/text") | (_, "/token") | (_, "/ngram") | (_, "/top") | (_, "/snapshot") => {
+                Response::error("405 Method Not Allowed", "Method not allowed")
+            }
+            _ => Response::error("404 Not Found", "Not found"),
+        }
+    }
+
+    /// Count submitted text, get the number of lines and tokens counted.
+    fn submit(&self, state: &Mutex<State>, text: &str) -> String {
+        let opts = CountOptions {
+            filter: self.filter.clone(),
+            ..CountOptions::default()
+        };
+        let mut counter = StreamCounter::new(&opts);
+        for line in text.lines() {
+            counter.count_line(line);
+        }
+        let counts = counter.take_counts();
+        let ngrams = ngrams::count_ngrams(
+            counts
+                .tokens
+                .iter()
+                .map(|(token, &count)| (token.as_str(), count)),
+            &self.options,
+        );
+
+        let mut state = state.lock().unwrap();
+        state.num_tokens += counts.num_tokens;
+        for (token, count) in counts.tokens {
+            *state.tokens.entry(token).or_insert(0) += count;
+        }
+        for (ngram, count) in ngrams {
+            *state.ngrams.entry(ngram).or_insert(0) += count;
+        }
+        format!("{}\t{}\n", counts.lines, counts.num_tokens)
+    }
+
+    /// Write the current counts to the count files.
+    fn snapshot(&self, state: &Mutex<State>) -> io::Result<()> {
+        let (tokens, ngrams) = {
+            let state = state.lock().unwrap();
+            (state.tokens.clone(), state.ngrams.clone())
+        };
+        let outputs = [(&self.token_counts, tokens), (&self.ngram_counts, ngrams)];
+        for (path, counts) in outputs {
+            if let Some(path) = path {
+                let mut writer = OutputFile::create(Some(path), &self.output_opts)?;
+                for (item, count) in counted_into_sorted(counts, None) {
+                    writeln!(writer, "{}\t{}", item, count)?;
+                }
+                writer.finish()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn serve_connection(&self, state: &Mutex<State>, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let response = match read_request(&mut reader) {
+            Ok(request) => self.handle(state, request),
+            Err(err) => Response::error("400 Bad Request", &err.to_string()),
+        };
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status,
+            response.body.len(),
+            response.body
+        )?;
+        stream.flush()
+    }
+}
+
+impl CorpusCountApp for ServeApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("serve")
+            .about("Serve token and n-gram counts of submitted text over HTTP")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&output_args())
+            .args(&filter_args())
+            .arg(
+                Arg::with_name(LISTEN)
+                    .long("listen")
+                    .default_value("127.0.0.1:8080")
+                    .help("Address to serve on"),
+            )
+            .arg(
+                Arg::with_name(TOKEN_COUNTS)
+                    .long("token_counts")
+                    .short("t")
+                    .help("Token count file written on snapshots")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(NGRAM_COUNTS)
+                    .long("ngram_counts")
+                    .short("n")
+                    .help("Ngram count file written on snapshots")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(MIN_N)
+                    .long("min_n")
+                    .validator(non_zero("The minimum n-gram length cannot be zero."))
+                    .default_value("3")
+                    .help("Minimal ngram length to be used."),
+            )
+            .arg(
+                Arg::with_name(MAX_N)
+                    .long("max_n")
+                    .validator(is_valid::<usize>)
+                    .default_value("6")
+                    .help("Maximum ngram length to be used."),
+            )
+            .arg(
+                Arg::with_name(NO_BRACKET)
+                    .long("no_bracket")
+                    .help("Do not bracket tokens before extracting ngrams"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let options = Options::builder()
+            .min_n(value_t!(matches, MIN_N, usize).unwrap())
+            .max_n(value_t!(matches, MAX_N, usize).unwrap())
+            .bracket(!matches.is_present(NO_BRACKET))
+            .build()
+            .unwrap_or_else(|err| usage_error(&err));
+        ServeApp {
+            output_opts: output_options_from_matches(matches),
+            listen: matches.value_of(LISTEN).map(ToOwned::to_owned).unwrap(),
+            token_counts: matches.value_of(TOKEN_COUNTS).map(ToOwned::to_owned),
+            ngram_counts: matches.value_of(NGRAM_COUNTS).map(ToOwned::to_owned),
+            options,
+            filter: filter_from_matches(matches),
+        }
+    }
+
+    fn run(&self) {
+        let listener = TcpListener::bind(&self.listen).expect("Can't listen for connections.");
+        eprintln!("Serving on {}", self.listen);
+        let state = Mutex::new(State::default());
+        thread::scope(|s| {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let state = &state;
+                        s.spawn(move || {
+                            if let Err(err) = self.serve_connection(state, stream) {
+                                eprintln!("Can't serve request: {}", err);
+                            }
+                        });
+                    }
+                    Err(err) => eprintln!("Can't accept connection: {}", err),
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+
+use crate::input::Corpus;
+use crate::output::{OutputFile, OutputOptions};
+use crate::rng::Rng;
+use crate::subcommands::CorpusCountApp;
+use crate::temp::TempDir;
+use crate::{
+    corpus_args, corpus_from_matches, is_valid, non_zero, output_args, output_options_from_matches,
+    tmp_dir_arg, tmp_dir_from_matches, DEFAULT_CLAP_SETTINGS,
+};
+
+static BUCKETS: &str = "BUCKETS";
+static OUTPUT: &str = "OUTPUT";
+static SEED: &str = "SEED";
+
+/// Shuffle the lines of the corpus.
+pub struct ShuffleApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    output: Option<String>,
+    buckets: usize,
+    seed: u64,
+    tmp_dir: PathBuf,
+}
+
+impl ShuffleApp {
+    /// Shuffle the corpus lines with bounded memory.
+    ///
+    /// Lines are first distributed over temporary bucket files uniformly at
+    /// random, then every bucket is shuffled in memory and appended to the
+    /// output. Only a single bucket is held in memory at any time.
+    fn shuffle(&self, mut writer: impl Write) -> io::Result<()> {
+        let mut rng = Rng::new(self.seed);
+        let dir = TempDir::new(&self.tmp_dir, "corpus-count-shuffle")?;
+        let paths = (0..self.buckets)
+            .map(|idx| dir.path().join(format!("bucket-{}", idx)))
+            .collect::<Vec<_>>();
+        let mut buckets = paths
+            .iter()
+            .map(|path| File::create(path).map(BufWriter::new))
+            .collect::<io::Result<Vec<_>>>()?;
+        self.corpus.for_each_line(|line| {
+            let bucket = rng.below(self.buckets as u64) as usize;
+            let bucket = &mut buckets[bucket];
+            bucket.write_all(line.as_bytes())?;
+            bucket.write_all(b"\n")
+        })?;
+        for bucket in buckets {
+            bucket.into_inner().map_err(|err| err.into_error())?;
+        }
+
+        for path in paths {
+            let mut lines = BufReader::new(File::open(&path)?)
+                .lines()
+                .collect::<io::Result<Vec<_>>>()?;
+            fs::remove_file(&path)?;
+            rng.shuffle(&mut lines);
+            for line in lines {
+                writer.write_all(line.as_bytes())?;
+                writer.write_all(b"\n")?;
+            }
+        }
+        writer.flush()
+    }
+}
+
+impl CorpusCountApp for ShuffleApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("shuffle")
+            .about("Shuffle the lines of the corpus")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .arg(tmp_dir_arg())
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("Shuffled corpus file")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(BUCKETS)
+                    .long("buckets")
+                    .validator(non_zero("The number of buckets cannot be zero."))
+                    .default_value("64")
+                    .help("Number of temporary buckets, memory use is about corpus size / buckets"),
+            )
+            .arg(
+                Arg::with_name(SEED)
+                    .long("seed")
+                    .validator(is_valid::<u64>)
+                    .default_value("42")
+                    .help("Seed of the random number generator"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let buckets = value_t!(matches, BUCKETS, usize).unwrap();
+        ShuffleApp {
+            corpus: corpus_from_matches(matches),
+            output_opts: output_options_from_matches(matches),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            buckets,
+            seed: value_t!(matches, SEED, u64).unwrap(),
+            tmp_dir: tmp_dir_from_matches(matches),
+        }
+    }
+
+    fn run(&self) {
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't open output to write shuffled corpus.");
+        self.shuffle(&mut output).expect("Can't shuffle corpus");
+        output.finish().expect("Can't write shuffled corpus.");
+    }
+}
@@ -0,0 +1,165 @@
+use std::io::{self, Write};
+
+use clap::{value_t, values_t, App, Arg, ArgMatches, SubCommand};
+
+use crate::input::Corpus;
+use crate::output::{OutputFile, OutputOptions};
+use crate::rng::Rng;
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    corpus_args, corpus_from_matches, is_valid, output_args, output_options_from_matches,
+    usage_error, validate, DEFAULT_CLAP_SETTINGS,
+};
+
+static BY_DOCUMENTS: &str = "BY_DOCUMENTS";
+static OUTPUTS: &str = "OUTPUTS";
+static RATIOS: &str = "RATIOS";
+static SEED: &str = "SEED";
+
+/// Randomly partition the corpus into multiple files.
+pub struct SplitApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    outputs: Vec<String>,
+    /// Cumulative share of the outputs.
+    bounds: Vec<f64>,
+    by_documents: bool,
+    seed: u64,
+}
+
+impl SplitApp {
+    fn split(&self, writers: &mut [impl Write]) -> io::Result<Vec<usize>> {
+        let mut rng = Rng::new(self.seed);
+        let mut counts = vec![0; writers.len()];
+        let mut choose = || {
+            let r = rng.next_f64();
+            self.bounds
+                .iter()
+                .position(|&bound| r < bound)
+                .unwrap_or(self.bounds.len() - 1)
+        };
+        if self.by_documents {
+            self.corpus.for_each_document(|doc| {
+                let idx = choose();
+                let writer = &mut writers[idx];
+                // Keep documents separated by blank lines.
+                if counts[idx] != 0 {
+                    writer.write_all(b"\n")?;
+                }
+                for line in doc {
+                    writer.write_all(line.as_bytes())?;
+                    writer.write_all(b"\n")?;
+                }
+                counts[idx] += 1;
+                Ok(())
+            })?;
+        } else {
+            self.corpus.for_each_line(|line| {
+                let idx = choose();
+                writers[idx].write_all(line.as_bytes())?;
+                writers[idx].write_all(b"\n")?;
+                counts[idx] += 1;
+                Ok(())
+            })?;
+        }
+        for writer in writers {
+            writer.flush()?;
+        }
+        Ok(counts)
+    }
+}
+
+impl CorpusCountApp for SplitApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("split")
+            .about("Randomly partition corpus lines or documents into multiple files")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .arg(
+                Arg::with_name(OUTPUTS)
+                    .long("outputs")
+                    .short("o")
+                    .help("Output files, one per ratio")
+                    .takes_value(true)
+                    .multiple(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(RATIOS)
+                    .long("ratios")
+                    .validator(|v| {
+                        validate(&v, |&r: &f64| r >= 0., "Ratios should be non-negative.")
+                    })
+                    .default_value("0.98,0.01,0.01")
+                    .use_delimiter(true)
+                    .help("Comma-separated shares of the outputs"),
+            )
+            .arg(
+                Arg::with_name(BY_DOCUMENTS)
+                    .long("by_documents")
+                    .help("Split documents instead of lines, see --documents"),
+            )
+            .arg(
+                Arg::with_name(SEED)
+                    .long("seed")
+                    .validator(is_valid::<u64>)
+                    .default_value("42")
+                    .help("Seed of the random number generator"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let outputs = matches
+            .values_of(OUTPUTS)
+            .unwrap()
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>();
+        let ratios = values_t!(matches, RATIOS, f64).unwrap();
+        if ratios.len() != outputs.len() {
+            usage_error("The number of ratios should match the number of outputs.");
+        }
+        if ratios.iter().all(|&r| r == 0.) {
+            usage_error("Ratios should not all be zero.");
+        }
+        let total = ratios.iter().sum::<f64>();
+        let bounds = ratios
+            .iter()
+            .scan(0., |acc, r| {
+                *acc += r / total;
+                Some(*acc)
+            })
+            .collect();
+        SplitApp {
+            corpus: corpus_from_matches(matches),
+            output_opts: output_options_from_matches(matches),
+            outputs,
+            bounds,
+            by_documents: matches.is_present(BY_DOCUMENTS),
+            seed: value_t!(matches, SEED, u64).unwrap(),
+        }
+    }
+
+    fn run(&self) {
+        let mut writers = self
+            .outputs
+            .iter()
+            .map(|output| {
+                OutputFile::create(Some(output), &self.output_opts)
+                    .expect("Can't create output file.")
+            })
+            .collect::<Vec<_>>();
+        let counts = self.split(&mut writers).expect("Can't split corpus");
+        for writer in writers {
+            writer.finish().expect("Can't write output file.");
+        }
+        let unit = if self.by_documents {
+            "documents"
+        } else {
+            "lines"
+        };
+        for (output, count) in self.outputs.iter().zip(counts) {
+            eprintln!("{}: {} {}", output, count, unit);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+
+use crate::count::{CountOptions, StreamCounter};
+use crate::filter::TokenFilter;
+use crate::output::{OutputFile, OutputOptions};
+use crate::sort::count_order;
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    counted_into_sorted, filter_args, filter_from_matches, is_valid, non_zero, output_args,
+    output_options_from_matches, validate, DEFAULT_CLAP_SETTINGS,
+};
+
+static HALF_LIFE: &str = "HALF_LIFE";
+static LISTEN: &str = "LISTEN";
+static MIN_COUNT: &str = "MIN_COUNT";
+static OUTPUT: &str = "OUTPUT";
+static SLIDING: &str = "SLIDING";
+static WINDOW: &str = "WINDOW";
+
+/// Count lines received over TCP in time windows.
+pub struct StreamApp {
+    output_opts: OutputOptions,
+    output: Option<String>,
+    listen: String,
+    window: Duration,
+    min_count: usize,
+    sliding: Option<usize>,
+    half_life: Option<f64>,
+    filter: TokenFilter,
+}
+
+/// Decayed counts below this value are dropped.
+const MIN_DECAYED: f64 = 0.01;
+
+/// Aggregation of the counts of windows.
+enum Aggregation {
+    /// Counts of the current window.
+    Window,
+    /// Counts of the last `n` windows.
+    Sliding {
+        n: usize,
+        windows: VecDeque<HashMap<String, usize>>,
+        totals: HashMap<String, usize>,
+    },
+    /// Exponentially decayed counts, multiplied by `factor` every window.
+    Decayed {
+        factor: f64,
+        counts: HashMap<String, f64>,
+    },
+}
+
+/// Send the lines of a connection to the counter.
+fn read_connection(stream: TcpStream, lines: Sender<String>) {
+    let peer = stream
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_default();
+    for line in BufReader::new(stream).lines() {
+        match line {
+            Ok(line) => {
+                if lines.send(line).is_err() {
+                    return;
+                }
+            }
+            Err(err) => {
+                eprintln!("Closing connection from {}: {}", peer, err);
+                return;
+            }
+        }
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+impl StreamApp {
+    /// Count received lines, writing `window<TAB>token<TAB>count` rows
+    /// at the end of every window.
+    ///
+    /// Windows are identified by their start as Unix time in seconds. The
+    /// counts are those of the window, of the last windows if sliding or
+    /// decayed counts of all windows.
+    fn stream(&self, lines: mpsc::Receiver<String>, mut writer: impl Write) -> io::Result<()> {
+        let opts = CountOptions {
+            filter: self.filter.clone(),
+            ..CountOptions::default()
+        };
+        let mut counter = StreamCounter::new(&opts);
+        let mut aggregation = if let Some(n) = self.sliding {
+            Aggregation::Sliding {
+                n,
+                windows: VecDeque::new(),
+                totals: HashMap::new(),
+            }
+        } else if let Some(half_life) = self.half_life {
+            Aggregation::Decayed {
+                factor: 0.5f64.powf(self.window.as_secs_f64() / half_life),
+                counts: HashMap::new(),
+            }
+        } else {
+            Aggregation::Window
+        };
+        let mut window_start = SystemTime::now();
+        let mut deadline = Instant::now() + self.window;
+        loop {
+            match lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(line) => {
+                    counter.count_line(&line);
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+            let counts = counter.take_counts().tokens;
+            let window = unix_secs(window_start);
+            match &mut aggregation {
+                Aggregation::Window => {
+                    for (token, count) in counted_into_sorted(counts, Some(self.min_count)) {
+                        writeln!(writer, "{}\t{}\t{}", window, token, count)?;
+                    }
+                }
+                Aggregation::Sliding { n, windows, totals } => {
+                    for (token, &count) in &counts {
+                        *totals.entry(token.clone()).or_insert(0) += count;
+                    }
+                    windows.push_back(counts);
+                    if windows.len() > *n {
+                        for (token, count) in windows.pop_front().unwrap() {
+                            let total = totals.get_mut(&token).unwrap();
+                            *total -= count;
+                            if *total == 0 {
+                                totals.remove(&token);
+                            }
+                        }
+                    }
+                    let mut rows = totals
+                        .iter()
+                        .filter(|(_, &count)| count >= self.min_count)
+                        .collect::<Vec<_>>();
+                    rows.sort_unstable_by(|(t1, c1), (t2, c2)| count_order(t1, **c1, t2, **c2));
+                    for (token, count) in rows {
+                        writeln!(writer, "{}\t{}\t{}", window, token, count)?;
+                    }
+                }
+                Aggregation::Decayed {
+                    factor,
+                    counts: decayed,
+                } => {
+                    for count in decayed.values_mut() {
+                        *count *= *factor;
+                    }
+                    decayed.retain(|_, count| *count >= MIN_DECAYED);
+                    for (token, count) in counts {
+                        *decayed.entry(token).or_insert(0.) += count as f64;
+                    }
+                    let mut rows = decayed
+                        .iter()
+                        .filter(|(_, &count)| count >= self.min_count as f64)
+                        .collect::<Vec<_>>();
+                    rows.sort_unstable_by(|(t1, c1), (t2, c2)| {
+                        c2.partial_cmp(c1).unwrap().then_with(|| t1.cmp(t2))
+                    });
+                    for (token, count) in rows {
+                        writeln!(writer, "{}\t{}\t{:.4}", window, token, count)?;
+                    }
+                }
+            }
+            writer.flush()?;
+            window_start += self.window;
+            deadline += self.window;
+        }
+    }
+}
+
+impl CorpusCountApp for StreamApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("stream")
+            .about("Count lines received over TCP in time windows")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&output_args())
+            .args(&filter_args())
+            .arg(
+                Arg::with_name(LISTEN)
+                    .long("listen")
+                    .help("Address to accept connections on, e.g. 127.0.0.1:7878")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("Windowed count file")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(WINDOW)
+                    .long("window")
+                    .validator(|v| {
+                        validate(&v, |&n: &u64| n != 0, "The window length cannot be zero.")
+                    })
+                    .default_value("60")
+                    .help("Window length in seconds"),
+            )
+            .arg(
+                Arg::with_name(SLIDING)
+                    .long("sliding")
+                    .validator(non_zero("The number of sliding windows cannot be zero."))
+                    .conflicts_with(HALF_LIFE)
+                    .help("Write the counts of the last N windows")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(HALF_LIFE)
+                    .long("half_life")
+                    .validator(|v| {
+                        validate(&v, |&f: &f64| f > 0., "The half-life should be positive.")
+                    })
+                    .help("Write exponentially decayed counts with this half-life in seconds")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(MIN_COUNT)
+                    .long("min_count")
+                    .validator(is_valid::<usize>)
+                    .default_value("1")
+                    .help("Token min count per window"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let window = value_t!(matches, WINDOW, u64).unwrap();
+        let sliding = value_t!(matches, SLIDING, usize).ok();
+        let half_life = value_t!(matches, HALF_LIFE, f64).ok();
+        StreamApp {
+            // The output is a stream, it is never complete.
+            output_opts: OutputOptions {
+                atomic: false,
+                ..output_options_from_matches(matches)
+            },
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            listen: matches.value_of(LISTEN).map(ToOwned::to_owned).unwrap(),
+            window: Duration::from_secs(window),
+            min_count: value_t!(matches, MIN_COUNT, usize).unwrap(),
+            sliding,
+            half_life,
+            filter: filter_from_matches(matches),
+        }
+    }
+
+    fn run(&self) {
+        let listener = TcpListener::bind(&self.listen).expect("Can't listen for connections.");
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let sender = sender.clone();
+                        thread::spawn(move || read_connection(stream, sender));
+                    }
+                    Err(err) => eprintln!("Can't accept connection: {}", err),
+                }
+            }
+        });
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't open output to write windowed counts.");
+        self.stream(receiver, &mut output)
+            .expect("Can't write windowed counts.");
+        output.finish().expect("Can't write windowed counts.");
+    }
+}
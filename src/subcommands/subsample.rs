@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::thread;
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+
+use crate::count::{self, CountOptions};
+use crate::input::Corpus;
+use crate::output::{OutputFile, OutputOptions};
+use crate::rng::Rng;
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    corpus_args, corpus_from_matches, is_valid, output_args, output_options_from_matches, validate,
+    DEFAULT_CLAP_SETTINGS,
+};
+
+static OUTPUT: &str = "OUTPUT";
+static SAMPLE: &str = "SAMPLE";
+static SEED: &str = "SEED";
+
+/// Write a corpus with frequent tokens randomly dropped.
+pub struct SubsampleApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    output: Option<String>,
+    sample: f64,
+    seed: u64,
+}
+
+impl SubsampleApp {
+    /// Get the probability of keeping every token.
+    ///
+    /// Uses the formula of word2vec: a token with relative frequency `f` is
+    /// kept with probability `(sqrt(f / sample) + 1) * sample / f`.
+    fn keep_probs(&self) -> io::Result<HashMap<String, f64>> {
+        let opts = CountOptions {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            ..CountOptions::default()
+        };
+        let counts = count::count_files(&self.corpus, &opts)?;
+        let total = counts.tokens.values().sum::<usize>() as f64;
+        Ok(counts
+            .tokens
+            .into_iter()
+            .map(|(token, count)| {
+                let f = count as f64 / total;
+                let prob = ((f / self.sample).sqrt() + 1.) * self.sample / f;
+                (token, prob.min(1.))
+            })
+            .collect())
+    }
+
+    fn subsample(
+        &self,
+        keep_probs: &HashMap<String, f64>,
+        mut writer: impl Write,
+    ) -> io::Result<()> {
+        let mut rng = Rng::new(self.seed);
+        let mut kept = 0usize;
+        let mut total = 0usize;
+        self.corpus.for_each_line(|line| {
+            let mut first = true;
+            for token in line.split_whitespace() {
+                total += 1;
+                let prob = keep_probs.get(token).copied().unwrap_or(1.);
+                if prob < 1. && rng.next_f64() >= prob {
+                    continue;
+                }
+                if !first {
+                    writer.write_all(b" ")?;
+                }
+                writer.write_all(token.as_bytes())?;
+                first = false;
+                kept += 1;
+            }
+            writer.write_all(b"\n")
+        })?;
+        writer.flush()?;
+        eprintln!(
+            "Kept {} of {} tokens ({:.2}%)",
+            kept,
+            total,
+            100. * kept as f64 / total.max(1) as f64
+        );
+        Ok(())
+    }
+}
+
+impl CorpusCountApp for SubsampleApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("subsample")
+            .about("Write the corpus with frequent tokens randomly dropped")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("Subsampled corpus file")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(SAMPLE)
+                    .long("sample")
+                    .validator(|v| {
+                        validate(
+                            &v,
+                            |&f: &f64| f > 0.,
+                            "The sampling threshold should be positive.",
+                        )
+                    })
+                    .default_value("1e-3")
+                    .help("Subsampling threshold, tokens more frequent than this are dropped"),
+            )
+            .arg(
+                Arg::with_name(SEED)
+                    .long("seed")
+                    .validator(is_valid::<u64>)
+                    .default_value("42")
+                    .help("Seed of the random number generator"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let sample = value_t!(matches, SAMPLE, f64).unwrap();
+        SubsampleApp {
+            corpus: corpus_from_matches(matches),
+            output_opts: output_options_from_matches(matches),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            sample,
+            seed: value_t!(matches, SEED, u64).unwrap(),
+        }
+    }
+
+    fn run(&self) {
+        assert!(
+            !self.corpus.paths.is_empty(),
+            "Subsampling requires corpus files, stdin can only be read once."
+        );
+        let keep_probs = self.keep_probs().expect("Can't count corpus");
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't open output to write subsampled corpus.");
+        self.subsample(&keep_probs, &mut output)
+            .expect("Can't subsample corpus");
+        output.finish().expect("Can't write subsampled corpus.");
+    }
+}
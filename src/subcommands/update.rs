@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use corpus_count::vocab::Vocab;
+
+use crate::count::{self, CountOptions};
+use crate::filter::TokenFilter;
+use crate::input::{self, Corpus};
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    corpus_args, corpus_from_matches, counted_into_sorted, filter_args, filter_from_matches,
+    output_args, output_options_from_matches, usage_error, DEFAULT_CLAP_SETTINGS,
+};
+
+static COUNTS: &str = "COUNTS";
+static MANIFEST: &str = "MANIFEST";
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Add the counts of new corpus files to an existing token count file.
+pub struct UpdateApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    counts: String,
+    manifest: String,
+    filter: TokenFilter,
+}
+
+/// Processed corpus files and the token count file they were merged into.
+#[derive(Default)]
+struct Manifest {
+    counts_hash: Option<u64>,
+    files: HashMap<PathBuf, u64>,
+}
+
+impl Manifest {
+    /// Read a manifest, a missing manifest is empty.
+    ///
+    /// The first line `#counts<TAB>hash` holds the hash of the count file,
+    /// the remaining lines are `path<TAB>hash` pairs of canonical paths.
+    /// Hashes are hexadecimal FNV-1a hashes of the file contents.
+    fn read(path: &str) -> io::Result<Self> {
+        let reader = match File::open(path) {
+            Ok(file) => BufReader::new(file),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+        let mut manifest = Manifest::default();
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = line?;
+            let (name, hash) = line
+                .rsplit_once('\t')
+                .and_then(|(name, hash)| Some((name, u64::from_str_radix(hash, 16).ok()?)))
+                .ok_or_else(|| {
+                    invalid_data(format!("Invalid manifest line {}: {}", line_no + 1, line))
+                })?;
+            if name == "#counts" {
+                manifest.counts_hash = Some(hash);
+            } else {
+                // Manifests of older versions have the paths as given.
+                let path = fs::canonicalize(name).unwrap_or_else(|_| PathBuf::from(name));
+                manifest.files.insert(path, hash);
+            }
+        }
+        Ok(manifest)
+    }
+
+    fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        if let Some(hash) = self.counts_hash {
+            writeln!(writer, "#counts\t{:016x}", hash)?;
+        }
+        let mut files = self.files.iter().collect::<Vec<_>>();
+        files.sort();
+        for (path, hash) in files {
+            writeln!(writer, "{}\t{:016x}", path.display(), hash)?;
+        }
+        Ok(())
+    }
+}
+
+fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hash = FNV_OFFSET;
+    let mut buf = [0; 1 << 16];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(hash);
+        }
+        for &byte in &buf[..n] {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl UpdateApp {
+    /// Read the existing counts, checking them against the manifest.
+    fn read_counts(&self, manifest: &Manifest) -> io::Result<HashMap<String, usize>> {
+        let path = Path::new(&self.counts);
+        if !path.exists() {
+            if manifest.counts_hash.is_some() {
+                return Err(invalid_data(format!(
+                    "Counts {} in the manifest are missing",
+                    self.counts
+                )));
+            }
+            return Ok(HashMap::new());
+        }
+        if manifest.counts_hash != Some(hash_file(path)?) {
+            return Err(invalid_data(format!(
+                "Counts {} were not written by the last update",
+                self.counts
+            )));
+        }
+        let vocab = Vocab::read(input::decompressed(BufReader::new(File::open(path)?))?)?;
+        Ok(vocab
+            .iter()
+            .map(|(token, count)| (token.to_string(), count))
+            .collect())
+    }
+
+    /// Get the corpus files that were not counted yet.
+    ///
+    /// Files are identified by their canonical paths, so that a file is
+    /// counted once however its path is given. Files that changed since
+    /// they were counted cannot be updated, their old counts are unknown.
+    fn new_files(&self, manifest: &Manifest) -> io::Result<Vec<(PathBuf, u64)>> {
+        let mut new_files = Vec::<(PathBuf, u64)>::new();
+        for file in input::corpus_files(&self.corpus.paths, &self.corpus.include)? {
+            let file = fs::canonicalize(file)?;
+            if new_files.iter().any(|(new_file, _)| *new_file == file) {
+                continue;
+            }
+            let hash = hash_file(&file)?;
+            match manifest.files.get(&file) {
+                Some(&counted) if counted == hash => continue,
+                Some(_) => {
+                    return Err(invalid_data(format!(
+                        "{} changed since it was counted, recount the corpus",
+                        file.display()
+                    )))
+                }
+                None => new_files.push((file, hash)),
+            }
+        }
+        Ok(new_files)
+    }
+}
+
+impl CorpusCountApp for UpdateApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("update")
+            .about("Add the counts of new corpus files to a token count file")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .args(&filter_args())
+            .arg(
+                Arg::with_name(COUNTS)
+                    .long("counts")
+                    .help("Token count file to update, created if missing")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(MANIFEST)
+                    .long("manifest")
+                    .help("Manifest of the counted files, created if missing")
+                    .takes_value(true)
+                    .required(true),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let corpus = corpus_from_matches(matches);
+        if corpus.paths.is_empty() {
+            usage_error("Updating requires corpus files, stdin is not supported.");
+        }
+        if !corpus.lines.is_full() || corpus.bytes.is_some() {
+            usage_error("Updating requires complete corpus files.");
+        }
+        UpdateApp {
+            corpus,
+            output_opts: output_options_from_matches(matches),
+            counts: matches.value_of(COUNTS).map(ToOwned::to_owned).unwrap(),
+            manifest: matches.value_of(MANIFEST).map(ToOwned::to_owned).unwrap(),
+            filter: filter_from_matches(matches),
+        }
+    }
+
+    fn run(&self) {
+        let mut manifest = Manifest::read(&self.manifest).expect("Can't read manifest.");
+        let mut counts = self.read_counts(&manifest).expect("Can't read counts.");
+        let new_files = self.new_files(&manifest).expect("Can't read corpus files.");
+        eprintln!("Counting {} new files", new_files.len());
+        if new_files.is_empty() && manifest.counts_hash.is_some() {
+            return;
+        }
+
+        let corpus = Corpus {
+            paths: new_files.iter().map(|(path, _)| path.clone()).collect(),
+            ..self.corpus.clone()
+        };
+        let opts = CountOptions {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            filter: self.filter.clone(),
+            ..CountOptions::default()
+        };
+        if !corpus.paths.is_empty() {
+            let new_counts = count::count_files(&corpus, &opts).expect("Can't read corpus");
+            for (token, count) in new_counts.tokens {
+                *counts.entry(token).or_insert(0) += count;
+            }
+        }
+
+        let mut writer = OutputFile::create(Some(&self.counts), &self.output_opts)
+            .expect("Can't open output to write token counts.");
+        for (token, count) in counted_into_sorted(counts, None) {
+            writeln!(writer, "{}\t{}", token, count).expect("Can't write token counts.");
+        }
+        writer.finish().expect("Can't write token counts.");
+
+        // The manifest is written last, an interrupted update leaves counts
+        // that do not match the manifest and are rejected.
+        manifest.counts_hash =
+            Some(hash_file(Path::new(&self.counts)).expect("Can't read token counts."));
+        manifest.files.extend(new_files);
+        let mut writer = OutputFile::create(Some(&self.manifest), &self.output_opts)
+            .expect("Can't open output to write manifest.");
+        manifest.write(&mut writer).expect("Can't write manifest.");
+        writer.finish().expect("Can't write manifest.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use corpus_count::temp;
+
+    use super::UpdateApp;
+    use crate::subcommands::CorpusCountApp;
+
+    fn update(dir: &str, files: &[String]) {
+        let mut args = vec![
+            "update".to_owned(),
+            "--counts".to_owned(),
+            format!("{}/counts.tsv", dir),
+            "--manifest".to_owned(),
+            format!("{}/manifest.tsv", dir),
+            "-c".to_owned(),
+        ];
+        args.extend(files.iter().cloned());
+        UpdateApp::parse(&UpdateApp::app().get_matches_from(args)).run();
+    }
+
+    #[test]
+    fn files_are_counted_once_under_every_path() {
+        let dir = temp::unique_path(&std::env::temp_dir(), "update-aliases");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("corpus.txt"), "a b\n").unwrap();
+        let dir = dir.to_str().unwrap();
+
+        update(dir, &[format!("{}/corpus.txt", dir)]);
+        update(
+            dir,
+            &[
+                format!("{}/./corpus.txt", dir),
+                format!("{}/sub/../corpus.txt", dir),
+            ],
+        );
+        assert_eq!(
+            fs::read_to_string(format!("{}/counts.tsv", dir)).unwrap(),
+            "a\t1\nb\t1\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, Write};
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+use corpus_count::vocab::Vocab;
+
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    is_valid, output_args, output_options_from_matches, usage_error, DEFAULT_CLAP_SETTINGS,
+};
+
+static COUNTS: &str = "COUNTS";
+static MAX_DISTANCE: &str = "MAX_DISTANCE";
+static MAX_VARIANT: &str = "MAX_VARIANT";
+static MIN_CANONICAL: &str = "MIN_CANONICAL";
+static MIN_LEN: &str = "MIN_LEN";
+static OUTPUT: &str = "OUTPUT";
+
+/// Add the strings with up to `distance` characters deleted from `chars`
+/// to `deletes`.
+fn deletes(chars: &[char], distance: usize, deletes: &mut HashSet<String>) {
+    if distance == 0 || chars.len() <= 1 {
+        return;
+    }
+    for idx in 0..chars.len() {
+        let mut deleted = chars.to_vec();
+        deleted.remove(idx);
+        if deletes.insert(deleted.iter().collect()) {
+            self::deletes(&deleted, distance - 1, deletes);
+        }
+    }
+}
+
+/// Get the optimal string alignment distance of two strings, `None` if it
+/// exceeds `max`.
+///
+/// This is the Levenshtein distance with transpositions of adjacent
+/// characters as a single edit.
+fn osa_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        rows[i][0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    Some(rows[a.len()][b.len()]).filter(|&distance| distance <= max)
+}
+
+/// Group rare tokens with frequent tokens of a small edit distance.
+pub struct VariantsApp {
+    output_opts: OutputOptions,
+    counts: String,
+    output: Option<String>,
+    max_distance: usize,
+    max_variant: usize,
+    min_canonical: usize,
+    min_len: usize,
+}
+
+impl CorpusCountApp for VariantsApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("variants")
+            .about("Group rare tokens with frequent tokens of a small edit distance")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&output_args())
+            .arg(
+                Arg::with_name(COUNTS)
+                    .long("counts")
+                    .help("Token count file of the vocabulary")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("File for canonical<TAB>variant<TAB>distance<TAB>canonical count<TAB>variant count lines")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(MAX_DISTANCE)
+                    .long("max_distance")
+                    .validator(is_valid::<usize>)
+                    .default_value("2")
+                    .help("Maximum edit distance of variants"),
+            )
+            .arg(
+                Arg::with_name(MAX_VARIANT)
+                    .long("max_variant")
+                    .validator(is_valid::<usize>)
+                    .default_value("5")
+                    .help("Maximum count of variants"),
+            )
+            .arg(
+                Arg::with_name(MIN_CANONICAL)
+                    .long("min_canonical")
+                    .validator(is_valid::<usize>)
+                    .default_value("100")
+                    .help("Minimum count of canonical forms"),
+            )
+            .arg(
+                Arg::with_name(MIN_LEN)
+                    .long("min_len")
+                    .validator(is_valid::<usize>)
+                    .default_value("4")
+                    .help("Minimum length of variants in characters"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let max_variant = value_t!(matches, MAX_VARIANT, usize).unwrap();
+        let min_canonical = value_t!(matches, MIN_CANONICAL, usize).unwrap();
+        if max_variant >= min_canonical {
+            usage_error("The maximum variant count should be below the minimum canonical count.");
+        }
+        VariantsApp {
+            output_opts: output_options_from_matches(matches),
+            counts: matches.value_of(COUNTS).map(ToOwned::to_owned).unwrap(),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            max_distance: value_t!(matches, MAX_DISTANCE, usize).unwrap(),
+            max_variant,
+            min_canonical,
+            min_len: value_t!(matches, MIN_LEN, usize).unwrap(),
+        }
+    }
+
+    fn run(&self) {
+        let vocab = Vocab::read(BufReader::new(
+            File::open(&self.counts).expect("Can't open token counts."),
+        ))
+        .expect("Can't read token counts.");
+
+        // Index the canonical forms by their deletes, as in SymSpell.
+        let canonical = vocab
+            .iter()
+            .take_while(|&(_, count)| count >= self.min_canonical)
+            .map(|(token, count)| (token, token.chars().collect::<Vec<_>>(), count))
+            .collect::<Vec<_>>();
+        let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+        for (idx, (token, chars, _)) in canonical.iter().enumerate() {
+            let mut token_deletes = HashSet::new();
+            token_deletes.insert(token.to_string());
+            deletes(chars, self.max_distance, &mut token_deletes);
+            for delete in token_deletes {
+                index.entry(delete).or_default().push(idx);
+            }
+        }
+
+        let mut groups = vec![Vec::new(); canonical.len()];
+        for (token, count) in vocab.iter() {
+            if count > self.max_variant {
+                continue;
+            }
+            let chars = token.chars().collect::<Vec<_>>();
+            if chars.len() < self.min_len {
+                continue;
+            }
+            let mut token_deletes = HashSet::new();
+            token_deletes.insert(token.to_owned());
+            deletes(&chars, self.max_distance, &mut token_deletes);
+            let candidates = token_deletes
+                .iter()
+                .filter_map(|delete| index.get(delete))
+                .flatten()
+                .copied()
+                .collect::<HashSet<_>>();
+            // The closest canonical form, the most frequent one on ties.
+            let best = candidates
+                .into_iter()
+                .filter_map(|idx| {
+                    osa_distance(&chars, &canonical[idx].1, self.max_distance)
+                        .map(|distance| (distance, idx))
+                })
+                .min();
+            if let Some((distance, idx)) = best {
+                groups[idx].push((token, distance, count));
+            }
+        }
+
+        let mut writer = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't create output.");
+        for ((token, _, count), variants) in canonical.iter().zip(groups) {
+            for (variant, distance, variant_count) in variants {
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}\t{}",
+                    token, variant, distance, count, variant_count
+                )
+                .expect("Can't write variants.");
+            }
+        }
+        writer.finish().expect("Can't write variants.");
+    }
+}
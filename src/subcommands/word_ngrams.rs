@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
+
+use crate::input::Corpus;
+use crate::output::{OutputFile, OutputOptions};
+use crate::subcommands::CorpusCountApp;
+use crate::{
+    corpus_args, corpus_from_matches, non_zero, output_args, output_options_from_matches,
+    DEFAULT_CLAP_SETTINGS,
+};
+
+static BOS: &str = "BOS";
+static EOS: &str = "EOS";
+static ORDER: &str = "ORDER";
+static OUTPUT: &str = "OUTPUT";
+
+/// Count word n-grams for language model estimation.
+pub struct WordNGramsApp {
+    corpus: Corpus,
+    output_opts: OutputOptions,
+    output: Option<String>,
+    order: usize,
+    bos: Option<String>,
+    eos: Option<String>,
+}
+
+impl WordNGramsApp {
+    /// Count the word n-grams up to the model order.
+    ///
+    /// Every line is a sentence and wrapped in the sentence boundary
+    /// symbols. The boundary symbols only occur in n-grams of two or more
+    /// tokens, they are not counted as unigrams.
+    fn count(&self) -> io::Result<HashMap<String, usize>> {
+        let mut counts = HashMap::new();
+        let mut ngram = String::new();
+        self.corpus.for_each_line(|line| {
+            let mut tokens = Vec::new();
+            tokens.extend(self.bos.as_deref());
+            tokens.extend(line.split_whitespace());
+            tokens.extend(self.eos.as_deref());
+            let words =
+                &tokens[self.bos.is_some() as usize..tokens.len() - self.eos.is_some() as usize];
+            for n in 1..=self.order {
+                let tokens = if n == 1 { words } else { &tokens[..] };
+                for window in tokens.windows(n) {
+                    ngram.clear();
+                    for (idx, token) in window.iter().enumerate() {
+                        if idx > 0 {
+                            ngram.push(' ');
+                        }
+                        ngram.push_str(token);
+                    }
+                    if let Some(cnt) = counts.get_mut(&ngram) {
+                        *cnt += 1;
+                    } else {
+                        counts.insert(ngram.clone(), 1);
+                    }
+                }
+            }
+            Ok(())
+        })?;
+        Ok(counts)
+    }
+}
+
+impl CorpusCountApp for WordNGramsApp {
+    fn app() -> App<'static, 'static> {
+        SubCommand::with_name("word-ngrams")
+            .about("Count word n-grams with sentence boundaries for language modeling")
+            .settings(DEFAULT_CLAP_SETTINGS)
+            .args(&corpus_args())
+            .args(&output_args())
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long("output")
+                    .short("o")
+                    .help("N-gram count file")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(ORDER)
+                    .long("order")
+                    .validator(non_zero("The n-gram order cannot be zero."))
+                    .default_value("3")
+                    .help("Maximum n-gram order"),
+            )
+            .arg(
+                Arg::with_name(BOS)
+                    .long("bos")
+                    .default_value("<s>")
+                    .empty_values(true)
+                    .help("Symbol prepended to every sentence, none if empty"),
+            )
+            .arg(
+                Arg::with_name(EOS)
+                    .long("eos")
+                    .default_value("</s>")
+                    .empty_values(true)
+                    .help("Symbol appended to every sentence, none if empty"),
+            )
+    }
+
+    fn parse(matches: &ArgMatches) -> Self {
+        let order = value_t!(matches, ORDER, usize).unwrap();
+        WordNGramsApp {
+            corpus: corpus_from_matches(matches),
+            output_opts: output_options_from_matches(matches),
+            output: matches.value_of(OUTPUT).map(ToOwned::to_owned),
+            order,
+            bos: matches
+                .value_of(BOS)
+                .filter(|v| !v.is_empty())
+                .map(ToOwned::to_owned),
+            eos: matches
+                .value_of(EOS)
+                .filter(|v| !v.is_empty())
+                .map(ToOwned::to_owned),
+        }
+    }
+
+    fn run(&self) {
+        let counts = self.count().expect("Can't read corpus");
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        // Group n-grams by order and sort each order lexicographically.
+        counts.sort_unstable_by(|(ngram1, _), (ngram2, _)| {
+            let order1 = ngram1.matches(' ').count();
+            let order2 = ngram2.matches(' ').count();
+            order1.cmp(&order2).then_with(|| ngram1.cmp(ngram2))
+        });
+        let mut output = OutputFile::create(self.output.as_deref(), &self.output_opts)
+            .expect("Can't open output to write n-gram counts.");
+        for (ngram, count) in counts {
+            writeln!(output, "{}\t{}", count, ngram).expect("Can't write n-gram counts.");
+        }
+        output.finish().expect("Can't write n-gram counts.");
+    }
+}
@@ -0,0 +1,253 @@
+//! Subword vocabularies of tokens and their character n-grams.
+//!
+//! Indices follow finalfrontier: tokens are indexed by frequency rank and
+//! the n-grams of the bracketed token `<token>` are indexed after the
+//! tokens, either explicitly through an n-gram vocabulary or by hashing
+//! the n-grams into buckets.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::ngrams::NGrams;
+use crate::vocab::{invalid_data, Vocab};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Indexing of n-grams.
+#[derive(Clone, Debug)]
+pub enum NGramIndexer {
+    /// N-grams are indexed by the rank in an n-gram vocabulary, unknown
+    /// n-grams have no index.
+    Explicit(Vocab),
+    /// N-grams are hashed into `2^buckets_exp` buckets.
+    Hashed { buckets_exp: u32 },
+}
+
+impl NGramIndexer {
+    /// Get the index of an n-gram.
+    pub fn idx(&self, ngram: &str) -> Option<usize> {
+        match self {
+            NGramIndexer::Explicit(ngrams) => ngrams.idx(ngram),
+            NGramIndexer::Hashed { buckets_exp } => {
+                let mask = (1u64 << buckets_exp) - 1;
+                Some((fnv_ngram(ngram) & mask) as usize)
+            }
+        }
+    }
+
+    /// Get the number of n-gram indices.
+    pub fn len(&self) -> usize {
+        match self {
+            NGramIndexer::Explicit(ngrams) => ngrams.len(),
+            NGramIndexer::Hashed { buckets_exp } => 1 << buckets_exp,
+        }
+    }
+
+    /// Check whether there are no n-gram indices.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// FNV-1a hash of an n-gram as computed by finalfusion.
+///
+/// The n-gram is hashed as its length in characters followed by its
+/// characters as 32-bit integers.
+fn fnv_ngram(ngram: &str) -> u64 {
+    let mut hash = FNV_OFFSET;
+    let mut write = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+    write(&(ngram.chars().count() as u64).to_le_bytes());
+    for ch in ngram.chars() {
+        write(&(ch as u32).to_le_bytes());
+    }
+    hash
+}
+
+/// Vocabulary of tokens and their character n-grams.
+#[derive(Clone, Debug)]
+pub struct SubwordVocab {
+    tokens: Vocab,
+    ngrams: NGramIndexer,
+    min_n: usize,
+    max_n: usize,
+    bracket: bool,
+}
+
+impl SubwordVocab {
+    /// Construct a subword vocabulary.
+    pub fn new(
+        tokens: Vocab,
+        ngrams: NGramIndexer,
+        min_n: usize,
+        max_n: usize,
+        bracket: bool,
+    ) -> Self {
+        assert_ne!(min_n, 0, "The minimum n-gram length cannot be zero.");
+        assert!(
+            min_n <= max_n,
+            "The maximum length should be equal to or greater than the minimum length."
+        );
+        SubwordVocab {
+            tokens,
+            ngrams,
+            min_n,
+            max_n,
+            bracket,
+        }
+    }
+
+    /// Construct a subword vocabulary with an explicit n-gram vocabulary.
+    ///
+    /// N-grams are counted with the counts of the tokens they occur in,
+    /// n-grams occurring less than `ngram_min` times are not indexed.
+    pub fn explicit(
+        tokens: Vocab,
+        min_n: usize,
+        max_n: usize,
+        bracket: bool,
+        ngram_min: usize,
+    ) -> Self {
+        let mut counts = HashMap::new();
+        for (token, count) in tokens.iter() {
+            for ngram in NGrams::new(&bracketed(token, bracket), min_n, max_n) {
+                if let Some(cnt) = counts.get_mut(ngram) {
+                    *cnt += count;
+                } else {
+                    counts.insert(ngram.to_string(), count);
+                }
+            }
+        }
+        let ngrams = Vocab::from_counts(counts.into_iter().filter(|(_, cnt)| *cnt >= ngram_min));
+        Self::new(
+            tokens,
+            NGramIndexer::Explicit(ngrams),
+            min_n,
+            max_n,
+            bracket,
+        )
+    }
+
+    /// Get the index of a token followed by the indices of its n-grams.
+    ///
+    /// Unknown tokens only have n-gram indices.
+    pub fn indices(&self, token: &str) -> Vec<usize> {
+        let mut indices = self.tokens.idx(token).into_iter().collect::<Vec<_>>();
+        indices.extend(self.ngram_indices(token));
+        indices
+    }
+
+    /// Get the number of indices, i.e. the number of tokens and n-grams.
+    pub fn len(&self) -> usize {
+        self.tokens.len() + self.ngrams.len()
+    }
+
+    /// Check whether the vocabulary has no indices.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the n-gram indexer.
+    pub fn ngrams(&self) -> &NGramIndexer {
+        &self.ngrams
+    }
+
+    /// Get the indices of the n-grams of a token.
+    ///
+    /// N-gram indices are offset by the number of tokens.
+    pub fn ngram_indices(&self, token: &str) -> Vec<usize> {
+        NGrams::new(&bracketed(token, self.bracket), self.min_n, self.max_n)
+            .filter_map(|ngram| self.ngrams.idx(ngram))
+            .map(|idx| self.tokens.len() + idx)
+            .collect()
+    }
+
+    /// Get the token vocabulary.
+    pub fn tokens(&self) -> &Vocab {
+        &self.tokens
+    }
+
+    /// Read a subword vocabulary written by `write`.
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        let header = lines
+            .next()
+            .transpose()?
+            .ok_or_else(|| invalid_data("Empty subword vocabulary".to_string()))?;
+        let fields = header.split('\t').collect::<Vec<_>>();
+        let invalid_header =
+            || invalid_data(format!("Invalid subword vocabulary header: {}", header));
+        if fields.len() != 7 || fields[0] != "subword_vocab" {
+            return Err(invalid_header());
+        }
+        let field = |idx: usize| fields[idx].parse::<usize>().map_err(|_| invalid_header());
+        let (min_n, max_n, bracket) = (field(1)?, field(2)?, field(3)? != 0);
+        if min_n == 0 || min_n > max_n {
+            return Err(invalid_header());
+        }
+        let n_tokens = field(5)?;
+        let tokens = Vocab::read_lines(lines.by_ref().take(n_tokens))?;
+        if tokens.len() != n_tokens {
+            return Err(invalid_data("Truncated subword vocabulary".to_string()));
+        }
+        let ngrams = match fields[4] {
+            "explicit" => {
+                let n_ngrams = field(6)?;
+                let ngrams = Vocab::read_lines(lines.by_ref().take(n_ngrams))?;
+                if ngrams.len() != n_ngrams {
+                    return Err(invalid_data("Truncated subword vocabulary".to_string()));
+                }
+                NGramIndexer::Explicit(ngrams)
+            }
+            "hashed" => {
+                let buckets_exp = field(6)? as u32;
+                if buckets_exp >= usize::BITS {
+                    return Err(invalid_header());
+                }
+                NGramIndexer::Hashed { buckets_exp }
+            }
+            _ => return Err(invalid_header()),
+        };
+        Ok(Self::new(tokens, ngrams, min_n, max_n, bracket))
+    }
+
+    /// Write the subword vocabulary.
+    ///
+    /// The header `subword_vocab<TAB>min_n<TAB>max_n<TAB>bracket<TAB>indexer<TAB>tokens<TAB>n`
+    /// is followed by the token counts and, for explicit indexers, by the
+    /// `n` n-gram counts. For hashed indexers, `n` is the bucket exponent.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        let (indexer, n) = match &self.ngrams {
+            NGramIndexer::Explicit(ngrams) => ("explicit", ngrams.len()),
+            NGramIndexer::Hashed { buckets_exp } => ("hashed", *buckets_exp as usize),
+        };
+        writeln!(
+            writer,
+            "subword_vocab\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.min_n,
+            self.max_n,
+            self.bracket as usize,
+            indexer,
+            self.tokens.len(),
+            n
+        )?;
+        self.tokens.write(&mut writer)?;
+        if let NGramIndexer::Explicit(ngrams) = &self.ngrams {
+            ngrams.write(&mut writer)?;
+        }
+        Ok(())
+    }
+}
+
+fn bracketed(token: &str, bracket: bool) -> String {
+    if bracket {
+        format!("<{}>", token)
+    } else {
+        token.to_string()
+    }
+}
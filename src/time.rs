@@ -0,0 +1,80 @@
+//! Time buckets of timestamps.
+
+use std::str::FromStr;
+
+use crate::json::Value;
+
+/// Granularity of time buckets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimeBucket {
+    Year,
+    Month,
+}
+
+impl TimeBucket {
+    pub fn variants() -> &'static [&'static str] {
+        &["month", "year"]
+    }
+
+    /// Get the bucket of a timestamp as `YYYY` or `YYYY-MM`.
+    ///
+    /// Timestamps are ISO 8601 dates or date-times, or numbers of seconds
+    /// since the Unix epoch. Returns `None` for other values.
+    pub fn of(self, timestamp: &Value) -> Option<String> {
+        let (year, month) = match timestamp {
+            Value::String(date) => parse_date(date)?,
+            Value::Number(seconds) => {
+                let seconds = seconds.parse::<f64>().ok()?;
+                let (year, month, _) = civil_from_days((seconds / 86400.).floor() as i64);
+                (year, month)
+            }
+            _ => return None,
+        };
+        Some(match self {
+            TimeBucket::Year => format!("{:04}", year),
+            TimeBucket::Month => format!("{:04}-{:02}", year, month),
+        })
+    }
+}
+
+impl FromStr for TimeBucket {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "year" => Ok(TimeBucket::Year),
+            "month" => Ok(TimeBucket::Month),
+            _ => Err(format!("Unknown time bucket: {}", s)),
+        }
+    }
+}
+
+/// Get the year and month of an ISO 8601 date, a year alone has month 1.
+fn parse_date(date: &str) -> Option<(i64, u32)> {
+    let year = date.get(..4)?.parse::<i64>().ok()?;
+    let month = match date.get(4..) {
+        None | Some("") => 1,
+        Some(rest) => rest.strip_prefix('-')?.get(..2)?.parse::<u32>().ok()?,
+    };
+    if (1..=12).contains(&month) {
+        Some((year, month))
+    } else {
+        None
+    }
+}
+
+/// Get the proleptic Gregorian date of days since 1970-01-01.
+///
+/// Uses Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,326 @@
+//! Tokenization of lines.
+//!
+//! The counter splits lines into tokens through a `Tokenizer`, which makes
+//! the tokenization strategy independent of the counting loop.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::normalize;
+
+/// Tokenization of lines.
+pub trait Tokenizer: fmt::Debug + Send + Sync {
+    /// Call `token` for every token of `line`.
+    fn tokenize(&self, line: &str, token: &mut dyn FnMut(&str));
+}
+
+/// Split on whitespace.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Whitespace;
+
+impl Tokenizer for Whitespace {
+    fn tokenize(&self, line: &str, token: &mut dyn FnMut(&str)) {
+        line.split_whitespace().for_each(token)
+    }
+}
+
+/// Split into words, whitespace and punctuation are dropped.
+///
+/// Apostrophes between word characters are part of the words, so
+/// contractions like `don't` are single tokens.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UnicodeWords;
+
+impl UnicodeWords {
+    fn is_word_char(c: char) -> bool {
+        !c.is_whitespace() && !c.is_ascii_punctuation() && !normalize::is_punctuation(c)
+    }
+}
+
+impl Tokenizer for UnicodeWords {
+    fn tokenize(&self, line: &str, token: &mut dyn FnMut(&str)) {
+        let mut start = None;
+        let mut chars = line.char_indices().peekable();
+        while let Some((idx, c)) = chars.next() {
+            if Self::is_word_char(c) {
+                start.get_or_insert(idx);
+                continue;
+            }
+            let inner_apostrophe = (c == '\'' || c == '\u{2019}')
+                && start.is_some()
+                && chars
+                    .peek()
+                    .map(|&(_, next)| Self::is_word_char(next))
+                    .unwrap_or(false);
+            if inner_apostrophe {
+                continue;
+            }
+            if let Some(start) = start.take() {
+                token(&line[start..idx]);
+            }
+        }
+        if let Some(start) = start {
+            token(&line[start..]);
+        }
+    }
+}
+
+/// Every non-whitespace character is a token.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Chars;
+
+impl Tokenizer for Chars {
+    fn tokenize(&self, line: &str, token: &mut dyn FnMut(&str)) {
+        for (idx, c) in line.char_indices() {
+            if !c.is_whitespace() {
+                token(&line[idx..idx + c.len_utf8()]);
+            }
+        }
+    }
+}
+
+/// Character classes of Japanese text.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum JapaneseClass {
+    Kanji,
+    Hiragana,
+    Katakana,
+    Other,
+    Separator,
+}
+
+impl JapaneseClass {
+    fn of(c: char) -> Self {
+        match c {
+            '\u{3005}' | '\u{3006}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' => {
+                JapaneseClass::Kanji
+            }
+            '\u{3041}'..='\u{309f}' => JapaneseClass::Hiragana,
+            // The prolonged sound mark is used with both kana.
+            '\u{30a0}'..='\u{30ff}' | '\u{ff66}'..='\u{ff9f}' => JapaneseClass::Katakana,
+            c if c.is_whitespace() || c.is_ascii_punctuation() || normalize::is_punctuation(c) => {
+                JapaneseClass::Separator
+            }
+            c if ('\u{ff01}'..='\u{ff0f}').contains(&c) => JapaneseClass::Separator,
+            _ => JapaneseClass::Other,
+        }
+    }
+}
+
+/// Split Japanese text into runs of the same script.
+///
+/// Runs of kanji, hiragana, katakana and other characters are tokens,
+/// whitespace and punctuation are dropped. This is not a morphological
+/// segmentation, the runs only approximate word boundaries:
+/// `東京に行きました` is split into `東京`, `に`, `行` and `きました`. A
+/// prolonged sound mark after hiragana stays with the hiragana.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScriptRuns;
+
+impl Tokenizer for ScriptRuns {
+    fn tokenize(&self, line: &str, token: &mut dyn FnMut(&str)) {
+        let mut start = 0;
+        let mut current = JapaneseClass::Separator;
+        for (idx, c) in line.char_indices() {
+            let mut class = JapaneseClass::of(c);
+            if c == '\u{30fc}' && current == JapaneseClass::Hiragana {
+                class = current;
+            }
+            if class != current {
+                if current != JapaneseClass::Separator {
+                    token(&line[start..idx]);
+                }
+                start = idx;
+                current = class;
+            }
+        }
+        if current != JapaneseClass::Separator {
+            token(&line[start..]);
+        }
+    }
+}
+
+/// Dictionary-based segmentation with jieba's algorithm.
+///
+/// Text is split at whitespace and punctuation. Every remaining run of
+/// characters is segmented into the dictionary words that maximize the
+/// product of their unigram probabilities, characters that start no
+/// dictionary word are words of their own with count 1. Consecutive
+/// unknown ASCII letters and digits are joined to one token.
+#[derive(Clone, Debug)]
+pub struct DictSegmenter {
+    /// Word counts, prefixes of words that are not words have count 0.
+    counts: HashMap<String, u64>,
+    ln_total: f64,
+    /// Join all consecutive unknown clusters, not only ASCII.
+    join_unknown: bool,
+}
+
+impl DictSegmenter {
+    /// Read a dictionary of `word count` lines like jieba's `dict.txt`.
+    ///
+    /// Columns after the count, e.g. part-of-speech tags, are ignored.
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        let mut counts = HashMap::new();
+        let mut total = 0;
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = line?;
+            let mut parts = line.split_whitespace();
+            let word = match parts.next() {
+                Some(word) => word,
+                None => continue,
+            };
+            let count = parts
+                .next()
+                .and_then(|count| count.parse::<u64>().ok())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid dictionary entry in line {}", line_no + 1),
+                    )
+                })?;
+            Self::insert(&mut counts, word, count);
+            total += count;
+        }
+        Ok(DictSegmenter {
+            counts,
+            ln_total: (total.max(1) as f64).ln(),
+            join_unknown: false,
+        })
+    }
+
+    /// Read a word list with one word per line.
+    ///
+    /// All words are equally probable, so text is segmented into the
+    /// fewest words, as in maximal matching for Thai. Consecutive clusters
+    /// that start no word are joined to one token.
+    pub fn read_word_list(reader: impl BufRead) -> io::Result<Self> {
+        let mut counts = HashMap::new();
+        let mut total = 0;
+        for line in reader.lines() {
+            let line = line?;
+            if let Some(word) = line.split_whitespace().next() {
+                Self::insert(&mut counts, word, 1);
+                total += 1;
+            }
+        }
+        Ok(DictSegmenter {
+            counts,
+            ln_total: (total.max(1) as f64).ln(),
+            join_unknown: true,
+        })
+    }
+
+    fn insert(counts: &mut HashMap<String, u64>, word: &str, count: u64) {
+        *counts.entry(word.to_owned()).or_insert(0) += count;
+        for (idx, _) in word.char_indices().skip(1) {
+            counts.entry(word[..idx].to_owned()).or_insert(0);
+        }
+    }
+
+    fn segment(&self, text: &str, token: &mut dyn FnMut(&str)) {
+        let bounds = cluster_bounds(text);
+        let n = bounds.len() - 1;
+
+        // Best log probability and word end of the suffix at every cluster.
+        let mut route = vec![(0f64, n); n + 1];
+        for start in (0..n).rev() {
+            let mut best = None;
+            for end in start + 1..=n {
+                let count = match self.counts.get(&text[bounds[start]..bounds[end]]) {
+                    Some(&count) => count,
+                    None => break,
+                };
+                if count > 0 || end == start + 1 {
+                    let score = (count.max(1) as f64).ln() - self.ln_total + route[end].0;
+                    // Ties are resolved in favor of longer words.
+                    if best.map(|(best, _)| score >= best).unwrap_or(true) {
+                        best = Some((score, end));
+                    }
+                }
+            }
+            route[start] = best.unwrap_or((route[start + 1].0 - self.ln_total, start + 1));
+        }
+
+        let mut start = 0;
+        let mut unknown = None;
+        while start < n {
+            let end = route[start].1;
+            let word = &text[bounds[start]..bounds[end]];
+            let known = self
+                .counts
+                .get(word)
+                .map(|&count| count > 0)
+                .unwrap_or(false);
+            let join = self.join_unknown || word.bytes().all(|b| b.is_ascii_alphanumeric());
+            if end == start + 1 && !known && join {
+                unknown.get_or_insert(bounds[start]);
+            } else {
+                if let Some(unknown_start) = unknown.take() {
+                    token(&text[unknown_start..bounds[start]]);
+                }
+                token(word);
+            }
+            start = end;
+        }
+        if let Some(unknown_start) = unknown {
+            token(&text[unknown_start..]);
+        }
+    }
+}
+
+/// Get the byte offsets of the clusters of `text`, including its end.
+///
+/// Words cannot end within a cluster. Clusters are characters with their
+/// combining marks, Thai and Lao leading vowels with the following
+/// consonant and Khmer subscript consonants with the preceding consonant.
+fn cluster_bounds(text: &str) -> Vec<usize> {
+    let mut bounds = Vec::new();
+    let mut joins_next = false;
+    for (idx, c) in text.char_indices() {
+        if !joins_next && !is_combining(c) {
+            bounds.push(idx);
+        }
+        joins_next = matches!(
+            c,
+            // Thai and Lao leading vowels, Khmer coeng.
+            '\u{0e40}'..='\u{0e44}' | '\u{0ec0}'..='\u{0ec4}' | '\u{17d2}'
+        );
+    }
+    if bounds.first() != Some(&0) {
+        bounds.insert(0, 0);
+    }
+    bounds.push(text.len());
+    bounds
+}
+
+/// Check whether a character is a mark combining with the preceding
+/// character.
+fn is_combining(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036f}'
+            // Thai vowel signs and tone marks.
+            | '\u{0e31}'
+            | '\u{0e34}'..='\u{0e3a}'
+            | '\u{0e47}'..='\u{0e4e}'
+            // Lao vowel signs and tone marks.
+            | '\u{0eb1}'
+            | '\u{0eb4}'..='\u{0ebc}'
+            | '\u{0ec8}'..='\u{0ece}'
+            // Khmer dependent vowels and signs.
+            | '\u{17b6}'..='\u{17d3}'
+            | '\u{17dd}'
+    )
+}
+
+impl Tokenizer for DictSegmenter {
+    fn tokenize(&self, line: &str, token: &mut dyn FnMut(&str)) {
+        for block in line.split(|c| !UnicodeWords::is_word_char(c)) {
+            if !block.is_empty() {
+                self.segment(block, token);
+            }
+        }
+    }
+}
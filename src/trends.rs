@@ -0,0 +1,193 @@
+//! Trends of tokens between two periods of time buckets.
+//!
+//! The change of the relative frequency of a token is measured by the log
+//! ratio of Hardie (2014), its significance by the log-likelihood ratio
+//! test of Dunning (1993).
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::count::LabelCounts;
+
+/// A range of time buckets, e.g. `2019` or `2019-01..2019-06`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Period {
+    pub first: String,
+    pub last: String,
+}
+
+impl Period {
+    /// Get a period of the single bucket.
+    pub fn bucket(bucket: &str) -> Self {
+        Period {
+            first: bucket.to_owned(),
+            last: bucket.to_owned(),
+        }
+    }
+
+    /// Check whether a bucket is in the period.
+    ///
+    /// Buckets of years and months are compared as strings, so that the
+    /// period `2019..2020` includes the months of 2019 and 2020 but no
+    /// months of 2021.
+    pub fn contains(&self, bucket: &str) -> bool {
+        bucket >= self.first.as_str()
+            && bucket.get(..self.last.len()).unwrap_or(bucket) <= self.last.as_str()
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (first, last) = s.split_once("..").unwrap_or((s, s));
+        if first.is_empty() || last.is_empty() || first > last {
+            return Err(format!("Invalid period: {}", s));
+        }
+        Ok(Period {
+            first: first.to_owned(),
+            last: last.to_owned(),
+        })
+    }
+}
+
+/// The trend of a token.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trend {
+    pub token: String,
+    pub before: usize,
+    pub after: usize,
+    /// Binary logarithm of the ratio of the relative frequencies.
+    pub log_ratio: f64,
+    /// Log-likelihood ratio statistic.
+    pub g2: f64,
+    pub p: f64,
+}
+
+/// Get the complementary error function.
+///
+/// Uses the approximation of Numerical Recipes, with a relative error
+/// below 1.2e-7.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1. / (1. + 0.5 * z);
+    let poly = [
+        -1.26551223,
+        1.00002368,
+        0.37409196,
+        0.09678418,
+        -0.18628806,
+        0.27886807,
+        -1.13520398,
+        1.48851587,
+        -0.82215223,
+        0.17087277,
+    ]
+    .iter()
+    .rev()
+    .fold(0., |acc, &c| acc * t + c);
+    let erfc = t * (-z * z + poly).exp();
+    if x >= 0. {
+        erfc
+    } else {
+        2. - erfc
+    }
+}
+
+/// Get `c ln(c / e)`, which is 0 for `c = 0`.
+fn llr_term(count: f64, expected: f64) -> f64 {
+    if count > 0. {
+        count * (count / expected).ln()
+    } else {
+        0.
+    }
+}
+
+/// Sum the token counts of the buckets in a period.
+fn period_counts<'a>(
+    buckets: &'a HashMap<String, LabelCounts>,
+    period: &Period,
+) -> (HashMap<&'a str, usize>, usize) {
+    let mut counts = HashMap::new();
+    let mut total = 0;
+    for (_, bucket_counts) in buckets.iter().filter(|(bucket, _)| period.contains(bucket)) {
+        for (token, &count) in &bucket_counts.tokens {
+            *counts.entry(token.as_str()).or_insert(0) += count;
+            total += count;
+        }
+    }
+    (counts, total)
+}
+
+/// Get the trends of tokens between two periods.
+///
+/// Only trends with a p-value of at most `max_p` are returned, sorted by
+/// the absolute log ratio, so that the largest rises and falls come first.
+/// Zero counts are replaced by 0.5 in the log ratio.
+pub fn trends(
+    buckets: &HashMap<String, LabelCounts>,
+    before: &Period,
+    after: &Period,
+    max_p: f64,
+) -> Vec<Trend> {
+    let (before_counts, before_total) = period_counts(buckets, before);
+    let (after_counts, after_total) = period_counts(buckets, after);
+    if before_total == 0 || after_total == 0 {
+        return Vec::new();
+    }
+    let (n1, n2) = (before_total as f64, after_total as f64);
+
+    let mut tokens = before_counts.keys().collect::<Vec<_>>();
+    tokens.extend(
+        after_counts
+            .keys()
+            .filter(|t| !before_counts.contains_key(*t)),
+    );
+    let mut trends = tokens
+        .into_iter()
+        .filter_map(|&token| {
+            let before = before_counts.get(token).copied().unwrap_or(0);
+            let after = after_counts.get(token).copied().unwrap_or(0);
+            let (c1, c2) = (before as f64, after as f64);
+            let e1 = n1 * (c1 + c2) / (n1 + n2);
+            let e2 = n2 * (c1 + c2) / (n1 + n2);
+            let g2 = 2. * (llr_term(c1, e1) + llr_term(c2, e2));
+            // The statistic has one degree of freedom.
+            let p = erfc((g2.max(0.) / 2.).sqrt());
+            if p > max_p {
+                return None;
+            }
+            let log_ratio = ((c2.max(0.5) / n2) / (c1.max(0.5) / n1)).log2();
+            Some(Trend {
+                token: token.to_owned(),
+                before,
+                after,
+                log_ratio,
+                g2,
+                p,
+            })
+        })
+        .collect::<Vec<_>>();
+    trends.sort_unstable_by(|t1, t2| {
+        t2.log_ratio
+            .abs()
+            .total_cmp(&t1.log_ratio.abs())
+            .then(t2.g2.total_cmp(&t1.g2))
+            .then(t1.token.cmp(&t2.token))
+    });
+    trends
+}
+
+/// Write `token<TAB>before<TAB>after<TAB>log_ratio<TAB>g2<TAB>p` lines.
+pub fn write_trends(mut writer: impl Write, trends: &[Trend]) -> io::Result<()> {
+    writeln!(writer, "token\tbefore\tafter\tlog_ratio\tg2\tp")?;
+    for trend in trends {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{:.4}\t{:.4}\t{:.3e}",
+            trend.token, trend.before, trend.after, trend.log_ratio, trend.g2, trend.p
+        )?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,115 @@
+//! Vocabularies built from token counts.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::sort::count_order;
+
+/// Vocabulary with tokens indexed by frequency rank.
+///
+/// Tokens are ordered like count tables, by descending count and ties by
+/// token, the most frequent token has index 0.
+#[derive(Clone, Debug, Default)]
+pub struct Vocab {
+    tokens: Vec<(String, usize)>,
+    indices: HashMap<String, usize>,
+}
+
+impl Vocab {
+    /// Build a vocabulary from token counts.
+    ///
+    /// Counts of repeated tokens are summed.
+    pub fn from_counts(counts: impl IntoIterator<Item = (String, usize)>) -> Self {
+        let mut summed = HashMap::new();
+        for (token, count) in counts {
+            *summed.entry(token).or_insert(0) += count;
+        }
+        let mut tokens = summed.into_iter().collect::<Vec<_>>();
+        tokens.sort_unstable_by(|(t1, c1), (t2, c2)| count_order(t1, *c1, t2, *c2));
+        let indices = tokens
+            .iter()
+            .enumerate()
+            .map(|(idx, (token, _))| (token.clone(), idx))
+            .collect();
+        Vocab { tokens, indices }
+    }
+
+    /// Read a vocabulary from `token<TAB>count` lines.
+    ///
+    /// Further columns, e.g. normalized frequencies, are ignored. Tokens
+    /// may occur only once.
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        Self::read_lines(reader.lines())
+    }
+
+    /// Read a vocabulary from `token<TAB>count` lines.
+    pub(crate) fn read_lines(lines: impl Iterator<Item = io::Result<String>>) -> io::Result<Self> {
+        let mut counts = Vec::new();
+        let mut seen = HashMap::new();
+        for (line_no, line) in lines.enumerate() {
+            let line = line?;
+            let mut fields = line.split('\t');
+            let token = fields.next().unwrap_or_default();
+            let count = fields
+                .next()
+                .and_then(|count| count.parse::<usize>().ok())
+                .ok_or_else(|| {
+                    invalid_data(format!("Invalid vocabulary line {}: {}", line_no + 1, line))
+                })?;
+            if let Some(first) = seen.insert(token.to_string(), line_no) {
+                return Err(invalid_data(format!(
+                    "Token {} in lines {} and {}",
+                    token,
+                    first + 1,
+                    line_no + 1
+                )));
+            }
+            counts.push((token.to_string(), count));
+        }
+        Ok(Self::from_counts(counts))
+    }
+
+    /// Write the vocabulary as `token<TAB>count` lines in rank order.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        for (token, count) in self.iter() {
+            writeln!(writer, "{}\t{}", token, count)?;
+        }
+        Ok(())
+    }
+
+    /// Get the count of a token.
+    pub fn count(&self, token: &str) -> Option<usize> {
+        self.idx(token).map(|idx| self.tokens[idx].1)
+    }
+
+    /// Get the index of a token.
+    pub fn idx(&self, token: &str) -> Option<usize> {
+        self.indices.get(token).copied()
+    }
+
+    /// Check whether the vocabulary is empty.
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Iterate over tokens and their counts in rank order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, usize)> + ExactSizeIterator {
+        self.tokens
+            .iter()
+            .map(|(token, count)| (token.as_str(), *count))
+    }
+
+    /// Get the number of tokens.
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Get the token with an index.
+    pub fn token(&self, idx: usize) -> Option<&str> {
+        self.tokens.get(idx).map(|(token, _)| token.as_str())
+    }
+}
+
+pub(crate) fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,356 @@
+//! Reading of WARC and WET web archives.
+//!
+//! Common Crawl distributes WET files with extracted plain text in
+//! `conversion` records, WARC files hold the raw HTTP responses.
+
+use std::io::{self, BufRead, Read};
+
+/// A single WARC record.
+pub struct Record {
+    headers: Vec<(String, String)>,
+    payload: Vec<u8>,
+}
+
+impl Record {
+    /// Get the value of the given (case-insensitive) header.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the `WARC-Type` of the record.
+    pub fn record_type(&self) -> Option<&str> {
+        self.header("WARC-Type")
+    }
+
+    /// Extract the plain text of a WET `conversion` record.
+    pub fn wet_text(&self) -> Option<String> {
+        if self.record_type() != Some("conversion") {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&self.payload).into_owned())
+    }
+
+    /// Extract the plain text of a WARC `response` or `resource` record.
+    ///
+    /// HTML documents are stripped of markup; records with content types
+    /// other than text are skipped. Returns an error for malformed chunked
+    /// bodies.
+    pub fn warc_text(&self) -> io::Result<Option<String>> {
+        let record_type = match self.record_type() {
+            Some(record_type) => record_type,
+            None => return Ok(None),
+        };
+        let (content_type, body) = match record_type {
+            "response" => {
+                let (headers, body) = split_http(&self.payload);
+                let content_type = http_header(headers, "Content-Type").unwrap_or_default();
+                let body = if http_header(headers, "Transfer-Encoding")
+                    .map(|v| v.eq_ignore_ascii_case("chunked"))
+                    .unwrap_or(false)
+                {
+                    dechunk(body)?
+                } else {
+                    body.to_vec()
+                };
+                (content_type.to_ascii_lowercase(), body)
+            }
+            "resource" => (
+                self.header("Content-Type")
+                    .unwrap_or_default()
+                    .to_ascii_lowercase(),
+                self.payload.clone(),
+            ),
+            _ => return Ok(None),
+        };
+        let body = String::from_utf8_lossy(&body);
+        Ok(if content_type.contains("html") {
+            Some(html_to_text(&body))
+        } else if content_type.starts_with("text/") {
+            Some(body.into_owned())
+        } else {
+            None
+        })
+    }
+}
+
+/// Iterator over the records of a WARC file.
+pub struct Records<R> {
+    reader: R,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(reader: R) -> Self {
+        Records { reader }
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<Record>> {
+        let mut line = String::new();
+        // Skip the blank lines separating records.
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if !line.trim().is_empty() {
+                break;
+            }
+        }
+        if !line.starts_with("WARC/") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected WARC record, found: {}", line.trim_end()),
+            ));
+        }
+
+        let mut headers = Vec::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated WARC header",
+                ));
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(idx) = header.find(':') {
+                headers.push((
+                    header[..idx].trim().to_string(),
+                    header[idx + 1..].trim().to_string(),
+                ));
+            }
+        }
+
+        let len = headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Content-Length"))
+            .and_then(|(_, value)| value.parse::<u64>().ok())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "missing WARC Content-Length")
+            })?;
+        // The length is untrusted, the payload grows as it is read.
+        let mut payload = Vec::with_capacity(len.min(1 << 20) as usize);
+        (&mut self.reader).take(len).read_to_end(&mut payload)?;
+        if (payload.len() as u64) < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated WARC record",
+            ));
+        }
+        Ok(Some(Record { headers, payload }))
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Split an HTTP response into its header block and body.
+fn split_http(payload: &[u8]) -> (&[u8], &[u8]) {
+    let (end, sep) = match payload.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(idx) => (idx, 4),
+        None => match payload.windows(2).position(|w| w == b"\n\n") {
+            Some(idx) => (idx, 2),
+            None => return (payload, &[]),
+        },
+    };
+    (&payload[..end], &payload[end + sep..])
+}
+
+fn http_header(headers: &[u8], name: &str) -> Option<String> {
+    String::from_utf8_lossy(headers)
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let idx = line.find(':')?;
+            if line[..idx].trim().eq_ignore_ascii_case(name) {
+                Some(line[idx + 1..].trim().to_string())
+            } else {
+                None
+            }
+        })
+        .next()
+}
+
+/// Decode a body with chunked transfer encoding.
+///
+/// Returns an error if a chunk extends past the end of the body.
+fn dechunk(mut body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(body.len());
+    while let Some(end) = body.windows(2).position(|w| w == b"\r\n") {
+        let size = String::from_utf8_lossy(&body[..end]);
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size = match usize::from_str_radix(size, 16) {
+            Ok(0) | Err(_) => break,
+            Ok(size) => size,
+        };
+        let start = end + 2;
+        let stop = start
+            .checked_add(size)
+            .filter(|&stop| stop <= body.len())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "chunk extends past the HTTP body",
+                )
+            })?;
+        decoded.extend_from_slice(&body[start..stop]);
+        body = &body[(stop + 2).min(body.len())..];
+    }
+    Ok(decoded)
+}
+
+/// Elements whose content is not part of the visible text.
+static SKIPPED_ELEMENTS: &[&str] = &["script", "style", "noscript", "template", "svg"];
+
+/// Elements that start a new line of text.
+static BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "title",
+    "tr",
+    "ul",
+];
+
+/// Extract the visible text of an HTML document.
+///
+/// This is a simple tag stripper: markup, comments and the content of
+/// scripts and style sheets are removed, block-level elements start new
+/// lines and common character references are decoded.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        push_decoded(&mut text, &rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map(|end| &rest[end + 3..]).unwrap_or("");
+            continue;
+        }
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if !closing && SKIPPED_ELEMENTS.contains(&name.as_str()) {
+            let close = format!("</{}", name);
+            rest = find_ignore_case(rest, &close)
+                .and_then(|idx| rest[idx..].find('>').map(|end| &rest[idx + end + 1..]))
+                .unwrap_or("");
+        } else if BLOCK_ELEMENTS.contains(&name.as_str()) {
+            text.push('\n');
+        } else {
+            text.push(' ');
+        }
+    }
+    push_decoded(&mut text, rest);
+
+    let mut lines = String::with_capacity(text.len());
+    for line in text.lines() {
+        let mut parts = line.split_whitespace();
+        if let Some(first) = parts.next() {
+            lines.push_str(first);
+            for part in parts {
+                lines.push(' ');
+                lines.push_str(part);
+            }
+            lines.push('\n');
+        }
+    }
+    lines
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Append `text` with character references decoded.
+fn push_decoded(out: &mut String, mut text: &str) {
+    while let Some(start) = text.find('&') {
+        out.push_str(&text[..start]);
+        text = &text[start..];
+        let decoded = text
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&text[1..end]).map(|c| (c, end)));
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                text = &text[end + 1..];
+            }
+            None => {
+                out.push('&');
+                text = &text[1..];
+            }
+        }
+    }
+    out.push_str(text);
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(num) = entity.strip_prefix('#') {
+        let code = if let Some(hex) = num.strip_prefix('x').or_else(|| num.strip_prefix('X')) {
+            u32::from_str_radix(hex, 16).ok()?
+        } else {
+            num.parse().ok()?
+        };
+        return std::char::from_u32(code);
+    }
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => None,
+    }
+}
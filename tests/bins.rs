@@ -0,0 +1,37 @@
+use corpus_count::bins::{balanced_cutoffs, log_freq_bin};
+
+#[test]
+fn bins_span_factor_of_base() {
+    let bins = [1000, 999, 501, 500, 251, 250, 2, 1]
+        .iter()
+        .map(|&count| log_freq_bin(count, 1000, 2))
+        .collect::<Vec<_>>();
+    assert_eq!(bins, vec![0, 0, 0, 1, 1, 2, 8, 9]);
+}
+
+#[test]
+fn zero_counts_share_bin_of_one() {
+    assert_eq!(log_freq_bin(0, 100, 10), log_freq_bin(1, 100, 10));
+    assert_eq!(log_freq_bin(1, 100, 10), 2);
+    assert_eq!(log_freq_bin(10, 100, 10), 1);
+    assert_eq!(log_freq_bin(11, 100, 10), 0);
+}
+
+#[test]
+fn cutoffs_balance_mass() {
+    let counts = [50, 20, 10, 10, 5, 5];
+    // Halves of the mass of 100: [50] and the rest.
+    assert_eq!(balanced_cutoffs(&counts, 2), vec![1]);
+    // Cumulative quarters of 25: the head already covers two quarters,
+    // the next clusters still get a token each.
+    assert_eq!(balanced_cutoffs(&counts, 4), vec![1, 2, 3]);
+    assert_eq!(balanced_cutoffs(&[10, 10, 10, 10, 10, 10], 3), vec![2, 4]);
+    assert!(balanced_cutoffs(&counts, 1).is_empty());
+}
+
+#[test]
+fn cutoffs_keep_clusters_non_empty() {
+    assert_eq!(balanced_cutoffs(&[100, 1, 1], 3), vec![1, 2]);
+    assert_eq!(balanced_cutoffs(&[100, 1], 5), vec![1]);
+    assert!(balanced_cutoffs(&[], 3).is_empty());
+}
@@ -0,0 +1,39 @@
+use corpus_count::bloom::BloomFilter;
+
+#[test]
+fn no_false_negatives() {
+    let mut filter = BloomFilter::with_fpr(1000, 0.01);
+    for idx in 0..1000 {
+        filter.insert(&format!("token{}", idx));
+    }
+    assert_eq!(filter.len(), 1000);
+    assert!((0..1000).all(|idx| filter.contains(&format!("token{}", idx))));
+}
+
+#[test]
+fn false_positive_rate() {
+    let mut filter = BloomFilter::with_fpr(10_000, 0.01);
+    assert_eq!(filter.n_hashes(), 7);
+    for idx in 0..10_000 {
+        filter.insert(&format!("token{}", idx));
+    }
+    let false_positives = (0..10_000)
+        .filter(|idx| filter.contains(&format!("other{}", idx)))
+        .count();
+    assert!(false_positives < 200, "{}", false_positives);
+}
+
+#[test]
+fn serialization_roundtrip() {
+    let mut filter = BloomFilter::new(100, 3);
+    filter.insert("a");
+    filter.insert("b");
+    let mut data = Vec::new();
+    filter.write(&mut data).unwrap();
+    assert_eq!(&data[..4], b"CCBF");
+    assert_eq!(data.len(), 4 + 4 + 8 + 8 + 2 * 8);
+    let read = BloomFilter::read(&data[..]).unwrap();
+    assert_eq!(read, filter);
+    assert!(read.contains("a") && read.contains("b"));
+    assert!(BloomFilter::read(&b"XXXX"[..]).is_err());
+}
@@ -0,0 +1,44 @@
+use corpus_count::charlm::CharLm;
+
+const COUNTS: &[(&str, usize)] = &[("the", 20), ("then", 5), ("than", 3), ("hen", 2)];
+
+#[test]
+fn probabilities_sum_to_one() {
+    let lm = CharLm::from_token_counts(COUNTS.iter().copied(), 2, false);
+    // `x` stands for all unknown characters.
+    let chars = ['t', 'h', 'e', 'n', 'a', 'x'];
+    let total = chars
+        .iter()
+        .flat_map(|&c1| chars.iter().map(move |&c2| format!("{}{}", c1, c2)))
+        .map(|token| lm.log_prob(&token).exp())
+        .sum::<f64>();
+    assert!((total - 1.).abs() < 1e-9, "{}", total);
+}
+
+#[test]
+fn frequent_patterns_are_probable() {
+    let lm = CharLm::from_token_counts(COUNTS.iter().copied(), 3, true);
+    assert!(lm.log_prob("the") > lm.log_prob("teh"));
+    assert!(lm.log_prob("hen") > lm.log_prob("hxn"));
+    assert!(lm.log_prob_per_char("then") > lm.log_prob_per_char("tttt"));
+}
+
+#[test]
+fn ngram_counts_outside_the_orders_are_ignored() {
+    let ngrams = vec![("<th", 10), ("the", 10), ("he>", 10), ("th", 1000)];
+    let lm = CharLm::from_ngram_counts(ngrams.clone(), 3, 3, true);
+    let lm_without = CharLm::from_ngram_counts(ngrams[..3].to_vec(), 3, 3, true);
+    assert_eq!(lm.log_prob("the"), lm_without.log_prob("the"));
+    assert!(lm.log_prob("the") > lm.log_prob("eht"));
+}
+
+#[test]
+fn held_out_hapaxes_are_improbable() {
+    let mut counts = COUNTS.to_vec();
+    counts.push(("xqzv", 1));
+    let lm = CharLm::from_token_counts(counts.iter().copied(), 3, true);
+    assert!(lm.held_out_log_prob_per_char("xqzv") < lm.log_prob_per_char("xqzv"));
+    assert!(lm.held_out_log_prob_per_char("xqzv") < lm.held_out_log_prob_per_char("then"));
+    // One occurrence of frequent tokens hardly matters.
+    assert!((lm.held_out_log_prob_per_char("the") - lm.log_prob_per_char("the")).abs() < 0.1);
+}
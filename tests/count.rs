@@ -0,0 +1,119 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use corpus_count::count::{count_corpus, CountOptions};
+use corpus_count::normalize::Lowercase;
+use corpus_count::options::Options;
+
+const CORPUS: &str = "the cat saw the dog\nThe dog ran\n\nthe cat ran\nthe cat ran\n";
+
+fn sorted_counts(pairs: &[(&str, usize)]) -> Vec<(String, usize)> {
+    pairs
+        .iter()
+        .map(|&(item, count)| (item.to_string(), count))
+        .collect()
+}
+
+#[test]
+fn counts_tokens_and_totals() {
+    let tables = count_corpus(
+        Cursor::new(CORPUS),
+        &Options::default(),
+        &CountOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        tables.tokens,
+        sorted_counts(&[
+            ("the", 4),
+            ("cat", 3),
+            ("ran", 3),
+            ("dog", 2),
+            ("The", 1),
+            ("saw", 1)
+        ])
+    );
+    assert_eq!(tables.counts.lines, 5);
+    assert_eq!(tables.counts.num_tokens, 14);
+    assert_eq!(tables.counts.bytes, CORPUS.len());
+    assert_eq!(tables.counts.num_documents, 2);
+}
+
+#[test]
+fn normalizes_and_deduplicates_lines() {
+    let count_opts = CountOptions {
+        dedup_lines: true,
+        normalizer: Some(Arc::new(Lowercase)),
+        ..CountOptions::default()
+    };
+    let tables = count_corpus(Cursor::new(CORPUS), &Options::default(), &count_opts).unwrap();
+    assert_eq!(
+        tables.tokens,
+        sorted_counts(&[("the", 4), ("cat", 2), ("dog", 2), ("ran", 2), ("saw", 1)])
+    );
+    assert_eq!(tables.counts.duplicate_lines, 1);
+    assert_eq!(tables.counts.num_tokens, 11);
+}
+
+#[test]
+fn counts_and_prunes_ngrams() {
+    let opts = Options::builder()
+        .token_min(2)
+        .ngram_min(4)
+        .min_n(2)
+        .max_n(3)
+        .filter_first(true)
+        .build()
+        .unwrap();
+    let tables = count_corpus(Cursor::new(CORPUS), &opts, &CountOptions::default()).unwrap();
+    assert_eq!(
+        tables.tokens,
+        sorted_counts(&[("the", 4), ("cat", 3), ("ran", 3), ("dog", 2)])
+    );
+    // The n-grams of `The` and `saw` are not counted, e.g. `he` would be
+    // counted 5 times otherwise.
+    assert_eq!(
+        tables.ngrams,
+        sorted_counts(&[
+            ("<t", 4),
+            ("<th", 4),
+            ("e>", 4),
+            ("he", 4),
+            ("he>", 4),
+            ("th", 4),
+            ("the", 4)
+        ])
+    );
+    assert_eq!(tables.ngram_min, 4);
+}
+
+#[test]
+fn counts_document_frequencies_of_blank_line_documents() {
+    let count_opts = CountOptions {
+        doc_freqs: true,
+        ..CountOptions::default()
+    };
+    let tables = count_corpus(Cursor::new(CORPUS), &Options::default(), &count_opts).unwrap();
+    assert_eq!(tables.counts.doc_freqs["the"], 2);
+    assert_eq!(tables.counts.doc_freqs["saw"], 1);
+    assert_eq!(tables.counts.doc_freqs["ran"], 2);
+}
+
+#[test]
+fn caps_the_contributions_of_documents() {
+    let count_opts = CountOptions {
+        max_contributions: Some(2),
+        ..CountOptions::default()
+    };
+    let tables = count_corpus(Cursor::new(CORPUS), &Options::default(), &count_opts).unwrap();
+    // Only the first two distinct tokens of every document are counted,
+    // repetitions within a document are counted once.
+    assert_eq!(
+        tables.counts.contributions,
+        [("the", 2), ("cat", 2)]
+            .iter()
+            .map(|&(token, count)| (token.to_string(), count))
+            .collect()
+    );
+    assert_eq!(tables.tokens[0], ("the".to_string(), 4));
+}
@@ -0,0 +1,115 @@
+use corpus_count::dawg::{Dawg, DawgBuilder};
+use corpus_count::rng::Rng;
+
+fn build(entries: &[(&str, u64)]) -> Dawg<Vec<u8>> {
+    let mut builder = DawgBuilder::new();
+    for (key, count) in entries {
+        builder.insert(key, *count).unwrap();
+    }
+    builder.finish()
+}
+
+#[test]
+fn counts_and_indices() {
+    let dawg = build(&[("", 3), ("car", 5), ("cart", 2), ("cat", 300), ("dog", 9)]);
+    assert_eq!(dawg.len(), 5);
+    assert_eq!(dawg.get(""), Some(3));
+    assert_eq!(dawg.get("cart"), Some(2));
+    assert_eq!(dawg.get("cat"), Some(300));
+    assert_eq!(dawg.get("ca"), None);
+    assert_eq!(dawg.get("cats"), None);
+    assert_eq!(dawg.index("car"), Some(1));
+    assert_eq!(dawg.index("dog"), Some(4));
+}
+
+#[test]
+fn prefix_in_byte_order() {
+    let dawg = build(&[("car", 5), ("cart", 2), ("cat", 9), ("dog", 1)]);
+    assert_eq!(
+        dawg.prefix("ca"),
+        vec![
+            (b"car".to_vec(), 5),
+            (b"cart".to_vec(), 2),
+            (b"cat".to_vec(), 9)
+        ]
+    );
+    assert!(dawg.prefix("do!").is_empty());
+}
+
+#[test]
+fn keys_have_to_be_sorted() {
+    let mut builder = DawgBuilder::new();
+    builder.insert("b", 1).unwrap();
+    assert!(builder.insert("a", 1).is_err());
+    assert!(builder.insert("b", 1).is_err());
+}
+
+#[test]
+fn inflections_are_shared() {
+    let stems = ["haus", "maus", "laus", "baum", "raum", "traum"];
+    let suffixes = ["", "e", "en", "es", "chen", "chens"];
+    let mut keys = stems
+        .iter()
+        .flat_map(|stem| {
+            suffixes
+                .iter()
+                .map(move |suffix| format!("{}{}", stem, suffix))
+        })
+        .collect::<Vec<_>>();
+    keys.sort();
+    let text_len = keys.iter().map(|key| key.len() + 3).sum::<usize>();
+    let mut builder = DawgBuilder::new();
+    for key in &keys {
+        builder.insert(key, 10).unwrap();
+    }
+    let bytes = builder.into_bytes();
+    assert!(bytes.len() < text_len / 2, "{} {}", bytes.len(), text_len);
+    let dawg = Dawg::new(bytes).unwrap();
+    assert!(keys.iter().all(|key| dawg.get(key) == Some(10)));
+}
+
+#[test]
+fn random_dawgs() {
+    let mut rng = Rng::new(7);
+    let mut keys = (0..2000)
+        .map(|_| {
+            let len = rng.below(8) as usize;
+            (0..len)
+                .map(|_| (b'a' + rng.below(4) as u8) as char)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+    let entries = keys
+        .into_iter()
+        .map(|key| {
+            let count = rng.below(100_000);
+            (key, count)
+        })
+        .collect::<Vec<_>>();
+    let mut builder = DawgBuilder::new();
+    for (key, count) in &entries {
+        builder.insert(key, *count).unwrap();
+    }
+    let dawg = builder.finish();
+    for (idx, (key, count)) in entries.iter().enumerate() {
+        assert_eq!(dawg.index(key), Some(idx as u64), "{}", key);
+        assert_eq!(dawg.get(key), Some(*count));
+    }
+    let found = dawg.entries();
+    assert_eq!(found.len(), entries.len());
+    for ((key, count), (found_key, found_count)) in entries.iter().zip(found) {
+        assert_eq!(key.as_bytes(), found_key.as_slice());
+        assert_eq!(*count, found_count);
+    }
+}
+
+#[test]
+fn invalid_dawgs() {
+    assert!(Dawg::new(b"CCFS".to_vec()).is_err());
+    let mut bytes = DawgBuilder::new().into_bytes();
+    assert!(Dawg::new(bytes.clone()).unwrap().is_empty());
+    bytes.pop();
+    assert!(Dawg::new(bytes).is_err());
+}
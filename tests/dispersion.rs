@@ -0,0 +1,30 @@
+use corpus_count::dispersion::Dispersion;
+
+#[test]
+fn single_document_has_zero_juilland_d() {
+    let dispersion = Dispersion::new(3, &[(3, 10)], 1, 10);
+    assert_eq!(dispersion.juilland_d, 0.);
+    assert_eq!(dispersion.dp, 0.);
+}
+
+#[test]
+fn even_dispersion() {
+    let dispersion = Dispersion::new(4, &[(2, 10), (2, 10)], 2, 20);
+    assert!((dispersion.juilland_d - 1.).abs() < 1e-9);
+    assert!(dispersion.dp.abs() < 1e-9);
+}
+
+#[test]
+fn concentrated_dispersion() {
+    // All occurrences in one of four equally long documents.
+    let dispersion = Dispersion::new(4, &[(4, 10)], 4, 40);
+    assert!(dispersion.juilland_d.abs() < 1e-9);
+    assert!((dispersion.dp - 0.75).abs() < 1e-9);
+}
+
+#[test]
+fn tokens_without_documents_are_finite() {
+    let dispersion = Dispersion::new(0, &[], 3, 30);
+    assert!(dispersion.juilland_d.is_finite());
+    assert!(dispersion.dp.is_finite());
+}
@@ -0,0 +1,48 @@
+use std::io::{Cursor, ErrorKind};
+
+use corpus_count::embeddings::{read_vocab, EmbeddingFormat};
+
+fn word2vec(header: &str, words: &[&str], dims: usize) -> Vec<u8> {
+    let mut data = format!("{}\n", header).into_bytes();
+    for word in words {
+        data.extend(word.as_bytes());
+        data.push(b' ');
+        data.extend(vec![0; dims * 4]);
+        data.push(b'\n');
+    }
+    data
+}
+
+#[test]
+fn reads_word2vec_vocabularies() {
+    let data = word2vec("2 3", &["house", "tree"], 3);
+    let vocab = read_vocab(Cursor::new(data), EmbeddingFormat::Word2Vec).unwrap();
+    assert_eq!(vocab.words.len(), 2);
+    assert!(vocab.words.contains("house"));
+    assert!(vocab.words.contains("tree"));
+}
+
+#[test]
+fn corrupt_word2vec_headers_are_errors() {
+    for header in &["99999999999 99999999999", "1 18446744073709551615", "2 3"] {
+        let data = word2vec(header, &["house"], 3);
+        let err = read_vocab(Cursor::new(data), EmbeddingFormat::Word2Vec).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData, "header {}", header);
+    }
+}
+
+#[test]
+fn huge_finalfusion_strings_are_truncated() {
+    let mut data = b"FiFu".to_vec();
+    // Version, one simple vocabulary chunk.
+    for value in &[1u32, 1, 1, 1] {
+        data.extend(&value.to_le_bytes());
+    }
+    // Chunk and vocabulary lengths.
+    data.extend(&0u64.to_le_bytes());
+    data.extend(&1u64.to_le_bytes());
+    data.extend(&u32::MAX.to_le_bytes());
+    data.extend(b"house");
+    let err = read_vocab(Cursor::new(data), EmbeddingFormat::FinalFusion).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,49 @@
+use corpus_count::fasttext::{std_sort, Dictionary, EntryType, EOS};
+
+fn words(dict: &Dictionary) -> Vec<(&str, usize)> {
+    dict.entries()
+        .iter()
+        .map(|entry| (entry.word.as_str(), entry.count))
+        .collect()
+}
+
+#[test]
+fn sort_matches_libstdcxx() {
+    // Order of std::sort of libstdc++ by descending value, ties are not
+    // kept in input order.
+    let values = [1, 2, 1, 3, 2, 1, 1, 2, 3, 1, 2, 1, 1, 1, 2, 3, 1, 2, 1, 1];
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    std_sort(&mut order, |&idx1, &idx2| values[idx1] > values[idx2]);
+    assert_eq!(
+        order,
+        vec![15, 3, 8, 1, 17, 14, 10, 7, 4, 6, 9, 5, 11, 12, 13, 2, 16, 0, 18, 19]
+    );
+}
+
+#[test]
+fn words_before_labels() {
+    let mut dict = Dictionary::new("__label__");
+    dict.add_line("b a\tb");
+    dict.add_line("__label__x  c");
+    dict.threshold(1, 1);
+    assert_eq!(
+        words(&dict),
+        vec![("b", 2), (EOS, 2), ("a", 1), ("c", 1), ("__label__x", 1)]
+    );
+    assert_eq!(dict.entries()[4].entry_type, EntryType::Label);
+    assert_eq!(dict.tokens(), 7);
+    dict.threshold(2, 1);
+    assert_eq!(words(&dict), vec![("b", 2), (EOS, 2), ("__label__x", 1)]);
+}
+
+#[test]
+fn prune_while_reading() {
+    let mut dict = Dictionary::with_max_vocab_size("__label__", 4);
+    for word in &["a", "a", "b", "c", "d", "e"] {
+        dict.add(word);
+    }
+    // The fourth type exceeds 75% of the maximum size, types occurring
+    // once are pruned.
+    assert_eq!(words(&dict), vec![("a", 2), ("e", 1)]);
+    assert_eq!(dict.tokens(), 6);
+}
@@ -0,0 +1,103 @@
+use corpus_count::fst::{Map, MapBuilder};
+use corpus_count::rng::Rng;
+
+#[test]
+fn get_values() {
+    let mut builder = MapBuilder::new();
+    for (key, value) in &[("", 7), ("car", 5), ("cart", 2), ("cat", 9), ("dog", 9)] {
+        builder.insert(key, *value).unwrap();
+    }
+    let map = builder.finish();
+    assert_eq!(map.len(), 5);
+    assert_eq!(map.get(""), Some(7));
+    assert_eq!(map.get("car"), Some(5));
+    assert_eq!(map.get("cart"), Some(2));
+    assert_eq!(map.get("cat"), Some(9));
+    assert_eq!(map.get("dog"), Some(9));
+    assert_eq!(map.get("ca"), None);
+    assert_eq!(map.get("cats"), None);
+}
+
+#[test]
+fn prefix_in_byte_order() {
+    let mut builder = MapBuilder::new();
+    for (key, value) in &[("car", 5), ("cart", 2), ("cat", 9), ("dog", 1)] {
+        builder.insert(key, *value).unwrap();
+    }
+    let map = builder.finish();
+    let entries = map.prefix("ca");
+    assert_eq!(
+        entries,
+        vec![
+            (b"car".to_vec(), 5),
+            (b"cart".to_vec(), 2),
+            (b"cat".to_vec(), 9)
+        ]
+    );
+    assert!(map.prefix("x").is_empty());
+    assert_eq!(map.entries().len(), 4);
+}
+
+#[test]
+fn keys_have_to_be_sorted() {
+    let mut builder = MapBuilder::new();
+    builder.insert("b", 1).unwrap();
+    assert!(builder.insert("a", 1).is_err());
+    assert!(builder.insert("b", 1).is_err());
+}
+
+#[test]
+fn suffixes_are_shared() {
+    let mut builder = MapBuilder::new();
+    let mut unshared = MapBuilder::new();
+    for key in &["hearing", "reading", "seeing", "testing"] {
+        builder.insert(key, 0).unwrap();
+    }
+    unshared.insert("hearing", 0).unwrap();
+    let (shared, unshared) = (builder.into_bytes(), unshared.into_bytes());
+    // The states of "ing" and "ding" are shared.
+    assert!(shared.len() < 3 * unshared.len());
+}
+
+#[test]
+fn random_maps() {
+    let mut rng = Rng::new(42);
+    let mut keys = (0..2000)
+        .map(|_| {
+            let len = rng.below(8) as usize;
+            (0..len)
+                .map(|_| (b'a' + rng.below(4) as u8) as char)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+    let entries = keys
+        .into_iter()
+        .map(|key| {
+            let value = rng.below(1000);
+            (key, value)
+        })
+        .collect::<Vec<_>>();
+    let mut builder = MapBuilder::new();
+    for (key, value) in &entries {
+        builder.insert(key, *value).unwrap();
+    }
+    let map = Map::new(builder.into_bytes()).unwrap();
+    assert_eq!(map.len(), entries.len() as u64);
+    for (key, value) in &entries {
+        assert_eq!(map.get(key), Some(*value), "{}", key);
+    }
+    let found = map.entries();
+    assert_eq!(found.len(), entries.len());
+    for ((key, value), (found_key, found_value)) in entries.iter().zip(found) {
+        assert_eq!(key.as_bytes(), found_key.as_slice());
+        assert_eq!(*value, found_value);
+    }
+}
+
+#[test]
+fn invalid_maps() {
+    assert!(Map::new(b"CCBF".to_vec()).is_err());
+    assert!(Map::new(Vec::new()).is_err());
+}
@@ -0,0 +1,131 @@
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+use corpus_count::gzip::{fuzz_gzip, GzDecoder, GzEncoder};
+use corpus_count::rng::Rng;
+
+const CASES: usize = 100;
+
+/// Get random data from a small alphabet, so that there are many matches.
+fn random_data(rng: &mut Rng, max_len: u64) -> Vec<u8> {
+    let len = rng.below(max_len) as usize;
+    let alphabet = 1 + rng.below(255);
+    let mut data = Vec::with_capacity(len);
+    while data.len() < len {
+        if !data.is_empty() && rng.below(4) == 0 {
+            // Repeat an earlier slice, possibly overlapping the end.
+            let start = rng.below(data.len() as u64) as usize;
+            let repeat = 1 + rng.below(300) as usize;
+            for idx in 0..repeat.min(len - data.len()) {
+                data.push(data[start + idx]);
+            }
+        } else {
+            data.push(rng.below(alphabet) as u8);
+        }
+    }
+    data
+}
+
+fn encode(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new());
+    encoder.write_all(data).unwrap();
+    encoder.try_finish().unwrap();
+    encoder.get_ref().clone()
+}
+
+fn decode(data: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut decoded).unwrap();
+    decoded
+}
+
+/// Run the system `gzip` with `args` on `input`, `None` if it is missing.
+fn system_gzip(args: &[&str], input: &[u8]) -> Option<Vec<u8>> {
+    let mut child = match Command::new("gzip")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(_) => {
+            eprintln!("gzip is not installed, skipping comparison");
+            return None;
+        }
+    };
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_owned();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap().unwrap();
+    assert!(output.status.success());
+    Some(output.stdout)
+}
+
+#[test]
+fn random_data_round_trips() {
+    let mut rng = Rng::new(3);
+    for _ in 0..CASES {
+        let data = random_data(&mut rng, 1 << 16);
+        assert!(decode(&encode(&data)) == data, "{} bytes", data.len());
+    }
+}
+
+#[test]
+fn concatenated_members_are_decoded() {
+    let mut data = encode(b"first member\n");
+    data.extend(encode(b""));
+    data.extend(encode(b"second member\n"));
+    assert_eq!(decode(&data), b"first member\nsecond member\n");
+}
+
+#[test]
+fn corrupted_checksums_are_invalid() {
+    let mut data = encode(b"some text\n");
+    let crc = data.len() - 8;
+    data[crc] ^= 1;
+    let mut decoded = Vec::new();
+    assert!(GzDecoder::new(&data[..]).read_to_end(&mut decoded).is_err());
+}
+
+#[test]
+fn fuzz_arbitrary_bytes() {
+    let mut rng = Rng::new(17);
+    for _ in 0..CASES * 10 {
+        let len = rng.below(64) as usize;
+        let mut data = (0..len).map(|_| rng.next_u64() as u8).collect::<Vec<_>>();
+        // Start some inputs with a gzip header to get past the magic bytes.
+        if rng.below(2) == 0 {
+            data.splice(0..0, vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255]);
+        }
+        fuzz_gzip(&data);
+    }
+}
+
+#[test]
+fn decodes_output_of_system_gzip() {
+    let mut rng = Rng::new(5);
+    for level in &["-1", "-6", "-9"] {
+        for _ in 0..CASES / 10 {
+            let data = random_data(&mut rng, 1 << 16);
+            let compressed = match system_gzip(&["-c", level], &data) {
+                Some(compressed) => compressed,
+                None => return,
+            };
+            assert!(decode(&compressed) == data, "{} bytes", data.len());
+        }
+    }
+}
+
+#[test]
+fn system_gzip_decodes_output() {
+    let mut rng = Rng::new(11);
+    for _ in 0..CASES / 10 {
+        let data = random_data(&mut rng, 1 << 16);
+        let decompressed = match system_gzip(&["-dc"], &encode(&data)) {
+            Some(decompressed) => decompressed,
+            None => return,
+        };
+        assert!(decompressed == data, "{} bytes", data.len());
+    }
+}
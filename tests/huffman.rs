@@ -0,0 +1,39 @@
+use corpus_count::huffman::huffman_paths;
+
+#[test]
+fn frequent_tokens_get_short_codes() {
+    let paths = huffman_paths(&[8, 4, 2, 1, 1]);
+    let codes = paths
+        .iter()
+        .map(|path| path.code_string())
+        .collect::<Vec<_>>();
+    assert_eq!(codes, vec!["1", "01", "001", "0001", "0000"]);
+    // Inner nodes from the root at index 3.
+    assert_eq!(paths[0].points, vec![3]);
+    assert_eq!(paths[3].points, vec![3, 2, 1, 0]);
+}
+
+#[test]
+fn codes_are_prefix_free() {
+    let counts = [30, 20, 20, 10, 9, 5, 5, 1];
+    let codes = huffman_paths(&counts)
+        .iter()
+        .map(|path| path.code_string())
+        .collect::<Vec<_>>();
+    for (idx1, code1) in codes.iter().enumerate() {
+        for (idx2, code2) in codes.iter().enumerate() {
+            assert!(idx1 == idx2 || !code2.starts_with(code1.as_str()));
+        }
+    }
+    for path in huffman_paths(&counts) {
+        assert_eq!(path.code.len(), path.points.len());
+        assert_eq!(path.points[0], counts.len() - 2);
+    }
+}
+
+#[test]
+fn paths_of_tiny_vocabs() {
+    assert!(huffman_paths(&[]).is_empty());
+    assert!(huffman_paths(&[3])[0].code.is_empty());
+    assert_eq!(huffman_paths(&[3, 1])[1].code_string(), "0");
+}
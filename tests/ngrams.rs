@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use corpus_count::ngrams::{
+    count_ngrams, fuzz_ngrams, naive_ngrams, prune_ngrams, redundant_ngrams, NGrams,
+};
+use corpus_count::options::Options;
+use corpus_count::rng::Rng;
+
+const CASES: usize = 2000;
+
+/// Characters of different UTF-8 lengths, including combining marks.
+const CHARS: &[char] = &[
+    'a',
+    'b',
+    'z',
+    '<',
+    '>',
+    ' ',
+    '\u{e9}',
+    '\u{301}',
+    '\u{3b1}',
+    '\u{5d0}',
+    '\u{4e2d}',
+    '\u{65e5}',
+    '\u{1f600}',
+    '\u{1f1e9}',
+    '\u{200d}',
+];
+
+fn random_string(rng: &mut Rng) -> String {
+    let len = rng.below(12) as usize;
+    (0..len)
+        .map(|_| CHARS[rng.below(CHARS.len() as u64) as usize])
+        .collect()
+}
+
+/// Get arbitrary strings with `1 <= min_n <= max_n`.
+fn cases() -> impl Iterator<Item = (String, usize, usize)> {
+    let mut rng = Rng::new(7);
+    (0..CASES).map(move |_| {
+        let min_n = 1 + rng.below(6) as usize;
+        let max_n = min_n + rng.below(6) as usize;
+        (random_string(&mut rng), min_n, max_n)
+    })
+}
+
+#[test]
+fn ngrams_match_naive() {
+    for (string, min_n, max_n) in cases() {
+        assert_eq!(
+            NGrams::new(&string, min_n, max_n).collect::<Vec<_>>(),
+            naive_ngrams(&string, min_n, max_n),
+            "n-grams of {:?} with min_n {} and max_n {}",
+            string,
+            min_n,
+            max_n
+        );
+    }
+}
+
+#[test]
+fn size_hint_is_exact() {
+    for (string, min_n, max_n) in cases() {
+        let mut ngrams = NGrams::new(&string, min_n, max_n);
+        let mut remaining = naive_ngrams(&string, min_n, max_n).len();
+        loop {
+            assert_eq!(
+                ngrams.size_hint(),
+                (remaining, Some(remaining)),
+                "size hint for {:?} with min_n {} and max_n {}",
+                string,
+                min_n,
+                max_n
+            );
+            assert_eq!(ngrams.len(), remaining);
+            if ngrams.next().is_none() {
+                break;
+            }
+            remaining -= 1;
+        }
+        // Exhausted iterators stay empty.
+        assert!(ngrams.next().is_none());
+        assert_eq!(ngrams.len(), 0);
+    }
+}
+
+#[test]
+fn fuzz_arbitrary_bytes() {
+    let mut rng = Rng::new(13);
+    for _ in 0..CASES {
+        let len = rng.below(32) as usize;
+        let data = (0..len).map(|_| rng.next_u64() as u8).collect::<Vec<_>>();
+        fuzz_ngrams(&data);
+    }
+}
+
+#[test]
+fn reversed_ngrams_match_naive() {
+    for (string, min_n, max_n) in cases() {
+        let mut expected = naive_ngrams(&string, min_n, max_n);
+        expected.reverse();
+        assert_eq!(
+            NGrams::new(&string, min_n, max_n).rev().collect::<Vec<_>>(),
+            expected,
+            "reversed n-grams of {:?} with min_n {} and max_n {}",
+            string,
+            min_n,
+            max_n
+        );
+    }
+}
+
+#[test]
+fn ngrams_from_both_ends_meet() {
+    let mut rng = Rng::new(11);
+    for (string, min_n, max_n) in cases() {
+        let expected = naive_ngrams(&string, min_n, max_n);
+        let mut ngrams = NGrams::new(&string, min_n, max_n);
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        loop {
+            let ngram = if rng.below(2) == 0 {
+                ngrams.next().map(|ngram| front.push(ngram))
+            } else {
+                ngrams.next_back().map(|ngram| back.push(ngram))
+            };
+            assert_eq!(ngrams.len(), expected.len() - front.len() - back.len());
+            if ngram.is_none() {
+                break;
+            }
+        }
+        back.reverse();
+        front.extend(back);
+        assert_eq!(front, expected, "n-grams of {:?}", string);
+        // Exhausted iterators stay empty from both ends.
+        assert!(ngrams.next().is_none());
+        assert!(ngrams.next_back().is_none());
+    }
+}
+
+#[test]
+fn clones_are_independent() {
+    for (string, min_n, max_n) in cases() {
+        let mut ngrams = NGrams::new(&string, min_n, max_n);
+        ngrams.next();
+        let clone = ngrams.clone();
+        let rest = ngrams.collect::<Vec<_>>();
+        assert_eq!(clone.collect::<Vec<_>>(), rest);
+    }
+}
+
+#[test]
+fn redundant_ngrams_are_explained_by_extensions() {
+    let counts = [
+        ("<th", 100),
+        ("<the", 98),
+        ("he", 300),
+        ("he>", 120),
+        ("e>", 125),
+    ]
+    .iter()
+    .map(|&(ngram, count)| (ngram.to_owned(), count))
+    .collect::<HashMap<_, _>>();
+    let mut redundant = redundant_ngrams(&counts, 0.95, 1)
+        .into_iter()
+        .collect::<Vec<_>>();
+    redundant.sort();
+    assert_eq!(redundant, vec!["<th".to_owned(), "e>".to_owned()]);
+    assert!(redundant_ngrams(&counts, 1., 1).is_empty());
+    // Extensions below the minimum count explain nothing.
+    assert!(redundant_ngrams(&counts, 0.95, 121).is_empty());
+}
+
+#[test]
+fn ngrams_are_counted_with_token_counts() {
+    let tokens = [("ab", 3), ("b", 2), ("a", 1)];
+    let options = Options::builder().min_n(2).max_n(2).build().unwrap();
+    let counts = count_ngrams(tokens.iter().copied(), &options);
+    assert_eq!(counts["<a"], 4);
+    assert_eq!(counts["b>"], 5);
+    assert_eq!(counts["ab"], 3);
+    assert_eq!(counts.len(), 5);
+
+    let options = options
+        .to_builder()
+        .token_min(2)
+        .filter_first(true)
+        .bracket(false)
+        .build()
+        .unwrap();
+    let counts = count_ngrams(tokens.iter().copied(), &options);
+    assert_eq!(
+        counts.into_iter().collect::<Vec<_>>(),
+        vec![("ab".to_owned(), 3)]
+    );
+}
+
+#[test]
+fn target_size_sets_ngram_min_count() {
+    let mut counts = [("a", 10), ("b", 5), ("c", 5), ("d", 2), ("e", 1)]
+        .iter()
+        .map(|&(ngram, count)| (ngram.to_owned(), count))
+        .collect::<HashMap<_, _>>();
+    let options = Options::builder().ngram_min(3).build().unwrap();
+    assert_eq!(prune_ngrams(&mut counts, &options), 3);
+    let options = options
+        .to_builder()
+        .ngram_target_size(Some(2))
+        .build()
+        .unwrap();
+    // Keeping 3 n-grams is closer to the target size than keeping 1.
+    assert_eq!(prune_ngrams(&mut counts, &options), 5);
+    let options = options
+        .to_builder()
+        .ngram_target_size(Some(10))
+        .build()
+        .unwrap();
+    assert_eq!(prune_ngrams(&mut counts, &options), 1);
+    assert_eq!(counts.len(), 5);
+}
@@ -0,0 +1,56 @@
+use std::borrow::Cow;
+
+use corpus_count::normalize::{Normalizer, Registry};
+
+/// Custom normalizer replacing tokens that look like record numbers.
+#[derive(Debug)]
+struct RecordNumbers;
+
+impl Normalizer for RecordNumbers {
+    fn normalize<'a>(&self, token: &'a str) -> Cow<'a, str> {
+        if token.starts_with("MRN") {
+            Cow::Borrowed("<mrn>")
+        } else {
+            Cow::Borrowed(token)
+        }
+    }
+}
+
+#[test]
+fn builtin_normalizers() {
+    let registry = Registry::builtin();
+    assert_eq!(
+        registry.names().collect::<Vec<_>>(),
+        ["digits", "lowercase", "strip_punct"]
+    );
+    let normalizer = registry.create("lowercase").unwrap();
+    assert_eq!(normalizer.normalize("Straße"), "straße");
+    assert!(matches!(normalizer.normalize("lower"), Cow::Borrowed(_)));
+    let normalizer = registry.create("digits").unwrap();
+    assert_eq!(normalizer.normalize("1990s"), "0000s");
+    let normalizer = registry.create("strip_punct").unwrap();
+    assert_eq!(normalizer.normalize("«(word)»,"), "word");
+    assert_eq!(normalizer.normalize("..."), "");
+}
+
+#[test]
+fn chained_from_left_to_right() {
+    let normalizer = Registry::builtin().create("strip_punct,lowercase").unwrap();
+    assert_eq!(normalizer.normalize("\"Hello!\""), "hello");
+    assert_eq!(normalizer.normalize("plain"), "plain");
+}
+
+#[test]
+fn unknown_normalizers_are_rejected() {
+    let err = Registry::builtin().create("lowercase,nfc").unwrap_err();
+    assert_eq!(err, "Unknown normalizer: nfc");
+}
+
+#[test]
+fn custom_normalizers() {
+    let mut registry = Registry::builtin();
+    registry.register("records", || Box::new(RecordNumbers));
+    let normalizer = registry.create("records,lowercase").unwrap();
+    assert_eq!(normalizer.normalize("MRN12345"), "<mrn>");
+    assert_eq!(normalizer.normalize("Patient"), "patient");
+}
@@ -0,0 +1,45 @@
+use corpus_count::options::Options;
+
+#[test]
+fn builder_starts_from_defaults() {
+    let options = Options::builder().build().unwrap();
+    assert_eq!(options, Options::default());
+    assert_eq!(options.token_min(), 1);
+    assert_eq!((options.min_n(), options.max_n()), (3, 6));
+    assert!(options.bracket());
+    assert!(!options.filter_first());
+}
+
+#[test]
+fn builder_rejects_inconsistent_options() {
+    assert!(Options::builder().token_min(0).build().is_err());
+    assert!(Options::builder().ngram_min(0).build().is_err());
+    assert!(Options::builder().min_n(0).build().is_err());
+    assert!(Options::builder().min_n(4).max_n(3).build().is_err());
+    assert!(Options::builder().min_n(4).max_n(4).build().is_ok());
+    assert!(Options::builder()
+        .ngram_redundancy(Some(1.5))
+        .build()
+        .is_err());
+    assert!(Options::builder()
+        .ngram_target_size(Some(0))
+        .build()
+        .is_err());
+}
+
+#[test]
+fn to_builder_keeps_options() {
+    let options = Options::builder()
+        .token_min(5)
+        .min_n(2)
+        .max_n(4)
+        .bracket(false)
+        .build()
+        .unwrap();
+    let changed = options.to_builder().token_min(10).build().unwrap();
+    assert_eq!(changed.token_min(), 10);
+    assert_eq!((changed.min_n(), changed.max_n()), (2, 4));
+    assert!(!changed.bracket());
+    // The changed options are validated again.
+    assert!(options.to_builder().max_n(1).build().is_err());
+}
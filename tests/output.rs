@@ -0,0 +1,73 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::{symlink, FileTypeExt};
+use std::process::Command;
+use std::thread;
+
+use corpus_count::output::{OutputFile, OutputOptions};
+use corpus_count::temp;
+
+#[test]
+fn fifos_are_written_in_place() {
+    let dir = temp::unique_path(&std::env::temp_dir(), "output-fifo");
+    fs::create_dir(&dir).unwrap();
+    let fifo = dir.join("counts.fifo");
+    let status = Command::new("mkfifo").arg(&fifo).status().unwrap();
+    assert!(status.success());
+
+    let reader = {
+        let fifo = fifo.clone();
+        thread::spawn(move || {
+            let mut contents = String::new();
+            fs::File::open(fifo)
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            contents
+        })
+    };
+    let mut output = OutputFile::create(fifo.to_str(), &OutputOptions::default()).unwrap();
+    writeln!(output, "the\t2").unwrap();
+    output.finish().unwrap();
+
+    assert_eq!(reader.join().unwrap(), "the\t2\n");
+    assert!(fs::metadata(&fifo).unwrap().file_type().is_fifo());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn symlinks_are_followed() {
+    let dir = temp::unique_path(&std::env::temp_dir(), "output-symlink");
+    fs::create_dir_all(dir.join("real")).unwrap();
+    let real = dir.join("real").join("counts.tsv");
+    fs::write(&real, "old\t1\n").unwrap();
+    let link = dir.join("counts.tsv");
+    symlink(&real, &link).unwrap();
+
+    let mut output = OutputFile::create(link.to_str(), &OutputOptions::default()).unwrap();
+    writeln!(output, "the\t2").unwrap();
+    output.finish().unwrap();
+
+    assert!(fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(fs::read_to_string(&real).unwrap(), "the\t2\n");
+    assert_eq!(fs::read_dir(dir.join("real")).unwrap().count(), 1);
+
+    // Dangling links are written through.
+    let dangling = dir.join("dangling.tsv");
+    symlink(dir.join("real").join("new.tsv"), &dangling).unwrap();
+    let mut output = OutputFile::create(dangling.to_str(), &OutputOptions::default()).unwrap();
+    writeln!(output, "a\t1").unwrap();
+    output.finish().unwrap();
+    assert!(fs::symlink_metadata(&dangling)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(
+        fs::read_to_string(dir.join("real").join("new.tsv")).unwrap(),
+        "a\t1\n"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,239 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use corpus_count::regex::{fuzz_regex, Regex};
+use corpus_count::rng::Rng;
+
+const CASES: usize = 2000;
+
+/// Characters of the texts, including a multi-byte character.
+const TEXT_CHARS: &[char] = &['a', 'b', 'A', 'B', '0', '1', '_', ' ', '-', '\u{e9}'];
+
+/// Atoms of the patterns and their Python equivalents.
+const ATOMS: &[(&str, &str)] = &[
+    ("a", "a"),
+    ("b", "b"),
+    ("A", "A"),
+    ("0", "0"),
+    ("-", "-"),
+    ("\u{e9}", "\u{e9}"),
+    (".", "."),
+    ("\\.", "\\."),
+    ("[ab]", "[ab]"),
+    ("[^a]", "[^a]"),
+    ("[a-z]", "[a-z]"),
+    ("[0-9_]", "[0-9_]"),
+    ("[\\w-]", "[\\w-]"),
+    ("\\d", "\\d"),
+    ("\\D", "\\D"),
+    ("\\w", "\\w"),
+    ("\\W", "\\W"),
+    ("\\s", "\\s"),
+    ("\\S", "\\S"),
+];
+
+/// Zero-width assertions, `$` only matches at the end of the text.
+const ASSERTIONS: &[(&str, &str)] = &[("^", "^"), ("$", "\\Z"), ("\\b", "\\b"), ("\\B", "\\B")];
+
+/// Quantifiers and whether they allow zero repetitions.
+const QUANTIFIERS: &[(&str, bool)] = &[
+    ("*", true),
+    ("+", false),
+    ("?", true),
+    ("{2}", false),
+    ("{1,}", false),
+    ("{0,2}", true),
+    ("{1,3}", false),
+];
+
+/// Compares the matches with the `re` module, patterns are read as
+/// `fold<TAB>pattern<TAB>text` lines. Prints the spans of the groups of the
+/// full match, or `-` if there is none, and the spans of the searches at
+/// every character offset.
+const PYTHON: &str = r#"
+import re
+import sys
+
+def span(match, group=0):
+    if match is None or match.start(group) < 0:
+        return "-"
+    return "%d,%d" % match.span(group)
+
+for line in sys.stdin.read().split("\n")[:-1]:
+    fold, pattern, text = line.split("\t")
+    flags = re.ASCII | (re.IGNORECASE if fold == "1" else 0)
+    regex = re.compile(pattern, flags)
+    full = regex.fullmatch(text)
+    groups = [span(full, group) for group in range(regex.groups + 1 if full else 1)]
+    searches = [span(regex.search(text, pos)) for pos in range(len(text) + 1)]
+    print(" ".join(groups) + "\t" + " ".join(searches))
+"#;
+
+/// Generate a pattern and its Python equivalent, returns whether the
+/// pattern matches the empty string.
+///
+/// Only sub-patterns that cannot match the empty string are repeated, the
+/// engines end repetitions at empty iterations differently.
+fn random_pattern(rng: &mut Rng, depth: usize, pattern: &mut String, python: &mut String) -> bool {
+    let alternatives = if depth > 0 && rng.below(4) == 0 { 2 } else { 1 };
+    let mut nullable = false;
+    for alternative in 0..alternatives {
+        if alternative > 0 {
+            pattern.push('|');
+            python.push('|');
+        }
+        let mut pieces_nullable = true;
+        for _ in 0..rng.below(4) {
+            pieces_nullable &= random_piece(rng, depth, pattern, python);
+        }
+        nullable |= pieces_nullable;
+    }
+    nullable
+}
+
+fn random_piece(rng: &mut Rng, depth: usize, pattern: &mut String, python: &mut String) -> bool {
+    let nullable = match rng.below(10) {
+        0 => {
+            let (assertion, py) = ASSERTIONS[rng.below(ASSERTIONS.len() as u64) as usize];
+            pattern.push_str(assertion);
+            python.push_str(py);
+            return true;
+        }
+        1 | 2 if depth < 3 => {
+            let open = if rng.below(2) == 0 { "(" } else { "(?:" };
+            pattern.push_str(open);
+            python.push_str(open);
+            let nullable = random_pattern(rng, depth + 1, pattern, python);
+            pattern.push(')');
+            python.push(')');
+            nullable
+        }
+        _ => {
+            let (atom, py) = ATOMS[rng.below(ATOMS.len() as u64) as usize];
+            pattern.push_str(atom);
+            python.push_str(py);
+            false
+        }
+    };
+    if nullable || rng.below(3) != 0 {
+        return nullable;
+    }
+    let (quantifier, optional) = QUANTIFIERS[rng.below(QUANTIFIERS.len() as u64) as usize];
+    let lazy = if rng.below(3) == 0 { "?" } else { "" };
+    for string in &mut [pattern, python] {
+        string.push_str(quantifier);
+        string.push_str(lazy);
+    }
+    optional
+}
+
+fn random_text(rng: &mut Rng, min_len: usize) -> String {
+    let len = min_len + rng.below(8) as usize;
+    (0..len)
+        .map(|_| TEXT_CHARS[rng.below(TEXT_CHARS.len() as u64) as usize])
+        .collect()
+}
+
+/// Format a span of byte offsets as a span of character offsets.
+fn char_span(text: &str, span: Option<(usize, usize)>) -> String {
+    let chars = |offset: usize| text[..offset].chars().count();
+    match span {
+        Some((start, end)) => format!("{},{}", chars(start), chars(end)),
+        None => "-".to_string(),
+    }
+}
+
+/// Get the groups of the full match and the searches at every character
+/// offset in the format of the Python script.
+fn matches(regex: &Regex, text: &str) -> String {
+    let groups = match regex.full_captures(text) {
+        Some(caps) => caps
+            .iter()
+            .map(|&span| char_span(text, span))
+            .collect::<Vec<_>>(),
+        None => vec!["-".to_string()],
+    };
+    let searches = text
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .chain(Some(text.len()))
+        .map(|start| char_span(text, regex.find_at(text, start)))
+        .collect::<Vec<_>>();
+    format!("{}\t{}", groups.join(" "), searches.join(" "))
+}
+
+#[test]
+fn matches_agree_with_python() {
+    let mut rng = Rng::new(23);
+    let mut cases = Vec::new();
+    let mut input = String::new();
+    for _ in 0..CASES {
+        let (mut pattern, mut python) = (String::new(), String::new());
+        random_pattern(&mut rng, 0, &mut pattern, &mut python);
+        let fold = rng.below(4) == 0;
+        // Unlike other engines, `\B` never matches the empty text in Python.
+        let text = random_text(&mut rng, python.contains("\\B") as usize);
+        input.push_str(&format!("{}\t{}\t{}\n", fold as u8, python, text));
+        if fold {
+            pattern.insert_str(0, "(?i)");
+        }
+        cases.push((pattern, text));
+    }
+
+    let mut child = match Command::new("python3")
+        .args(["-c", PYTHON])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(_) => {
+            eprintln!("python3 is not installed, skipping comparison");
+            return;
+        }
+    };
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let expected = String::from_utf8(output.stdout).unwrap();
+    let expected = expected.lines().collect::<Vec<_>>();
+    assert_eq!(expected.len(), cases.len());
+
+    let mut full_matches = 0;
+    for ((pattern, text), expected) in cases.iter().zip(expected) {
+        let regex = Regex::new(pattern).unwrap();
+        if regex.is_full_match(text) {
+            full_matches += 1;
+        }
+        assert_eq!(
+            matches(&regex, text),
+            expected,
+            "matches of {:?} in {:?}",
+            pattern,
+            text
+        );
+    }
+    // Make sure the comparison is not dominated by failing matches.
+    assert!(full_matches > CASES / 20);
+}
+
+#[test]
+fn fuzz_arbitrary_bytes() {
+    let mut rng = Rng::new(29);
+    let bytes = b"ab()[]^$-|*+?{},0\\.dwsbB";
+    for _ in 0..CASES * 5 {
+        let len = rng.below(24) as usize;
+        let data = (0..len)
+            .map(|_| match rng.below(8) {
+                0 => rng.next_u64() as u8,
+                _ => bytes[rng.below(bytes.len() as u64) as usize],
+            })
+            .collect::<Vec<_>>();
+        fuzz_regex(&data);
+    }
+}
@@ -0,0 +1,60 @@
+use corpus_count::roaring::RoaringBitmap;
+
+#[test]
+fn insert_and_contains() {
+    let mut bitmap = RoaringBitmap::new();
+    assert!(bitmap.is_empty());
+    assert!(bitmap.insert(3));
+    assert!(bitmap.insert(70_000));
+    assert!(bitmap.insert(1));
+    assert!(!bitmap.insert(3));
+    assert_eq!(bitmap.len(), 3);
+    assert!(bitmap.contains(70_000));
+    assert!(!bitmap.contains(2));
+    assert_eq!(bitmap.iter().collect::<Vec<_>>(), vec![1, 3, 70_000]);
+}
+
+#[test]
+fn dense_chunks_are_bitmaps() {
+    let mut bitmap = RoaringBitmap::new();
+    for value in (0..20_000).map(|value| value * 4) {
+        bitmap.insert(value);
+    }
+    assert_eq!(bitmap.len(), 20_000);
+    // The first chunk of 16384 values is dense, the second one an array.
+    assert_eq!(bitmap.serialized_len(), 8 + 8 + 8192 + 8 + 2 * 3616);
+    assert!(bitmap.contains(79_996));
+    assert!(!bitmap.contains(79_997));
+    assert!(bitmap.iter().eq((0..20_000).map(|value| value * 4)));
+}
+
+#[test]
+fn portable_serialization() {
+    let mut bitmap = RoaringBitmap::new();
+    bitmap.insert(1);
+    bitmap.insert(65_536 + 2);
+    let mut data = Vec::new();
+    bitmap.serialize(&mut data).unwrap();
+    assert_eq!(data.len(), bitmap.serialized_len());
+    #[rustfmt::skip]
+    let expected = [
+        0x3a, 0x30, 0, 0, 2, 0, 0, 0,
+        0, 0, 0, 0, 1, 0, 0, 0,
+        24, 0, 0, 0, 26, 0, 0, 0,
+        1, 0, 2, 0,
+    ];
+    assert_eq!(data, expected);
+    assert_eq!(RoaringBitmap::deserialize(&data[..]).unwrap(), bitmap);
+}
+
+#[test]
+fn serialization_roundtrip() {
+    let mut bitmap = RoaringBitmap::new();
+    for value in (0..100_000).filter(|value| value % 3 == 0 || *value > 90_000) {
+        bitmap.insert(value);
+    }
+    let mut data = Vec::new();
+    bitmap.serialize(&mut data).unwrap();
+    assert_eq!(RoaringBitmap::deserialize(&data[..]).unwrap(), bitmap);
+    assert!(RoaringBitmap::deserialize(&[0x3b, 0x30, 0, 0][..]).is_err());
+}
@@ -0,0 +1,25 @@
+use corpus_count::sampling::{smoothed_probs, unigram_table};
+
+#[test]
+fn probs_are_smoothed() {
+    let probs = smoothed_probs(&[16, 1], 0.5);
+    assert!((probs[0] - 0.8).abs() < 1e-12);
+    assert!((probs[1] - 0.2).abs() < 1e-12);
+    assert_eq!(smoothed_probs(&[3, 1], 1.), vec![0.75, 0.25]);
+}
+
+#[test]
+fn table_follows_probs() {
+    let table = unigram_table(&[16, 1], 0.5, 1000);
+    assert_eq!(table.len(), 1000);
+    let zeros = table.iter().filter(|&&idx| idx == 0).count();
+    assert!((799..=802).contains(&zeros), "{}", zeros);
+    // Indices are ascending.
+    assert!(table.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(table.last(), Some(&1));
+}
+
+#[test]
+fn table_of_empty_vocab() {
+    assert!(unigram_table(&[], 0.75, 100).is_empty());
+}
@@ -0,0 +1,88 @@
+use std::io::Cursor;
+
+use corpus_count::subword::{NGramIndexer, SubwordVocab};
+use corpus_count::vocab::Vocab;
+
+fn tokens() -> Vocab {
+    Vocab::from_counts(vec![("ab".to_string(), 3), ("b".to_string(), 2)])
+}
+
+#[test]
+fn explicit_ngram_indices() {
+    let vocab = SubwordVocab::explicit(tokens(), 2, 3, true, 1);
+    // N-grams of <ab> and <b> by count: b> occurs in both tokens, <a, <ab,
+    // ab, ab> occur 3 and <b, <b> 2 times. Ties are ordered by bytes.
+    let ngrams = match vocab.ngrams() {
+        NGramIndexer::Explicit(ngrams) => ngrams,
+        NGramIndexer::Hashed { .. } => unreachable!(),
+    };
+    assert_eq!(
+        ngrams.iter().map(|(ngram, _)| ngram).collect::<Vec<_>>(),
+        vec!["b>", "<a", "<ab", "ab", "ab>", "<b", "<b>"]
+    );
+    assert_eq!(vocab.len(), 2 + 7);
+    // 3-grams come before 2-grams of the same offset, offset by 2 tokens.
+    assert_eq!(vocab.indices("ab"), vec![0, 4, 3, 6, 5, 2]);
+    assert_eq!(vocab.ngram_indices("b"), vec![8, 7, 2]);
+    // Unknown tokens only have n-gram indices.
+    assert_eq!(vocab.indices("a"), vec![3]);
+}
+
+#[test]
+fn explicit_ngram_min() {
+    let vocab = SubwordVocab::explicit(tokens(), 2, 3, true, 3);
+    assert_eq!(vocab.ngrams().len(), 5);
+    assert_eq!(vocab.ngram_indices("b"), vec![2]);
+}
+
+#[test]
+fn hashed_ngram_indices_in_range() {
+    let vocab = SubwordVocab::new(
+        tokens(),
+        NGramIndexer::Hashed { buckets_exp: 4 },
+        3,
+        6,
+        true,
+    );
+    assert_eq!(vocab.len(), 2 + 16);
+    let indices = vocab.ngram_indices("house");
+    assert_eq!(indices.len(), 14);
+    assert!(indices.iter().all(|idx| (2..18).contains(idx)));
+    assert_eq!(indices, vocab.ngram_indices("house"));
+}
+
+#[test]
+fn write_read_roundtrip() {
+    for vocab in [
+        SubwordVocab::explicit(tokens(), 2, 3, true, 1),
+        SubwordVocab::new(
+            tokens(),
+            NGramIndexer::Hashed { buckets_exp: 10 },
+            3,
+            6,
+            false,
+        ),
+    ] {
+        let mut data = Vec::new();
+        vocab.write(&mut data).unwrap();
+        let read = SubwordVocab::read(Cursor::new(&data)).unwrap();
+        let mut reread = Vec::new();
+        read.write(&mut reread).unwrap();
+        assert_eq!(data, reread);
+        for token in &["ab", "b", "abc", "house"] {
+            assert_eq!(read.indices(token), vocab.indices(token));
+        }
+    }
+}
+
+#[test]
+fn read_rejects_truncated_vocab() {
+    let mut data = Vec::new();
+    SubwordVocab::explicit(tokens(), 2, 3, true, 1)
+        .write(&mut data)
+        .unwrap();
+    data.truncate(data.len() - 4);
+    let data = String::from_utf8(data).unwrap();
+    let truncated = &data[..data.rfind('\n').unwrap()];
+    assert!(SubwordVocab::read(Cursor::new(truncated)).is_err());
+}
@@ -0,0 +1,85 @@
+use std::io::Cursor;
+
+use corpus_count::tokenize::{
+    Chars, DictSegmenter, ScriptRuns, Tokenizer, UnicodeWords, Whitespace,
+};
+
+fn tokens(tokenizer: &dyn Tokenizer, line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    tokenizer.tokenize(line, &mut |token| tokens.push(token.to_string()));
+    tokens
+}
+
+#[test]
+fn whitespace() {
+    assert_eq!(
+        tokens(&Whitespace, " a\tb,  c \u{3000}d"),
+        ["a", "b,", "c", "d"]
+    );
+    assert!(tokens(&Whitespace, "  ").is_empty());
+}
+
+#[test]
+fn unicode_words() {
+    assert_eq!(
+        tokens(&UnicodeWords, "Don't stop, e-mail «Zürich»!"),
+        ["Don't", "stop", "e", "mail", "Zürich"]
+    );
+    assert_eq!(tokens(&UnicodeWords, "'quoted' it's’"), ["quoted", "it's"]);
+    assert_eq!(tokens(&UnicodeWords, "नमस्ते दुनिया"), ["नमस्ते", "दुनिया"]);
+}
+
+#[test]
+fn chars() {
+    assert_eq!(tokens(&Chars, "中文 ab"), ["中", "文", "a", "b"]);
+}
+
+#[test]
+fn script_runs() {
+    assert_eq!(
+        tokens(&ScriptRuns, "東京に行きました。コーヒーを飲みたいなー"),
+        [
+            "東京",
+            "に",
+            "行",
+            "きました",
+            "コーヒー",
+            "を",
+            "飲",
+            "みたいなー"
+        ]
+    );
+    assert_eq!(
+        tokens(&ScriptRuns, "iPhone 15を買った"),
+        ["iPhone", "15", "を", "買", "った"]
+    );
+}
+
+#[test]
+fn dictionary_segmentation() {
+    let dict =
+        "我 1000\n来 500\n来到 200\n到 300\n北京 300\n清华 50\n大学 200\n清华大学 30\n华大 5\n";
+    let segmenter = DictSegmenter::read(Cursor::new(dict)).unwrap();
+    assert_eq!(
+        tokens(&segmenter, "我来到北京清华大学。"),
+        ["我", "来到", "北京", "清华大学"]
+    );
+    // Unknown characters are single words, unknown ASCII is joined.
+    assert_eq!(
+        tokens(&segmenter, "我用iPhone15到北京"),
+        ["我", "用", "iPhone15", "到", "北京"]
+    );
+    assert!(DictSegmenter::read(Cursor::new("北京\n")).is_err());
+}
+
+#[test]
+fn word_list_segmentation() {
+    let words = "ไป\nโรงเรียน\nโรง\nเรียน\nฉัน\n";
+    let segmenter = DictSegmenter::read_word_list(Cursor::new(words)).unwrap();
+    assert_eq!(tokens(&segmenter, "ฉันไปโรงเรียน"), ["ฉัน", "ไป", "โรงเรียน"]);
+    // Unknown clusters are joined, words cannot end within a cluster.
+    assert_eq!(tokens(&segmenter, "ฉันกินข้าว"), ["ฉัน", "กินข้าว"]);
+    assert_eq!(tokens(&segmenter, "ไปเด็ก"), ["ไป", "เด็ก"]);
+    let segmenter = DictSegmenter::read_word_list(Cursor::new("ก\nเด\n")).unwrap();
+    assert_eq!(tokens(&segmenter, "เด็ก"), ["เด็", "ก"]);
+}
@@ -0,0 +1,58 @@
+use std::io::Cursor;
+
+use corpus_count::vocab::Vocab;
+
+fn vocab() -> Vocab {
+    Vocab::from_counts(vec![
+        ("b".to_string(), 2),
+        ("c".to_string(), 5),
+        ("a".to_string(), 2),
+        ("b".to_string(), 1),
+    ])
+}
+
+#[test]
+fn lookup_by_rank() {
+    let vocab = vocab();
+    assert_eq!(vocab.len(), 3);
+    assert_eq!(vocab.idx("c"), Some(0));
+    assert_eq!(vocab.idx("b"), Some(1));
+    assert_eq!(vocab.idx("a"), Some(2));
+    assert_eq!(vocab.idx("d"), None);
+    assert_eq!(vocab.token(2), Some("a"));
+    assert_eq!(vocab.token(3), None);
+    assert_eq!(vocab.count("b"), Some(3));
+    assert_eq!(
+        vocab.iter().collect::<Vec<_>>(),
+        vec![("c", 5), ("b", 3), ("a", 2)]
+    );
+}
+
+#[test]
+fn write_read_roundtrip() {
+    let vocab = vocab();
+    let mut data = Vec::new();
+    vocab.write(&mut data).unwrap();
+    assert_eq!(
+        String::from_utf8(data.clone()).unwrap(),
+        "c\t5\nb\t3\na\t2\n"
+    );
+    let read = Vocab::read(Cursor::new(data)).unwrap();
+    assert_eq!(
+        read.iter().collect::<Vec<_>>(),
+        vocab.iter().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn read_ignores_extra_columns() {
+    let vocab = Vocab::read(Cursor::new("a\t2\t0.5\t8.7\nb\t1\t0.2\t8.3\n")).unwrap();
+    assert_eq!(vocab.iter().collect::<Vec<_>>(), vec![("a", 2), ("b", 1)]);
+}
+
+#[test]
+fn read_rejects_invalid_lines() {
+    assert!(Vocab::read(Cursor::new("a\tx\n")).is_err());
+    assert!(Vocab::read(Cursor::new("a\n")).is_err());
+    assert!(Vocab::read(Cursor::new("a\t1\na\t2\n")).is_err());
+}
@@ -0,0 +1,47 @@
+use std::io::{Cursor, ErrorKind};
+
+use corpus_count::warc::Records;
+
+fn response(body: &str) -> String {
+    let payload = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\n\r\n{}",
+        body
+    );
+    format!(
+        "WARC/1.0\r\nWARC-Type: response\r\nContent-Length: {}\r\n\r\n{}\r\n\r\n",
+        payload.len(),
+        payload
+    )
+}
+
+fn warc_text(body: &str) -> std::io::Result<Option<String>> {
+    let warc = response(body);
+    let record = Records::new(Cursor::new(warc)).next().unwrap().unwrap();
+    record.warc_text()
+}
+
+#[test]
+fn chunked_bodies_are_decoded() {
+    assert_eq!(
+        warc_text("4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n").unwrap(),
+        Some("Wikipedia".to_owned())
+    );
+}
+
+#[test]
+fn oversized_chunks_are_invalid() {
+    for body in &["ff\r\nWiki\r\n0\r\n\r\n", "ffffffffffffffff\r\nWiki\r\n"] {
+        assert_eq!(warc_text(body).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+fn huge_content_lengths_are_truncated_records() {
+    let warc = "WARC/1.0\r\nWARC-Type: response\r\nContent-Length: 99999999999999999\r\n\r\nWiki";
+    let err = Records::new(Cursor::new(warc))
+        .next()
+        .unwrap()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}